@@ -4,7 +4,7 @@
 
 use ark_bls12_381::{Bls12_381, Fr as BlsFr};
 use ark_ff::PrimeField;
-use ark_marlin::{Marlin, SimpleHashFiatShamirRng};
+use ark_marlin::{Marlin, SimpleHashFiatShamirRng, ZkMode};
 use ark_mnt4_298::{Fr as MNT4Fr, MNT4_298};
 use ark_mnt4_753::{Fr as MNT4BigFr, MNT4_753};
 use ark_mnt6_298::{Fr as MNT6Fr, MNT6_298};
@@ -67,7 +67,7 @@ impl<F: PrimeField> ConstraintSynthesizer<F> for DummyCircuit<F> {
 }
 
 macro_rules! marlin_prove_bench {
-    ($bench_name:ident, $bench_field:ty, $bench_pairing_engine:ty) => {
+    ($bench_name:ident, $bench_field:ty, $bench_pairing_engine:ty, $zk_mode:expr) => {
         let rng = &mut ark_std::test_rng();
         let c = DummyCircuit::<$bench_field> {
             a: Some(<$bench_field>::rand(rng)),
@@ -96,13 +96,14 @@ macro_rules! marlin_prove_bench {
                 $bench_field,
                 SonicKZG10<$bench_pairing_engine, DensePolynomial<$bench_field>>,
                 SimpleHashFiatShamirRng<Blake2s, ChaChaRng>,
-            >::prove(&pk, c.clone(), rng)
+            >::prove_with_zk_mode(&pk, c.clone(), $zk_mode, rng)
             .unwrap();
         }
 
         println!(
-            "per-constraint proving time for {}: {} ns/constraint",
+            "per-constraint proving time for {} ({:?}): {} ns/constraint",
             stringify!($bench_pairing_engine),
+            $zk_mode,
             start.elapsed().as_nanos() / NUM_PROVE_REPEATITIONS as u128 / 65536u128
         );
     };
@@ -159,11 +160,13 @@ macro_rules! marlin_verify_bench {
 }
 
 fn bench_prove() {
-    marlin_prove_bench!(bls, BlsFr, Bls12_381);
-    marlin_prove_bench!(mnt4, MNT4Fr, MNT4_298);
-    marlin_prove_bench!(mnt6, MNT6Fr, MNT6_298);
-    marlin_prove_bench!(mnt4big, MNT4BigFr, MNT4_753);
-    marlin_prove_bench!(mnt6big, MNT6BigFr, MNT6_753);
+    for &zk_mode in &[ZkMode::ZeroKnowledge, ZkMode::NonZeroKnowledge] {
+        marlin_prove_bench!(bls, BlsFr, Bls12_381, zk_mode);
+        marlin_prove_bench!(mnt4, MNT4Fr, MNT4_298, zk_mode);
+        marlin_prove_bench!(mnt6, MNT6Fr, MNT6_298, zk_mode);
+        marlin_prove_bench!(mnt4big, MNT4BigFr, MNT4_753, zk_mode);
+        marlin_prove_bench!(mnt6big, MNT6BigFr, MNT6_753, zk_mode);
+    }
 }
 
 fn bench_verify() {
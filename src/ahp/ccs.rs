@@ -0,0 +1,153 @@
+use crate::ahp::indexer::Matrix;
+use crate::{String, ToString, Vec};
+use ark_ff::Field;
+use ark_relations::{
+    lc,
+    r1cs::{ConstraintSystemRef, LinearCombination, SynthesisError},
+};
+use ark_std::{marker::PhantomData, vec};
+use derivative::Derivative;
+
+/// The largest number of factors of a product gate.
+pub const MAX_PRODUCT_GATE_DEGREE: usize = 23;
+
+/// Product gates that extend the R1CS constraints of a circuit to a
+/// customizable constraint system (CCS).
+///
+/// A product gate enforces `f_1 * f_2 * ... * f_d = o` for linear
+/// combinations `f_1, ..., f_d` and `o`, in a single row. The resulting
+/// system has the matrices `A`, `B`, `C` of the R1CS constraints and one
+/// matrix `D`, `E`, `F`, ... per factor of the gates, and checks
+///
+/// `Az ∘ Bz + Dz ∘ Ez ∘ Fz ∘ ... = Cz`,
+///
+/// where the rows of the R1CS constraints are zero in the factor matrices,
+/// and the rows of the gates are zero in `A` and `B`. Gates with fewer
+/// factors than the others are padded with the constant one.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+pub struct ProductGates<F: Field> {
+    /// The first of the constraints that carry the linear combinations of
+    /// each gate, and the number of its factors.
+    gates: Vec<(usize, usize)>,
+
+    #[doc(hidden)]
+    f: PhantomData<F>,
+}
+
+impl<F: Field> ProductGates<F> {
+    /// Construct an empty set of gates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enforce that the product of `factors` equals `output`.
+    ///
+    /// The linear combinations of the gate are carried by `factors.len() / 2`
+    /// constraints of `cs`, rounded up, which the indexer merges into a
+    /// single row. Returns `SynthesisError::Unsatisfiable` unless the gate has
+    /// between 2 and `MAX_PRODUCT_GATE_DEGREE` factors.
+    pub fn enforce_product(
+        &mut self,
+        cs: &ConstraintSystemRef<F>,
+        factors: Vec<LinearCombination<F>>,
+        output: LinearCombination<F>,
+    ) -> Result<(), SynthesisError> {
+        if !(2..=MAX_PRODUCT_GATE_DEGREE).contains(&factors.len()) {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let first = cs.num_constraints();
+        let num_factors = factors.len();
+        let mut output = Some(output);
+        let mut factors = factors.into_iter();
+        while let Some(left) = factors.next() {
+            let right = factors.next().unwrap_or_else(|| lc!());
+            cs.enforce_constraint(left, right, output.take().unwrap_or_else(|| lc!()))?;
+        }
+        self.gates.push((first, num_factors));
+        Ok(())
+    }
+
+    /// The number of factors of the gates with the most factors, or zero if
+    /// there are no gates.
+    pub fn degree(&self) -> usize {
+        self.gates.iter().map(|(_, d)| *d).max().unwrap_or(0)
+    }
+
+    /// The number of constraints that carry the gates, but that the indexer
+    /// removes because they are merged into the first one of their gate.
+    pub(crate) fn num_merged_constraints(&self) -> usize {
+        self.gates.iter().map(|(_, d)| d / 2 + d % 2 - 1).sum()
+    }
+
+    /// Merge the constraints that carry each gate into a single row of the
+    /// matrices `a`, `b`, `c` and of the factor matrices, which are returned.
+    pub(crate) fn merge_into(
+        &self,
+        a: &mut Matrix<F>,
+        b: &mut Matrix<F>,
+        c: &mut Matrix<F>,
+    ) -> Vec<Matrix<F>> {
+        let degree = self.degree();
+        let one_row = vec![(F::one(), 0)];
+        let num_rows = a.len();
+        let mut factor_matrices = vec![Vec::with_capacity(num_rows); degree];
+
+        let mut gates = self.gates.iter().peekable();
+        let (mut new_a, mut new_b, mut new_c) = (Vec::new(), Vec::new(), Vec::new());
+        let mut row = 0;
+        while row < num_rows {
+            match gates.peek() {
+                Some(&&(first, num_factors)) if first == row => {
+                    gates.next();
+                    let num_carriers = num_factors / 2 + num_factors % 2;
+                    let carriers = row..row + num_carriers;
+                    let factors = carriers
+                        .clone()
+                        .flat_map(|r| vec![&a[r], &b[r]])
+                        .take(num_factors);
+                    for (matrix, factor) in factor_matrices
+                        .iter_mut()
+                        .zip(factors.chain(ark_std::iter::repeat(&one_row)))
+                    {
+                        matrix.push(factor.clone());
+                    }
+                    new_a.push(Vec::new());
+                    new_b.push(Vec::new());
+                    new_c.push(ark_std::mem::take(&mut c[row]));
+                    row += num_carriers;
+                }
+                _ => {
+                    for matrix in &mut factor_matrices {
+                        matrix.push(Vec::new());
+                    }
+                    new_a.push(ark_std::mem::take(&mut a[row]));
+                    new_b.push(ark_std::mem::take(&mut b[row]));
+                    new_c.push(ark_std::mem::take(&mut c[row]));
+                    row += 1;
+                }
+            }
+        }
+        *a = new_a;
+        *b = new_b;
+        *c = new_c;
+        factor_matrices
+    }
+}
+
+/// The names of the factor matrices of product gates with `degree` factors:
+/// `d`, `e`, `f`, and so on.
+pub(crate) fn gate_matrix_names(degree: usize) -> impl Iterator<Item = String> {
+    (0..degree).map(|k| ((b'd' + k as u8) as char).to_string())
+}
+
+/// A circuit whose constraints may include product gates.
+pub trait CcsConstraintSynthesizer<F: Field> {
+    /// Generate the constraints of the circuit in `cs`, and its product
+    /// gates in `gates`.
+    fn generate_constraints_and_gates(
+        self,
+        cs: ConstraintSystemRef<F>,
+        gates: &mut ProductGates<F>,
+    ) -> Result<(), SynthesisError>;
+}
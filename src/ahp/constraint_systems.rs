@@ -1,14 +1,16 @@
 #![allow(non_snake_case)]
 
+use crate::ahp::ccs::{CcsConstraintSynthesizer, ProductGates};
 use crate::ahp::indexer::Matrix;
+use crate::ahp::lookup::{LookupConstraintSynthesizer, LookupTables};
 use crate::ahp::*;
 use crate::BTreeMap;
 use ark_ff::{Field, PrimeField};
 use ark_poly::{EvaluationDomain, Evaluations as EvaluationsOnDomain, GeneralEvaluationDomain};
-use ark_relations::{lc, r1cs::ConstraintSystemRef};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use ark_std::{
-    cfg_iter_mut,
+    cfg_iter, cfg_iter_mut,
     io::{Read, Write},
 };
 use derivative::Derivative;
@@ -17,29 +19,62 @@ use derivative::Derivative;
 /* ************************************************************************* */
 /* ************************************************************************* */
 
-pub(crate) fn num_non_zero(joint_matrix: &Vec<Vec<usize>>) -> usize {
-    joint_matrix.iter().map(|row| row.len()).sum()
+/// A circuit, together with the lookups and the product gates that extend its
+/// R1CS constraints. The indexer and the prover synthesize circuits through
+/// this trait, whichever of the extensions they use.
+pub(crate) trait Circuit<F: Field> {
+    fn synthesize(
+        self,
+        cs: ConstraintSystemRef<F>,
+        tables: &mut LookupTables<F>,
+        gates: &mut ProductGates<F>,
+    ) -> Result<(), SynthesisError>;
+}
+
+/// A circuit with R1CS constraints only.
+pub(crate) struct R1CS<C>(pub C);
+
+impl<F: Field, C: ConstraintSynthesizer<F>> Circuit<F> for R1CS<C> {
+    fn synthesize(
+        self,
+        cs: ConstraintSystemRef<F>,
+        _: &mut LookupTables<F>,
+        _: &mut ProductGates<F>,
+    ) -> Result<(), SynthesisError> {
+        self.0.generate_constraints(cs)
+    }
 }
 
-pub(crate) fn make_matrices_square_for_indexer<F: PrimeField>(cs: ConstraintSystemRef<F>) {
-    let num_variables = cs.num_instance_variables() + cs.num_witness_variables();
-    let matrix_dim = padded_matrix_dim(num_variables, cs.num_constraints());
-    make_matrices_square(cs.clone(), num_variables);
-    assert_eq!(
-        cs.num_instance_variables() + cs.num_witness_variables(),
-        cs.num_constraints(),
-        "padding failed!"
-    );
-    assert_eq!(
-        cs.num_instance_variables() + cs.num_witness_variables(),
-        matrix_dim,
-        "padding does not result in expected matrix size!"
-    );
+/// A circuit with lookups.
+pub(crate) struct WithLookups<C>(pub C);
+
+impl<F: Field, C: LookupConstraintSynthesizer<F>> Circuit<F> for WithLookups<C> {
+    fn synthesize(
+        self,
+        cs: ConstraintSystemRef<F>,
+        tables: &mut LookupTables<F>,
+        _: &mut ProductGates<F>,
+    ) -> Result<(), SynthesisError> {
+        self.0.generate_constraints_and_lookups(cs, tables)
+    }
 }
 
-/// This must *always* be in sync with `make_matrices_square`.
-pub(crate) fn padded_matrix_dim(num_formatted_variables: usize, num_constraints: usize) -> usize {
-    core::cmp::max(num_formatted_variables, num_constraints)
+/// A circuit with product gates.
+pub(crate) struct WithGates<C>(pub C);
+
+impl<F: Field, C: CcsConstraintSynthesizer<F>> Circuit<F> for WithGates<C> {
+    fn synthesize(
+        self,
+        cs: ConstraintSystemRef<F>,
+        _: &mut LookupTables<F>,
+        gates: &mut ProductGates<F>,
+    ) -> Result<(), SynthesisError> {
+        self.0.generate_constraints_and_gates(cs, gates)
+    }
+}
+
+pub(crate) fn num_non_zero(joint_matrix: &[Vec<usize>]) -> usize {
+    joint_matrix.iter().map(|row| row.len()).sum()
 }
 
 pub(crate) fn pad_input_for_indexer_and_prover<F: PrimeField>(cs: ConstraintSystemRef<F>) {
@@ -57,29 +92,6 @@ pub(crate) fn pad_input_for_indexer_and_prover<F: PrimeField>(cs: ConstraintSyst
     }
 }
 
-pub(crate) fn make_matrices_square<F: Field>(
-    cs: ConstraintSystemRef<F>,
-    num_formatted_variables: usize,
-) {
-    let num_constraints = cs.num_constraints();
-    let matrix_padding = ((num_formatted_variables as isize) - (num_constraints as isize)).abs();
-
-    if num_formatted_variables > num_constraints {
-        // Add dummy constraints of the form 0 * 0 == 0
-        for _ in 0..matrix_padding {
-            cs.enforce_constraint(lc!(), lc!(), lc!())
-                .expect("enforce 0 * 0 == 0 failed");
-        }
-    } else {
-        // Add dummy unconstrained variables
-        for _ in 0..matrix_padding {
-            let _ = cs
-                .new_witness_variable(|| Ok(F::one()))
-                .expect("alloc failed");
-        }
-    }
-}
-
 /// Evaluations of various polynomials related to the constraint matrices,
 /// over the same domain.
 #[derive(Derivative, CanonicalSerialize, CanonicalDeserialize)]
@@ -91,15 +103,12 @@ pub struct MatrixEvals<F: PrimeField> {
     pub col: EvaluationsOnDomain<F>,
     /// Evaluations of the `row_col` polynomial.
     pub row_col: EvaluationsOnDomain<F>,
-    /// Evaluations of the `val_a` polynomial.
-    pub val_a: EvaluationsOnDomain<F>,
-    /// Evaluations of the `val_b` polynomial.
-    pub val_b: EvaluationsOnDomain<F>,
-    /// Evaluations of the `val_c` polynomial.
-    pub val_c: EvaluationsOnDomain<F>,
+    /// Evaluations of the `val` polynomials.
+    pub val: Vec<EvaluationsOnDomain<F>>,
 }
 
-/// Contains information about the arithmetization of the matrix M^*.
+/// Contains information about the arithmetization of one or more matrices M^*
+/// that share their `row` and `col` polynomials.
 /// Here `M^*(i, j) := M(j, i) * u_H(j, j)`. For more details, see [\[COS20\]](https://eprint.iacr.org/2019/1076).
 #[derive(Derivative, CanonicalSerialize, CanonicalDeserialize)]
 #[derivative(Debug(bound = "F: PrimeField"), Clone(bound = "F: PrimeField"))]
@@ -108,113 +117,105 @@ pub struct MatrixArithmetization<F: PrimeField> {
     pub row: LabeledPolynomial<F>,
     /// LDE of the column indices of M^*.
     pub col: LabeledPolynomial<F>,
-    /// LDE of the non-zero entries of A^*.
-    pub val_a: LabeledPolynomial<F>,
-    /// LDE of the non-zero entries of B^*.
-    pub val_b: LabeledPolynomial<F>,
-    /// LDE of the non-zero entries of C^*.
-    pub val_c: LabeledPolynomial<F>,
+    /// LDEs of the non-zero entries of each M^*, in the order in which the
+    /// matrices were arithmetized.
+    pub val: Vec<LabeledPolynomial<F>>,
     /// LDE of the vector containing entry-wise products of `row` and `col`,
     /// where `row` and `col` are as above.
     pub row_col: LabeledPolynomial<F>,
 
     /// Evaluation of `self.row`, `self.col`, and `self.val` on the domain `K`.
     pub evals_on_K: MatrixEvals<F>,
+    /// Evaluations of `self.row`, `self.col`, `self.row_col` and `self.val`
+    /// on the coset of a domain larger than `K` by the multiplicative
+    /// generator of `F`, if the indexer precomputed them for the inner
+    /// sumcheck. The evaluations are listed in the order of the domain.
+    pub evals_on_coset: Option<MatrixEvals<F>>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn arithmetize_matrix<F: PrimeField>(
-    joint_matrix: &Vec<Vec<usize>>,
-    a: &Matrix<F>,
-    b: &Matrix<F>,
-    c: &Matrix<F>,
+    joint_matrix: &[Vec<usize>],
+    matrices: &[(&str, &Matrix<F>)],
+    label_prefix: &str,
     interpolation_domain: GeneralEvaluationDomain<F>,
-    output_domain: GeneralEvaluationDomain<F>,
+    constraint_domain: GeneralEvaluationDomain<F>,
+    variable_domain: GeneralEvaluationDomain<F>,
     input_domain: GeneralEvaluationDomain<F>,
+    coset_domain: Option<GeneralEvaluationDomain<F>>,
 ) -> MatrixArithmetization<F> {
     let matrix_time = start_timer!(|| "Computing row, col, and val LDEs");
 
-    let elems: Vec<_> = output_domain.elements().collect();
+    let constraint_elems: Vec<_> = constraint_domain.elements().collect();
+    let variable_elems: Vec<_> = variable_domain.elements().collect();
 
     let lde_evals_time = start_timer!(|| "Computing row, col and val evals");
     // Recall that we are computing the arithmetization of M^*,
-    // where `M^*(i, j) := M(j, i) * u_H(j, j)`.
-    let a = a
-        .iter()
-        .enumerate()
-        .map(|(r, row)| row.iter().map(move |(f, i)| ((r, *i), *f)))
-        .flatten()
-        .collect::<BTreeMap<(usize, usize), F>>();
-
-    let b = b
-        .iter()
-        .enumerate()
-        .map(|(r, row)| row.iter().map(move |(f, i)| ((r, *i), *f)))
-        .flatten()
-        .collect::<BTreeMap<(usize, usize), F>>();
-
-    let c = c
-        .iter()
-        .enumerate()
-        .map(|(r, row)| row.iter().map(move |(f, i)| ((r, *i), *f)))
-        .flatten()
-        .collect::<BTreeMap<(usize, usize), F>>();
+    // where `M^*(i, j) := M(j, i) * u_H(j, j)`. The rows of M^* are indexed
+    // by the variable domain, and its columns by the constraint domain.
+    let entries = cfg_iter!(matrices)
+        .map(|(_, matrix)| {
+            matrix
+                .iter()
+                .enumerate()
+                .flat_map(|(r, row)| row.iter().map(move |(f, i)| ((r, *i), *f)))
+                .collect::<BTreeMap<(usize, usize), F>>()
+        })
+        .collect::<Vec<_>>();
 
     let eq_poly_vals_time = start_timer!(|| "Precomputing eq_poly_vals");
-    let eq_poly_vals: BTreeMap<F, F> = output_domain
+    let eq_poly_vals: BTreeMap<F, F> = variable_domain
         .elements()
-        .zip(output_domain.batch_eval_unnormalized_bivariate_lagrange_poly_with_same_inputs())
+        .zip(variable_domain.batch_eval_unnormalized_bivariate_lagrange_poly_with_same_inputs())
         .collect();
     end_timer!(eq_poly_vals_time);
 
-    let mut row_vec = Vec::with_capacity(interpolation_domain.size());
-    let mut col_vec = Vec::with_capacity(interpolation_domain.size());
-    let mut val_a_vec = Vec::with_capacity(interpolation_domain.size());
-    let mut val_b_vec = Vec::with_capacity(interpolation_domain.size());
-    let mut val_c_vec = Vec::with_capacity(interpolation_domain.size());
-    let mut inverses = Vec::with_capacity(interpolation_domain.size());
-    let mut count = 0;
-
-    for (r, row) in joint_matrix.into_iter().enumerate() {
-        for i in row {
-            let row_val = elems[r];
-            let col_val = elems[output_domain.reindex_by_subdomain(input_domain, *i)];
-
-            // We are dealing with the transpose of M
-            row_vec.push(col_val);
-            col_vec.push(row_val);
-            // We insert zeros if a matrix doesn't contain an entry at the given (row, col) location.
-            val_a_vec.push(a.get(&(r, *i)).copied().unwrap_or(F::zero()));
-            val_b_vec.push(b.get(&(r, *i)).copied().unwrap_or(F::zero()));
-            val_c_vec.push(c.get(&(r, *i)).copied().unwrap_or(F::zero()));
-            inverses.push(eq_poly_vals[&col_val]);
-
-            count += 1;
-        }
-    }
+    let positions: Vec<(usize, usize)> = joint_matrix
+        .iter()
+        .enumerate()
+        .flat_map(|(r, row)| row.iter().map(move |i| (r, *i)))
+        .collect();
+    let count = positions.len();
+
+    // We are dealing with the transpose of M
+    let mut row_vec: Vec<F> = cfg_iter!(positions)
+        .map(|(_, i)| variable_elems[variable_domain.reindex_by_subdomain(input_domain, *i)])
+        .collect();
+    let mut col_vec: Vec<F> = cfg_iter!(positions)
+        .map(|(r, _)| constraint_elems[*r])
+        .collect();
+    // We insert zeros if a matrix doesn't contain an entry at the given (row, col) location.
+    let mut val_vecs: Vec<Vec<F>> = entries
+        .iter()
+        .map(|entries| {
+            cfg_iter!(positions)
+                .map(|position| entries.get(position).copied().unwrap_or_else(F::zero))
+                .collect()
+        })
+        .collect();
+    let mut inverses: Vec<F> = cfg_iter!(row_vec)
+        .map(|col_val| eq_poly_vals[col_val])
+        .collect();
+    drop(positions);
     ark_ff::batch_inversion::<F>(&mut inverses);
     drop(eq_poly_vals);
 
-    cfg_iter_mut!(val_a_vec)
-        .zip(&mut val_b_vec)
-        .zip(&mut val_c_vec)
-        .zip(inverses)
-        .for_each(|(((v_a, v_b), v_c), inv)| {
-            *v_a *= &inv;
-            *v_b *= &inv;
-            *v_c *= &inv;
-        });
+    for val_vec in &mut val_vecs {
+        cfg_iter_mut!(val_vec)
+            .zip(&inverses)
+            .for_each(|(v, inv)| *v *= inv);
+    }
     end_timer!(lde_evals_time);
 
     for _ in count..interpolation_domain.size() {
-        col_vec.push(elems[0]);
-        row_vec.push(elems[0]);
-        val_a_vec.push(F::zero());
-        val_b_vec.push(F::zero());
-        val_c_vec.push(F::zero());
+        col_vec.push(constraint_elems[0]);
+        row_vec.push(variable_elems[0]);
+        for val_vec in &mut val_vecs {
+            val_vec.push(F::zero());
+        }
     }
 
-    let row_col_vec: Vec<_> = row_vec
-        .iter()
+    let row_col_vec: Vec<_> = cfg_iter!(row_vec)
         .zip(&col_vec)
         .map(|(row, col)| *row * col)
         .collect();
@@ -222,42 +223,59 @@ pub(crate) fn arithmetize_matrix<F: PrimeField>(
     let interpolate_time = start_timer!(|| "Interpolating on K");
     let row_evals_on_K = EvaluationsOnDomain::from_vec_and_domain(row_vec, interpolation_domain);
     let col_evals_on_K = EvaluationsOnDomain::from_vec_and_domain(col_vec, interpolation_domain);
-    let val_a_evals_on_K =
-        EvaluationsOnDomain::from_vec_and_domain(val_a_vec, interpolation_domain);
-    let val_b_evals_on_K =
-        EvaluationsOnDomain::from_vec_and_domain(val_b_vec, interpolation_domain);
-    let val_c_evals_on_K =
-        EvaluationsOnDomain::from_vec_and_domain(val_c_vec, interpolation_domain);
+    let val_evals_on_K: Vec<_> = val_vecs
+        .into_iter()
+        .map(|val_vec| EvaluationsOnDomain::from_vec_and_domain(val_vec, interpolation_domain))
+        .collect();
     let row_col_evals_on_K =
         EvaluationsOnDomain::from_vec_and_domain(row_col_vec, interpolation_domain);
 
     let row = row_evals_on_K.clone().interpolate();
     let col = col_evals_on_K.clone().interpolate();
-    let val_a = val_a_evals_on_K.clone().interpolate();
-    let val_b = val_b_evals_on_K.clone().interpolate();
-    let val_c = val_c_evals_on_K.clone().interpolate();
+    let val_polys: Vec<_> = cfg_iter!(val_evals_on_K)
+        .map(|evals| evals.clone().interpolate())
+        .collect();
     let row_col = row_col_evals_on_K.clone().interpolate();
 
     end_timer!(interpolate_time);
 
+    let evals_on_coset = coset_domain.map(|domain| {
+        let coset_time = start_timer!(|| "Evaluating on the coset");
+        let evaluate = |poly: &DensePolynomial<F>| {
+            EvaluationsOnDomain::from_vec_and_domain(domain.coset_fft(&poly.coeffs), domain)
+        };
+        let evals = MatrixEvals {
+            row: evaluate(&row),
+            col: evaluate(&col),
+            row_col: evaluate(&row_col),
+            val: cfg_iter!(val_polys).map(evaluate).collect(),
+        };
+        end_timer!(coset_time);
+        evals
+    });
+
+    let val = val_polys
+        .into_iter()
+        .zip(matrices)
+        .map(|(poly, (name, _))| LabeledPolynomial::new(format!("{}_val", name), poly, None, None))
+        .collect();
+
     end_timer!(matrix_time);
     let evals_on_K = MatrixEvals {
         row: row_evals_on_K,
         col: col_evals_on_K,
         row_col: row_col_evals_on_K,
-        val_a: val_a_evals_on_K,
-        val_b: val_b_evals_on_K,
-        val_c: val_c_evals_on_K,
+        val: val_evals_on_K,
     };
 
+    let label = |name: &str| format!("{}{}", label_prefix, name);
     MatrixArithmetization {
-        row: LabeledPolynomial::new("row".into(), row, None, None),
-        col: LabeledPolynomial::new("col".into(), col, None, None),
-        val_a: LabeledPolynomial::new("a_val".into(), val_a, None, None),
-        val_b: LabeledPolynomial::new("b_val".into(), val_b, None, None),
-        val_c: LabeledPolynomial::new("c_val".into(), val_c, None, None),
-        row_col: LabeledPolynomial::new("row_col".into(), row_col, None, None),
+        row: LabeledPolynomial::new(label("row"), row, None, None),
+        col: LabeledPolynomial::new(label("col"), col, None, None),
+        val,
+        row_col: LabeledPolynomial::new(label("row_col"), row_col, None, None),
         evals_on_K,
+        evals_on_coset,
     }
 }
 
@@ -279,16 +297,6 @@ pub(crate) fn unformat_public_input<F: PrimeField>(input: &[F]) -> Vec<F> {
     input[1..].to_vec()
 }
 
-pub(crate) fn make_matrices_square_for_prover<F: PrimeField>(cs: ConstraintSystemRef<F>) {
-    let num_variables = cs.num_instance_variables() + cs.num_witness_variables();
-    make_matrices_square(cs.clone(), num_variables);
-    assert_eq!(
-        cs.num_instance_variables() + cs.num_witness_variables(),
-        cs.num_constraints(),
-        "padding failed!"
-    );
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -340,19 +348,20 @@ mod tests {
             vec![],
             vec![],
         ];
-        let joint_matrix = indexer::sum_matrices(&a, &b, &c);
+        let joint_matrix = indexer::sum_matrices(&[&a, &b, &c]);
         let num_non_zero = dbg!(num_non_zero(&joint_matrix));
         let interpolation_domain = EvaluationDomain::new(num_non_zero).unwrap();
         let output_domain = EvaluationDomain::new(2 + 6).unwrap();
         let input_domain = EvaluationDomain::new(2).unwrap();
         let joint_arith = arithmetize_matrix(
             &joint_matrix,
-            &a,
-            &b,
-            &c,
+            &[("a", &a), ("b", &b), ("c", &c)],
+            "",
             interpolation_domain,
             output_domain,
+            output_domain,
             input_domain,
+            None,
         );
         let inverse_map = output_domain
             .elements()
@@ -383,14 +392,14 @@ mod tests {
             let inverse = (eq_poly_vals[&row_val]).inverse().unwrap();
             // we're in transpose land.
 
-            let val_a = joint_arith.val_a.evaluate(&k);
-            let val_b = joint_arith.val_b.evaluate(&k);
-            let val_c = joint_arith.val_c.evaluate(&k);
+            let val_a = joint_arith.val[0].evaluate(&k);
+            let val_b = joint_arith.val[1].evaluate(&k);
+            let val_c = joint_arith.val[2].evaluate(&k);
             assert_eq!(joint_arith.evals_on_K.row[k_index], row_val);
             assert_eq!(joint_arith.evals_on_K.col[k_index], col_val);
-            assert_eq!(joint_arith.evals_on_K.val_a[k_index], val_a);
-            assert_eq!(joint_arith.evals_on_K.val_b[k_index], val_b);
-            assert_eq!(joint_arith.evals_on_K.val_c[k_index], val_c);
+            assert_eq!(joint_arith.evals_on_K.val[0][k_index], val_a);
+            assert_eq!(joint_arith.evals_on_K.val[1][k_index], val_b);
+            assert_eq!(joint_arith.evals_on_K.val[2][k_index], val_c);
             if k_index < num_non_zero {
                 let col = *dbg!(reindexed_inverse_map.get(&row_val).unwrap());
                 let row = *dbg!(inverse_map.get(&col_val).unwrap());
@@ -3,11 +3,18 @@
 use ark_std::collections::BTreeSet;
 
 use crate::ahp::{
-    constraint_systems::{arithmetize_matrix, MatrixArithmetization},
+    ccs::{gate_matrix_names, CcsConstraintSynthesizer, ProductGates},
+    constraint_systems::{
+        arithmetize_matrix, Circuit, MatrixArithmetization, WithGates, WithLookups, R1CS,
+    },
+    lookup::{
+        arithmetize_lookups, lookup_domain, lookup_max_degree, LookupArithmetization,
+        LookupConstraintSynthesizer, LookupTables,
+    },
     AHPForR1CS, Error, LabeledPolynomial,
 };
-use crate::Vec;
-use ark_ff::PrimeField;
+use crate::{format, String, ToString, Vec};
+use ark_ff::{to_bytes, PrimeField};
 use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
 use ark_relations::r1cs::{
     ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisError, SynthesisMode,
@@ -18,10 +25,89 @@ use ark_std::{
     marker::PhantomData,
 };
 use derivative::Derivative;
+use digest::Digest;
 
-use crate::ahp::constraint_systems::{
-    make_matrices_square_for_indexer, num_non_zero, pad_input_for_indexer_and_prover,
-};
+use crate::ahp::constraint_systems::{num_non_zero, pad_input_for_indexer_and_prover};
+
+/// How the indexer arithmetizes the constraint matrices.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ArithmetizationLayout {
+    /// A single arithmetization of A, B and C together, with shared `row`,
+    /// `col` and `row_col` polynomials and one `val` polynomial per matrix.
+    /// The domain `K` has to cover the union of the non-zero entries of the
+    /// three matrices.
+    #[default]
+    Joint,
+    /// A separate arithmetization of each of A, B and C, as in the original
+    /// Marlin. The domain `K` only has to cover the non-zero entries of the
+    /// densest matrix, but the indexer commits to twice as many polynomials
+    /// and the degree of the inner sumcheck polynomial `h_2` triples.
+    PerMatrix,
+}
+
+impl ArithmetizationLayout {
+    /// The prefixes of the labels of the polynomials in each arithmetization
+    /// of `matrices`, together with the matrices that each arithmetization
+    /// covers.
+    pub(crate) fn arithmetizations(&self, matrices: &[String]) -> Vec<(String, Vec<String>)> {
+        match self {
+            ArithmetizationLayout::Joint => vec![(String::new(), matrices.to_vec())],
+            ArithmetizationLayout::PerMatrix => matrices
+                .iter()
+                .map(|name| (format!("{}_", name), vec![name.clone()]))
+                .collect(),
+        }
+    }
+}
+
+impl CanonicalSerialize for ArithmetizationLayout {
+    fn serialize<W: Write>(&self, writer: W) -> Result<(), SerializationError> {
+        (*self as u8).serialize(writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        (*self as u8).serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for ArithmetizationLayout {
+    fn deserialize<R: Read>(reader: R) -> Result<Self, SerializationError> {
+        match u8::deserialize(reader)? {
+            0 => Ok(ArithmetizationLayout::Joint),
+            1 => Ok(ArithmetizationLayout::PerMatrix),
+            _ => Err(SerializationError::InvalidData),
+        }
+    }
+}
+
+/// Options of the indexer.
+#[derive(Copy, Clone, Debug)]
+pub struct IndexerConfig {
+    /// The layout of the arithmetization of the constraint matrices.
+    pub arithmetization: ArithmetizationLayout,
+    /// The number of evaluation queries to each oracle of the prover that
+    /// proofs stay zero-knowledge for. One query suffices for Marlin itself,
+    /// but protocols that open the same oracles more than once, such as
+    /// aggregation schemes, need a larger bound. Indexing fails with
+    /// `Error::InvalidZkBound` if the bound is zero.
+    pub zk_bound: usize,
+    /// Whether to store the evaluations of the index polynomials on a coset
+    /// of `(n + 1) |K|` elements, where `n` is the number of arithmetizations,
+    /// in the index. The prover then computes the inner sumcheck polynomial
+    /// `h_2` with coset FFTs instead of polynomial multiplications, at the
+    /// cost of an index prover key that is several times as large.
+    pub precompute_coset_evals: bool,
+}
+
+impl Default for IndexerConfig {
+    fn default() -> Self {
+        Self {
+            arithmetization: ArithmetizationLayout::default(),
+            zk_bound: 1,
+            precompute_coset_evals: false,
+        }
+    }
+}
 
 /// Information about the index, including the field of definition, the number of
 /// variables, the number of constraints, and the maximum number of non-zero
@@ -33,10 +119,25 @@ pub struct IndexInfo<F> {
     pub num_variables: usize,
     /// The number of constraints.
     pub num_constraints: usize,
-    /// The total number of non-zero entries in the sum of all constraint matrices.
+    /// The number of non-zero entries covered by each arithmetization: that
+    /// of the sum of all constraint matrices in the joint layout, and that of
+    /// the densest matrix in the per-matrix layout.
     pub num_non_zero: usize,
-    /// The number of input elements.
+    /// The number of formatted public inputs, that is, the number of public
+    /// inputs plus one for the constant "one" variable. This is the count
+    /// before the input is padded to the size of the input domain.
     pub num_instance_variables: usize,
+    /// The layout of the arithmetization of the constraint matrices.
+    pub arithmetization: ArithmetizationLayout,
+    /// The number of entries of the combined lookup table, or zero if the
+    /// circuit declares no lookup tables.
+    pub lookup_table_size: usize,
+    /// The number of factors of the product gates, or zero if the circuit
+    /// has none.
+    pub product_gate_degree: usize,
+    /// The number of evaluation queries to each oracle of the prover that
+    /// proofs stay zero-knowledge for.
+    pub zk_bound: usize,
 
     #[doc(hidden)]
     f: PhantomData<F>,
@@ -44,17 +145,26 @@ pub struct IndexInfo<F> {
 
 impl<F> IndexInfo<F> {
     /// Construct a new index info
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         num_variables: usize,
         num_constraints: usize,
         num_non_zero: usize,
         num_instance_variables: usize,
+        arithmetization: ArithmetizationLayout,
+        lookup_table_size: usize,
+        product_gate_degree: usize,
+        zk_bound: usize,
     ) -> Self {
         Self {
             num_variables,
             num_constraints,
             num_non_zero,
             num_instance_variables,
+            arithmetization,
+            lookup_table_size,
+            product_gate_degree,
+            zk_bound,
             f: PhantomData,
         }
     }
@@ -64,7 +174,12 @@ impl<F: PrimeField> ark_ff::ToBytes for IndexInfo<F> {
     fn write<W: Write>(&self, mut w: W) -> ark_std::io::Result<()> {
         (self.num_variables as u64).write(&mut w)?;
         (self.num_constraints as u64).write(&mut w)?;
-        (self.num_non_zero as u64).write(&mut w)
+        (self.num_non_zero as u64).write(&mut w)?;
+        (self.num_instance_variables as u64).write(&mut w)?;
+        (self.arithmetization as u8).write(&mut w)?;
+        (self.lookup_table_size as u64).write(&mut w)?;
+        (self.product_gate_degree as u64).write(&mut w)?;
+        (self.zk_bound as u64).write(&mut w)
     }
 }
 
@@ -72,28 +187,97 @@ impl<F: PrimeField> IndexInfo<F> {
     /// The maximum degree of polynomial required to represent this index in the
     /// the AHP.
     pub fn max_degree(&self) -> usize {
-        AHPForR1CS::<F>::max_degree(self.num_constraints, self.num_variables, self.num_non_zero)
-            .unwrap()
+        let max_degree = AHPForR1CS::<F>::max_degree_with_zk_bound(
+            self.num_constraints,
+            self.num_variables,
+            self.num_non_zero,
+            self.zk_bound,
+        )
+        .unwrap();
+        // The mask polynomial grows with the query bound and with the number
+        // of factors of the products in the outer sumcheck.
+        let h_size = GeneralEvaluationDomain::<F>::compute_size_of_domain(core::cmp::max(
+            self.num_constraints,
+            self.num_variables,
+        ))
+        .unwrap();
+        let mask_poly_degree = AHPForR1CS::<F>::mask_poly_degree(
+            h_size,
+            self.first_round_zk_bound(),
+            self.product_degree(),
+        );
+        // With `n` arithmetizations, the inner sumcheck polynomial `h_2` has
+        // degree `n * (|K| - 1) - 1`.
+        let k_size =
+            GeneralEvaluationDomain::<F>::compute_size_of_domain(self.num_non_zero).unwrap();
+        let num_arithmetizations = self.arithmetizations().len();
+        let max_degree = *[
+            max_degree,
+            mask_poly_degree,
+            num_arithmetizations * (k_size - 1) - 1,
+        ]
+        .iter()
+        .max()
+        .unwrap();
+        match lookup_domain(self).unwrap() {
+            Some(domain_l) => core::cmp::max(
+                max_degree,
+                lookup_max_degree(domain_l.size(), self.zk_bound),
+            ),
+            None => max_degree,
+        }
+    }
+
+    /// Whether the circuit looks values up in fixed tables.
+    pub fn has_lookups(&self) -> bool {
+        self.lookup_table_size != 0
+    }
+
+    /// Whether the circuit has product gates.
+    pub fn has_product_gates(&self) -> bool {
+        self.product_gate_degree != 0
+    }
+
+    /// The names of the constraint matrices: `a`, `b` and `c`, followed by
+    /// the factor matrices of the product gates, if any.
+    pub fn matrix_names(&self) -> Vec<String> {
+        ["a", "b", "c"]
+            .iter()
+            .map(|name| name.to_string())
+            .chain(gate_matrix_names(self.product_gate_degree))
+            .collect()
+    }
+
+    /// The prefixes of the labels of the polynomials in each arithmetization,
+    /// together with the matrices that each arithmetization covers.
+    pub(crate) fn arithmetizations(&self) -> Vec<(String, Vec<String>)> {
+        self.arithmetization.arithmetizations(&self.matrix_names())
+    }
+
+    /// The number of factors of the products of the polynomials `z_M` in the
+    /// outer sumcheck: two for `z_a * z_b`, or more with product gates.
+    pub(crate) fn product_degree(&self) -> usize {
+        core::cmp::max(2, self.product_gate_degree)
+    }
+
+    /// The number of evaluation queries that the oracles of the first round
+    /// are masked for: `zk_bound`, plus one if the lookup argument queries
+    /// `z_a` a second time.
+    pub(crate) fn first_round_zk_bound(&self) -> usize {
+        self.zk_bound + self.has_lookups() as usize
     }
 }
 
 /// Represents a matrix.
 pub type Matrix<F> = Vec<Vec<(F, usize)>>;
 
-pub(crate) fn sum_matrices<F: PrimeField>(
-    a: &Matrix<F>,
-    b: &Matrix<F>,
-    c: &Matrix<F>,
-) -> Vec<Vec<usize>> {
-    a.iter()
-        .zip(b)
-        .zip(c)
-        .map(|((row_a, row_b), row_c)| {
-            row_a
+/// The positions of the non-zero entries of the sum of `matrices`, row by row.
+pub(crate) fn sum_matrices<F: PrimeField>(matrices: &[&Matrix<F>]) -> Vec<Vec<usize>> {
+    (0..matrices[0].len())
+        .map(|r| {
+            matrices
                 .iter()
-                .map(|(_, i)| *i)
-                .chain(row_b.iter().map(|(_, i)| *i))
-                .chain(row_c.iter().map(|(_, i)| *i))
+                .flat_map(|m| m[r].iter().map(|(_, i)| *i))
                 .collect::<BTreeSet<_>>()
                 .into_iter()
                 .collect()
@@ -107,8 +291,8 @@ pub(crate) fn sum_matrices<F: PrimeField>(
 /// This struct contains three kinds of objects:
 /// 1) `index_info` is information about the index, such as the size of the
 ///     public input
-/// 2) `{a,b,c}` are the matrices defining the R1CS instance
-/// 3) `{a,b,c}_star_arith` are structs containing information about A^*, B^*, and C^*,
+/// 2) `{a,b,c}` are the R1CS matrices, and `gates` the factor matrices of any product gates
+/// 3) `arithmetizations` are structs containing information about A^*, B^*, and C^*,
 /// which are matrices defined as `M^*(i, j) = M(j, i) * u_H(j, j)`.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct Index<F: PrimeField> {
@@ -121,9 +305,18 @@ pub struct Index<F: PrimeField> {
     pub b: Matrix<F>,
     /// The C matrix for the R1CS instance
     pub c: Matrix<F>,
+    /// The factor matrices D, E, F, ... of the product gates, if the circuit
+    /// has product gates.
+    pub gates: Vec<Matrix<F>>,
+
+    /// Arithmetizations of the A*, B*, and C* matrices, and of those of the
+    /// product gates: a single one of all matrices in the joint layout, and
+    /// one per matrix in the per-matrix layout.
+    pub arithmetizations: Vec<MatrixArithmetization<F>>,
 
-    /// Joint arithmetization of the A*, B*, and C* matrices.
-    pub joint_arith: MatrixArithmetization<F>,
+    /// The polynomials that encode the lookup tables of the circuit and its
+    /// lookups, if the circuit declares tables.
+    pub lookup: Option<LookupArithmetization<F>>,
 }
 
 impl<F: PrimeField> Index<F> {
@@ -132,94 +325,196 @@ impl<F: PrimeField> Index<F> {
         self.index_info.max_degree()
     }
 
+    /// A digest of the circuit under `D`: the hash of the index info, which
+    /// holds the sizes of the circuit, the arithmetization layout and the zk
+    /// bound, followed by the constraint matrices, the factor matrices of the
+    /// product gates and the lookup tables. Unlike the digest of the verifier
+    /// key, it does not depend on the universal parameters.
+    pub fn circuit_digest<D: Digest>(&self) -> Vec<u8> {
+        let mut bytes = to_bytes![self.index_info].expect("failed to serialize the index info");
+        for matrix in [&self.a, &self.b, &self.c]
+            .iter()
+            .copied()
+            .chain(&self.gates)
+        {
+            matrix
+                .serialize(&mut bytes)
+                .expect("failed to serialize the matrix");
+        }
+        if let Some(lookup) = &self.lookup {
+            lookup
+                .evals_on_L
+                .serialize(&mut bytes)
+                .expect("failed to serialize the lookup tables");
+        }
+        D::digest(&bytes).to_vec()
+    }
+
     /// Iterate over the indexed polynomials.
     pub fn iter(&self) -> impl Iterator<Item = &LabeledPolynomial<F>> {
-        ark_std::vec![
-            &self.joint_arith.row,
-            &self.joint_arith.col,
-            &self.joint_arith.val_a,
-            &self.joint_arith.val_b,
-            &self.joint_arith.val_c,
-            &self.joint_arith.row_col,
-        ]
-        .into_iter()
+        self.arithmetizations
+            .iter()
+            .flat_map(|arith| {
+                ark_std::iter::once(&arith.row)
+                    .chain(ark_std::iter::once(&arith.col))
+                    .chain(&arith.val)
+                    .chain(ark_std::iter::once(&arith.row_col))
+            })
+            .chain(self.lookup.iter().flat_map(|arith| arith.iter()))
     }
 }
 
 impl<F: PrimeField> AHPForR1CS<F> {
-    /// Generate the index for this constraint system.
+    /// Generate the index for this constraint system, with the default
+    /// `IndexerConfig`.
     pub fn index<C: ConstraintSynthesizer<F>>(c: C) -> Result<Index<F>, Error> {
+        Self::index_with_config(c, IndexerConfig::default())
+    }
+
+    /// Generate the index for this constraint system, as specified by `config`.
+    pub fn index_with_config<C: ConstraintSynthesizer<F>>(
+        c: C,
+        config: IndexerConfig,
+    ) -> Result<Index<F>, Error> {
+        Self::index_circuit(R1CS(c), config)
+    }
+
+    /// Generate the index for this constraint system and its lookup tables,
+    /// as specified by `config`.
+    pub fn index_with_lookups<C: LookupConstraintSynthesizer<F>>(
+        c: C,
+        config: IndexerConfig,
+    ) -> Result<Index<F>, Error> {
+        Self::index_circuit(WithLookups(c), config)
+    }
+
+    /// Generate the index for this constraint system and its product gates,
+    /// as specified by `config`.
+    pub fn index_with_gates<C: CcsConstraintSynthesizer<F>>(
+        c: C,
+        config: IndexerConfig,
+    ) -> Result<Index<F>, Error> {
+        Self::index_circuit(WithGates(c), config)
+    }
+
+    pub(crate) fn index_circuit<C: Circuit<F>>(
+        c: C,
+        config: IndexerConfig,
+    ) -> Result<Index<F>, Error> {
         let index_time = start_timer!(|| "AHP::Index");
+        if config.zk_bound == 0 {
+            return Err(Error::InvalidZkBound);
+        }
 
         let constraint_time = start_timer!(|| "Generating constraints");
         let ics = ConstraintSystem::new_ref();
         ics.set_optimization_goal(OptimizationGoal::Weight);
         ics.set_mode(SynthesisMode::Setup);
-        c.generate_constraints(ics.clone())?;
+        let mut lookup_tables = LookupTables::new();
+        let mut gates = ProductGates::new();
+        c.synthesize(ics.clone(), &mut lookup_tables, &mut gates)?;
         end_timer!(constraint_time);
 
-        let padding_time = start_timer!(|| "Padding matrices to make them square");
+        let num_instance_variables = ics.num_instance_variables();
+
+        let padding_time = start_timer!(|| "Padding the public input");
         pad_input_for_indexer_and_prover(ics.clone());
         end_timer!(padding_time);
         let matrix_processing_time = start_timer!(|| "Processing matrices");
         ics.finalize();
-        make_matrices_square_for_indexer(ics.clone());
         let matrices = ics.to_matrices().expect("should not be `None`");
-        let joint_matrix = sum_matrices(&matrices.a, &matrices.b, &matrices.c);
-        let num_non_zero_val = num_non_zero(&joint_matrix);
         let (mut a, mut b, mut c) = (matrices.a, matrices.b, matrices.c);
+        let gate_matrices = gates.merge_into(&mut a, &mut b, &mut c);
+
+        let mut index_info = IndexInfo {
+            num_variables: 0,
+            num_constraints: 0,
+            num_non_zero: 0,
+            num_instance_variables,
+            arithmetization: config.arithmetization,
+            lookup_table_size: lookup_tables.combined_table_size(),
+            product_gate_degree: gates.degree(),
+            zk_bound: config.zk_bound,
+
+            f: PhantomData,
+        };
+        let all_matrices: Vec<_> = vec![&a, &b, &c].into_iter().chain(&gate_matrices).collect();
+        let matrix_names = index_info.matrix_names();
+        let matrix = |name: &String| {
+            let position = matrix_names.iter().position(|n| n == name).unwrap();
+            all_matrices[position]
+        };
+        let arithmetizations = index_info.arithmetizations();
+        let joint_matrices: Vec<_> = arithmetizations
+            .iter()
+            .map(|(_, names)| {
+                let matrices: Vec<_> = names.iter().map(matrix).collect();
+                sum_matrices(&matrices)
+            })
+            .collect();
+        let num_non_zero_val = joint_matrices
+            .iter()
+            .map(|m| num_non_zero(m))
+            .max()
+            .unwrap();
         end_timer!(matrix_processing_time);
 
         let (num_formatted_input_variables, num_witness_variables, num_constraints, num_non_zero) = (
             ics.num_instance_variables(),
             ics.num_witness_variables(),
-            ics.num_constraints(),
+            a.len(),
             num_non_zero_val,
         );
         let num_variables = num_formatted_input_variables + num_witness_variables;
+        index_info.num_variables = num_variables;
+        index_info.num_constraints = num_constraints;
+        index_info.num_non_zero = num_non_zero;
 
-        if num_constraints != num_formatted_input_variables + num_witness_variables {
-            eprintln!(
-                "number of (formatted) input_variables: {}",
-                num_formatted_input_variables
-            );
-            eprintln!("number of witness_variables: {}", num_witness_variables);
-            eprintln!("number of num_constraints: {}", num_constraints);
-            eprintln!("number of num_non_zero: {}", num_non_zero);
-            return Err(Error::NonSquareMatrix);
-        }
-
-        if !Self::num_formatted_public_inputs_is_admissible(num_formatted_input_variables) {
-            return Err(Error::InvalidPublicInputLength);
-        }
-
-        let index_info = IndexInfo {
-            num_variables,
-            num_constraints,
-            num_non_zero,
-            num_instance_variables: num_formatted_input_variables,
-
-            f: PhantomData,
-        };
-
-        let domain_h = GeneralEvaluationDomain::new(num_constraints)
+        let constraint_domain = GeneralEvaluationDomain::new(num_constraints)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let variable_domain = GeneralEvaluationDomain::new(num_variables)
             .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
         let domain_k = GeneralEvaluationDomain::new(num_non_zero)
             .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
         let x_domain = GeneralEvaluationDomain::new(num_formatted_input_variables)
             .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        // The numerator `a - b * f` of the inner sumcheck has degree
+        // `(n + 1) (|K| - 1)`, which this coset is large enough for.
+        let coset_domain = if config.precompute_coset_evals {
+            let size = (arithmetizations.len() + 1) * domain_k.size();
+            Some(
+                GeneralEvaluationDomain::new(size)
+                    .ok_or(SynthesisError::PolynomialDegreeTooLarge)?,
+            )
+        } else {
+            None
+        };
 
-        let joint_arithmetization_time = start_timer!(|| "Arithmetizing all matrices");
-        let joint_arith = arithmetize_matrix(
-            &joint_matrix,
-            &mut a,
-            &mut b,
-            &mut c,
-            domain_k,
-            domain_h,
-            x_domain,
-        );
-        end_timer!(joint_arithmetization_time);
+        let arithmetization_time = start_timer!(|| "Arithmetizing all matrices");
+        let arithmetizations = arithmetizations
+            .iter()
+            .zip(&joint_matrices)
+            .map(|((prefix, names), joint_matrix)| {
+                let matrices: Vec<_> = names
+                    .iter()
+                    .map(|name| (name.as_str(), matrix(name)))
+                    .collect();
+                arithmetize_matrix(
+                    joint_matrix,
+                    &matrices,
+                    prefix,
+                    domain_k,
+                    constraint_domain,
+                    variable_domain,
+                    x_domain,
+                    coset_domain,
+                )
+            })
+            .collect();
+        end_timer!(arithmetization_time);
+
+        let lookup = lookup_domain(&index_info)?
+            .map(|domain_l| arithmetize_lookups(&lookup_tables, constraint_domain, domain_l));
 
         end_timer!(index_time);
         Ok(Index {
@@ -228,8 +523,10 @@ impl<F: PrimeField> AHPForR1CS<F> {
             a,
             b,
             c,
+            gates: gate_matrices,
 
-            joint_arith,
+            arithmetizations,
+            lookup,
         })
     }
 }
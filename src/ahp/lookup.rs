@@ -0,0 +1,248 @@
+#![allow(non_snake_case)]
+
+use crate::ahp::{indexer::IndexInfo, LabeledPolynomial};
+use crate::{BTreeMap, ToString, Vec};
+use ark_ff::{Field, PrimeField};
+use ark_poly::{
+    univariate::DensePolynomial, EvaluationDomain, Evaluations as EvaluationsOnDomain,
+    GeneralEvaluationDomain, UVPolynomial,
+};
+use ark_relations::{
+    lc,
+    r1cs::{ConstraintSystemRef, LinearCombination, SynthesisError, Variable},
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::{
+    io::{Read, Write},
+    vec,
+};
+use derivative::Derivative;
+
+/// Fixed tables that a circuit looks values up in, together with the
+/// constraints that perform the lookups.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+pub struct LookupTables<F: Field> {
+    tables: Vec<Vec<F>>,
+    /// Pairs of a constraint whose `A` row is looked up, and of the table
+    /// that it is looked up in.
+    lookups: Vec<(usize, usize)>,
+}
+
+impl<F: Field> LookupTables<F> {
+    /// Construct an empty set of tables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a table with the given entries, and return its index.
+    pub fn add_table(&mut self, entries: Vec<F>) -> usize {
+        assert!(!entries.is_empty(), "lookup tables must not be empty");
+        self.tables.push(entries);
+        self.tables.len() - 1
+    }
+
+    /// Enforce that the value of `lc` is an entry of the table at index
+    /// `table`. This costs a single constraint, `lc * 1 = lc`, whose `A`
+    /// row is then looked up.
+    pub fn enforce_lookup(
+        &mut self,
+        cs: &ConstraintSystemRef<F>,
+        lc: LinearCombination<F>,
+        table: usize,
+    ) -> Result<(), SynthesisError> {
+        assert!(table < self.tables.len(), "unknown lookup table");
+        let constraint = cs.num_constraints();
+        cs.enforce_constraint(lc.clone(), lc!() + Variable::One, lc)?;
+        self.lookups.push((constraint, table));
+        Ok(())
+    }
+
+    /// The size of the combined table that the lookup argument runs over: an
+    /// entry reserved for the constraints that are not lookups, followed by
+    /// the entries of every table. This is zero if there are no tables.
+    pub fn combined_table_size(&self) -> usize {
+        if self.tables.is_empty() {
+            0
+        } else {
+            1 + self.tables.iter().map(Vec::len).sum::<usize>()
+        }
+    }
+}
+
+/// A circuit whose constraints may include lookups into fixed tables.
+pub trait LookupConstraintSynthesizer<F: Field> {
+    /// Generate the constraints of the circuit in `cs`, and declare its
+    /// tables and lookups in `tables`.
+    fn generate_constraints_and_lookups(
+        self,
+        cs: ConstraintSystemRef<F>,
+        tables: &mut LookupTables<F>,
+    ) -> Result<(), SynthesisError>;
+}
+
+/// Evaluations of the indexed lookup polynomials over the lookup domain `L`.
+#[derive(Derivative, CanonicalSerialize, CanonicalDeserialize)]
+#[derivative(Debug(bound = "F: PrimeField"), Clone(bound = "F: PrimeField"))]
+pub struct LookupEvals<F: PrimeField> {
+    /// Evaluations of the `lookup_selector` polynomial.
+    pub selector: EvaluationsOnDomain<F>,
+    /// Evaluations of the `lookup_tag` polynomial.
+    pub tag: EvaluationsOnDomain<F>,
+    /// Evaluations of the `lookup_table_value` polynomial.
+    pub table_value: EvaluationsOnDomain<F>,
+    /// Evaluations of the `lookup_table_tag` polynomial.
+    pub table_tag: EvaluationsOnDomain<F>,
+}
+
+/// The indexed polynomials of the lookup argument, over the lookup domain `L`.
+///
+/// The tables are combined into a single one, in which an entry `v` of the
+/// `j`-th table becomes `(v, j + 1)`; the entry `(0, 0)` is reserved for the
+/// constraints that are not lookups. The verifier compresses pairs `(v, tag)`
+/// into `v + zeta * tag` with a random `zeta`.
+#[derive(Derivative, CanonicalSerialize, CanonicalDeserialize)]
+#[derivative(Debug(bound = "F: PrimeField"), Clone(bound = "F: PrimeField"))]
+pub struct LookupArithmetization<F: PrimeField> {
+    /// One at the elements of `L` that correspond to looked-up constraints,
+    /// and zero elsewhere.
+    pub selector: LabeledPolynomial<F>,
+    /// The tag of the table that each looked-up constraint is looked up in,
+    /// and zero elsewhere.
+    pub tag: LabeledPolynomial<F>,
+    /// The values of the combined table.
+    pub table_value: LabeledPolynomial<F>,
+    /// The tags of the combined table.
+    pub table_tag: LabeledPolynomial<F>,
+
+    /// Evaluations of the above polynomials on `L`.
+    pub evals_on_L: LookupEvals<F>,
+}
+
+impl<F: PrimeField> LookupArithmetization<F> {
+    /// Iterate over the indexed lookup polynomials.
+    pub fn iter(&self) -> impl Iterator<Item = &LabeledPolynomial<F>> {
+        vec![
+            &self.selector,
+            &self.tag,
+            &self.table_value,
+            &self.table_tag,
+        ]
+        .into_iter()
+    }
+}
+
+/// The domain `L` of the lookup argument, if the index has lookups. It is
+/// large enough for both the constraints and the combined table.
+pub(crate) fn lookup_domain<F: PrimeField>(
+    info: &IndexInfo<F>,
+) -> Result<Option<GeneralEvaluationDomain<F>>, SynthesisError> {
+    if info.lookup_table_size == 0 {
+        return Ok(None);
+    }
+    let size = core::cmp::max(info.num_constraints, info.lookup_table_size);
+    GeneralEvaluationDomain::new(size)
+        .map(Some)
+        .ok_or(SynthesisError::PolynomialDegreeTooLarge)
+}
+
+/// The maximum degree of the polynomials of the lookup argument over a lookup
+/// domain of size `l_size`, for the query bound `zk_bound`: that of the
+/// quotient of the grand product check.
+pub(crate) fn lookup_max_degree(l_size: usize, zk_bound: usize) -> usize {
+    core::cmp::max(3 * l_size + 2 * zk_bound - 1, 2 * l_size + 3 * zk_bound + 1)
+}
+
+pub(crate) fn arithmetize_lookups<F: PrimeField>(
+    tables: &LookupTables<F>,
+    constraint_domain: GeneralEvaluationDomain<F>,
+    lookup_domain: GeneralEvaluationDomain<F>,
+) -> LookupArithmetization<F> {
+    let lookup_time = start_timer!(|| "Computing lookup polynomials");
+    let l_size = lookup_domain.size();
+    let ratio = l_size / constraint_domain.size();
+
+    let mut selector = vec![F::zero(); l_size];
+    let mut tag = vec![F::zero(); l_size];
+    for &(constraint, table) in &tables.lookups {
+        selector[constraint * ratio] = F::one();
+        tag[constraint * ratio] = F::from((table + 1) as u64);
+    }
+
+    let mut table_value = vec![F::zero()];
+    let mut table_tag = vec![F::zero()];
+    for (j, table) in tables.tables.iter().enumerate() {
+        table_value.extend_from_slice(table);
+        table_tag.extend(vec![F::from((j + 1) as u64); table.len()]);
+    }
+    // Pad the table by repeating its last entry.
+    let (last_value, last_tag) = (*table_value.last().unwrap(), *table_tag.last().unwrap());
+    table_value.resize(l_size, last_value);
+    table_tag.resize(l_size, last_tag);
+
+    let evals_on_L = LookupEvals {
+        selector: EvaluationsOnDomain::from_vec_and_domain(selector, lookup_domain),
+        tag: EvaluationsOnDomain::from_vec_and_domain(tag, lookup_domain),
+        table_value: EvaluationsOnDomain::from_vec_and_domain(table_value, lookup_domain),
+        table_tag: EvaluationsOnDomain::from_vec_and_domain(table_tag, lookup_domain),
+    };
+    let interpolate = |label: &str, evals: &EvaluationsOnDomain<F>| {
+        LabeledPolynomial::new(label.to_string(), evals.clone().interpolate(), None, None)
+    };
+    let arith = LookupArithmetization {
+        selector: interpolate("lookup_selector", &evals_on_L.selector),
+        tag: interpolate("lookup_tag", &evals_on_L.tag),
+        table_value: interpolate("lookup_table_value", &evals_on_L.table_value),
+        table_tag: interpolate("lookup_table_tag", &evals_on_L.table_tag),
+        evals_on_L,
+    };
+    end_timer!(lookup_time);
+    arith
+}
+
+/// Sort the entries of `f` and of `t` by their first position in `t`, and
+/// split the result into its entries at even and at odd positions. Returns
+/// `None` if some entry of `f` is not in `t`.
+pub(crate) fn sort_by_table<F: PrimeField>(f: &[F], t: &[F]) -> Option<(Vec<F>, Vec<F>)> {
+    let mut positions = BTreeMap::new();
+    for (i, entry) in t.iter().enumerate() {
+        positions.entry(*entry).or_insert(i);
+    }
+    let mut counts = vec![1usize; t.len()];
+    for entry in f {
+        counts[*positions.get(entry)?] += 1;
+    }
+    let mut evens = Vec::with_capacity(t.len());
+    let mut odds = Vec::with_capacity(t.len());
+    let sorted = t
+        .iter()
+        .zip(counts)
+        .flat_map(|(entry, count)| ark_std::iter::repeat(*entry).take(count));
+    for (i, entry) in sorted.enumerate() {
+        if i % 2 == 0 {
+            evens.push(entry);
+        } else {
+            odds.push(entry);
+        }
+    }
+    Some((evens, odds))
+}
+
+/// The polynomial `p(omega * X)`, for the generator `omega` of `domain`.
+pub(crate) fn shift<F: PrimeField>(
+    p: &DensePolynomial<F>,
+    domain: GeneralEvaluationDomain<F>,
+) -> DensePolynomial<F> {
+    let omega = domain.element(1);
+    let mut power = F::one();
+    let coeffs = p
+        .coeffs
+        .iter()
+        .map(|c| {
+            let c = *c * power;
+            power *= omega;
+            c
+        })
+        .collect();
+    DensePolynomial::from_coefficients_vec(coeffs)
+}
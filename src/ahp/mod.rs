@@ -1,6 +1,6 @@
-use crate::{String, ToString, Vec};
+use crate::{BTreeMap, String, ToString, Vec};
 use ark_ff::{Field, PrimeField};
-use ark_poly::univariate::DensePolynomial;
+use ark_poly::{univariate::DensePolynomial, Polynomial, UVPolynomial};
 use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
 use ark_poly_commit::{LCTerm, LinearCombination};
 use ark_relations::r1cs::SynthesisError;
@@ -9,11 +9,23 @@ use ark_std::{borrow::Borrow, cfg_iter_mut, format, marker::PhantomData, vec};
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
+use ccs::gate_matrix_names;
+use indexer::{ArithmetizationLayout, IndexInfo};
+
+/// Describes the product gates that extend R1CS constraints to a
+/// customizable constraint system.
+pub mod ccs;
 pub(crate) mod constraint_systems;
 /// Describes data structures and the algorithms used by the AHP indexer.
 pub mod indexer;
+/// Describes the lookup tables that circuits declare, and the data structures
+/// of the lookup argument.
+pub mod lookup;
 /// Describes data structures and the algorithms used by the AHP prover.
 pub mod prover;
+/// An optional disk cache for the vectors that the prover keeps between
+/// rounds.
+pub mod spill;
 /// Describes data structures and the algorithms used by the AHP verifier.
 pub mod verifier;
 
@@ -21,21 +33,40 @@ pub mod verifier;
 pub type LabeledPolynomial<F> = ark_poly_commit::LabeledPolynomial<F, DensePolynomial<F>>;
 
 /// The algebraic holographic proof defined in [CHMMVW19](https://eprint.iacr.org/2019/1047).
-/// Currently, this AHP only supports inputs of size one
-/// less than a power of 2 (i.e., of the form 2^n - 1).
 pub struct AHPForR1CS<F: Field> {
     field: PhantomData<F>,
 }
 
 impl<F: PrimeField> AHPForR1CS<F> {
-    /// The labels for the polynomials output by the AHP indexer.
+    /// The labels for the polynomials output by the AHP indexer with the
+    /// joint arithmetization layout, for a circuit without product gates.
     #[rustfmt::skip]
     pub const INDEXER_POLYNOMIALS: [&'static str; 6] = [
         // Polynomials for M
         "row", "col", "a_val", "b_val", "c_val", "row_col",
     ];
 
-    /// The labels for the polynomials output by the AHP prover.
+    /// The labels for the polynomials output by the AHP indexer with the
+    /// per-matrix arithmetization layout, for a circuit without product gates.
+    #[rustfmt::skip]
+    pub const PER_MATRIX_INDEXER_POLYNOMIALS: [&'static str; 12] = [
+        // Polynomials for A
+        "a_row", "a_col", "a_val", "a_row_col",
+        // Polynomials for B
+        "b_row", "b_col", "b_val", "b_row_col",
+        // Polynomials for C
+        "c_row", "c_col", "c_val", "c_row_col",
+    ];
+
+    /// The labels for the polynomials output by the AHP indexer for the
+    /// lookup argument, if the circuit declares lookup tables.
+    #[rustfmt::skip]
+    pub const LOOKUP_INDEXER_POLYNOMIALS: [&'static str; 4] = [
+        "lookup_selector", "lookup_tag", "lookup_table_value", "lookup_table_tag",
+    ];
+
+    /// The labels for the polynomials output by the AHP prover. With product
+    /// gates, the prover also sends `z_d`, `z_e`, ... after `z_b`.
     #[rustfmt::skip]
     pub const PROVER_POLYNOMIALS: [&'static str; 9] = [
         // First sumcheck
@@ -44,24 +75,68 @@ impl<F: PrimeField> AHPForR1CS<F> {
         "g_2", "h_2",
     ];
 
-    /// THe linear combinations that are statically known to evaluate to zero.
-    pub const LC_WITH_ZERO_EVAL: [&'static str; 2] = ["inner_sumcheck", "outer_sumcheck"];
+    /// The labels for the polynomials output by the AHP prover for the lookup
+    /// argument, if the circuit declares lookup tables.
+    #[rustfmt::skip]
+    pub const LOOKUP_PROVER_POLYNOMIALS: [&'static str; 4] = [
+        // Second round: the sorted vector
+        "lookup_s_1", "lookup_s_2",
+        // Third round: the grand product
+        "lookup_z", "lookup_quotient",
+    ];
 
-    pub(crate) fn polynomial_labels() -> impl Iterator<Item = String> {
-        Self::INDEXER_POLYNOMIALS
-            .iter()
-            .chain(&Self::PROVER_POLYNOMIALS)
-            .map(|s| s.to_string())
-    }
+    /// THe linear combinations that are statically known to evaluate to zero.
+    pub const LC_WITH_ZERO_EVAL: [&'static str; 4] = [
+        "inner_sumcheck",
+        "outer_sumcheck",
+        "lookup_check",
+        "lookup_boundary",
+    ];
 
-    /// Check that the (formatted) public input is of the form 2^n for some integer n.
-    pub fn num_formatted_public_inputs_is_admissible(num_inputs: usize) -> bool {
-        num_inputs.count_ones() == 1
+    /// The labels for the polynomials output by the AHP indexer for the index
+    /// described by `info`.
+    pub fn indexer_polynomials(info: &IndexInfo<F>) -> impl Iterator<Item = String> {
+        let matrices: Vec<String> = info
+            .arithmetizations()
+            .into_iter()
+            .flat_map(|(prefix, names)| {
+                ark_std::iter::once(format!("{}row", prefix))
+                    .chain(ark_std::iter::once(format!("{}col", prefix)))
+                    .chain(names.into_iter().map(|name| format!("{}_val", name)))
+                    .chain(ark_std::iter::once(format!("{}row_col", prefix)))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let lookups: &[&str] = if info.has_lookups() {
+            &Self::LOOKUP_INDEXER_POLYNOMIALS
+        } else {
+            &[]
+        };
+        matrices
+            .into_iter()
+            .chain(lookups.iter().map(|s| s.to_string()))
     }
 
-    /// Check that the (formatted) public input is of the form 2^n for some integer n.
-    pub fn formatted_public_input_is_admissible(input: &[F]) -> bool {
-        Self::num_formatted_public_inputs_is_admissible(input.len())
+    pub(crate) fn polynomial_labels(info: &IndexInfo<F>) -> impl Iterator<Item = String> {
+        let (second_round_lookups, third_round_lookups) = if info.has_lookups() {
+            Self::LOOKUP_PROVER_POLYNOMIALS.split_at(2)
+        } else {
+            (&[][..], &[][..])
+        };
+        let (first_round_z, rest) = Self::PROVER_POLYNOMIALS.split_at(3);
+        let (first_and_second_rounds, third_round) = rest.split_at(4);
+        let z_gates = gate_matrix_names(info.product_gate_degree).map(|name| format!("z_{}", name));
+        Self::indexer_polynomials(info)
+            .chain(first_round_z.iter().map(|s| s.to_string()))
+            .chain(z_gates)
+            .chain(
+                first_and_second_rounds
+                    .iter()
+                    .chain(second_round_lookups)
+                    .chain(third_round)
+                    .chain(third_round_lookups)
+                    .map(|s| s.to_string()),
+            )
     }
 
     /// The maximum degree of polynomials produced by the indexer and prover
@@ -73,11 +148,23 @@ impl<F: PrimeField> AHPForR1CS<F> {
         num_variables: usize,
         num_non_zero: usize,
     ) -> Result<usize, Error> {
-        let padded_matrix_dim =
-            constraint_systems::padded_matrix_dim(num_variables, num_constraints);
-        let zk_bound = 1;
-        let domain_h_size = GeneralEvaluationDomain::<F>::compute_size_of_domain(padded_matrix_dim)
-            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        Self::max_degree_with_zk_bound(num_constraints, num_variables, num_non_zero, 1)
+    }
+
+    /// The maximum degree of polynomials produced by the indexer and prover
+    /// of this protocol, when proofs stay zero-knowledge for `zk_bound`
+    /// evaluation queries to each oracle.
+    pub fn max_degree_with_zk_bound(
+        num_constraints: usize,
+        num_variables: usize,
+        num_non_zero: usize,
+        zk_bound: usize,
+    ) -> Result<usize, Error> {
+        let domain_h_size = GeneralEvaluationDomain::<F>::compute_size_of_domain(core::cmp::max(
+            num_constraints,
+            num_variables,
+        ))
+        .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
         let domain_k_size = GeneralEvaluationDomain::<F>::compute_size_of_domain(num_non_zero)
             .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
         Ok(*[
@@ -92,12 +179,23 @@ impl<F: PrimeField> AHPForR1CS<F> {
         .unwrap())
     }
 
+    /// The degree of the mask polynomial of the outer sumcheck over a domain
+    /// `H` of size `h_size`, for the query bound `zk_bound` and products of
+    /// `product_degree` polynomials `z_M`: that of `r(alpha, X)` times such a
+    /// product.
+    pub(crate) fn mask_poly_degree(h_size: usize, zk_bound: usize, product_degree: usize) -> usize {
+        h_size - 1 + product_degree * (h_size + zk_bound - 1)
+    }
+
     /// Get all the strict degree bounds enforced in the AHP.
-    pub fn get_degree_bounds(info: &indexer::IndexInfo<F>) -> [usize; 2] {
+    pub fn get_degree_bounds(info: &IndexInfo<F>) -> [usize; 2] {
         let mut degree_bounds = [0usize; 2];
-        let num_constraints = info.num_constraints;
         let num_non_zero = info.num_non_zero;
-        let h_size = GeneralEvaluationDomain::<F>::compute_size_of_domain(num_constraints).unwrap();
+        let h_size = GeneralEvaluationDomain::<F>::compute_size_of_domain(core::cmp::max(
+            info.num_constraints,
+            info.num_variables,
+        ))
+        .unwrap();
         let k_size = GeneralEvaluationDomain::<F>::compute_size_of_domain(num_non_zero).unwrap();
 
         degree_bounds[0] = h_size - 2;
@@ -116,21 +214,26 @@ impl<F: PrimeField> AHPForR1CS<F> {
         E: EvaluationsProvider<F>,
     {
         let domain_h = state.domain_h;
+        let constraint_domain = state.constraint_domain;
+        let variable_domain = state.variable_domain;
         let domain_k = state.domain_k;
         let k_size = domain_k.size_as_field_element();
 
+        let x_domain = state.domain_x;
+
         let public_input = constraint_systems::format_public_input(public_input);
-        if !Self::formatted_public_input_is_admissible(&public_input) {
+        if public_input.len() != state.index_info.num_instance_variables {
             return Err(Error::InvalidPublicInputLength);
         }
-        let x_domain = GeneralEvaluationDomain::new(public_input.len())
-            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
 
         let first_round_msg = state.first_round_msg.unwrap();
         let alpha = first_round_msg.alpha;
         let eta_a = first_round_msg.eta_a;
         let eta_b = first_round_msg.eta_b;
         let eta_c = first_round_msg.eta_c;
+        let matrix_names = state.index_info.matrix_names();
+        let gate_names = &matrix_names[3..];
+        let gate_etas = first_round_msg.gate_etas(gate_names.len());
 
         let beta = state.second_round_msg.unwrap().beta;
         let gamma = state.gamma.unwrap();
@@ -142,8 +245,13 @@ impl<F: PrimeField> AHPForR1CS<F> {
         let g_1 = LinearCombination::new("g_1", vec![(F::one(), "g_1")]);
         let t = LinearCombination::new("t", vec![(F::one(), "t")]);
 
-        let r_alpha_at_beta = domain_h.eval_unnormalized_bivariate_lagrange_poly(alpha, beta);
-        let v_H_at_alpha = domain_h.evaluate_vanishing_polynomial(alpha);
+        // The outer sumcheck runs over `H`, the larger of the constraint and
+        // variable domains. The terms that are defined over the smaller one
+        // are restricted to it by its selector.
+        let r_alpha_at_beta = constraint_domain
+            .eval_unnormalized_bivariate_lagrange_poly(alpha, beta)
+            * evaluate_selector_polynomial(domain_h, constraint_domain, beta);
+        let selector_at_beta = evaluate_selector_polynomial(domain_h, variable_domain, beta);
         let v_H_at_beta = domain_h.evaluate_vanishing_polynomial(beta);
         let v_X_at_beta = x_domain.evaluate_vanishing_polynomial(beta);
 
@@ -151,6 +259,29 @@ impl<F: PrimeField> AHPForR1CS<F> {
         let t_at_beta = evals.get_lc_eval(&t, beta)?;
         let g_1_at_beta = evals.get_lc_eval(&g_1, beta)?;
 
+        // With product gates, `z_c` is the virtual oracle
+        // `z_a * z_b + prod_k z_k` for the factor matrices `k`, and each
+        // `z_k` also enters the sum of the linchecks.
+        let z_gates: Vec<_> = gate_names
+            .iter()
+            .map(|name| {
+                let label = format!("z_{}", name);
+                LinearCombination::new(label.clone(), vec![(F::one(), label)])
+            })
+            .collect();
+        let mut gates_at_beta = F::zero();
+        if !z_gates.is_empty() {
+            let mut product = F::one();
+            for (z_k, eta_k) in z_gates.iter().zip(&gate_etas) {
+                let z_k_at_beta = evals.get_lc_eval(z_k, beta)?;
+                product *= z_k_at_beta;
+                gates_at_beta += *eta_k * z_k_at_beta;
+            }
+            gates_at_beta += eta_c * product;
+        }
+
+        // The prover pads the input with zeros up to the size of `X`, so only
+        // the Lagrange coefficients of the actual inputs contribute to `x(beta)`.
         let x_at_beta = x_domain
             .evaluate_all_lagrange_coefficients(beta)
             .into_iter()
@@ -165,10 +296,10 @@ impl<F: PrimeField> AHPForR1CS<F> {
                 (F::one(), "mask_poly".into()),
 
                 (r_alpha_at_beta * (eta_a + eta_c * z_b_at_beta), "z_a".into()),
-                (r_alpha_at_beta * eta_b * z_b_at_beta, LCTerm::One),
+                (r_alpha_at_beta * (eta_b * z_b_at_beta + gates_at_beta), LCTerm::One),
 
-                (-t_at_beta * v_X_at_beta, "w".into()),
-                (-t_at_beta * x_at_beta, LCTerm::One),
+                (-t_at_beta * selector_at_beta * v_X_at_beta, "w".into()),
+                (-t_at_beta * selector_at_beta * x_at_beta, LCTerm::One),
 
                 (-v_H_at_beta, "h_1".into()),
                 (-beta * g_1_at_beta, LCTerm::One),
@@ -180,6 +311,7 @@ impl<F: PrimeField> AHPForR1CS<F> {
         linear_combinations.push(g_1);
         linear_combinations.push(t);
         linear_combinations.push(outer_sumcheck);
+        linear_combinations.extend(z_gates);
 
         //  Inner sumcheck:
         let beta_alpha = beta * alpha;
@@ -189,25 +321,88 @@ impl<F: PrimeField> AHPForR1CS<F> {
 
         let v_K_at_gamma = domain_k.evaluate_vanishing_polynomial(gamma);
 
-        let mut a = LinearCombination::new(
-            "a_poly",
-            vec![(eta_a, "a_val"), (eta_b, "b_val"), (eta_c, "c_val")],
-        );
-        a *= v_H_at_alpha * v_H_at_beta;
-
-        let mut b = LinearCombination::new(
-            "denom",
-            vec![
-                (beta_alpha, LCTerm::One),
-                (-alpha, "row".into()),
-                (-beta, "col".into()),
-                (F::one(), "row_col".into()),
-            ],
-        );
-        b *= gamma * g_2_at_gamma + &(t_at_beta / k_size);
-
-        let mut inner_sumcheck = a;
-        inner_sumcheck -= &b;
+        let v_R_at_alpha_v_C_at_beta = constraint_domain.evaluate_vanishing_polynomial(alpha)
+            * variable_domain.evaluate_vanishing_polynomial(beta);
+        let denom = |prefix: &str| {
+            LinearCombination::new(
+                format!("{}denom", prefix),
+                vec![
+                    (beta_alpha, LCTerm::One),
+                    (-alpha, format!("{}row", prefix).into()),
+                    (-beta, format!("{}col", prefix).into()),
+                    (F::one(), format!("{}row_col", prefix).into()),
+                ],
+            )
+        };
+
+        let etas: Vec<F> = [eta_a, eta_b, eta_c]
+            .iter()
+            .chain(&gate_etas)
+            .copied()
+            .collect();
+        let val = |name: &String| format!("{}_val", name);
+        let mut inner_sumcheck = match state.index_info.arithmetization {
+            ArithmetizationLayout::Joint => {
+                let mut a = LinearCombination::new(
+                    "a_poly",
+                    etas.iter()
+                        .zip(&matrix_names)
+                        .map(|(eta, name)| (*eta, val(name)))
+                        .collect::<Vec<_>>(),
+                );
+                a *= v_R_at_alpha_v_C_at_beta;
+
+                let mut b = denom("");
+                b *= gamma * g_2_at_gamma + t_at_beta / k_size;
+
+                a -= &b;
+                a
+            }
+            ArithmetizationLayout::PerMatrix => {
+                // The denominators of the matrices are opened at `gamma`, so
+                // that the inner sumcheck stays linear in the committed
+                // polynomials:
+                //   a(X) := sum_M (eta_M v_R(alpha) v_C(beta) val_M(X) prod_{N != M} b_N(X))
+                //   b(X) := prod_M b_M(X)
+                let denoms: Vec<_> = matrix_names
+                    .iter()
+                    .map(|name| denom(&format!("{}_", name)))
+                    .collect();
+                let denoms_at_gamma = denoms
+                    .iter()
+                    .map(|denom| evals.get_lc_eval(denom, gamma))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let b_at_gamma: F = denoms_at_gamma.iter().product();
+
+                let mut a = LinearCombination::new(
+                    "a_poly",
+                    etas.iter()
+                        .zip(&matrix_names)
+                        .enumerate()
+                        .map(|(m, (eta, name))| {
+                            let others: F = denoms_at_gamma
+                                .iter()
+                                .enumerate()
+                                .filter(|(n, _)| *n != m)
+                                .map(|(_, denom_at_gamma)| *denom_at_gamma)
+                                .product();
+                            (*eta * others, val(name))
+                        })
+                        .collect::<Vec<_>>(),
+                );
+                a *= v_R_at_alpha_v_C_at_beta;
+                a -= &LinearCombination::new(
+                    "b_poly",
+                    vec![(
+                        b_at_gamma * (gamma * g_2_at_gamma + t_at_beta / k_size),
+                        LCTerm::One,
+                    )],
+                );
+
+                linear_combinations.extend(denoms);
+                a
+            }
+        };
         inner_sumcheck -= &LinearCombination::new("h_2", vec![(v_K_at_gamma, "h_2")]);
 
         inner_sumcheck.label = "inner_sumcheck".into();
@@ -216,9 +411,92 @@ impl<F: PrimeField> AHPForR1CS<F> {
         linear_combinations.push(g_2);
         linear_combinations.push(inner_sumcheck);
 
+        if let Some(lookup_domain) = state.lookup_domain {
+            linear_combinations.extend(Self::construct_lookup_linear_combinations(
+                evals,
+                state,
+                lookup_domain,
+            )?);
+        }
+
         linear_combinations.sort_by(|a, b| a.label.cmp(&b.label));
         Ok(linear_combinations)
     }
+
+    /// Construct the linear combinations that check the lookup argument over
+    /// the lookup domain `L`.
+    #[allow(non_snake_case)]
+    fn construct_lookup_linear_combinations<E>(
+        evals: &E,
+        state: &verifier::VerifierState<F>,
+        lookup_domain: GeneralEvaluationDomain<F>,
+    ) -> Result<Vec<LinearCombination<F>>, Error>
+    where
+        E: EvaluationsProvider<F>,
+    {
+        let zeta = state.first_round_msg.unwrap().zeta.unwrap();
+        let second_round_msg = state.second_round_msg.unwrap();
+        let delta = second_round_msg.delta.unwrap();
+        let epsilon = second_round_msg.epsilon.unwrap();
+        let gamma = state.gamma.unwrap();
+        let shifted_gamma = gamma * lookup_domain.element(1);
+
+        let single = |label: &str| LinearCombination::new(label, vec![(F::one(), label)]);
+        let z_a = single("z_a");
+        let selector = single("lookup_selector");
+        let tag = single("lookup_tag");
+        let s_1 = single("lookup_s_1");
+        let s_2 = single("lookup_s_2");
+        let z = single("lookup_z");
+        let table = LinearCombination::new(
+            "lookup_table",
+            vec![(F::one(), "lookup_table_value"), (zeta, "lookup_table_tag")],
+        );
+
+        let f_at_gamma = evals.get_lc_eval(&selector, gamma)? * evals.get_lc_eval(&z_a, gamma)?
+            + zeta * evals.get_lc_eval(&tag, gamma)?;
+        let s_1_at_gamma = evals.get_lc_eval(&s_1, gamma)?;
+        let s_2_at_gamma = evals.get_lc_eval(&s_2, gamma)?;
+        let s_1_at_shifted_gamma = evals.get_lc_eval(&s_1, shifted_gamma)?;
+        let t_at_gamma = evals.get_lc_eval(&table, gamma)?;
+        let t_at_shifted_gamma = evals.get_lc_eval(&table, shifted_gamma)?;
+        let z_at_shifted_gamma = evals.get_lc_eval(&z, shifted_gamma)?;
+
+        let one_plus_delta = F::one() + delta;
+        let epsilon_one_plus_delta = epsilon * one_plus_delta;
+        let denominator = (epsilon_one_plus_delta + s_1_at_gamma + delta * s_2_at_gamma)
+            * (epsilon_one_plus_delta + s_2_at_gamma + delta * s_1_at_shifted_gamma);
+        let numerator = one_plus_delta
+            * (epsilon + f_at_gamma)
+            * (epsilon_one_plus_delta + t_at_gamma + delta * t_at_shifted_gamma);
+        let v_L_at_gamma = lookup_domain.evaluate_vanishing_polynomial(gamma);
+
+        let lookup_check = LinearCombination::new(
+            "lookup_check",
+            vec![
+                (z_at_shifted_gamma * denominator, LCTerm::One),
+                (-numerator, "lookup_z".into()),
+                (-v_L_at_gamma, "lookup_quotient".into()),
+            ],
+        );
+
+        let lookup_boundary = LinearCombination::new(
+            "lookup_boundary",
+            vec![(F::one(), "lookup_z".into()), (-F::one(), LCTerm::One)],
+        );
+
+        Ok(vec![
+            z_a,
+            selector,
+            tag,
+            table,
+            s_1,
+            s_2,
+            z,
+            lookup_check,
+            lookup_boundary,
+        ])
+    }
 }
 
 /// Abstraction that provides evaluations of (linear combinations of) polynomials
@@ -241,28 +519,45 @@ impl<'a, F: Field> EvaluationsProvider<F> for ark_poly_commit::Evaluations<F, F>
 
 impl<F: Field, T: Borrow<LabeledPolynomial<F>>> EvaluationsProvider<F> for Vec<T> {
     fn get_lc_eval(&self, lc: &LinearCombination<F>, point: F) -> Result<F, Error> {
-        let mut eval = F::zero();
-        for (coeff, term) in lc.iter() {
-            let value = if let LCTerm::PolyLabel(label) = term {
-                self.iter()
-                    .find(|p| {
-                        let p: &LabeledPolynomial<F> = (*p).borrow();
-                        p.label() == label
-                    })
-                    .ok_or(Error::MissingEval(format!(
-                        "Missing {} for {}",
-                        label, lc.label
-                    )))?
-                    .borrow()
-                    .evaluate(&point)
-            } else {
-                assert!(term.is_one());
-                F::one()
-            };
-            eval += *coeff * value
-        }
-        Ok(eval)
+        evaluate_lc(lc, point, |label| {
+            self.iter()
+                .map(|p| p.borrow())
+                .find(|p| p.label() == label)
+                .map(|p| p.polynomial())
+        })
+    }
+}
+
+/// Polynomials by label. Unlike `LabeledPolynomial`s, these can be shared
+/// between threads.
+impl<'a, F: Field> EvaluationsProvider<F> for BTreeMap<&'a str, &'a DensePolynomial<F>> {
+    fn get_lc_eval(&self, lc: &LinearCombination<F>, point: F) -> Result<F, Error> {
+        evaluate_lc(lc, point, |label| self.get(label).copied())
+    }
+}
+
+/// Evaluate `lc` at `point`, with the polynomials that `poly` finds by label.
+fn evaluate_lc<'a, F: Field>(
+    lc: &LinearCombination<F>,
+    point: F,
+    poly: impl Fn(&str) -> Option<&'a DensePolynomial<F>>,
+) -> Result<F, Error> {
+    let mut eval = F::zero();
+    for (coeff, term) in lc.iter() {
+        let value = if let LCTerm::PolyLabel(label) = term {
+            poly(label)
+                .ok_or(Error::MissingEval(format!(
+                    "Missing {} for {}",
+                    label, lc.label
+                )))?
+                .evaluate(&point)
+        } else {
+            assert!(term.is_one());
+            F::one()
+        };
+        eval += *coeff * value
     }
+    Ok(eval)
 }
 
 /// Describes the failure modes of the AHP scheme.
@@ -274,10 +569,15 @@ pub enum Error {
     InvalidPublicInputLength,
     /// The instance generated during proving does not match that in the index.
     InstanceDoesNotMatchIndex,
-    /// Currently we only support square constraint matrices.
-    NonSquareMatrix,
+    /// A value that the circuit looks up is not in its table.
+    LookupValueNotInTable,
+    /// The indexer was asked for proofs that stay zero-knowledge for no
+    /// evaluation queries; the query bound must be at least one.
+    InvalidZkBound,
     /// An error occurred during constraint generation.
     ConstraintSystemError(SynthesisError),
+    /// A vector that the prover spilled to disk could not be written or read.
+    SpillError(ark_std::io::Error),
 }
 
 impl From<SynthesisError> for Error {
@@ -286,6 +586,42 @@ impl From<SynthesisError> for Error {
     }
 }
 
+impl From<ark_std::io::Error> for Error {
+    fn from(other: ark_std::io::Error) -> Self {
+        Error::SpillError(other)
+    }
+}
+
+/// The selector of `subdomain` in `domain`: the polynomial of degree
+/// `|domain| - |subdomain|` that is one on `subdomain` and zero on the rest of
+/// `domain`, for a subgroup `subdomain` of `domain`.
+pub(crate) fn selector_polynomial<F: PrimeField>(
+    domain: GeneralEvaluationDomain<F>,
+    subdomain: GeneralEvaluationDomain<F>,
+) -> DensePolynomial<F> {
+    // `|subdomain| / |domain| * (X^|domain| - 1) / (X^|subdomain| - 1)`
+    let mut coeffs = vec![F::zero(); domain.size() - subdomain.size() + 1];
+    let scale = subdomain.size_as_field_element() / domain.size_as_field_element();
+    for coeff in coeffs.iter_mut().step_by(subdomain.size()) {
+        *coeff = scale;
+    }
+    DensePolynomial::from_coefficients_vec(coeffs)
+}
+
+/// Evaluate the selector of `subdomain` in `domain` at a `point` outside
+/// `domain`.
+pub(crate) fn evaluate_selector_polynomial<F: PrimeField>(
+    domain: GeneralEvaluationDomain<F>,
+    subdomain: GeneralEvaluationDomain<F>,
+    point: F,
+) -> F {
+    if domain.size() == subdomain.size() {
+        return F::one();
+    }
+    subdomain.size_as_field_element() * domain.evaluate_vanishing_polynomial(point)
+        / (domain.size_as_field_element() * subdomain.evaluate_vanishing_polynomial(point))
+}
+
 /// The derivative of the vanishing polynomial
 pub trait UnnormalizedBivariateLagrangePoly<F: ark_ff::FftField> {
     /// Evaluate the polynomial
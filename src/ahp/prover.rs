@@ -4,9 +4,17 @@ use crate::ahp::indexer::*;
 use crate::ahp::verifier::*;
 use crate::ahp::*;
 
+use crate::ahp::ccs::{gate_matrix_names, CcsConstraintSynthesizer, ProductGates};
 use crate::ahp::constraint_systems::{
-    make_matrices_square_for_prover, pad_input_for_indexer_and_prover, unformat_public_input,
+    pad_input_for_indexer_and_prover, unformat_public_input, Circuit, WithGates, WithLookups, R1CS,
 };
+use crate::ahp::lookup::{
+    lookup_domain, shift, sort_by_table, LookupArithmetization, LookupConstraintSynthesizer,
+    LookupTables,
+};
+#[cfg(feature = "std")]
+use crate::ahp::spill::DiskCache;
+use crate::ahp::spill::{SpillVec, Spiller};
 use crate::{ToString, Vec};
 use ark_ff::{Field, PrimeField, Zero};
 use ark_poly::{
@@ -20,22 +28,59 @@ use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError
 use ark_std::rand::RngCore;
 use ark_std::{
     cfg_into_iter, cfg_iter, cfg_iter_mut,
+    collections::BTreeSet,
     io::{Read, Write},
 };
 
+/// Whether the prover hides the witness.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ZkMode {
+    /// Proofs are zero-knowledge: the prover masks the outer sumcheck, blinds
+    /// its oracles and commits to them with hiding randomness.
+    #[default]
+    ZeroKnowledge,
+    /// Proofs are only succinct: the prover skips the mask polynomial and all
+    /// blinding, and its commitments are not hiding. Such proofs are faster
+    /// to produce, and the verifier accepts them like any other proof.
+    NonZeroKnowledge,
+}
+
+impl ZkMode {
+    /// The number of evaluation queries that an oracle queried up to
+    /// `num_queries` times is hidden for: `num_queries` in zero-knowledge
+    /// mode, and `None` otherwise.
+    pub(crate) fn hiding_bound(self, num_queries: usize) -> Option<usize> {
+        match self {
+            ZkMode::ZeroKnowledge => Some(num_queries),
+            ZkMode::NonZeroKnowledge => None,
+        }
+    }
+}
+
 /// State for the AHP prover.
 pub struct ProverState<'a, F: PrimeField> {
     formatted_input_assignment: Vec<F>,
-    witness_assignment: Vec<F>,
+    witness_assignment: Option<SpillVec<F>>,
     /// Az
-    z_a: Option<Vec<F>>,
+    z_a: Option<SpillVec<F>>,
     /// Bz
-    z_b: Option<Vec<F>>,
-    /// query bound b
+    z_b: Option<SpillVec<F>>,
+    /// Dz, Ez, ... for the factor matrices of the product gates
+    z_gates: Vec<SpillVec<F>>,
+    /// query bound b of the first round: the configured bound, plus one if
+    /// the lookup argument queries `z_a` a second time
     zk_bound: usize,
+    /// whether the prover hides the witness
+    zk_mode: ZkMode,
+
+    /// the coefficients of the first round oracles, for the second round
+    w_poly: Option<SpillVec<F>>,
+    z_a_poly: Option<SpillVec<F>>,
+    z_b_poly: Option<SpillVec<F>>,
+    z_gate_polys: Vec<SpillVec<F>>,
 
-    w_poly: Option<LabeledPolynomial<F>>,
-    mz_polys: Option<(LabeledPolynomial<F>, LabeledPolynomial<F>)>,
+    /// holds the vectors above, in memory or on disk
+    spiller: Spiller,
 
     index: &'a Index<F>,
 
@@ -48,17 +93,71 @@ pub struct ProverState<'a, F: PrimeField> {
     /// domain X, sized for the public input
     domain_x: GeneralEvaluationDomain<F>,
 
-    /// domain H, sized for constraints
+    /// domain H, the larger of the constraint and variable domains
     domain_h: GeneralEvaluationDomain<F>,
 
+    /// domain for the rows of the matrices, sized for constraints
+    constraint_domain: GeneralEvaluationDomain<F>,
+
+    /// domain for the columns of the matrices, sized for variables
+    variable_domain: GeneralEvaluationDomain<F>,
+
     /// domain K, sized for matrix nonzero elements
     domain_k: GeneralEvaluationDomain<F>,
+
+    /// domain L of the lookup argument, if the index has lookups
+    lookup_domain: Option<GeneralEvaluationDomain<F>>,
+
+    /// the vectors of the lookup argument, computed in the second round
+    lookup_vectors: Option<LookupVectors<F>>,
+}
+
+/// The vectors of the lookup argument over the lookup domain `L`, and the
+/// polynomials of the sorted vector.
+struct LookupVectors<F: PrimeField> {
+    /// The compressed looked-up values.
+    f: Vec<F>,
+    /// The compressed combined table.
+    t: Vec<F>,
+    /// The entries of the sorted vector at even positions.
+    s_1: Vec<F>,
+    /// The entries of the sorted vector at odd positions.
+    s_2: Vec<F>,
+    s_1_poly: LabeledPolynomial<F>,
+    s_2_poly: LabeledPolynomial<F>,
 }
 
 impl<'a, F: PrimeField> ProverState<'a, F> {
     /// Get the public input.
     pub fn public_input(&self) -> Vec<F> {
-        unformat_public_input(&self.formatted_input_assignment)
+        let num_instance_variables = self.index.index_info.num_instance_variables;
+        unformat_public_input(&self.formatted_input_assignment[..num_instance_variables])
+    }
+
+    /// Set whether the prover hides the witness. Provers are zero-knowledge
+    /// unless this is called before the first round.
+    pub fn set_zk_mode(&mut self, zk_mode: ZkMode) {
+        self.zk_mode = zk_mode;
+    }
+
+    /// The most bytes that the vectors held between rounds have taken in
+    /// memory at once.
+    #[cfg(test)]
+    pub(crate) fn peak_held_bytes(&self) -> usize {
+        self.spiller.peak_bytes()
+    }
+}
+
+/// A random multiple of `v` that blinds an oracle for `hiding_bound`
+/// evaluation queries, or zero if the oracle is not hidden.
+fn blinding_polynomial<F: PrimeField, R: RngCore>(
+    hiding_bound: Option<usize>,
+    v: &DensePolynomial<F>,
+    rng: &mut R,
+) -> DensePolynomial<F> {
+    match hiding_bound {
+        Some(bound) => &DensePolynomial::rand(bound - 1, rng) * v,
+        None => DensePolynomial::zero(),
     }
 }
 
@@ -81,6 +180,9 @@ impl<F: Field> ark_ff::ToBytes for ProverMsg<F> {
     }
 }
 
+/// The message, the oracles and the next state that a prover round outputs.
+pub type ProverRound<'a, F, Oracles> = (ProverMsg<F>, Oracles, ProverState<'a, F>);
+
 impl<F: Field> CanonicalSerialize for ProverMsg<F> {
     fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
         let res: Option<Vec<F>> = match self {
@@ -163,6 +265,9 @@ pub struct ProverFirstOracles<F: Field> {
     pub z_a: LabeledPolynomial<F>,
     /// The LDE of `Bz`.
     pub z_b: LabeledPolynomial<F>,
+    /// The LDEs of `Dz`, `Ez`, ... for the factor matrices of the product
+    /// gates, if the index has product gates.
+    pub z_gates: Vec<LabeledPolynomial<F>>,
     /// The sum-check hiding polynomial.
     pub mask_poly: LabeledPolynomial<F>,
 }
@@ -170,7 +275,10 @@ pub struct ProverFirstOracles<F: Field> {
 impl<F: Field> ProverFirstOracles<F> {
     /// Iterate over the polynomials output by the prover in the first round.
     pub fn iter(&self) -> impl Iterator<Item = &LabeledPolynomial<F>> {
-        vec![&self.w, &self.z_a, &self.z_b, &self.mask_poly].into_iter()
+        vec![&self.w, &self.z_a, &self.z_b]
+            .into_iter()
+            .chain(&self.z_gates)
+            .chain(ark_std::iter::once(&self.mask_poly))
     }
 }
 
@@ -182,12 +290,21 @@ pub struct ProverSecondOracles<F: Field> {
     pub g_1: LabeledPolynomial<F>,
     /// The polynomial `h` resulting from the first sumcheck.
     pub h_1: LabeledPolynomial<F>,
+    /// The entries of the sorted vector of the lookup argument at even
+    /// positions, if the index has lookups.
+    pub lookup_s_1: Option<LabeledPolynomial<F>>,
+    /// The entries of the sorted vector of the lookup argument at odd
+    /// positions, if the index has lookups.
+    pub lookup_s_2: Option<LabeledPolynomial<F>>,
 }
 
 impl<F: Field> ProverSecondOracles<F> {
     /// Iterate over the polynomials output by the prover in the second round.
     pub fn iter(&self) -> impl Iterator<Item = &LabeledPolynomial<F>> {
-        vec![&self.t, &self.g_1, &self.h_1].into_iter()
+        vec![&self.t, &self.g_1, &self.h_1]
+            .into_iter()
+            .chain(&self.lookup_s_1)
+            .chain(&self.lookup_s_2)
     }
 }
 
@@ -197,12 +314,20 @@ pub struct ProverThirdOracles<F: Field> {
     pub g_2: LabeledPolynomial<F>,
     /// The polynomial `h` resulting from the second sumcheck.
     pub h_2: LabeledPolynomial<F>,
+    /// The grand product of the lookup argument, if the index has lookups.
+    pub lookup_z: Option<LabeledPolynomial<F>>,
+    /// The quotient of the grand product check by `v_L`, if the index has
+    /// lookups.
+    pub lookup_quotient: Option<LabeledPolynomial<F>>,
 }
 
 impl<F: Field> ProverThirdOracles<F> {
     /// Iterate over the polynomials output by the prover in the third round.
     pub fn iter(&self) -> impl Iterator<Item = &LabeledPolynomial<F>> {
-        vec![&self.g_2, &self.h_2].into_iter()
+        vec![&self.g_2, &self.h_2]
+            .into_iter()
+            .chain(&self.lookup_z)
+            .chain(&self.lookup_quotient)
     }
 }
 
@@ -211,6 +336,41 @@ impl<F: PrimeField> AHPForR1CS<F> {
     pub fn prover_init<'a, C: ConstraintSynthesizer<F>>(
         index: &'a Index<F>,
         c: C,
+    ) -> Result<ProverState<'a, F>, Error> {
+        Self::prover_init_circuit(index, R1CS(c), Spiller::unbounded())
+    }
+
+    /// Initialize the AHP prover for a circuit with lookups.
+    pub fn prover_init_with_lookups<'a, C: LookupConstraintSynthesizer<F>>(
+        index: &'a Index<F>,
+        c: C,
+    ) -> Result<ProverState<'a, F>, Error> {
+        Self::prover_init_circuit(index, WithLookups(c), Spiller::unbounded())
+    }
+
+    /// Initialize the AHP prover for a circuit with product gates.
+    pub fn prover_init_with_gates<'a, C: CcsConstraintSynthesizer<F>>(
+        index: &'a Index<F>,
+        c: C,
+    ) -> Result<ProverState<'a, F>, Error> {
+        Self::prover_init_circuit(index, WithGates(c), Spiller::unbounded())
+    }
+
+    /// Initialize the AHP prover, writing the vectors that it keeps between
+    /// rounds to `cache` once they no longer fit in it.
+    #[cfg(feature = "std")]
+    pub fn prover_init_with_disk_cache<'a, C: ConstraintSynthesizer<F>>(
+        index: &'a Index<F>,
+        c: C,
+        cache: DiskCache,
+    ) -> Result<ProverState<'a, F>, Error> {
+        Self::prover_init_circuit(index, R1CS(c), Spiller::with_cache(cache))
+    }
+
+    pub(crate) fn prover_init_circuit<'a, C: Circuit<F>>(
+        index: &'a Index<F>,
+        c: C,
+        mut spiller: Spiller,
     ) -> Result<ProverState<'a, F>, Error> {
         let init_time = start_timer!(|| "AHP::Prover::Init");
 
@@ -220,23 +380,29 @@ impl<F: PrimeField> AHPForR1CS<F> {
         pcs.set_mode(ark_relations::r1cs::SynthesisMode::Prove {
             construct_matrices: true,
         });
-        c.generate_constraints(pcs.clone())?;
+        let mut gates = ProductGates::new();
+        c.synthesize(pcs.clone(), &mut LookupTables::new(), &mut gates)?;
         end_timer!(constraint_time);
 
-        let padding_time = start_timer!(|| "Padding matrices to make them square");
+        if pcs.num_instance_variables() != index.index_info.num_instance_variables {
+            return Err(Error::InstanceDoesNotMatchIndex);
+        }
+
+        let padding_time = start_timer!(|| "Padding the public input");
         pad_input_for_indexer_and_prover(pcs.clone());
         pcs.finalize();
-        make_matrices_square_for_prover(pcs.clone());
         end_timer!(padding_time);
 
         let num_non_zero = index.index_info.num_non_zero;
 
+        // The indexer merges the constraints that carry each product gate
+        // into a single row.
         let (formatted_input_assignment, witness_assignment, num_constraints) = {
             let pcs = pcs.into_inner().unwrap();
             (
                 pcs.instance_assignment,
                 pcs.witness_assignment,
-                pcs.num_constraints,
+                pcs.num_constraints - gates.num_merged_constraints(),
             )
         };
 
@@ -248,10 +414,6 @@ impl<F: PrimeField> AHPForR1CS<F> {
             return Err(Error::InstanceDoesNotMatchIndex);
         }
 
-        if !Self::formatted_public_input_is_admissible(&formatted_input_assignment) {
-            return Err(Error::InvalidPublicInputLength);
-        }
-
         // Perform matrix multiplications
         let inner_prod_fn = |row: &[(F, usize)]| {
             let mut acc = F::zero();
@@ -267,18 +429,68 @@ impl<F: PrimeField> AHPForR1CS<F> {
             acc
         };
 
+        let lookup_domain = lookup_domain(&index.index_info)?;
+        let constraint_domain = GeneralEvaluationDomain::new(num_constraints)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+
+        // The rows of the matrices are multiplied by `z` in chunks, which are
+        // written to disk as they are computed if they do not fit in memory.
+        // The values that `z_A` looks up are checked chunk by chunk.
+        let chunk_len = spiller.chunk_len::<F>();
+        let lookup_table = match (&index.lookup, lookup_domain) {
+            (Some(lookup), Some(lookup_domain)) => {
+                Some((lookup, Self::lookup_table_entries(lookup), lookup_domain))
+            }
+            _ => None,
+        };
+        let mul_by_z = |matrix: &Matrix<F>, check_lookups: bool, spiller: &mut Spiller| {
+            let mut builder = spiller.builder();
+            for (i, rows) in matrix.chunks(chunk_len).enumerate() {
+                let chunk: Vec<F> = cfg_iter!(rows).map(|row| inner_prod_fn(row)).collect();
+                if let (true, Some((lookup, table, lookup_domain))) = (check_lookups, &lookup_table)
+                {
+                    Self::check_lookups(
+                        lookup,
+                        table,
+                        i * chunk_len,
+                        &chunk,
+                        constraint_domain,
+                        *lookup_domain,
+                    )?;
+                }
+                builder.extend(chunk)?;
+            }
+            Ok::<_, Error>(builder.finish()?)
+        };
+
         let eval_z_a_time = start_timer!(|| "Evaluating z_A");
-        let z_a = index.a.iter().map(|row| inner_prod_fn(row)).collect();
+        let z_a = mul_by_z(&index.a, true, &mut spiller)?;
         end_timer!(eval_z_a_time);
 
         let eval_z_b_time = start_timer!(|| "Evaluating z_B");
-        let z_b = index.b.iter().map(|row| inner_prod_fn(row)).collect();
+        let z_b = mul_by_z(&index.b, false, &mut spiller)?;
         end_timer!(eval_z_b_time);
 
-        let zk_bound = 1; // One query is sufficient for our desired soundness
+        let eval_z_gates_time = start_timer!(|| "Evaluating z_D, z_E, ...");
+        let z_gates = index
+            .gates
+            .iter()
+            .map(|matrix| mul_by_z(matrix, false, &mut spiller))
+            .collect::<Result<_, _>>()?;
+        end_timer!(eval_z_gates_time);
+        let witness_assignment = spiller.store(witness_assignment)?;
 
-        let domain_h = GeneralEvaluationDomain::new(num_constraints)
-            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let zk_bound = index.index_info.first_round_zk_bound();
+
+        let variable_domain =
+            GeneralEvaluationDomain::new(num_input_variables + num_witness_variables)
+                .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+
+        let domain_h = if constraint_domain.size() > variable_domain.size() {
+            constraint_domain
+        } else {
+            variable_domain
+        };
 
         let domain_k = GeneralEvaluationDomain::new(num_non_zero)
             .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
@@ -290,31 +502,81 @@ impl<F: PrimeField> AHPForR1CS<F> {
 
         Ok(ProverState {
             formatted_input_assignment,
-            witness_assignment,
+            witness_assignment: Some(witness_assignment),
             z_a: Some(z_a),
             z_b: Some(z_b),
+            z_gates,
             w_poly: None,
-            mz_polys: None,
+            z_a_poly: None,
+            z_b_poly: None,
+            z_gate_polys: Vec::new(),
+            spiller,
             zk_bound,
+            zk_mode: ZkMode::default(),
             index,
             verifier_first_msg: None,
             mask_poly: None,
             domain_h,
+            constraint_domain,
+            variable_domain,
             domain_k,
             domain_x,
+            lookup_domain,
+            lookup_vectors: None,
         })
     }
 
+    /// The entries of the combined table, with their tags.
+    fn lookup_table_entries(lookup: &LookupArithmetization<F>) -> BTreeSet<(F, F)> {
+        let evals = &lookup.evals_on_L;
+        evals
+            .table_value
+            .evals
+            .iter()
+            .copied()
+            .zip(evals.table_tag.evals.iter().copied())
+            .collect()
+    }
+
+    /// Check that every value that the circuit looks up in `z_a`, which
+    /// starts at constraint `offset`, is in `table`.
+    fn check_lookups(
+        lookup: &LookupArithmetization<F>,
+        table: &BTreeSet<(F, F)>,
+        offset: usize,
+        z_a: &[F],
+        constraint_domain: GeneralEvaluationDomain<F>,
+        lookup_domain: GeneralEvaluationDomain<F>,
+    ) -> Result<(), Error> {
+        let evals = &lookup.evals_on_L;
+        let ratio = lookup_domain.size() / constraint_domain.size();
+        for (i, value) in (offset..).zip(z_a) {
+            let (selector, tag) = (evals.selector[i * ratio], evals.tag[i * ratio]);
+            if selector.is_one() && !table.contains(&(*value, tag)) {
+                return Err(Error::LookupValueNotInTable);
+            }
+        }
+        Ok(())
+    }
+
     /// Output the first round message and the next state.
     pub fn prover_first_round<'a, R: RngCore>(
         mut state: ProverState<'a, F>,
         rng: &mut R,
-    ) -> Result<(ProverMsg<F>, ProverFirstOracles<F>, ProverState<'a, F>), Error> {
+    ) -> Result<ProverRound<'a, F, ProverFirstOracles<F>>, Error> {
         let round_time = start_timer!(|| "AHP::Prover::FirstRound");
         let domain_h = state.domain_h;
+        let constraint_domain = state.constraint_domain;
+        let variable_domain = state.variable_domain;
         let zk_bound = state.zk_bound;
+        // The oracles of this round are blinded for the query bound of `z_a`,
+        // which the mask polynomial is sized for, but only `z_a` may be
+        // queried more often than the configured bound.
+        let blinding_bound = state.zk_mode.hiding_bound(zk_bound);
+        let hiding_bound = state.zk_mode.hiding_bound(state.index.index_info.zk_bound);
 
-        let v_H = domain_h.vanishing_polynomial().into();
+        let v_H_constraints = constraint_domain.vanishing_polynomial().into();
+        let v_H_variables = variable_domain.vanishing_polynomial().into();
 
         let x_time = start_timer!(|| "Computing x polynomial and evals");
         let domain_x = state.domain_x;
@@ -323,85 +585,138 @@ impl<F: PrimeField> AHPForR1CS<F> {
             domain_x,
         )
         .interpolate();
-        let x_evals = domain_h.fft(&x_poly);
+        let x_evals = variable_domain.fft(&x_poly);
         end_timer!(x_time);
 
-        let ratio = domain_h.size() / domain_x.size();
-
-        let mut w_extended = state.witness_assignment.clone();
-        w_extended.extend(vec![
-            F::zero();
-            domain_h.size()
-                - domain_x.size()
-                - state.witness_assignment.len()
-        ]);
+        let ratio = variable_domain.size() / domain_x.size();
 
+        // The witness is read in chunks, and interleaved with the zeros at
+        // the elements of the input domain.
         let w_poly_time = start_timer!(|| "Computing w polynomial");
-        let w_poly_evals = cfg_into_iter!(0..domain_h.size())
-            .map(|k| {
-                if k % ratio == 0 {
-                    F::zero()
-                } else {
-                    w_extended[k - (k / ratio) - 1] - &x_evals[k]
+        let mut w_poly_evals = Vec::with_capacity(variable_domain.size());
+        let witness = state.witness_assignment.take().unwrap();
+        for chunk in state.spiller.chunks(witness)? {
+            for w in chunk? {
+                if w_poly_evals.len() % ratio == 0 {
+                    w_poly_evals.push(F::zero());
                 }
-            })
-            .collect();
+                w_poly_evals.push(w - x_evals[w_poly_evals.len()]);
+            }
+        }
+        let num_evals = w_poly_evals.len();
+        w_poly_evals.extend((num_evals..).zip(&x_evals[num_evals..]).map(|(k, x)| {
+            if k % ratio == 0 {
+                F::zero()
+            } else {
+                -*x
+            }
+        }));
 
-        let w_poly = &EvaluationsOnDomain::from_vec_and_domain(w_poly_evals, domain_h)
+        let w_poly = &EvaluationsOnDomain::from_vec_and_domain(w_poly_evals, variable_domain)
             .interpolate()
-            + &(&DensePolynomial::from_coefficients_slice(&[F::rand(rng)]) * &v_H);
+            + &blinding_polynomial(blinding_bound, &v_H_variables, rng);
         let (w_poly, remainder) = w_poly.divide_by_vanishing_poly(domain_x).unwrap();
         assert!(remainder.is_zero());
         end_timer!(w_poly_time);
 
         let z_a_poly_time = start_timer!(|| "Computing z_A polynomial");
-        let z_a = state.z_a.clone().unwrap();
-        let z_a_poly = &EvaluationsOnDomain::from_vec_and_domain(z_a, domain_h).interpolate()
-            + &(&DensePolynomial::from_coefficients_slice(&[F::rand(rng)]) * &v_H);
+        let z_a = state.spiller.load(state.z_a.take().unwrap())?;
+        // The lookup argument needs `z_a` again in the second round.
+        if state.index.lookup.is_some() {
+            state.z_a = Some(state.spiller.store(z_a.clone())?);
+        }
+        let z_a_poly = &EvaluationsOnDomain::from_vec_and_domain(z_a, constraint_domain)
+            .interpolate()
+            + &blinding_polynomial(blinding_bound, &v_H_constraints, rng);
         end_timer!(z_a_poly_time);
 
         let z_b_poly_time = start_timer!(|| "Computing z_B polynomial");
-        let z_b = state.z_b.clone().unwrap();
-        let z_b_poly = &EvaluationsOnDomain::from_vec_and_domain(z_b, domain_h).interpolate()
-            + &(&DensePolynomial::from_coefficients_slice(&[F::rand(rng)]) * &v_H);
+        let z_b = state.spiller.load(state.z_b.take().unwrap())?;
+        let z_b_poly = &EvaluationsOnDomain::from_vec_and_domain(z_b, constraint_domain)
+            .interpolate()
+            + &blinding_polynomial(blinding_bound, &v_H_constraints, rng);
         end_timer!(z_b_poly_time);
 
+        let z_gate_polys_time = start_timer!(|| "Computing z_D, z_E, ... polynomials");
+        let z_gate_polys: Vec<_> = core::mem::take(&mut state.z_gates)
+            .into_iter()
+            .map(|z_k| {
+                let z_k = state.spiller.load(z_k)?;
+                Ok(
+                    &EvaluationsOnDomain::from_vec_and_domain(z_k, constraint_domain).interpolate()
+                        + &blinding_polynomial(blinding_bound, &v_H_constraints, rng),
+                )
+            })
+            .collect::<Result<_, Error>>()?;
+        end_timer!(z_gate_polys_time);
+
         let mask_poly_time = start_timer!(|| "Computing mask polynomial");
-        let mask_poly_degree = 3 * domain_h.size() + 2 * zk_bound - 3;
-        let mut mask_poly = DensePolynomial::rand(mask_poly_degree, rng);
-
-        let nh = domain_h.size();
-        let upper_bound = mask_poly_degree / nh;
-        let mut r_0 = F::zero();
-        for i in 0..upper_bound + 1 {
-            r_0 += mask_poly[nh * i];
-        }
+        let mask_poly_degree = Self::mask_poly_degree(
+            domain_h.size(),
+            zk_bound,
+            state.index.index_info.product_degree(),
+        );
+        let mask_poly = match state.zk_mode {
+            ZkMode::ZeroKnowledge => {
+                let mut mask_poly = DensePolynomial::rand(mask_poly_degree, rng);
+
+                let nh = domain_h.size();
+                let upper_bound = mask_poly_degree / nh;
+                let mut r_0 = F::zero();
+                for i in 0..upper_bound + 1 {
+                    r_0 += mask_poly[nh * i];
+                }
 
-        mask_poly[0] -= &r_0;
+                mask_poly[0] -= &r_0;
+                mask_poly
+            }
+            // The outer sumcheck needs no masking: the committed mask is zero.
+            ZkMode::NonZeroKnowledge => DensePolynomial::zero(),
+        };
         end_timer!(mask_poly_time);
 
         let msg = ProverMsg::EmptyMessage;
 
-        assert!(w_poly.degree() < domain_h.size() - domain_x.size() + zk_bound);
-        assert!(z_a_poly.degree() < domain_h.size() + zk_bound);
-        assert!(z_b_poly.degree() < domain_h.size() + zk_bound);
-        assert!(mask_poly.degree() <= 3 * domain_h.size() + 2 * zk_bound - 3);
-
-        let w = LabeledPolynomial::new("w".to_string(), w_poly, None, Some(1));
-        let z_a = LabeledPolynomial::new("z_a".to_string(), z_a_poly, None, Some(1));
-        let z_b = LabeledPolynomial::new("z_b".to_string(), z_b_poly, None, Some(1));
+        assert!(w_poly.degree() < variable_domain.size() - domain_x.size() + zk_bound);
+        assert!(z_a_poly.degree() < constraint_domain.size() + zk_bound);
+        assert!(z_b_poly.degree() < constraint_domain.size() + zk_bound);
+        for z_k_poly in &z_gate_polys {
+            assert!(z_k_poly.degree() < constraint_domain.size() + zk_bound);
+        }
+        assert!(mask_poly.degree() <= mask_poly_degree);
+
+        let w = LabeledPolynomial::new("w".to_string(), w_poly, None, hiding_bound);
+        let z_a = LabeledPolynomial::new("z_a".to_string(), z_a_poly, None, blinding_bound);
+        let z_b = LabeledPolynomial::new("z_b".to_string(), z_b_poly, None, hiding_bound);
+        let z_gates: Vec<_> = gate_matrix_names(state.index.index_info.product_gate_degree)
+            .zip(z_gate_polys)
+            .map(|(name, poly)| {
+                LabeledPolynomial::new(format!("z_{}", name), poly, None, hiding_bound)
+            })
+            .collect();
         let mask_poly =
             LabeledPolynomial::new("mask_poly".to_string(), mask_poly.clone(), None, None);
 
         let oracles = ProverFirstOracles {
-            w: w.clone(),
-            z_a: z_a.clone(),
-            z_b: z_b.clone(),
+            w,
+            z_a,
+            z_b,
+            z_gates,
             mask_poly: mask_poly.clone(),
         };
 
-        state.w_poly = Some(w);
-        state.mz_polys = Some((z_a, z_b));
+        // The second round needs the coefficients of the oracles.
+        let spiller = &mut state.spiller;
+        let mut store_coeffs =
+            |poly: &LabeledPolynomial<F>| spiller.store(poly.polynomial().coeffs.clone());
+        state.w_poly = Some(store_coeffs(&oracles.w)?);
+        state.z_a_poly = Some(store_coeffs(&oracles.z_a)?);
+        state.z_b_poly = Some(store_coeffs(&oracles.z_b)?);
+        state.z_gate_polys = oracles
+            .z_gates
+            .iter()
+            .map(store_coeffs)
+            .collect::<Result<_, _>>()?;
         state.mask_poly = Some(mask_poly);
         end_timer!(round_time);
 
@@ -412,19 +727,19 @@ impl<F: PrimeField> AHPForR1CS<F> {
         matrices: impl Iterator<Item = &'a Matrix<F>>,
         matrix_randomizers: &[F],
         input_domain: GeneralEvaluationDomain<F>,
-        domain_h: GeneralEvaluationDomain<F>,
-        r_alpha_x_on_h: Vec<F>,
+        variable_domain: GeneralEvaluationDomain<F>,
+        r_alpha_x_on_constraint_domain: Vec<F>,
     ) -> DensePolynomial<F> {
-        let mut t_evals_on_h = vec![F::zero(); domain_h.size()];
+        let mut t_evals = vec![F::zero(); variable_domain.size()];
         for (matrix, eta) in matrices.zip(matrix_randomizers) {
             for (r, row) in matrix.iter().enumerate() {
                 for (coeff, c) in row.iter() {
-                    let index = domain_h.reindex_by_subdomain(input_domain, *c);
-                    t_evals_on_h[index] += *eta * coeff * r_alpha_x_on_h[r];
+                    let index = variable_domain.reindex_by_subdomain(input_domain, *c);
+                    t_evals[index] += *eta * coeff * r_alpha_x_on_constraint_domain[r];
                 }
             }
         }
-        EvaluationsOnDomain::from_vec_and_domain(t_evals_on_h, domain_h).interpolate()
+        EvaluationsOnDomain::from_vec_and_domain(t_evals, variable_domain).interpolate()
     }
 
     /// Output the number of oracles sent by the prover in the first round.
@@ -434,20 +749,22 @@ impl<F: PrimeField> AHPForR1CS<F> {
 
     /// Output the degree bounds of oracles in the first round.
     pub fn prover_first_round_degree_bounds(
-        _info: &IndexInfo<F>,
+        info: &IndexInfo<F>,
     ) -> impl Iterator<Item = Option<usize>> {
-        vec![None; 4].into_iter()
+        vec![None; 4 + info.product_gate_degree].into_iter()
     }
 
     /// Output the second round message and the next state.
     pub fn prover_second_round<'a, R: RngCore>(
         ver_message: &VerifierFirstMsg<F>,
         mut state: ProverState<'a, F>,
-        _r: &mut R,
-    ) -> (ProverMsg<F>, ProverSecondOracles<F>, ProverState<'a, F>) {
+        rng: &mut R,
+    ) -> Result<ProverRound<'a, F, ProverSecondOracles<F>>, Error> {
         let round_time = start_timer!(|| "AHP::Prover::SecondRound");
 
         let domain_h = state.domain_h;
+        let constraint_domain = state.constraint_domain;
+        let variable_domain = state.variable_domain;
         let zk_bound = state.zk_bound;
 
         let mask_poly = state
@@ -460,11 +777,39 @@ impl<F: PrimeField> AHPForR1CS<F> {
             eta_a,
             eta_b,
             eta_c,
+            zeta,
+            ..
         } = *ver_message;
+        let gate_etas = ver_message.gate_etas(state.z_gate_polys.len());
 
         let summed_z_m_poly_time = start_timer!(|| "Compute z_m poly");
-        let (z_a_poly, z_b_poly) = state.mz_polys.as_ref().unwrap();
-        let z_c_poly = z_a_poly.polynomial() * z_b_poly.polynomial();
+        let z_a_poly = state.z_a_poly.take().unwrap();
+        let z_b_poly = state.z_b_poly.take().unwrap();
+        let z_gate_polys = core::mem::take(&mut state.z_gate_polys);
+        let spiller = &mut state.spiller;
+        let mut load_poly = |coeffs: SpillVec<F>| {
+            Ok::<_, Error>(DensePolynomial::from_coefficients_vec(
+                spiller.load(coeffs)?,
+            ))
+        };
+        let z_a_poly = load_poly(z_a_poly)?;
+        let z_b_poly = load_poly(z_b_poly)?;
+        let z_gate_polys = z_gate_polys
+            .into_iter()
+            .map(load_poly)
+            .collect::<Result<Vec<_>, _>>()?;
+        // The lookup argument needs `z_a` again in the third round.
+        if state.index.lookup.is_some() {
+            state.z_a_poly = Some(state.spiller.store(z_a_poly.coeffs.clone())?);
+        }
+        let mut z_c_poly = &z_a_poly * &z_b_poly;
+        // With product gates, `z_c` also includes the product of `z_D`, `z_E`, ...
+        if let Some((z_d_poly, z_gate_polys)) = z_gate_polys.split_first() {
+            let product = z_gate_polys
+                .iter()
+                .fold(z_d_poly.clone(), |acc, z_k| &acc * z_k);
+            z_c_poly += &product;
+        }
 
         let mut summed_z_m_coeffs = z_c_poly.coeffs;
         // Note: Can't combine these two loops, because z_c_poly has 2x the degree
@@ -472,32 +817,59 @@ impl<F: PrimeField> AHPForR1CS<F> {
         // the `zip`s.
         cfg_iter_mut!(summed_z_m_coeffs).for_each(|c| *c *= &eta_c);
         cfg_iter_mut!(summed_z_m_coeffs)
-            .zip(&z_a_poly.polynomial().coeffs)
-            .zip(&z_b_poly.polynomial().coeffs)
+            .zip(&z_a_poly.coeffs)
+            .zip(&z_b_poly.coeffs)
             .for_each(|((c, a), b)| *c += &(eta_a * a + &(eta_b * b)));
+        for (eta_k, z_k_poly) in gate_etas.iter().zip(&z_gate_polys) {
+            cfg_iter_mut!(summed_z_m_coeffs)
+                .zip(&z_k_poly.coeffs)
+                .for_each(|(c, z_k)| *c += *eta_k * z_k);
+        }
+        drop(z_a_poly);
+        drop(z_b_poly);
+        drop(z_gate_polys);
 
         let summed_z_m = DensePolynomial::from_coefficients_vec(summed_z_m_coeffs);
         end_timer!(summed_z_m_poly_time);
 
         let r_alpha_x_evals_time = start_timer!(|| "Compute r_alpha_x evals");
-        let r_alpha_x_evals =
-            domain_h.batch_eval_unnormalized_bivariate_lagrange_poly_with_diff_inputs(alpha);
+        let r_alpha_x_evals = constraint_domain
+            .batch_eval_unnormalized_bivariate_lagrange_poly_with_diff_inputs(alpha);
         end_timer!(r_alpha_x_evals_time);
 
         let r_alpha_poly_time = start_timer!(|| "Compute r_alpha_x poly");
-        let r_alpha_poly = DensePolynomial::from_coefficients_vec(domain_h.ifft(&r_alpha_x_evals));
+        let mut r_alpha_poly =
+            DensePolynomial::from_coefficients_vec(constraint_domain.ifft(&r_alpha_x_evals));
         end_timer!(r_alpha_poly_time);
 
         let t_poly_time = start_timer!(|| "Compute t poly");
+        let matrix_randomizers: Vec<F> = [eta_a, eta_b, eta_c]
+            .iter()
+            .chain(&gate_etas)
+            .copied()
+            .collect();
         let t_poly = Self::calculate_t(
-            vec![&state.index.a, &state.index.b, &state.index.c].into_iter(),
-            &[eta_a, eta_b, eta_c],
+            vec![&state.index.a, &state.index.b, &state.index.c]
+                .into_iter()
+                .chain(&state.index.gates),
+            &matrix_randomizers,
             state.domain_x,
-            state.domain_h,
+            variable_domain,
             r_alpha_x_evals,
         );
         end_timer!(t_poly_time);
 
+        // The outer sumcheck runs over `H`, the larger of the constraint and
+        // variable domains. Restrict the terms that are defined over the
+        // smaller one to it by multiplying them with its selector.
+        let mut t_poly_on_h = t_poly.clone();
+        if constraint_domain.size() < domain_h.size() {
+            r_alpha_poly = &r_alpha_poly * &selector_polynomial(domain_h, constraint_domain);
+        }
+        if variable_domain.size() < domain_h.size() {
+            t_poly_on_h = &t_poly_on_h * &selector_polynomial(domain_h, variable_domain);
+        }
+
         let z_poly_time = start_timer!(|| "Compute z poly");
 
         let domain_x = GeneralEvaluationDomain::new(state.formatted_input_assignment.len())
@@ -508,12 +880,13 @@ impl<F: PrimeField> AHPForR1CS<F> {
             domain_x,
         )
         .interpolate();
-        let w_poly = state.w_poly.as_ref().unwrap();
-        let mut z_poly = w_poly.polynomial().mul_by_vanishing_poly(domain_x);
+        let w_poly = state.spiller.load(state.w_poly.take().unwrap())?;
+        let mut z_poly =
+            DensePolynomial::from_coefficients_vec(w_poly).mul_by_vanishing_poly(domain_x);
         cfg_iter_mut!(z_poly.coeffs)
             .zip(&x_poly.coeffs)
             .for_each(|(z, x)| *z += x);
-        assert!(z_poly.degree() < domain_h.size() + zk_bound);
+        assert!(z_poly.degree() < variable_domain.size() + zk_bound);
 
         end_timer!(z_poly_time);
 
@@ -522,7 +895,7 @@ impl<F: PrimeField> AHPForR1CS<F> {
         let mul_domain_size = *[
             mask_poly.len(),
             r_alpha_poly.coeffs.len() + summed_z_m.coeffs.len(),
-            t_poly.coeffs.len() + z_poly.len(),
+            t_poly_on_h.coeffs.len() + z_poly.len(),
         ]
         .iter()
         .max()
@@ -532,7 +905,7 @@ impl<F: PrimeField> AHPForR1CS<F> {
         let mut r_alpha_evals = r_alpha_poly.evaluate_over_domain_by_ref(mul_domain);
         let summed_z_m_evals = summed_z_m.evaluate_over_domain_by_ref(mul_domain);
         let z_poly_evals = z_poly.evaluate_over_domain_by_ref(mul_domain);
-        let t_poly_m_evals = t_poly.evaluate_over_domain_by_ref(mul_domain);
+        let t_poly_m_evals = t_poly_on_h.evaluate_over_domain_by_ref(mul_domain);
 
         cfg_iter_mut!(r_alpha_evals.evals)
             .zip(&summed_z_m_evals.evals)
@@ -554,19 +927,101 @@ impl<F: PrimeField> AHPForR1CS<F> {
         let msg = ProverMsg::EmptyMessage;
 
         assert!(g_1.degree() <= domain_h.size() - 2);
-        assert!(h_1.degree() <= 2 * domain_h.size() + 2 * zk_bound - 2);
+        let mask_poly_degree = Self::mask_poly_degree(
+            domain_h.size(),
+            zk_bound,
+            state.index.index_info.product_degree(),
+        );
+        assert!(h_1.degree() <= mask_poly_degree - domain_h.size() + 1);
+
+        if let (Some(lookup), Some(lookup_domain)) = (&state.index.lookup, state.lookup_domain) {
+            let z_a = state.spiller.load(state.z_a.take().unwrap())?;
+            state.lookup_vectors = Some(Self::lookup_sorted_vector(
+                lookup,
+                zeta.unwrap(),
+                &z_a,
+                constraint_domain,
+                lookup_domain,
+                state.zk_mode.hiding_bound(state.index.index_info.zk_bound),
+                rng,
+            ));
+        }
+        let lookup_vectors = state.lookup_vectors.as_ref();
 
         let oracles = ProverSecondOracles {
             t: LabeledPolynomial::new("t".into(), t_poly, None, None),
-            g_1: LabeledPolynomial::new("g_1".into(), g_1, Some(domain_h.size() - 2), Some(1)),
+            g_1: LabeledPolynomial::new(
+                "g_1".into(),
+                g_1,
+                Some(domain_h.size() - 2),
+                state.zk_mode.hiding_bound(state.index.index_info.zk_bound),
+            ),
             h_1: LabeledPolynomial::new("h_1".into(), h_1, None, None),
+            lookup_s_1: lookup_vectors.map(|v| v.s_1_poly.clone()),
+            lookup_s_2: lookup_vectors.map(|v| v.s_2_poly.clone()),
         };
 
-        state.w_poly = None;
         state.verifier_first_msg = Some(*ver_message);
         end_timer!(round_time);
 
-        (msg, oracles, state)
+        Ok((msg, oracles, state))
+    }
+
+    /// Compute the compressed looked-up values and table over the lookup
+    /// domain, and commit to their sorted vector.
+    fn lookup_sorted_vector<R: RngCore>(
+        lookup: &LookupArithmetization<F>,
+        zeta: F,
+        z_a: &[F],
+        constraint_domain: GeneralEvaluationDomain<F>,
+        lookup_domain: GeneralEvaluationDomain<F>,
+        zk_bound: Option<usize>,
+        rng: &mut R,
+    ) -> LookupVectors<F> {
+        let sorted_time = start_timer!(|| "Computing lookup sorted vector");
+        let evals = &lookup.evals_on_L;
+        let ratio = lookup_domain.size() / constraint_domain.size();
+        // Only the elements of `L` that correspond to constraints may be
+        // selected, and `z_a` is interpolated over the constraint domain.
+        let f: Vec<F> = (0..lookup_domain.size())
+            .map(|k| {
+                let z_a_k = match k % ratio {
+                    0 => z_a.get(k / ratio).copied().unwrap_or_else(F::zero),
+                    _ => F::zero(),
+                };
+                evals.selector[k] * z_a_k + zeta * evals.tag[k]
+            })
+            .collect();
+        let t: Vec<F> = evals
+            .table_value
+            .evals
+            .iter()
+            .zip(&evals.table_tag.evals)
+            .map(|(value, tag)| *value + zeta * tag)
+            .collect();
+        let (s_1, s_2) =
+            sort_by_table(&f, &t).expect("looked-up values are checked by prover_init");
+
+        // `s_1` is queried at two points, and `s_2` at one, each up to
+        // `zk_bound` times.
+        let v_L: DensePolynomial<F> = lookup_domain.vanishing_polynomial().into();
+        let s_1_hiding_bound = zk_bound.map(|b| b + 1);
+        let s_1_poly = &EvaluationsOnDomain::from_vec_and_domain(s_1.clone(), lookup_domain)
+            .interpolate()
+            + &blinding_polynomial(s_1_hiding_bound, &v_L, rng);
+        let s_2_poly = &EvaluationsOnDomain::from_vec_and_domain(s_2.clone(), lookup_domain)
+            .interpolate()
+            + &blinding_polynomial(zk_bound, &v_L, rng);
+        end_timer!(sorted_time);
+
+        LookupVectors {
+            f,
+            t,
+            s_1,
+            s_2,
+            s_1_poly: LabeledPolynomial::new("lookup_s_1".into(), s_1_poly, None, s_1_hiding_bound),
+            s_2_poly: LabeledPolynomial::new("lookup_s_2".into(), s_2_poly, None, zk_bound),
+        }
     }
 
     /// Output the number of oracles sent by the prover in the second round.
@@ -578,133 +1033,355 @@ impl<F: PrimeField> AHPForR1CS<F> {
     pub fn prover_second_round_degree_bounds(
         info: &IndexInfo<F>,
     ) -> impl Iterator<Item = Option<usize>> {
-        let h_domain_size =
-            GeneralEvaluationDomain::<F>::compute_size_of_domain(info.num_constraints).unwrap();
+        let h_domain_size = GeneralEvaluationDomain::<F>::compute_size_of_domain(core::cmp::max(
+            info.num_constraints,
+            info.num_variables,
+        ))
+        .unwrap();
 
-        vec![None, Some(h_domain_size - 2), None].into_iter()
+        let num_lookup_oracles = if info.has_lookups() { 2 } else { 0 };
+        vec![None, Some(h_domain_size - 2), None]
+            .into_iter()
+            .chain(vec![None; num_lookup_oracles])
     }
 
     /// Output the third round message and the next state.
     pub fn prover_third_round<'a, R: RngCore>(
         ver_message: &VerifierSecondMsg<F>,
         prover_state: ProverState<'a, F>,
-        _r: &mut R,
+        rng: &mut R,
     ) -> Result<(ProverMsg<F>, ProverThirdOracles<F>), Error> {
         let round_time = start_timer!(|| "AHP::Prover::ThirdRound");
 
         let ProverState {
             index,
             verifier_first_msg,
-            domain_h,
+            constraint_domain,
+            variable_domain,
             domain_k,
+            lookup_domain,
+            lookup_vectors,
+            z_a_poly,
+            zk_mode,
+            mut spiller,
             ..
         } = prover_state;
 
+        let verifier_first_msg = verifier_first_msg.expect(
+            "ProverState should include verifier_first_msg when prover_third_round is called",
+        );
         let VerifierFirstMsg {
             eta_a,
             eta_b,
             eta_c,
             alpha,
-        } = verifier_first_msg.expect(
-            "ProverState should include verifier_first_msg when prover_third_round is called",
-        );
+            zeta,
+            ..
+        } = verifier_first_msg;
+        let gate_etas = verifier_first_msg.gate_etas(index.gates.len());
 
         let beta = ver_message.beta;
 
-        let v_H_at_alpha = domain_h.evaluate_vanishing_polynomial(alpha);
-        let v_H_at_beta = domain_h.evaluate_vanishing_polynomial(beta);
+        let v_H_at_alpha = constraint_domain.evaluate_vanishing_polynomial(alpha);
+        let v_H_at_beta = variable_domain.evaluate_vanishing_polynomial(beta);
 
         let v_H_alpha_v_H_beta = v_H_at_alpha * v_H_at_beta;
         let eta_a_times_v_H_alpha_v_H_beta = eta_a * v_H_alpha_v_H_beta;
         let eta_b_times_v_H_alpha_v_H_beta = eta_b * v_H_alpha_v_H_beta;
         let eta_c_times_v_H_alpha_v_H_beta = eta_c * v_H_alpha_v_H_beta;
+        // The arithmetizations list the `val` polynomials of A, B, C and of
+        // the factor matrices in this order, whatever the layout.
+        let mut etas = vec![
+            eta_a_times_v_H_alpha_v_H_beta,
+            eta_b_times_v_H_alpha_v_H_beta,
+            eta_c_times_v_H_alpha_v_H_beta,
+        ]
+        .into_iter()
+        .chain(gate_etas.into_iter().map(|eta| eta * v_H_alpha_v_H_beta));
 
-        let joint_arith = &index.joint_arith;
-
-        let a_poly_time = start_timer!(|| "Computing a poly");
-        let a_poly = {
-            let a = joint_arith.val_a.coeffs();
-            let b = joint_arith.val_b.coeffs();
-            let c = joint_arith.val_c.coeffs();
-            let coeffs: Vec<F> = cfg_iter!(a)
-                .zip(b)
-                .zip(c)
-                .map(|((a, b), c)| {
-                    eta_a_times_v_H_alpha_v_H_beta * a
-                        + eta_b_times_v_H_alpha_v_H_beta * b
-                        + eta_c_times_v_H_alpha_v_H_beta * c
-                })
-                .collect();
-            DensePolynomial::from_coefficients_vec(coeffs)
-        };
-        end_timer!(a_poly_time);
-
-        let (row_on_K, col_on_K, row_col_on_K) = (
-            &joint_arith.evals_on_K.row,
-            &joint_arith.evals_on_K.col,
-            &joint_arith.evals_on_K.row_col,
-        );
-        let b_poly_time = start_timer!(|| "Computing b poly");
         let alpha_beta = alpha * beta;
-        let b_poly = {
-            let evals: Vec<F> = cfg_iter!(row_on_K.evals)
-                .zip(&col_on_K.evals)
-                .zip(&row_col_on_K.evals)
-                .map(|((r, c), r_c)| alpha_beta - alpha * r - beta * c + r_c)
-                .collect();
-            EvaluationsOnDomain::from_vec_and_domain(evals, domain_k).interpolate()
-        };
-        end_timer!(b_poly_time);
+        let mut a_polys = Vec::with_capacity(index.arithmetizations.len());
+        let mut b_polys = Vec::with_capacity(index.arithmetizations.len());
+        let mut a_evals_on_coset = Vec::with_capacity(index.arithmetizations.len());
+        let mut b_evals_on_coset = Vec::with_capacity(index.arithmetizations.len());
+        let mut f_evals_on_K = vec![F::zero(); domain_k.size()];
+        for arith in &index.arithmetizations {
+            let etas: Vec<F> = etas.by_ref().take(arith.val.len()).collect();
+
+            if let Some(evals_on_coset) = &arith.evals_on_coset {
+                let a_evals_time = start_timer!(|| "Computing a evals on the coset");
+                let mut a_evals = vec![F::zero(); evals_on_coset.row.evals.len()];
+                for (eta, val) in etas.iter().zip(&evals_on_coset.val) {
+                    cfg_iter_mut!(a_evals)
+                        .zip(&val.evals)
+                        .for_each(|(a, v)| *a += *eta * v);
+                }
+                end_timer!(a_evals_time);
+
+                let b_evals_time = start_timer!(|| "Computing b evals on the coset");
+                let b_evals: Vec<F> = cfg_iter!(evals_on_coset.row.evals)
+                    .zip(&evals_on_coset.col.evals)
+                    .zip(&evals_on_coset.row_col.evals)
+                    .map(|((r, c), r_c)| alpha_beta - alpha * r - beta * c + r_c)
+                    .collect();
+                end_timer!(b_evals_time);
+
+                a_evals_on_coset.push(a_evals);
+                b_evals_on_coset.push(b_evals);
+            } else {
+                let a_poly_time = start_timer!(|| "Computing a poly");
+                let a_poly = {
+                    let mut coeffs = vec![F::zero(); domain_k.size()];
+                    for (eta, val) in etas.iter().zip(&arith.val) {
+                        cfg_iter_mut!(coeffs)
+                            .zip(val.coeffs())
+                            .for_each(|(coeff, v)| *coeff += *eta * v);
+                    }
+                    DensePolynomial::from_coefficients_vec(coeffs)
+                };
+                end_timer!(a_poly_time);
+
+                let b_poly_time = start_timer!(|| "Computing b poly");
+                let b_poly = {
+                    let evals: Vec<F> = cfg_iter!(arith.evals_on_K.row.evals)
+                        .zip(&arith.evals_on_K.col.evals)
+                        .zip(&arith.evals_on_K.row_col.evals)
+                        .map(|((r, c), r_c)| alpha_beta - alpha * r - beta * c + r_c)
+                        .collect();
+                    EvaluationsOnDomain::from_vec_and_domain(evals, domain_k).interpolate()
+                };
+                end_timer!(b_poly_time);
 
-        let f_evals_time = start_timer!(|| "Computing f evals on K");
-        let mut inverses: Vec<_> = cfg_into_iter!(0..domain_k.size())
-            .map(|i| (beta - row_on_K[i]) * (alpha - col_on_K[i]))
-            .collect();
-        ark_ff::batch_inversion(&mut inverses);
+                a_polys.push(a_poly);
+                b_polys.push(b_poly);
+            }
 
-        let (val_a_on_K, val_b_on_K, val_c_on_K) = (
-            &joint_arith.evals_on_K.val_a,
-            &joint_arith.evals_on_K.val_b,
-            &joint_arith.evals_on_K.val_c,
-        );
-        let f_evals_on_K: Vec<_> = cfg_into_iter!(0..(domain_k.size()))
-            .map(|i| {
-                inverses[i]
-                    * (eta_a_times_v_H_alpha_v_H_beta * val_a_on_K[i]
-                        + eta_b_times_v_H_alpha_v_H_beta * val_b_on_K[i]
-                        + eta_c_times_v_H_alpha_v_H_beta * val_c_on_K[i])
-            })
-            .collect();
-        end_timer!(f_evals_time);
+            let (row_on_K, col_on_K) = (&arith.evals_on_K.row, &arith.evals_on_K.col);
+            let f_evals_time = start_timer!(|| "Computing f evals on K");
+            let mut inverses: Vec<_> = cfg_into_iter!(0..domain_k.size())
+                .map(|i| (beta - row_on_K[i]) * (alpha - col_on_K[i]))
+                .collect();
+            ark_ff::batch_inversion(&mut inverses);
+
+            let vals_on_K = &arith.evals_on_K.val;
+            cfg_iter_mut!(f_evals_on_K).enumerate().for_each(|(i, f)| {
+                let val: F = etas
+                    .iter()
+                    .zip(vals_on_K)
+                    .map(|(eta, val_on_K)| *eta * val_on_K[i])
+                    .sum();
+                *f += inverses[i] * val;
+            });
+            end_timer!(f_evals_time);
+        }
 
         let f_poly_time = start_timer!(|| "Computing f poly");
         let f = EvaluationsOnDomain::from_vec_and_domain(f_evals_on_K, domain_k).interpolate();
         end_timer!(f_poly_time);
 
         let h_2_poly_time = start_timer!(|| "Computing sumcheck h poly");
-        let h_2 = (&a_poly - &(&b_poly * &f))
-            .divide_by_vanishing_poly(domain_k)
-            .unwrap()
-            .0;
+        let coset_domain = index.arithmetizations[0]
+            .evals_on_coset
+            .as_ref()
+            .map(|evals| evals.row.domain());
+        let h_2 = match coset_domain {
+            Some(coset_domain) => Self::inner_sumcheck_quotient_on_coset(
+                &a_evals_on_coset,
+                &b_evals_on_coset,
+                &f,
+                domain_k,
+                coset_domain,
+            ),
+            None => {
+                // With several arithmetizations, the fractions are brought to the
+                // common denominator `b = prod_j b_j`, and `a = sum_j a_j prod_{i != j} b_i`.
+                let a_poly = a_polys
+                    .iter()
+                    .enumerate()
+                    .map(|(j, a_j)| {
+                        b_polys
+                            .iter()
+                            .enumerate()
+                            .filter(|(i, _)| *i != j)
+                            .fold(a_j.clone(), |acc, (_, b_i)| &acc * b_i)
+                    })
+                    .fold(DensePolynomial::zero(), |acc, a_j| &acc + &a_j);
+                let b_poly = b_polys
+                    .iter()
+                    .skip(1)
+                    .fold(b_polys[0].clone(), |acc, b_i| &acc * b_i);
+                (&a_poly - &(&b_poly * &f))
+                    .divide_by_vanishing_poly(domain_k)
+                    .unwrap()
+                    .0
+            }
+        };
         end_timer!(h_2_poly_time);
-        drop(a_poly);
-        drop(b_poly);
+        drop(a_polys);
+        drop(b_polys);
+        drop(a_evals_on_coset);
+        drop(b_evals_on_coset);
         let g_2 = DensePolynomial::from_coefficients_slice(&f.coeffs[1..]);
         drop(f);
 
         let msg = ProverMsg::EmptyMessage;
 
-        assert!(h_2.degree() <= domain_k.size() - 2);
+        assert!(h_2.degree() < index.arithmetizations.len() * (domain_k.size() - 1));
         assert!(g_2.degree() <= domain_k.size() - 2);
+        let (lookup_z, lookup_quotient) = match (&index.lookup, lookup_domain, lookup_vectors) {
+            (Some(lookup), Some(lookup_domain), Some(vectors)) => {
+                let z_a_poly =
+                    DensePolynomial::from_coefficients_vec(spiller.load(z_a_poly.unwrap())?);
+                let (z, quotient) = Self::lookup_grand_product(
+                    lookup,
+                    &vectors,
+                    &z_a_poly,
+                    (
+                        zeta.unwrap(),
+                        ver_message.delta.unwrap(),
+                        ver_message.epsilon.unwrap(),
+                    ),
+                    lookup_domain,
+                    zk_mode.hiding_bound(index.index_info.zk_bound),
+                    rng,
+                );
+                (Some(z), Some(quotient))
+            }
+            _ => (None, None),
+        };
+
         let oracles = ProverThirdOracles {
             g_2: LabeledPolynomial::new("g_2".to_string(), g_2, Some(domain_k.size() - 2), None),
             h_2: LabeledPolynomial::new("h_2".to_string(), h_2, None, None),
+            lookup_z,
+            lookup_quotient,
         };
         end_timer!(round_time);
 
         Ok((msg, oracles))
     }
 
+    /// Compute the grand product `Z` of the lookup argument over the lookup
+    /// domain `L`, and the quotient of the grand product check by `v_L`.
+    fn lookup_grand_product<R: RngCore>(
+        lookup: &LookupArithmetization<F>,
+        vectors: &LookupVectors<F>,
+        z_a_poly: &DensePolynomial<F>,
+        (zeta, delta, epsilon): (F, F, F),
+        lookup_domain: GeneralEvaluationDomain<F>,
+        zk_bound: Option<usize>,
+        rng: &mut R,
+    ) -> (LabeledPolynomial<F>, LabeledPolynomial<F>) {
+        let grand_product_time = start_timer!(|| "Computing lookup grand product");
+        let l_size = lookup_domain.size();
+        let one_plus_delta = F::one() + delta;
+        let epsilon_one_plus_delta = epsilon * one_plus_delta;
+        let LookupVectors { f, t, s_1, s_2, .. } = vectors;
+
+        let mut numerators = Vec::with_capacity(l_size);
+        let mut denominators = Vec::with_capacity(l_size);
+        for j in 0..l_size {
+            let next = (j + 1) % l_size;
+            numerators.push(
+                one_plus_delta
+                    * (epsilon + f[j])
+                    * (epsilon_one_plus_delta + t[j] + delta * t[next]),
+            );
+            denominators.push(
+                (epsilon_one_plus_delta + s_1[j] + delta * s_2[j])
+                    * (epsilon_one_plus_delta + s_2[j] + delta * s_1[next]),
+            );
+        }
+        ark_ff::batch_inversion(&mut denominators);
+        let mut z_evals = Vec::with_capacity(l_size);
+        let mut z_j = F::one();
+        for (numerator, denominator) in numerators.iter().zip(&denominators) {
+            z_evals.push(z_j);
+            z_j *= *numerator * denominator;
+        }
+        // The product wraps around `L` to one if the sorted vector is correct.
+        debug_assert!(z_j.is_one());
+
+        // `Z` is queried at three points: at one, at `gamma` in the grand
+        // product check, and at `omega * gamma`, each up to `zk_bound` times.
+        let v_L: DensePolynomial<F> = lookup_domain.vanishing_polynomial().into();
+        let z_hiding_bound = zk_bound.map(|b| b + 2);
+        let z_poly = &EvaluationsOnDomain::from_vec_and_domain(z_evals, lookup_domain)
+            .interpolate()
+            + &blinding_polynomial(z_hiding_bound, &v_L, rng);
+        end_timer!(grand_product_time);
+
+        let quotient_time = start_timer!(|| "Computing lookup quotient");
+        let constant = |c: F| DensePolynomial::from_coefficients_vec(vec![c]);
+        let s_1_poly = vectors.s_1_poly.polynomial();
+        let s_2_poly = vectors.s_2_poly.polynomial();
+
+        let mut f_poly = lookup.selector.polynomial() * z_a_poly;
+        f_poly += (zeta, lookup.tag.polynomial());
+        f_poly += &constant(epsilon);
+        let mut t_poly = lookup.table_value.polynomial().clone();
+        t_poly += (zeta, lookup.table_tag.polynomial());
+
+        let mut d_1 = constant(epsilon_one_plus_delta);
+        d_1 += s_1_poly;
+        d_1 += (delta, s_2_poly);
+        let mut d_2 = constant(epsilon_one_plus_delta);
+        d_2 += s_2_poly;
+        d_2 += (delta, &shift(s_1_poly, lookup_domain));
+        let mut n_2 = constant(epsilon_one_plus_delta);
+        n_2 += &t_poly;
+        n_2 += (delta, &shift(&t_poly, lookup_domain));
+
+        let mut check = &(&shift(&z_poly, lookup_domain) * &d_1) * &d_2;
+        check += (-one_plus_delta, &(&(&z_poly * &f_poly) * &n_2));
+        let (quotient, remainder) = check.divide_by_vanishing_poly(lookup_domain).unwrap();
+        assert!(remainder.is_zero());
+        end_timer!(quotient_time);
+
+        (
+            LabeledPolynomial::new("lookup_z".to_string(), z_poly, None, z_hiding_bound),
+            LabeledPolynomial::new("lookup_quotient".to_string(), quotient, None, None),
+        )
+    }
+
+    /// Compute the inner sumcheck polynomial `h_2 = (a - b * f) / v_K` from
+    /// the evaluations of `a_j` and `b_j` of each arithmetization on the coset
+    /// of `coset_domain`, with `a` and `b` brought to a common denominator as
+    /// in the coefficient form.
+    fn inner_sumcheck_quotient_on_coset(
+        a_evals: &[Vec<F>],
+        b_evals: &[Vec<F>],
+        f: &DensePolynomial<F>,
+        domain_k: GeneralEvaluationDomain<F>,
+        coset_domain: GeneralEvaluationDomain<F>,
+    ) -> DensePolynomial<F> {
+        let f_evals = coset_domain.coset_fft(&f.coeffs);
+
+        // `v_K(g w^i) = g^|K| w^(i |K|) - 1` repeats every `|coset| / |K|` elements.
+        let period = coset_domain.size() / domain_k.size();
+        let mut v_K_inverses: Vec<F> = (0..period)
+            .map(|i| {
+                let x = F::multiplicative_generator() * coset_domain.element(i);
+                domain_k.evaluate_vanishing_polynomial(x)
+            })
+            .collect();
+        ark_ff::batch_inversion(&mut v_K_inverses);
+
+        let mut h_2_evals: Vec<F> = cfg_into_iter!(0..coset_domain.size())
+            .map(|i| {
+                let (a, b) = a_evals
+                    .iter()
+                    .zip(b_evals)
+                    .fold((F::zero(), F::one()), |(a, b), (a_j, b_j)| {
+                        (a * b_j[i] + a_j[i] * b, b * b_j[i])
+                    });
+                (a - b * f_evals[i]) * v_K_inverses[i % period]
+            })
+            .collect();
+        coset_domain.coset_ifft_in_place(&mut h_2_evals);
+        DensePolynomial::from_coefficients_vec(h_2_evals)
+    }
+
     /// Output the number of oracles sent by the prover in the third round.
     pub fn prover_num_third_round_oracles() -> usize {
         2
@@ -717,6 +1394,9 @@ impl<F: PrimeField> AHPForR1CS<F> {
         let num_non_zero = info.num_non_zero;
         let k_size = GeneralEvaluationDomain::<F>::compute_size_of_domain(num_non_zero).unwrap();
 
-        vec![Some(k_size - 2), None].into_iter()
+        let num_lookup_oracles = if info.has_lookups() { 2 } else { 0 };
+        vec![Some(k_size - 2), None]
+            .into_iter()
+            .chain(vec![None; num_lookup_oracles])
     }
 }
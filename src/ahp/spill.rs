@@ -0,0 +1,328 @@
+use crate::Vec;
+use ark_ff::PrimeField;
+use ark_std::io;
+
+#[cfg(feature = "std")]
+use ark_serialize::SerializationError;
+#[cfg(feature = "std")]
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The number of files that provers have spilled vectors to, which names the
+/// next one.
+#[cfg(feature = "std")]
+static NUM_SPILL_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// An optional disk cache for the vectors that the prover holds between
+/// rounds: the witness, `z_A`, `z_B` and the other products of the matrices
+/// with the assignment, and copies of its first round oracles. Once these
+/// vectors take more than `max_bytes`, the ones that do not fit are written to
+/// files in `dir`, and read back whole in the round that needs them.
+///
+/// This is not a bound on the memory of the prover. The index with its
+/// matrices, the constraint system that the prover synthesizes, the
+/// polynomials of the round that it computes, commits to and opens, and the
+/// vectors that it reads back are all in memory regardless of `max_bytes`.
+///
+/// The files hold the witness and vectors derived from it, unencrypted, so
+/// `dir` should only be readable by the prover. They are removed once the
+/// prover has read them back or is dropped, but stay behind if the process is
+/// killed or aborts before then.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct DiskCache {
+    /// The number of bytes that the vectors held between rounds may take in
+    /// memory before they are written to `dir`.
+    pub max_bytes: usize,
+    /// The directory that the vectors over `max_bytes` are written to.
+    pub dir: PathBuf,
+}
+
+#[cfg(feature = "std")]
+impl DiskCache {
+    /// A cache that writes the vectors over `max_bytes` bytes to `dir`.
+    pub fn new(max_bytes: usize, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            max_bytes,
+            dir: dir.into(),
+        }
+    }
+}
+
+/// The size in bytes of the uncompressed encoding of an element of `F`.
+fn element_size<F: PrimeField>() -> usize {
+    F::zero().uncompressed_size()
+}
+
+/// Decides which of the vectors that the prover holds between rounds stay in
+/// memory, and writes the others to disk.
+pub(crate) struct Spiller {
+    #[cfg(feature = "std")]
+    cache: Option<DiskCache>,
+    /// The bytes taken by the vectors that are held in memory.
+    resident_bytes: usize,
+    /// The most bytes that the vectors held in memory, including those that
+    /// are being built, have taken at once.
+    peak_bytes: usize,
+}
+
+impl Spiller {
+    /// A spiller that holds every vector in memory.
+    pub(crate) fn unbounded() -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            cache: None,
+            resident_bytes: 0,
+            peak_bytes: 0,
+        }
+    }
+
+    /// A spiller that holds vectors in memory up to the size of `cache`, and
+    /// writes the others to it.
+    #[cfg(feature = "std")]
+    pub(crate) fn with_cache(cache: DiskCache) -> Self {
+        Self {
+            cache: Some(cache),
+            ..Self::unbounded()
+        }
+    }
+
+    /// The number of elements of `F` that the prover processes at once when
+    /// it streams a vector: an eighth of the size of the cache, so that the
+    /// chunks of the few vectors that it streams together stay well within
+    /// it.
+    pub(crate) fn chunk_len<F: PrimeField>(&self) -> usize {
+        #[cfg(feature = "std")]
+        if let Some(cache) = &self.cache {
+            return core::cmp::max(1, cache.max_bytes / (8 * element_size::<F>()));
+        }
+        usize::MAX
+    }
+
+    /// Whether `bytes` more bytes fit in memory.
+    #[cfg(feature = "std")]
+    fn fits(&self, bytes: usize) -> bool {
+        match &self.cache {
+            Some(cache) => self.resident_bytes + bytes <= cache.max_bytes,
+            None => true,
+        }
+    }
+
+    /// Record that `bytes` more bytes are held in memory.
+    fn track(&mut self, bytes: usize) {
+        self.peak_bytes = core::cmp::max(self.peak_bytes, self.resident_bytes + bytes);
+    }
+
+    /// The most bytes that the vectors held between rounds have taken in
+    /// memory at once.
+    #[cfg(test)]
+    pub(crate) fn peak_bytes(&self) -> usize {
+        self.peak_bytes
+    }
+
+    /// Start a vector whose elements are appended in chunks, and which is
+    /// written to disk as soon as it no longer fits in memory.
+    pub(crate) fn builder<F: PrimeField>(&mut self) -> SpillVecBuilder<'_, F> {
+        SpillVecBuilder {
+            spiller: self,
+            elems: Vec::new(),
+            #[cfg(feature = "std")]
+            file: None,
+        }
+    }
+
+    /// Hold `elems` until it is loaded, in memory if it fits.
+    pub(crate) fn store<F: PrimeField>(&mut self, elems: Vec<F>) -> Result<SpillVec<F>, io::Error> {
+        let mut builder = self.builder();
+        builder.extend(elems)?;
+        builder.finish()
+    }
+
+    /// Read back all of `vec`, and release the memory it was holding.
+    pub(crate) fn load<F: PrimeField>(&mut self, vec: SpillVec<F>) -> Result<Vec<F>, io::Error> {
+        let mut elems = Vec::with_capacity(vec.len());
+        for chunk in self.chunks(vec)? {
+            elems.extend(chunk?);
+        }
+        Ok(elems)
+    }
+
+    /// Read back `vec` in chunks of `chunk_len` elements, and release the
+    /// memory it was holding.
+    pub(crate) fn chunks<F: PrimeField>(
+        &mut self,
+        vec: SpillVec<F>,
+    ) -> Result<SpillChunks<F>, io::Error> {
+        match vec.0 {
+            SpillVecInner::InMemory(elems) => {
+                self.resident_bytes -= elems.len() * element_size::<F>();
+                Ok(SpillChunks(SpillChunksInner::InMemory(Some(elems))))
+            }
+            #[cfg(feature = "std")]
+            SpillVecInner::OnDisk(file) => Ok(SpillChunks(SpillChunksInner::OnDisk {
+                reader: BufReader::new(File::open(&file.path)?),
+                remaining: file.len,
+                chunk_len: self.chunk_len::<F>(),
+                _file: file,
+            })),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn create_file(&self) -> Result<(File, PathBuf), io::Error> {
+        let dir = &self
+            .cache
+            .as_ref()
+            .expect("only spillers with a cache spill")
+            .dir;
+        let path = dir.join(format!(
+            "marlin-prover-{}-{}.bin",
+            std::process::id(),
+            NUM_SPILL_FILES.fetch_add(1, Ordering::Relaxed),
+        ));
+        Ok((File::create(&path)?, path))
+    }
+}
+
+/// A vector of field elements that the prover holds between rounds, in
+/// memory or in a file.
+pub(crate) struct SpillVec<F>(SpillVecInner<F>);
+
+enum SpillVecInner<F> {
+    InMemory(Vec<F>),
+    #[cfg(feature = "std")]
+    OnDisk(SpillFile),
+}
+
+impl<F> SpillVec<F> {
+    /// The number of elements of the vector.
+    pub(crate) fn len(&self) -> usize {
+        match &self.0 {
+            SpillVecInner::InMemory(elems) => elems.len(),
+            #[cfg(feature = "std")]
+            SpillVecInner::OnDisk(file) => file.len,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn into_io_error(err: SerializationError) -> io::Error {
+    match err {
+        SerializationError::IoError(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+/// A file that holds a spilled vector, which is removed when it is dropped.
+#[cfg(feature = "std")]
+struct SpillFile {
+    path: PathBuf,
+    len: usize,
+}
+
+#[cfg(feature = "std")]
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// A vector that is being appended to in chunks.
+pub(crate) struct SpillVecBuilder<'a, F> {
+    spiller: &'a mut Spiller,
+    elems: Vec<F>,
+    #[cfg(feature = "std")]
+    file: Option<(BufWriter<File>, SpillFile)>,
+}
+
+impl<'a, F: PrimeField> SpillVecBuilder<'a, F> {
+    /// Append `chunk` to the vector.
+    pub(crate) fn extend(&mut self, chunk: Vec<F>) -> Result<(), io::Error> {
+        #[cfg(feature = "std")]
+        {
+            let size = (self.elems.len() + chunk.len()) * element_size::<F>();
+            if self.file.is_none() && !self.spiller.fits(size) {
+                let (file, path) = self.spiller.create_file()?;
+                self.file = Some((BufWriter::new(file), SpillFile { path, len: 0 }));
+                let elems = core::mem::take(&mut self.elems);
+                self.write(&elems)?;
+            }
+            if self.file.is_some() {
+                return self.write(&chunk);
+            }
+        }
+        self.elems.extend(chunk);
+        self.spiller.track(self.elems.len() * element_size::<F>());
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    fn write(&mut self, elems: &[F]) -> Result<(), io::Error> {
+        let (writer, file) = self.file.as_mut().unwrap();
+        for elem in elems {
+            elem.serialize_uncompressed(&mut *writer)
+                .map_err(into_io_error)?;
+        }
+        file.len += elems.len();
+        Ok(())
+    }
+
+    /// The vector of all the chunks appended so far.
+    pub(crate) fn finish(self) -> Result<SpillVec<F>, io::Error> {
+        #[cfg(feature = "std")]
+        if let Some((mut writer, file)) = self.file {
+            writer.flush()?;
+            return Ok(SpillVec(SpillVecInner::OnDisk(file)));
+        }
+        self.spiller.resident_bytes += self.elems.len() * element_size::<F>();
+        Ok(SpillVec(SpillVecInner::InMemory(self.elems)))
+    }
+}
+
+/// The chunks of a vector that is read back.
+pub(crate) struct SpillChunks<F>(SpillChunksInner<F>);
+
+enum SpillChunksInner<F> {
+    InMemory(Option<Vec<F>>),
+    #[cfg(feature = "std")]
+    OnDisk {
+        reader: BufReader<File>,
+        remaining: usize,
+        chunk_len: usize,
+        _file: SpillFile,
+    },
+}
+
+impl<F: PrimeField> Iterator for SpillChunks<F> {
+    type Item = Result<Vec<F>, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            SpillChunksInner::InMemory(elems) => {
+                elems.take().filter(|elems| !elems.is_empty()).map(Ok)
+            }
+            #[cfg(feature = "std")]
+            SpillChunksInner::OnDisk {
+                reader,
+                remaining,
+                chunk_len,
+                ..
+            } => {
+                if *remaining == 0 {
+                    return None;
+                }
+                let len = core::cmp::min(*chunk_len, *remaining);
+                *remaining -= len;
+                Some(
+                    (0..len)
+                        .map(|_| F::deserialize_uncompressed(&mut *reader).map_err(into_io_error))
+                        .collect(),
+                )
+            }
+        }
+    }
+}
@@ -1,17 +1,24 @@
 #![allow(non_snake_case)]
 
-use crate::ahp::indexer::IndexInfo;
+use crate::ahp::indexer::{ArithmetizationLayout, IndexInfo};
+use crate::ahp::lookup::lookup_domain;
 use crate::ahp::*;
-use ark_std::rand::RngCore;
+use crate::rng::{FiatShamirRng, Transcript};
 
-use ark_ff::PrimeField;
+use ark_ff::{Field, PrimeField};
 use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
 use ark_poly_commit::QuerySet;
+use ark_std::collections::BTreeSet;
 
 /// State of the AHP verifier
 pub struct VerifierState<F: PrimeField> {
     pub(crate) domain_h: GeneralEvaluationDomain<F>,
+    pub(crate) constraint_domain: GeneralEvaluationDomain<F>,
+    pub(crate) variable_domain: GeneralEvaluationDomain<F>,
     pub(crate) domain_k: GeneralEvaluationDomain<F>,
+    pub(crate) domain_x: GeneralEvaluationDomain<F>,
+    pub(crate) lookup_domain: Option<GeneralEvaluationDomain<F>>,
+    pub(crate) index_info: IndexInfo<F>,
 
     pub(crate) first_round_msg: Option<VerifierFirstMsg<F>>,
     pub(crate) second_round_msg: Option<VerifierSecondMsg<F>>,
@@ -30,6 +37,28 @@ pub struct VerifierFirstMsg<F> {
     pub eta_b: F,
     /// Randomizer for the lincheck for `C`.
     pub eta_c: F,
+    /// Randomizer for the linchecks for the factor matrices of the product
+    /// gates, if the index has product gates.
+    pub eta_gate: Option<F>,
+    /// Randomizer that compresses the looked-up pairs of values and tags,
+    /// if the index has lookups.
+    pub zeta: Option<F>,
+}
+
+impl<F: Field> VerifierFirstMsg<F> {
+    /// The randomizers for the linchecks for the `num_gate_matrices` factor
+    /// matrices of the product gates: the powers `eta_gate^1, eta_gate^2, ...`.
+    pub fn gate_etas(&self, num_gate_matrices: usize) -> Vec<F> {
+        let mut etas = Vec::with_capacity(num_gate_matrices);
+        if let Some(eta_gate) = self.eta_gate {
+            let mut eta = eta_gate;
+            for _ in 0..num_gate_matrices {
+                etas.push(eta);
+                eta *= eta_gate;
+            }
+        }
+        etas
+    }
 }
 
 /// Second verifier message.
@@ -37,39 +66,68 @@ pub struct VerifierFirstMsg<F> {
 pub struct VerifierSecondMsg<F> {
     /// Query for the second round of polynomials.
     pub beta: F,
+    /// Randomizer for the pairs of consecutive entries in the lookup grand
+    /// product, if the index has lookups.
+    pub delta: Option<F>,
+    /// Randomizer for the entries in the lookup grand product, if the index
+    /// has lookups.
+    pub epsilon: Option<F>,
 }
 
 impl<F: PrimeField> AHPForR1CS<F> {
     /// Output the first message and next round state.
-    pub fn verifier_first_round<R: RngCore>(
+    pub fn verifier_first_round<FS: FiatShamirRng>(
         index_info: IndexInfo<F>,
-        rng: &mut R,
+        transcript: &mut Transcript<FS>,
     ) -> Result<(VerifierFirstMsg<F>, VerifierState<F>), Error> {
-        if index_info.num_constraints != index_info.num_variables {
-            return Err(Error::NonSquareMatrix);
-        }
+        let constraint_domain = GeneralEvaluationDomain::new(index_info.num_constraints)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
 
-        let domain_h = GeneralEvaluationDomain::new(index_info.num_constraints)
+        let variable_domain = GeneralEvaluationDomain::new(index_info.num_variables)
             .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
 
+        let domain_h = if constraint_domain.size() > variable_domain.size() {
+            constraint_domain
+        } else {
+            variable_domain
+        };
+
         let domain_k = GeneralEvaluationDomain::new(index_info.num_non_zero)
             .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
 
-        let alpha = domain_h.sample_element_outside_domain(rng);
-        let eta_a = F::rand(rng);
-        let eta_b = F::rand(rng);
-        let eta_c = F::rand(rng);
+        let domain_x = GeneralEvaluationDomain::new(index_info.num_instance_variables)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+
+        let alpha = Self::sample_challenge_outside_domain(b"alpha", &domain_h, transcript);
+        let eta_a = transcript.challenge_field_element(b"eta_a");
+        let eta_b = transcript.challenge_field_element(b"eta_b");
+        let eta_c = transcript.challenge_field_element(b"eta_c");
+        let eta_gate = if index_info.has_product_gates() {
+            Some(transcript.challenge_field_element(b"eta_gate"))
+        } else {
+            None
+        };
+
+        let lookup_domain = lookup_domain(&index_info)?;
+        let zeta = lookup_domain.map(|_| transcript.challenge_field_element(b"zeta"));
 
         let msg = VerifierFirstMsg {
             alpha,
             eta_a,
             eta_b,
             eta_c,
+            eta_gate,
+            zeta,
         };
 
         let new_state = VerifierState {
             domain_h,
+            constraint_domain,
+            variable_domain,
             domain_k,
+            domain_x,
+            lookup_domain,
+            index_info,
             first_round_msg: Some(msg),
             second_round_msg: None,
             gamma: None,
@@ -79,36 +137,94 @@ impl<F: PrimeField> AHPForR1CS<F> {
     }
 
     /// Output the second message and next round state.
-    pub fn verifier_second_round<R: RngCore>(
+    pub fn verifier_second_round<FS: FiatShamirRng>(
         mut state: VerifierState<F>,
-        rng: &mut R,
+        transcript: &mut Transcript<FS>,
     ) -> (VerifierSecondMsg<F>, VerifierState<F>) {
-        let beta = state.domain_h.sample_element_outside_domain(rng);
-        let msg = VerifierSecondMsg { beta };
+        let beta = Self::sample_challenge_outside_domain(b"beta", &state.domain_h, transcript);
+        let (delta, epsilon) = match state.lookup_domain {
+            Some(_) => (
+                Some(transcript.challenge_field_element(b"delta")),
+                Some(transcript.challenge_field_element(b"epsilon")),
+            ),
+            None => (None, None),
+        };
+        let msg = VerifierSecondMsg {
+            beta,
+            delta,
+            epsilon,
+        };
         state.second_round_msg = Some(msg);
 
         (msg, state)
     }
 
     /// Output the third message and next round state.
-    pub fn verifier_third_round<R: RngCore>(
+    pub fn verifier_third_round<FS: FiatShamirRng>(
         mut state: VerifierState<F>,
-        rng: &mut R,
+        transcript: &mut Transcript<FS>,
     ) -> VerifierState<F> {
-        state.gamma = Some(F::rand(rng));
+        state.gamma = Some(transcript.challenge_field_element(b"gamma"));
         state
     }
 
+    /// Draw a verifier challenge under `label` that does not lie in `domain`.
+    fn sample_challenge_outside_domain<FS: FiatShamirRng>(
+        label: &[u8],
+        domain: &GeneralEvaluationDomain<F>,
+        transcript: &mut Transcript<FS>,
+    ) -> F {
+        loop {
+            let challenge = transcript.challenge_field_element(label);
+            if !domain.evaluate_vanishing_polynomial(challenge).is_zero() {
+                break challenge;
+            }
+        }
+    }
+
     /// Output the query state and next round state.
-    pub fn verifier_query_set<'a, R: RngCore>(
+    pub fn verifier_query_set<FS: FiatShamirRng>(
         state: VerifierState<F>,
-        _: &'a mut R,
+        _: &mut Transcript<FS>,
     ) -> (QuerySet<F>, VerifierState<F>) {
         let beta = state.second_round_msg.unwrap().beta;
 
         let gamma = state.gamma.unwrap();
+        let shifted_gamma = state
+            .lookup_domain
+            .map(|lookup_domain| gamma * lookup_domain.element(1));
+
+        let query_set: QuerySet<F> = Self::query_labels(&state.index_info)
+            .into_iter()
+            .map(|(label, point_label)| {
+                let point = match point_label {
+                    "beta" => beta,
+                    "gamma" => gamma,
+                    "shifted_gamma" => shifted_gamma.unwrap(),
+                    _ => F::one(),
+                };
+                (label, (point_label.to_string(), point))
+            })
+            .collect();
+
+        (query_set, state)
+    }
+
+    /// The labels of the evaluations that a proof for an index described by
+    /// `info` carries, in order, each with the label of the point it is at.
+    pub fn evaluation_labels(info: &IndexInfo<F>) -> Vec<(String, String)> {
+        Self::query_labels(info)
+            .into_iter()
+            .filter(|(label, _)| !Self::LC_WITH_ZERO_EVAL.contains(&label.as_str()))
+            .map(|(label, point_label)| (label, point_label.to_string()))
+            .collect()
+    }
 
-        let mut query_set = QuerySet::new();
+    /// The labels of the polynomials and linear combinations that the
+    /// verifier queries, each with the label of the point it is queried at:
+    /// `beta`, `gamma`, `shifted_gamma` or `one`.
+    fn query_labels(info: &IndexInfo<F>) -> BTreeSet<(String, &'static str)> {
+        let mut query_set = BTreeSet::new();
         // For the first linear combination
         // Outer sumcheck test:
         //   s(beta) + r(alpha, beta) * (sum_M eta_M z_M(beta)) - t(beta) * z(beta)
@@ -136,10 +252,15 @@ impl<F: PrimeField> AHPForR1CS<F> {
         //  LinearCombination::new("z_b", vec![(F::one(), z_b)])
         //  LinearCombination::new("g_1", vec![(F::one(), g_1)], rhs::new(g_1_at_beta))
         //  LinearCombination::new("t", vec![(F::one(), t)])
-        query_set.insert(("g_1".into(), ("beta".into(), beta)));
-        query_set.insert(("z_b".into(), ("beta".into(), beta)));
-        query_set.insert(("t".into(), ("beta".into(), beta)));
-        query_set.insert(("outer_sumcheck".into(), ("beta".into(), beta)));
+        query_set.insert(("g_1".into(), "beta"));
+        query_set.insert(("z_b".into(), "beta"));
+        query_set.insert(("t".into(), "beta"));
+        query_set.insert(("outer_sumcheck".into(), "beta"));
+        // With product gates, the verifier also queries `z_d`, `z_e`, ... to
+        // compute the virtual oracle `z_c = z_a * z_b + prod_k z_k`.
+        for name in &info.matrix_names()[3..] {
+            query_set.insert((format!("z_{}", name), "beta"));
+        }
 
         // For the second linear combination
         // Inner sumcheck test:
@@ -181,9 +302,46 @@ impl<F: PrimeField> AHPForR1CS<F> {
         // // This LC is the only one that is evaluated:
         // let inner_sumcheck = a_poly_lc - (b_lc * (gamma * &g_2_at_gamma + &(t_at_beta / &k_size))) - h_lc
         // main_lc.set_label("inner_sumcheck");
-        query_set.insert(("g_2".into(), ("gamma".into(), gamma)));
-        query_set.insert(("inner_sumcheck".into(), ("gamma".into(), gamma)));
+        //
+        // With the per-matrix layout, each matrix M has its own denominator
+        //   b_M(X) := (beta - row_M(X)) (alpha - col_M(X)),
+        // which is queried at gamma as the linear combination "M_denom".
+        query_set.insert(("g_2".into(), "gamma"));
+        query_set.insert(("inner_sumcheck".into(), "gamma"));
+        if info.arithmetization == ArithmetizationLayout::PerMatrix {
+            for name in info.matrix_names() {
+                query_set.insert((format!("{}_denom", name), "gamma"));
+            }
+        }
 
-        (query_set, state)
+        // Lookup test, if the index has lookups. With the looked-up values
+        //   f(X) := selector(X) z_a(X) + zeta tag(X)
+        // and the combined table
+        //   t(X) := table_value(X) + zeta table_tag(X),
+        // the grand product Z over L must satisfy
+        //   Z(omega gamma) (e (1 + d) + s_1(gamma) + d s_2(gamma))
+        //       (e (1 + d) + s_2(gamma) + d s_1(omega gamma))
+        //   - Z(gamma) (1 + d) (e + f(gamma)) (e (1 + d) + t(gamma) + d t(omega gamma))
+        // = quotient(gamma) v_L(gamma)
+        // and Z(1) = 1, where omega generates L.
+        if info.has_lookups() {
+            for label in &[
+                "z_a",
+                "lookup_selector",
+                "lookup_tag",
+                "lookup_table",
+                "lookup_s_1",
+                "lookup_s_2",
+                "lookup_check",
+            ] {
+                query_set.insert((label.to_string(), "gamma"));
+            }
+            for label in &["lookup_table", "lookup_s_1", "lookup_z"] {
+                query_set.insert((label.to_string(), "shifted_gamma"));
+            }
+            query_set.insert(("lookup_boundary".into(), "one"));
+        }
+
+        query_set
     }
 }
@@ -0,0 +1,394 @@
+use crate::ahp::Error as AHPError;
+use crate::rng::{BytesEncoding, FiatShamirRng};
+use crate::{
+    AHPForR1CS, BTreeMap, Error, IndexVerifierKey, Marlin, PCCheck, Proof, String, ToString, Vec,
+};
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{One, PrimeField, Zero};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::{
+    kzg10, marlin_pc::MarlinKZG10, BatchLCProof, Error as PCError, Evaluations, LCTerm,
+    LabeledCommitment, LinearCombination, PolynomialCommitment, QuerySet,
+};
+use ark_std::{collections::BTreeSet, format, rand::RngCore};
+
+/// A polynomial commitment scheme whose `check_combinations` can be split into
+/// a cheap, per-proof reduction step and an expensive final check, so that the
+/// final checks of many proofs can be folded into a single randomized check.
+pub trait BatchVerifiablePC<F: PrimeField>: PolynomialCommitment<F, DensePolynomial<F>> {
+    /// The claims that remain to be checked once the opening check of a single
+    /// proof has been reduced.
+    type BatchCheck;
+
+    /// Reduce the check performed by `check_combinations` to a `BatchCheck`.
+    /// This does not perform any pairing (or other expensive) operation.
+    ///
+    /// Returns `None` if `proof` or `commitments` do not have the shape that
+    /// the linear combinations require, in which case the check fails.
+    fn reduce_combinations<'a>(
+        vk: &Self::VerifierKey,
+        linear_combinations: impl IntoIterator<Item = &'a LinearCombination<F>>,
+        commitments: impl IntoIterator<Item = &'a LabeledCommitment<Self::Commitment>>,
+        eqn_query_set: &QuerySet<F>,
+        eqn_evaluations: &Evaluations<F, F>,
+        proof: &BatchLCProof<F, DensePolynomial<F>, Self>,
+        opening_challenge: F,
+    ) -> Result<Option<Self::BatchCheck>, Self::Error>
+    where
+        Self::Commitment: 'a;
+
+    /// Whether claims reduced under `vk` and under `other_vk` can be checked
+    /// together, i.e. whether both keys were trimmed from the same universal
+    /// parameters.
+    fn can_batch_with(vk: &Self::VerifierKey, other_vk: &Self::VerifierKey) -> bool;
+
+    /// Check all of the claims in `batch` with a single randomized check.
+    fn check_batch<'a, R: RngCore>(
+        vk: &Self::VerifierKey,
+        batch: impl IntoIterator<Item = &'a Self::BatchCheck>,
+        rng: &mut R,
+    ) -> Result<bool, Self::Error>
+    where
+        Self::BatchCheck: 'a;
+}
+
+/// The KZG10 opening claims that a `MarlinKZG10` opening check reduces to:
+/// one (commitment, point, value, proof) tuple per query point.
+pub struct KZG10BatchCheck<E: PairingEngine> {
+    commitments: Vec<kzg10::Commitment<E>>,
+    points: Vec<E::Fr>,
+    values: Vec<E::Fr>,
+    proofs: Vec<kzg10::Proof<E>>,
+}
+
+impl<E: PairingEngine> BatchVerifiablePC<E::Fr> for MarlinKZG10<E, DensePolynomial<E::Fr>> {
+    type BatchCheck = KZG10BatchCheck<E>;
+
+    fn reduce_combinations<'a>(
+        vk: &Self::VerifierKey,
+        linear_combinations: impl IntoIterator<Item = &'a LinearCombination<E::Fr>>,
+        commitments: impl IntoIterator<Item = &'a LabeledCommitment<Self::Commitment>>,
+        eqn_query_set: &QuerySet<E::Fr>,
+        eqn_evaluations: &Evaluations<E::Fr, E::Fr>,
+        proof: &BatchLCProof<E::Fr, DensePolynomial<E::Fr>, Self>,
+        opening_challenge: E::Fr,
+    ) -> Result<Option<Self::BatchCheck>, Self::Error>
+    where
+        Self::Commitment: 'a,
+    {
+        let reduce_time = start_timer!(|| "Reducing MarlinKZG10 opening check");
+        let label_comm_map = commitments
+            .into_iter()
+            .map(|c| (c.label(), c))
+            .collect::<BTreeMap<_, _>>();
+
+        // Commit to every linear combination, moving the constant terms over
+        // to the claimed evaluations.
+        let mut evaluations = eqn_evaluations.clone();
+        let mut lc_commitments = BTreeMap::new();
+        for lc in linear_combinations {
+            let lc_label = lc.label();
+            let num_polys = lc.len();
+
+            let mut degree_bound = None;
+            let mut comm = E::G1Projective::zero();
+            let mut shifted_comm: Option<E::G1Projective> = None;
+            for (coeff, term) in lc.iter() {
+                match term {
+                    LCTerm::One => {
+                        for ((label, _), eval) in evaluations.iter_mut() {
+                            if label == lc_label {
+                                *eval -= coeff;
+                            }
+                        }
+                    }
+                    LCTerm::PolyLabel(label) => {
+                        let cur_comm = label_comm_map.get(label).ok_or_else(|| {
+                            PCError::MissingPolynomial {
+                                label: label.to_string(),
+                            }
+                        })?;
+                        if cur_comm.degree_bound().is_some() {
+                            if num_polys != 1 || !coeff.is_one() {
+                                return Err(PCError::EquationHasDegreeBounds(lc_label.clone()));
+                            }
+                            degree_bound = cur_comm.degree_bound();
+                        }
+
+                        let cur_comm = cur_comm.commitment();
+                        comm += &cur_comm.comm.0.mul(*coeff);
+                        if let Some(cur_shifted_comm) = &cur_comm.shifted_comm {
+                            let cur = cur_shifted_comm.0.mul(*coeff);
+                            shifted_comm = Some(shifted_comm.map_or(cur, |c| c + cur));
+                        }
+                    }
+                }
+            }
+            if degree_bound.is_some() != shifted_comm.is_some() {
+                end_timer!(reduce_time);
+                return Ok(None);
+            }
+            lc_commitments.insert(lc_label.clone(), (comm, shifted_comm, degree_bound));
+        }
+
+        // Combine the linear combinations queried at the same point, exactly as
+        // `MarlinKZG10::batch_check` does, but stop short of the pairing check.
+        let mut query_to_labels_map: BTreeMap<&String, (E::Fr, BTreeSet<&String>)> =
+            BTreeMap::new();
+        for (label, (point_label, point)) in eqn_query_set.iter() {
+            query_to_labels_map
+                .entry(point_label)
+                .or_insert((*point, BTreeSet::new()))
+                .1
+                .insert(label);
+        }
+
+        let proofs: Vec<_> = proof.proof.clone();
+        if proofs.len() != query_to_labels_map.len() {
+            end_timer!(reduce_time);
+            return Ok(None);
+        }
+
+        let mut combined_comms = Vec::with_capacity(proofs.len());
+        let mut points = Vec::with_capacity(proofs.len());
+        let mut values = Vec::with_capacity(proofs.len());
+        for (_, (point, labels)) in query_to_labels_map {
+            let mut combined_comm = E::G1Projective::zero();
+            let mut combined_value = E::Fr::zero();
+            let mut challenge = E::Fr::one();
+            for label in labels {
+                let (comm, shifted_comm, degree_bound) =
+                    lc_commitments
+                        .get(label)
+                        .ok_or_else(|| PCError::MissingPolynomial {
+                            label: label.to_string(),
+                        })?;
+                let value = *evaluations.get(&(label.clone(), point)).ok_or_else(|| {
+                    PCError::MissingEvaluation {
+                        label: label.to_string(),
+                    }
+                })?;
+
+                combined_comm += &comm.mul(challenge.into_repr());
+                combined_value += &(value * challenge);
+                challenge *= opening_challenge;
+
+                if let (Some(shifted_comm), Some(degree_bound)) = (shifted_comm, degree_bound) {
+                    let shift_power = vk
+                        .get_shift_power(*degree_bound)
+                        .ok_or(PCError::UnsupportedDegreeBound(*degree_bound))?;
+                    let adjusted_comm = *shifted_comm - shift_power.mul(value);
+                    combined_comm += &adjusted_comm.mul(challenge.into_repr());
+                    challenge *= opening_challenge;
+                }
+            }
+            combined_comms.push(combined_comm);
+            points.push(point);
+            values.push(combined_value);
+        }
+        let commitments = E::G1Projective::batch_normalization_into_affine(&combined_comms)
+            .into_iter()
+            .map(kzg10::Commitment)
+            .collect();
+        end_timer!(reduce_time);
+
+        Ok(Some(KZG10BatchCheck {
+            commitments,
+            points,
+            values,
+            proofs,
+        }))
+    }
+
+    fn can_batch_with(vk: &Self::VerifierKey, other_vk: &Self::VerifierKey) -> bool {
+        // Only the KZG10 verifier key is used by `check_batch`; the shift powers
+        // for degree bounds are already applied in `reduce_combinations`.
+        let (vk, other_vk) = (&vk.vk, &other_vk.vk);
+        vk.g == other_vk.g
+            && vk.gamma_g == other_vk.gamma_g
+            && vk.h == other_vk.h
+            && vk.beta_h == other_vk.beta_h
+    }
+
+    fn check_batch<'a, R: RngCore>(
+        vk: &Self::VerifierKey,
+        batch: impl IntoIterator<Item = &'a Self::BatchCheck>,
+        rng: &mut R,
+    ) -> Result<bool, Self::Error> {
+        let mut commitments = Vec::new();
+        let mut points = Vec::new();
+        let mut values = Vec::new();
+        let mut proofs = Vec::new();
+        for check in batch {
+            commitments.extend_from_slice(&check.commitments);
+            points.extend_from_slice(&check.points);
+            values.extend_from_slice(&check.values);
+            proofs.extend_from_slice(&check.proofs);
+        }
+        kzg10::KZG10::<E, DensePolynomial<E::Fr>>::batch_check(
+            &vk.vk,
+            &commitments,
+            &points,
+            &values,
+            &proofs,
+            rng,
+        )
+    }
+}
+
+impl<F: PrimeField, PC: BatchVerifiablePC<F>, FS: FiatShamirRng> Marlin<F, PC, FS> {
+    /// Verify many proofs for the same index at once. The Fiat-Shamir
+    /// transcript of each proof is replayed on its own, after which all of the
+    /// polynomial commitment checks are folded into a single randomized check.
+    ///
+    /// Returns the positions in `public_inputs_and_proofs` of the proofs that
+    /// were rejected, so the whole batch is valid if and only if this is empty.
+    pub fn batch_verify<R: RngCore>(
+        index_vk: &IndexVerifierKey<F, PC>,
+        public_inputs_and_proofs: &[(&[F], &Proof<F, PC>)],
+        rng: &mut R,
+    ) -> Result<Vec<usize>, Error<PC::Error>> {
+        let verifier_time = start_timer!(|| format!(
+            "Marlin::BatchVerify with {} proofs",
+            public_inputs_and_proofs.len()
+        ));
+
+        let batch = public_inputs_and_proofs
+            .iter()
+            .map(|(public_input, proof)| Self::reduce_proof(index_vk, public_input, proof));
+        let rejected = Self::find_rejected(&index_vk.verifier_key, batch, rng)?;
+
+        end_timer!(verifier_time, || format!(
+            " Rejected proofs: {:?}",
+            rejected
+        ));
+        Ok(rejected)
+    }
+
+    /// Verify many proofs for possibly different indices at once, as long as
+    /// all of the indices were derived from the same universal SRS. As in
+    /// `batch_verify`, every transcript is replayed on its own, and then the
+    /// polynomial commitment checks of all proofs are folded into one.
+    ///
+    /// Returns the positions in `vks_inputs_and_proofs` of the proofs that
+    /// were rejected, so the whole batch is valid if and only if this is empty.
+    #[allow(clippy::type_complexity)]
+    pub fn batch_verify_heterogeneous<R: RngCore>(
+        vks_inputs_and_proofs: &[(&IndexVerifierKey<F, PC>, &[F], &Proof<F, PC>)],
+        rng: &mut R,
+    ) -> Result<Vec<usize>, Error<PC::Error>> {
+        let verifier_time = start_timer!(|| format!(
+            "Marlin::BatchVerifyHeterogeneous with {} proofs",
+            vks_inputs_and_proofs.len()
+        ));
+
+        let vk = match vks_inputs_and_proofs.first() {
+            Some((index_vk, _, _)) => &index_vk.verifier_key,
+            None => return Ok(Vec::new()),
+        };
+        if vks_inputs_and_proofs
+            .iter()
+            .any(|(index_vk, _, _)| !PC::can_batch_with(vk, &index_vk.verifier_key))
+        {
+            return Err(Error::IncompatibleVerifierKeys);
+        }
+
+        let batch = vks_inputs_and_proofs
+            .iter()
+            .map(|(index_vk, public_input, proof)| {
+                Self::reduce_proof(index_vk, public_input, proof)
+            });
+        let rejected = Self::find_rejected(vk, batch, rng)?;
+
+        end_timer!(verifier_time, || format!(
+            " Rejected proofs: {:?}",
+            rejected
+        ));
+        Ok(rejected)
+    }
+
+    /// Replay the transcript of `proof` and reduce its opening check. Returns
+    /// `None` if the proof is malformed for `index_vk` and `public_input`, so
+    /// that it can be rejected without being checked.
+    fn reduce_proof(
+        index_vk: &IndexVerifierKey<F, PC>,
+        public_input: &[F],
+        proof: &Proof<F, PC>,
+    ) -> Result<Option<PC::BatchCheck>, Error<PC::Error>> {
+        let info = &index_vk.index_info;
+        let num_comms = [
+            AHPForR1CS::prover_first_round_degree_bounds(info).count(),
+            AHPForR1CS::prover_second_round_degree_bounds(info).count(),
+            AHPForR1CS::prover_third_round_degree_bounds(info).count(),
+        ];
+        if proof.commitments.len() != num_comms.len()
+            || proof.prover_messages.len() != num_comms.len()
+            || proof
+                .commitments
+                .iter()
+                .zip(&num_comms)
+                .any(|(comms, n)| comms.len() != *n)
+        {
+            return Ok(None);
+        }
+
+        let pc_check = match Self::replay_transcript::<BytesEncoding>(index_vk, public_input, proof)
+        {
+            Ok(pc_check) => pc_check,
+            Err(Error::AHPError(AHPError::InvalidPublicInputLength))
+            | Err(Error::AHPError(AHPError::MissingEval(_))) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let PCCheck {
+            lc_s,
+            commitments,
+            query_set,
+            evaluations,
+            opening_challenge,
+        } = pc_check;
+
+        PC::reduce_combinations(
+            &index_vk.verifier_key,
+            &lc_s,
+            &commitments,
+            &query_set,
+            &evaluations,
+            &proof.pc_proof,
+            opening_challenge,
+        )
+        .map_err(Error::from_pc_err)
+    }
+
+    /// Check `batch` with a single folded check. Only if that fails is every
+    /// element checked on its own, to find out which ones are invalid. The
+    /// malformed proofs, such as those with a public input of the wrong
+    /// length, are rejected without being checked, while any other error is
+    /// returned to the caller.
+    fn find_rejected<R: RngCore>(
+        vk: &PC::VerifierKey,
+        batch: impl Iterator<Item = Result<Option<PC::BatchCheck>, Error<PC::Error>>>,
+        rng: &mut R,
+    ) -> Result<Vec<usize>, Error<PC::Error>> {
+        let mut rejected = Vec::new();
+        let mut checks = Vec::new();
+        for (i, check) in batch.enumerate() {
+            match check? {
+                Some(check) => checks.push((i, check)),
+                None => rejected.push(i),
+            }
+        }
+        if PC::check_batch(vk, checks.iter().map(|(_, check)| check), rng)
+            .map_err(Error::from_pc_err)?
+        {
+            return Ok(rejected);
+        }
+
+        for (i, check) in &checks {
+            if !PC::check_batch(vk, ark_std::iter::once(check), rng).map_err(Error::from_pc_err)? {
+                rejected.push(*i);
+            }
+        }
+        rejected.sort_unstable();
+        Ok(rejected)
+    }
+}
@@ -0,0 +1,313 @@
+//! `marlin`: universal setup, indexing, proving and verification from the
+//! command line.
+//!
+//! Keys, proofs and universal parameters are written in the versioned format
+//! of `ark_marlin::versioned`. Constraint systems are read in the JSON form of
+//! `ark_marlin::json::R1csJson`, assignments in that of `AssignmentJson`, and
+//! public inputs as a JSON array of big-endian hex integers. Transcripts use
+//! `KeccakFiatShamirRng` and absorb commitments as the ABI encoding of their
+//! points, so that BN254 proofs for `marlin-kzg` are those that the generated
+//! Solidity verifier checks.
+
+use ark_ff::PrimeField;
+use ark_marlin::json::{decode_field_elements, AssignmentJson, IndexInfoJson, JsonCircuit};
+use ark_marlin::rng::PointCommitment;
+use ark_marlin::versioned::{read_versioned, write_versioned, ArtifactKind, VersionedPC};
+use ark_marlin::{AHPForR1CS, IndexProverKey, IndexVerifierKey, IndexerConfig};
+use ark_marlin::{KeccakFiatShamirRng, Marlin, Proof, ZkMode};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::{marlin_pc::MarlinKZG10, sonic_pc::SonicKZG10};
+use rand::rngs::{OsRng, StdRng};
+use rand::SeedableRng;
+use sha3::Keccak256;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::process;
+
+type MarlinInst<F, PC> = Marlin<F, PC, KeccakFiatShamirRng>;
+
+const USAGE: &str = "\
+usage: marlin <command> [--curve bn254|bls12-381] [--pc marlin-kzg|sonic-kzg] <options>
+
+commands:
+  setup    --max-constraints <n> --max-variables <n> --max-non-zero <n>
+           [--zk-bound <n>] --out <srs>
+  index    --r1cs <r1cs.json> --srs <srs> --pk <pk> --vk <vk>
+  prove    --pk <pk> --r1cs <r1cs.json> --witness <witness.json> --out <proof>
+  verify   --vk <vk> --public-inputs <inputs.json> --proof <proof>
+  inspect  --vk <vk> [--proof <proof>]
+
+`prove` checks that the constraint system is the one that the key was indexed
+from. `verify` exits with 0 if the proof is valid and with 1 if it is not.
+Every command exits with 2 if its arguments or files cannot be used.";
+
+/// The exit code of `verify` for an invalid proof.
+const INVALID_PROOF: i32 = 1;
+/// The exit code for arguments or files that cannot be used.
+const USAGE_ERROR: i32 = 2;
+
+/// The `--name value` options of a command, which are removed as the command
+/// reads them, so that those left over can be reported.
+struct Options(HashMap<String, String>);
+
+impl Options {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = HashMap::new();
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("unexpected argument `{}`", arg))?;
+            let value = args
+                .next()
+                .ok_or_else(|| format!("option `--{}` needs a value", name))?;
+            if options.insert(name.to_string(), value).is_some() {
+                return Err(format!("option `--{}` is given more than once", name));
+            }
+        }
+        Ok(Self(options))
+    }
+
+    fn optional(&mut self, name: &str) -> Option<String> {
+        self.0.remove(name)
+    }
+
+    fn required(&mut self, name: &str) -> Result<String, String> {
+        self.optional(name)
+            .ok_or_else(|| format!("missing option `--{}`", name))
+    }
+
+    fn optional_number(&mut self, name: &str) -> Result<Option<usize>, String> {
+        self.optional(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("option `--{}` is not a number: `{}`", name, value))
+            })
+            .transpose()
+    }
+
+    fn number(&mut self, name: &str) -> Result<usize, String> {
+        self.optional_number(name)?
+            .ok_or_else(|| format!("missing option `--{}`", name))
+    }
+
+    /// Fail if options that the command does not take are left over.
+    fn finish(self) -> Result<(), String> {
+        match self.0.keys().min() {
+            Some(name) => Err(format!("unknown option `--{}`", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn open(path: &str) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> Result<T, String> {
+    serde_json::from_reader(open(path)?).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn read_artifact<F, PC, T>(kind: ArtifactKind, path: &str) -> Result<T, String>
+where
+    F: PrimeField,
+    PC: VersionedPC<F>,
+    T: ark_serialize::CanonicalDeserialize,
+{
+    read_versioned::<F, PC, T, _>(kind, open(path)?).map_err(|e| format!("{}: {:?}", path, e))
+}
+
+fn write_artifact<F, PC, T>(kind: ArtifactKind, value: &T, path: &str) -> Result<(), String>
+where
+    F: PrimeField,
+    PC: VersionedPC<F>,
+    T: ark_serialize::CanonicalSerialize,
+{
+    let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut writer = BufWriter::new(file);
+    write_versioned::<F, PC, T, _>(kind, value, &mut writer)
+        .map_err(|e| format!("{}: {:?}", path, e))?;
+    writer.flush().map_err(|e| format!("{}: {}", path, e))
+}
+
+/// A generator seeded from the randomness of the operating system.
+fn os_rng() -> Result<StdRng, String> {
+    StdRng::from_rng(OsRng).map_err(|e| format!("cannot seed the generator: {}", e))
+}
+
+/// Run `command` with Marlin over `F` and `PC`, and return its exit code.
+fn run<F, PC>(command: &str, mut options: Options) -> Result<i32, String>
+where
+    F: PrimeField,
+    PC: VersionedPC<F>,
+    PC::Commitment: PointCommitment,
+{
+    match command {
+        "setup" => {
+            let num_constraints = options.number("max-constraints")?;
+            let num_variables = options.number("max-variables")?;
+            let num_non_zero = options.number("max-non-zero")?;
+            let zk_bound = options.optional_number("zk-bound")?.unwrap_or(1);
+            let out = options.required("out")?;
+            options.finish()?;
+
+            let srs = MarlinInst::<F, PC>::universal_setup_with_zk_bound(
+                num_constraints,
+                num_variables,
+                num_non_zero,
+                zk_bound,
+                &mut os_rng()?,
+            )
+            .map_err(|e| format!("setup failed: {:?}", e))?;
+            write_artifact::<F, PC, _>(ArtifactKind::UniversalSRS, &srs, &out)?;
+        }
+        "index" => {
+            let r1cs = options.required("r1cs")?;
+            let srs = options.required("srs")?;
+            let pk = options.required("pk")?;
+            let vk = options.required("vk")?;
+            options.finish()?;
+
+            let circuit = JsonCircuit::<F>::from_json_value(&read_json(&r1cs)?)
+                .map_err(|e| format!("{}: {:?}", r1cs, e))?;
+            let srs = read_artifact::<F, PC, _>(ArtifactKind::UniversalSRS, &srs)?;
+            let (index_pk, index_vk) = MarlinInst::<F, PC>::index(&srs, circuit)
+                .map_err(|e| format!("indexing failed: {:?}", e))?;
+            write_artifact::<F, PC, _>(ArtifactKind::IndexProverKey, &index_pk, &pk)?;
+            write_artifact::<F, PC, _>(ArtifactKind::IndexVerifierKey, &index_vk, &vk)?;
+        }
+        "prove" => {
+            let pk = options.required("pk")?;
+            let r1cs = options.required("r1cs")?;
+            let witness = options.required("witness")?;
+            let out = options.required("out")?;
+            options.finish()?;
+
+            let index_pk: IndexProverKey<F, PC> =
+                read_artifact::<F, PC, _>(ArtifactKind::IndexProverKey, &pk)?;
+            let assignment: AssignmentJson = read_json(&witness)?;
+            let circuit = JsonCircuit::<F>::from_json_value(&read_json(&r1cs)?)
+                .map_err(|e| format!("{}: {:?}", r1cs, e))?;
+
+            // The prover reads the constraints from the circuit, so they must
+            // be those that the key was indexed from.
+            let info = index_pk.index_vk.index_info;
+            let config = IndexerConfig {
+                arithmetization: info.arithmetization,
+                zk_bound: info.zk_bound,
+                ..IndexerConfig::default()
+            };
+            let index = AHPForR1CS::index_with_config(circuit.clone(), config)
+                .map_err(|e| format!("{}: {:?}", r1cs, e))?;
+            if index.circuit_digest::<Keccak256>() != index_pk.index.circuit_digest::<Keccak256>() {
+                return Err(format!("{}: not the constraint system of {}", r1cs, pk));
+            }
+
+            let circuit = circuit
+                .with_assignment(&assignment)
+                .map_err(|e| format!("{}: {:?}", witness, e))?;
+            let proof = MarlinInst::<F, PC>::prove_with_abi_commitments(
+                &index_pk,
+                circuit,
+                ZkMode::ZeroKnowledge,
+                &mut os_rng()?,
+            )
+            .map_err(|e| format!("proving failed: {:?}", e))?;
+            write_artifact::<F, PC, _>(ArtifactKind::Proof, &proof, &out)?;
+        }
+        "verify" => {
+            let vk = options.required("vk")?;
+            let public_inputs = options.required("public-inputs")?;
+            let proof = options.required("proof")?;
+            options.finish()?;
+
+            let index_vk: IndexVerifierKey<F, PC> =
+                read_artifact::<F, PC, _>(ArtifactKind::IndexVerifierKey, &vk)?;
+            let inputs: Vec<String> = read_json(&public_inputs)?;
+            let inputs = decode_field_elements::<F>(&inputs)
+                .map_err(|e| format!("{}: {:?}", public_inputs, e))?;
+            let proof: Proof<F, PC> = read_artifact::<F, PC, _>(ArtifactKind::Proof, &proof)?;
+            let rng = &mut os_rng()?;
+            let result =
+                MarlinInst::<F, PC>::verify_with_abi_commitments(&index_vk, &inputs, &proof, rng);
+            match result {
+                Ok(true) => println!("proof is valid"),
+                Ok(false) => {
+                    println!("proof is invalid");
+                    return Ok(INVALID_PROOF);
+                }
+                Err(e) => {
+                    println!("proof is invalid: {:?}", e);
+                    return Ok(INVALID_PROOF);
+                }
+            }
+        }
+        "inspect" => {
+            let vk = options.required("vk")?;
+            let proof = options.optional("proof");
+            options.finish()?;
+
+            let index_vk: IndexVerifierKey<F, PC> =
+                read_artifact::<F, PC, _>(ArtifactKind::IndexVerifierKey, &vk)?;
+            let info = serde_json::to_string_pretty(&IndexInfoJson::from(&index_vk.index_info))
+                .map_err(|e| e.to_string())?;
+            println!("Index info:\n{}", info);
+            if let Some(proof) = proof {
+                let proof: Proof<F, PC> = read_artifact::<F, PC, _>(ArtifactKind::Proof, &proof)?;
+                println!("\n{}", proof.size_info());
+            }
+        }
+        _ => return Err(format!("unknown command `{}`", command)),
+    }
+    Ok(0)
+}
+
+/// Run `command` for the curve and the polynomial commitment scheme chosen
+/// by `--curve` and `--pc`.
+fn dispatch(command: &str, mut options: Options) -> Result<i32, String> {
+    use ark_bls12_381::{Bls12_381, Fr as Bls12Fr};
+    use ark_bn254::{Bn254, Fr as Bn254Fr};
+
+    let curve = options.optional("curve").unwrap_or_else(|| "bn254".into());
+    let pc = options
+        .optional("pc")
+        .unwrap_or_else(|| "marlin-kzg".into());
+    match (curve.as_str(), pc.as_str()) {
+        ("bn254", "marlin-kzg") => {
+            run::<Bn254Fr, MarlinKZG10<Bn254, DensePolynomial<Bn254Fr>>>(command, options)
+        }
+        ("bn254", "sonic-kzg") => {
+            run::<Bn254Fr, SonicKZG10<Bn254, DensePolynomial<Bn254Fr>>>(command, options)
+        }
+        ("bls12-381", "marlin-kzg") => {
+            run::<Bls12Fr, MarlinKZG10<Bls12_381, DensePolynomial<Bls12Fr>>>(command, options)
+        }
+        ("bls12-381", "sonic-kzg") => {
+            run::<Bls12Fr, SonicKZG10<Bls12_381, DensePolynomial<Bls12Fr>>>(command, options)
+        }
+        ("bn254", _) | ("bls12-381", _) => Err(format!("unknown scheme `{}`", pc)),
+        _ => Err(format!("unknown curve `{}`", curve)),
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let command = match args.next() {
+        Some(command) if command != "help" && command != "--help" => command,
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(USAGE_ERROR);
+        }
+    };
+    let code = Options::parse(args)
+        .and_then(|options| dispatch(&command, options))
+        .unwrap_or_else(|e| {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            USAGE_ERROR
+        });
+    process::exit(code);
+}
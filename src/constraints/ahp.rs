@@ -0,0 +1,404 @@
+#![allow(non_snake_case)]
+
+use crate::ahp::indexer::{ArithmetizationLayout, IndexInfo};
+use crate::constraints::rng::{FiatShamirRngVar, TranscriptVar};
+use crate::rng::FiatShamirRng;
+use crate::{format, String, ToString, Vec};
+use ark_ff::PrimeField;
+use ark_nonnative_field::NonNativeFieldVar;
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_poly_commit::LCTerm;
+use ark_poly_commit::{
+    EvaluationsVar, LabeledPointVar, LinearCombinationCoeffVar, LinearCombinationVar, QuerySetVar,
+};
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::SynthesisError;
+use ark_std::{marker::PhantomData, vec};
+use derivative::Derivative;
+use hashbrown::HashSet;
+
+/// State of the AHP verifier gadget.
+pub struct VerifierStateVar<F: PrimeField, CF: PrimeField> {
+    domain_h: GeneralEvaluationDomain<F>,
+    constraint_domain: GeneralEvaluationDomain<F>,
+    variable_domain: GeneralEvaluationDomain<F>,
+    domain_k: GeneralEvaluationDomain<F>,
+    domain_x: GeneralEvaluationDomain<F>,
+    num_instance_variables: usize,
+    arithmetization: ArithmetizationLayout,
+
+    first_round_msg: Option<VerifierFirstMsgVar<F, CF>>,
+    second_round_msg: Option<VerifierSecondMsgVar<F, CF>>,
+
+    gamma: Option<NonNativeFieldVar<F, CF>>,
+}
+
+/// First message of the verifier gadget.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct VerifierFirstMsgVar<F: PrimeField, CF: PrimeField> {
+    /// Query for the random polynomial.
+    pub alpha: NonNativeFieldVar<F, CF>,
+    /// Randomizer for the lincheck for `A`.
+    pub eta_a: NonNativeFieldVar<F, CF>,
+    /// Randomizer for the lincheck for `B`.
+    pub eta_b: NonNativeFieldVar<F, CF>,
+    /// Randomizer for the lincheck for `C`.
+    pub eta_c: NonNativeFieldVar<F, CF>,
+}
+
+/// Second message of the verifier gadget.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct VerifierSecondMsgVar<F: PrimeField, CF: PrimeField> {
+    /// Query for the second round of polynomials.
+    pub beta: NonNativeFieldVar<F, CF>,
+}
+
+/// The AHP verifier of `AHPForR1CS`, expressed as constraints over `CF`.
+pub struct AHPForR1CSGadget<F: PrimeField, CF: PrimeField> {
+    field: PhantomData<F>,
+    constraint_field: PhantomData<CF>,
+}
+
+impl<F: PrimeField, CF: PrimeField> AHPForR1CSGadget<F, CF> {
+    /// Output the first message and next round state.
+    ///
+    /// The native verifier resamples `alpha` in the negligible event that it
+    /// lands in `H`, whereas the gadget always takes the first sample.
+    #[allow(clippy::type_complexity)]
+    pub fn verifier_first_round<FS: FiatShamirRng, FSG: FiatShamirRngVar<F, CF, FS>>(
+        index_info: IndexInfo<F>,
+        transcript: &mut TranscriptVar<F, CF, FS, FSG>,
+    ) -> Result<(VerifierFirstMsgVar<F, CF>, VerifierStateVar<F, CF>), SynthesisError> {
+        let constraint_domain = GeneralEvaluationDomain::new(index_info.num_constraints)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+
+        let variable_domain = GeneralEvaluationDomain::new(index_info.num_variables)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+
+        let domain_h = if constraint_domain.size() > variable_domain.size() {
+            constraint_domain
+        } else {
+            variable_domain
+        };
+
+        let domain_k = GeneralEvaluationDomain::new(index_info.num_non_zero)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+
+        let domain_x = GeneralEvaluationDomain::new(index_info.num_instance_variables)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+
+        let alpha = transcript.challenge_128_bits_field_element(b"alpha")?;
+        let eta_a = transcript.challenge_128_bits_field_element(b"eta_a")?;
+        let eta_b = transcript.challenge_128_bits_field_element(b"eta_b")?;
+        let eta_c = transcript.challenge_128_bits_field_element(b"eta_c")?;
+
+        let msg = VerifierFirstMsgVar {
+            alpha,
+            eta_a,
+            eta_b,
+            eta_c,
+        };
+
+        let new_state = VerifierStateVar {
+            domain_h,
+            constraint_domain,
+            variable_domain,
+            domain_k,
+            domain_x,
+            num_instance_variables: index_info.num_instance_variables,
+            arithmetization: index_info.arithmetization,
+            first_round_msg: Some(msg.clone()),
+            second_round_msg: None,
+            gamma: None,
+        };
+
+        Ok((msg, new_state))
+    }
+
+    /// Output the second message and next round state.
+    #[allow(clippy::type_complexity)]
+    pub fn verifier_second_round<FS: FiatShamirRng, FSG: FiatShamirRngVar<F, CF, FS>>(
+        mut state: VerifierStateVar<F, CF>,
+        transcript: &mut TranscriptVar<F, CF, FS, FSG>,
+    ) -> Result<(VerifierSecondMsgVar<F, CF>, VerifierStateVar<F, CF>), SynthesisError> {
+        let beta = transcript.challenge_128_bits_field_element(b"beta")?;
+
+        let msg = VerifierSecondMsgVar { beta };
+        state.second_round_msg = Some(msg.clone());
+
+        Ok((msg, state))
+    }
+
+    /// Output the third message and next round state.
+    pub fn verifier_third_round<FS: FiatShamirRng, FSG: FiatShamirRngVar<F, CF, FS>>(
+        mut state: VerifierStateVar<F, CF>,
+        transcript: &mut TranscriptVar<F, CF, FS, FSG>,
+    ) -> Result<VerifierStateVar<F, CF>, SynthesisError> {
+        state.gamma = Some(transcript.challenge_128_bits_field_element(b"gamma")?);
+        Ok(state)
+    }
+
+    /// Output the query state and next round state.
+    pub fn verifier_query_set(
+        state: VerifierStateVar<F, CF>,
+    ) -> (QuerySetVar<F, CF>, VerifierStateVar<F, CF>) {
+        let beta = LabeledPointVar {
+            name: "beta".to_string(),
+            value: state.second_round_msg.as_ref().unwrap().beta.clone(),
+        };
+        let gamma = LabeledPointVar {
+            name: "gamma".to_string(),
+            value: state.gamma.clone().unwrap(),
+        };
+
+        let mut query_set = HashSet::new();
+        // See `AHPForR1CS::verifier_query_set` for the linear combinations
+        // that are queried at each point.
+        query_set.insert(("g_1".to_string(), beta.clone()));
+        query_set.insert(("z_b".to_string(), beta.clone()));
+        query_set.insert(("t".to_string(), beta.clone()));
+        query_set.insert(("outer_sumcheck".to_string(), beta));
+
+        query_set.insert(("g_2".to_string(), gamma.clone()));
+        if state.arithmetization == ArithmetizationLayout::PerMatrix {
+            for denom in &["a_denom", "b_denom", "c_denom"] {
+                query_set.insert((denom.to_string(), gamma.clone()));
+            }
+        }
+        query_set.insert(("inner_sumcheck".to_string(), gamma));
+
+        (QuerySetVar(query_set), state)
+    }
+
+    /// Construct the linear combinations that are checked by the AHP.
+    pub fn construct_linear_combinations(
+        public_input: &[NonNativeFieldVar<F, CF>],
+        evals: &EvaluationsVar<F, CF>,
+        state: &VerifierStateVar<F, CF>,
+    ) -> Result<Vec<LinearCombinationVar<F, CF>>, SynthesisError> {
+        let domain_h = state.domain_h;
+        let constraint_domain = state.constraint_domain;
+        let variable_domain = state.variable_domain;
+        let domain_k = state.domain_k;
+        let k_size = domain_k.size_as_field_element();
+
+        let x_domain = state.domain_x;
+
+        let mut formatted_input = vec![NonNativeFieldVar::one()];
+        formatted_input.extend_from_slice(public_input);
+        if formatted_input.len() != state.num_instance_variables {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let first_round_msg = state.first_round_msg.as_ref().unwrap();
+        let alpha = &first_round_msg.alpha;
+        let eta_a = &first_round_msg.eta_a;
+        let eta_b = &first_round_msg.eta_b;
+        let eta_c = &first_round_msg.eta_c;
+
+        let beta = &state.second_round_msg.as_ref().unwrap().beta;
+        let gamma = state.gamma.as_ref().unwrap();
+
+        let mut linear_combinations = Vec::new();
+
+        // Outer sumcheck:
+        let v_H_at_beta = evaluate_vanishing_polynomial(&domain_h, beta)?;
+        let v_X_at_beta = evaluate_vanishing_polynomial(&x_domain, beta)?;
+        let v_R_at_alpha = evaluate_vanishing_polynomial(&constraint_domain, alpha)?;
+        let v_R_at_beta = if constraint_domain.size() == domain_h.size() {
+            v_H_at_beta.clone()
+        } else {
+            evaluate_vanishing_polynomial(&constraint_domain, beta)?
+        };
+        let v_C_at_beta = if variable_domain.size() == domain_h.size() {
+            v_H_at_beta.clone()
+        } else {
+            evaluate_vanishing_polynomial(&variable_domain, beta)?
+        };
+
+        // See `AHPForR1CS::construct_linear_combinations` for the selectors
+        // that restrict the outer sumcheck to the constraint and variable
+        // domains. The native verifier never samples `alpha == beta`, for
+        // which the inverse below does not exist.
+        let r_alpha_at_beta = (&v_R_at_alpha - &v_R_at_beta)
+            * (alpha - beta).inverse()?
+            * evaluate_selector_polynomial(
+                &domain_h,
+                &constraint_domain,
+                &v_H_at_beta,
+                &v_R_at_beta,
+            )?;
+        let selector_at_beta =
+            evaluate_selector_polynomial(&domain_h, &variable_domain, &v_H_at_beta, &v_C_at_beta)?;
+
+        let z_b_at_beta = get_lc_eval(evals, "z_b", beta)?;
+        let t_at_beta = get_lc_eval(evals, "t", beta)?;
+        let g_1_at_beta = get_lc_eval(evals, "g_1", beta)?;
+
+        // The Lagrange coefficient of the `i`-th element `w^i` of the input
+        // domain at `beta` is `v_X(beta) * w^i / (|X| * (beta - w^i))`. The
+        // input is implicitly padded with zeros up to `|X|`, so only the
+        // coefficients of the actual inputs are computed.
+        let v_X_at_beta_over_size =
+            &v_X_at_beta * x_domain.size_as_field_element().inverse().unwrap();
+        let mut x_at_beta = NonNativeFieldVar::zero();
+        for (x, element) in formatted_input.iter().zip(x_domain.elements()) {
+            let lagrange_coeff = (&v_X_at_beta_over_size * element) * (beta - element).inverse()?;
+            x_at_beta += x * lagrange_coeff;
+        }
+
+        #[rustfmt::skip]
+        let outer_sumcheck = LinearCombinationVar {
+            label: "outer_sumcheck".to_string(),
+            terms: vec![
+                (LinearCombinationCoeffVar::One, "mask_poly".into()),
+
+                (var(&r_alpha_at_beta * (eta_a + eta_c * &z_b_at_beta)), "z_a".into()),
+                (var(&r_alpha_at_beta * eta_b * &z_b_at_beta), LCTerm::One),
+
+                (var((&t_at_beta * &selector_at_beta * &v_X_at_beta).negate()?), "w".into()),
+                (var((&t_at_beta * &selector_at_beta * &x_at_beta).negate()?), LCTerm::One),
+
+                (var(v_H_at_beta.negate()?), "h_1".into()),
+                (var((beta * &g_1_at_beta).negate()?), LCTerm::One),
+            ],
+        };
+
+        linear_combinations.push(single_poly_lc("z_b"));
+        linear_combinations.push(single_poly_lc("g_1"));
+        linear_combinations.push(single_poly_lc("t"));
+        linear_combinations.push(outer_sumcheck);
+
+        //  Inner sumcheck:
+        let beta_alpha = beta * alpha;
+        let g_2_at_gamma = get_lc_eval(evals, "g_2", gamma)?;
+
+        let v_K_at_gamma = evaluate_vanishing_polynomial(&domain_k, gamma)?;
+
+        let a_scale = &v_R_at_alpha * &v_C_at_beta;
+        let b_scale = gamma * &g_2_at_gamma + &(&t_at_beta * k_size.inverse().unwrap());
+
+        let inner_sumcheck = match state.arithmetization {
+            ArithmetizationLayout::Joint => {
+                #[rustfmt::skip]
+                let inner_sumcheck = LinearCombinationVar {
+                    label: "inner_sumcheck".to_string(),
+                    terms: vec![
+                        (var(eta_a * &a_scale), "a_val".into()),
+                        (var(eta_b * &a_scale), "b_val".into()),
+                        (var(eta_c * &a_scale), "c_val".into()),
+
+                        (var((&beta_alpha * &b_scale).negate()?), LCTerm::One),
+                        (var(alpha * &b_scale), "row".into()),
+                        (var(beta * &b_scale), "col".into()),
+                        (var(b_scale.negate()?), "row_col".into()),
+
+                        (var(v_K_at_gamma.negate()?), "h_2".into()),
+                    ],
+                };
+                inner_sumcheck
+            }
+            ArithmetizationLayout::PerMatrix => {
+                // See `AHPForR1CS::construct_linear_combinations` for the
+                // inner sumcheck with one denominator per matrix.
+                let mut denoms_at_gamma = Vec::new();
+                for prefix in &["a_", "b_", "c_"] {
+                    let denom = LinearCombinationVar {
+                        label: format!("{}denom", prefix),
+                        terms: vec![
+                            (var(beta_alpha.clone()), LCTerm::One),
+                            (var(alpha.negate()?), format!("{}row", prefix).into()),
+                            (var(beta.negate()?), format!("{}col", prefix).into()),
+                            (
+                                LinearCombinationCoeffVar::One,
+                                format!("{}row_col", prefix).into(),
+                            ),
+                        ],
+                    };
+                    denoms_at_gamma.push(get_lc_eval(evals, &denom.label, gamma)?);
+                    linear_combinations.push(denom);
+                }
+                let (a_denom, b_denom, c_denom) = (
+                    &denoms_at_gamma[0],
+                    &denoms_at_gamma[1],
+                    &denoms_at_gamma[2],
+                );
+
+                #[rustfmt::skip]
+                let inner_sumcheck = LinearCombinationVar {
+                    label: "inner_sumcheck".to_string(),
+                    terms: vec![
+                        (var(eta_a * &a_scale * b_denom * c_denom), "a_val".into()),
+                        (var(eta_b * &a_scale * a_denom * c_denom), "b_val".into()),
+                        (var(eta_c * &a_scale * a_denom * b_denom), "c_val".into()),
+
+                        (var((a_denom * b_denom * c_denom * &b_scale).negate()?), LCTerm::One),
+
+                        (var(v_K_at_gamma.negate()?), "h_2".into()),
+                    ],
+                };
+                inner_sumcheck
+            }
+        };
+
+        linear_combinations.push(single_poly_lc("g_2"));
+        linear_combinations.push(inner_sumcheck);
+
+        linear_combinations.sort_by(|a, b| a.label.cmp(&b.label));
+        Ok(linear_combinations)
+    }
+}
+
+/// Evaluate the vanishing polynomial of `domain`, i.e. `X^|domain| - 1`.
+fn evaluate_vanishing_polynomial<F: PrimeField, CF: PrimeField>(
+    domain: &GeneralEvaluationDomain<F>,
+    point: &NonNativeFieldVar<F, CF>,
+) -> Result<NonNativeFieldVar<F, CF>, SynthesisError> {
+    Ok(point.pow_by_constant([domain.size() as u64])? - F::one())
+}
+
+/// Evaluate the selector of `subdomain` in `domain` at a point, given the
+/// evaluations of the vanishing polynomials of both domains at that point.
+fn evaluate_selector_polynomial<F: PrimeField, CF: PrimeField>(
+    domain: &GeneralEvaluationDomain<F>,
+    subdomain: &GeneralEvaluationDomain<F>,
+    v_domain_at_point: &NonNativeFieldVar<F, CF>,
+    v_subdomain_at_point: &NonNativeFieldVar<F, CF>,
+) -> Result<NonNativeFieldVar<F, CF>, SynthesisError> {
+    if domain.size() == subdomain.size() {
+        return Ok(NonNativeFieldVar::one());
+    }
+    let scale = subdomain.size_as_field_element() / domain.size_as_field_element();
+    Ok(v_domain_at_point * v_subdomain_at_point.inverse()? * scale)
+}
+
+fn get_lc_eval<F: PrimeField, CF: PrimeField>(
+    evals: &EvaluationsVar<F, CF>,
+    label: &str,
+    point: &NonNativeFieldVar<F, CF>,
+) -> Result<NonNativeFieldVar<F, CF>, SynthesisError> {
+    let key = LabeledPointVar {
+        name: label.to_string(),
+        value: point.clone(),
+    };
+    evals
+        .0
+        .get(&key)
+        .cloned()
+        .ok_or(SynthesisError::AssignmentMissing)
+}
+
+fn single_poly_lc<F: PrimeField, CF: PrimeField>(label: &str) -> LinearCombinationVar<F, CF> {
+    LinearCombinationVar {
+        label: label.to_string(),
+        terms: vec![(LinearCombinationCoeffVar::One, String::from(label).into())],
+    }
+}
+
+fn var<F: PrimeField, CF: PrimeField>(
+    coeff: NonNativeFieldVar<F, CF>,
+) -> LinearCombinationCoeffVar<F, CF> {
+    LinearCombinationCoeffVar::Var(coeff)
+}
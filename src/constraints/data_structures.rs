@@ -0,0 +1,249 @@
+use crate::ahp::indexer::IndexInfo;
+use crate::ahp::prover::ProverMsg;
+use crate::constraints::pc::PCVerifierGadget;
+use crate::{IndexVerifierKey, Proof, Vec};
+use ark_ff::{to_bytes, PrimeField};
+use ark_nonnative_field::NonNativeFieldVar;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::PolynomialCommitment;
+use ark_r1cs_std::prelude::*;
+use ark_relations::{
+    ns,
+    r1cs::{Namespace, SynthesisError},
+};
+use ark_std::{borrow::Borrow, marker::PhantomData};
+use derivative::Derivative;
+
+/// An allocated version of `IndexVerifierKey`. The index info determines the
+/// shape of the verifier circuit, so it is kept as a constant.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct IndexVerifierKeyVar<
+    F: PrimeField,
+    CF: PrimeField,
+    PC: PolynomialCommitment<F, DensePolynomial<F>>,
+    PCG: PCVerifierGadget<F, PC, CF>,
+> {
+    /// Stores information about the size of the index.
+    pub index_info: IndexInfo<F>,
+    /// Commitments to the indexed polynomials.
+    pub index_comms: Vec<PCG::CommitmentVar>,
+    /// The verifier key for this index, trimmed from the universal SRS.
+    pub verifier_key: PCG::VerifierKeyVar,
+    #[doc(hidden)]
+    pc: PhantomData<PC>,
+}
+
+impl<F, CF, PC, PCG> IndexVerifierKeyVar<F, CF, PC, PCG>
+where
+    F: PrimeField,
+    CF: PrimeField,
+    PC: PolynomialCommitment<F, DensePolynomial<F>>,
+    PCG: PCVerifierGadget<F, PC, CF>,
+{
+    /// Iterate over the commitments to indexed polynomials in `self`.
+    pub fn iter(&self) -> impl Iterator<Item = &PCG::CommitmentVar> {
+        self.index_comms.iter()
+    }
+}
+
+impl<F, CF, PC, PCG> AllocVar<IndexVerifierKey<F, PC>, CF> for IndexVerifierKeyVar<F, CF, PC, PCG>
+where
+    F: PrimeField,
+    CF: PrimeField,
+    PC: PolynomialCommitment<F, DensePolynomial<F>>,
+    PCG: PCVerifierGadget<F, PC, CF>,
+{
+    fn new_variable<T: Borrow<IndexVerifierKey<F, PC>>>(
+        cs: impl Into<Namespace<CF>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+
+        let index_vk = f()?;
+        let index_vk = index_vk.borrow();
+        let index_comms = index_vk
+            .iter()
+            .map(|comm| PCG::CommitmentVar::new_variable(ns!(cs, "index_comm"), || Ok(comm), mode))
+            .collect::<Result<_, _>>()?;
+        let verifier_key = PCG::VerifierKeyVar::new_variable(
+            ns!(cs, "verifier_key"),
+            || Ok(&index_vk.verifier_key),
+            mode,
+        )?;
+
+        Ok(Self {
+            index_info: index_vk.index_info,
+            index_comms,
+            verifier_key,
+            pc: PhantomData,
+        })
+    }
+}
+
+/// Matches the `ToBytes` output of `IndexVerifierKey`.
+impl<F, CF, PC, PCG> ToBytesGadget<CF> for IndexVerifierKeyVar<F, CF, PC, PCG>
+where
+    F: PrimeField,
+    CF: PrimeField,
+    PC: PolynomialCommitment<F, DensePolynomial<F>>,
+    PCG: PCVerifierGadget<F, PC, CF>,
+{
+    fn to_bytes(&self) -> Result<Vec<UInt8<CF>>, SynthesisError> {
+        let mut bytes = UInt8::constant_vec(&to_bytes![self.index_info].unwrap());
+        for comm in &self.index_comms {
+            bytes.extend(comm.to_bytes()?);
+        }
+        bytes.extend(self.verifier_key.to_bytes()?);
+        Ok(bytes)
+    }
+}
+
+/// An allocated version of `ProverMsg`.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub enum ProverMsgVar<F: PrimeField, CF: PrimeField> {
+    /// Some rounds, the prover sends only oracles.
+    EmptyMessage,
+    /// Otherwise, it's one or more field elements.
+    FieldElements(Vec<NonNativeFieldVar<F, CF>>),
+}
+
+impl<F: PrimeField, CF: PrimeField> AllocVar<ProverMsg<F>, CF> for ProverMsgVar<F, CF> {
+    fn new_variable<T: Borrow<ProverMsg<F>>>(
+        cs: impl Into<Namespace<CF>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+
+        match f()?.borrow() {
+            ProverMsg::EmptyMessage => Ok(ProverMsgVar::EmptyMessage),
+            ProverMsg::FieldElements(elems) => Ok(ProverMsgVar::FieldElements(
+                elems
+                    .iter()
+                    .map(|e| NonNativeFieldVar::new_variable(ns!(cs, "elem"), || Ok(e), mode))
+                    .collect::<Result<_, _>>()?,
+            )),
+        }
+    }
+}
+
+/// Matches the `ToBytes` output of `ProverMsg`.
+impl<F: PrimeField, CF: PrimeField> ToBytesGadget<CF> for ProverMsgVar<F, CF> {
+    fn to_bytes(&self) -> Result<Vec<UInt8<CF>>, SynthesisError> {
+        match self {
+            ProverMsgVar::EmptyMessage => Ok(Vec::new()),
+            ProverMsgVar::FieldElements(elems) => {
+                let mut bytes = Vec::new();
+                for elem in elems {
+                    bytes.extend(elem.to_bytes()?);
+                }
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+/// An allocated version of `Proof`.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct ProofVar<
+    F: PrimeField,
+    CF: PrimeField,
+    PC: PolynomialCommitment<F, DensePolynomial<F>>,
+    PCG: PCVerifierGadget<F, PC, CF>,
+> {
+    /// Commitments to the polynomials produced by the AHP prover.
+    pub commitments: Vec<Vec<PCG::CommitmentVar>>,
+    /// Evaluations of these polynomials.
+    pub evaluations: Vec<NonNativeFieldVar<F, CF>>,
+    /// The field elements sent by the prover.
+    pub prover_messages: Vec<ProverMsgVar<F, CF>>,
+    /// An evaluation proof from the polynomial commitment.
+    pub pc_batch_proof: PCG::BatchLCProofVar,
+}
+
+impl<F, CF, PC, PCG> AllocVar<Proof<F, PC>, CF> for ProofVar<F, CF, PC, PCG>
+where
+    F: PrimeField,
+    CF: PrimeField,
+    PC: PolynomialCommitment<F, DensePolynomial<F>>,
+    PCG: PCVerifierGadget<F, PC, CF>,
+{
+    fn new_variable<T: Borrow<Proof<F, PC>>>(
+        cs: impl Into<Namespace<CF>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+
+        let proof = f()?;
+        let proof = proof.borrow();
+        let commitments = proof
+            .commitments
+            .iter()
+            .map(|round_comms| {
+                round_comms
+                    .iter()
+                    .map(|comm| {
+                        PCG::CommitmentVar::new_variable(ns!(cs, "commitment"), || Ok(comm), mode)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<_, _>>()?;
+        let evaluations = proof
+            .evaluations
+            .iter()
+            .map(|eval| NonNativeFieldVar::new_variable(ns!(cs, "evaluation"), || Ok(eval), mode))
+            .collect::<Result<_, _>>()?;
+        let prover_messages = proof
+            .prover_messages
+            .iter()
+            .map(|msg| ProverMsgVar::new_variable(ns!(cs, "prover_message"), || Ok(msg), mode))
+            .collect::<Result<_, _>>()?;
+        let pc_batch_proof = PCG::BatchLCProofVar::new_variable(
+            ns!(cs, "pc_batch_proof"),
+            || Ok(&proof.pc_proof),
+            mode,
+        )?;
+
+        Ok(Self {
+            commitments,
+            evaluations,
+            prover_messages,
+            pc_batch_proof,
+        })
+    }
+}
+
+/// An allocated version of the (unpadded) public input of a proof.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct PublicInputVar<F: PrimeField, CF: PrimeField> {
+    /// The public input, as non-native field elements.
+    pub input: Vec<NonNativeFieldVar<F, CF>>,
+}
+
+impl<F: PrimeField, CF: PrimeField> AllocVar<[F], CF> for PublicInputVar<F, CF> {
+    fn new_variable<T: Borrow<[F]>>(
+        cs: impl Into<Namespace<CF>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+
+        let input = f()?
+            .borrow()
+            .iter()
+            .map(|x| NonNativeFieldVar::new_variable(ns!(cs, "input"), || Ok(x), mode))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { input })
+    }
+}
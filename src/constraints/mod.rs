@@ -0,0 +1,22 @@
+/// Implements the AHP verifier as constraints over a non-native field.
+pub mod ahp;
+pub use ahp::AHPForR1CSGadget;
+
+/// Allocated versions of the verifier key, proof and public input.
+pub mod data_structures;
+pub use data_structures::*;
+
+/// Describes the interface of polynomial commitment verifier gadgets, and
+/// implements it for `MarlinKZG10`.
+pub mod pc;
+pub use pc::{MarlinKZG10Gadget, PCVerifierGadget};
+
+/// Implements gadgets that replay a Fiat-Shamir transcript in-circuit.
+pub mod rng;
+pub use rng::{
+    FiatShamirRngVar, PoseidonFiatShamirRngVar, SimpleHashFiatShamirRngVar, TranscriptVar,
+};
+
+/// Implements the Marlin verifier as constraints.
+pub mod verifier;
+pub use verifier::MarlinVerifierGadget;
@@ -0,0 +1,554 @@
+use crate::{BTreeMap, String, ToString, Vec};
+use ark_ec::{AffineCurve, PairingEngine};
+use ark_ff::{to_bytes, BigInteger, PrimeField, Zero};
+use ark_nonnative_field::NonNativeFieldVar;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::{
+    marlin_pc, marlin_pc::MarlinKZG10, BatchLCProof, LCTerm, PolynomialCommitment,
+};
+use ark_poly_commit::{
+    EvaluationsVar, LabeledPointVar, LinearCombinationCoeffVar, LinearCombinationVar,
+    PCCheckRandomDataVar, QuerySetVar,
+};
+use ark_r1cs_std::{pairing::PairingVar, prelude::*};
+use ark_relations::{
+    ns,
+    r1cs::{Namespace, SynthesisError},
+};
+use ark_std::{borrow::Borrow, collections::BTreeSet, marker::PhantomData};
+use derivative::Derivative;
+
+/// Describes the interface of a gadget that checks, inside a constraint system
+/// over `CF`, the evaluation proofs of the polynomial commitment scheme `PC`.
+pub trait PCVerifierGadget<
+    F: PrimeField,
+    PC: PolynomialCommitment<F, DensePolynomial<F>>,
+    CF: PrimeField,
+>
+{
+    /// An allocated version of `PC::VerifierKey`. Its byte representation
+    /// must match the uncompressed canonical serialization of
+    /// `PC::VerifierKey`.
+    type VerifierKeyVar: AllocVar<PC::VerifierKey, CF> + ToBytesGadget<CF> + Clone;
+    /// An allocated version of `PC::Commitment`. Its byte representation must
+    /// match the `ToBytes` output of `PC::Commitment`.
+    type CommitmentVar: AllocVar<PC::Commitment, CF> + ToBytesGadget<CF> + Clone;
+    /// An allocated version of `LabeledCommitment<PC::Commitment>`.
+    type LabeledCommitmentVar: Clone;
+    /// An allocated version of `BatchLCProof<F, DensePolynomial<F>, PC>`.
+    type BatchLCProofVar: AllocVar<BatchLCProof<F, DensePolynomial<F>, PC>, CF>
+        + ToBytesGadget<CF>
+        + Clone;
+
+    /// Create a labeled commitment with an optional degree bound.
+    fn create_labeled_commitment(
+        label: String,
+        commitment: Self::CommitmentVar,
+        degree_bound: Option<usize>,
+    ) -> Self::LabeledCommitmentVar;
+
+    /// The gadget version of `PC::check_combinations`. The opening challenge
+    /// is `rand_data.opening_challenges[0]`, and `rand_data.batching_rands`
+    /// must be sampled only after `proof` has been fixed.
+    fn check_combinations(
+        verification_key: &Self::VerifierKeyVar,
+        linear_combinations: &[LinearCombinationVar<F, CF>],
+        commitments: &[Self::LabeledCommitmentVar],
+        query_set: &QuerySetVar<F, CF>,
+        evaluations: &EvaluationsVar<F, CF>,
+        proof: &Self::BatchLCProofVar,
+        rand_data: &PCCheckRandomDataVar<F, CF>,
+    ) -> Result<Boolean<CF>, SynthesisError>;
+}
+
+/// The verifier gadget for `MarlinKZG10` over the pairing engine `E`, whose
+/// base field is the native field of the constraint system.
+pub struct MarlinKZG10Gadget<E: PairingEngine, PG: PairingVar<E, E::Fq>>(
+    #[doc(hidden)] PhantomData<E>,
+    #[doc(hidden)] PhantomData<PG>,
+);
+
+/// An allocated version of `marlin_pc::VerifierKey`.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct VerifierKeyVar<E: PairingEngine, PG: PairingVar<E, E::Fq>> {
+    /// The generator of G1.
+    pub g: PG::G1Var,
+    /// The generator of G1 that is used for making a commitment hiding.
+    pub gamma_g: PG::G1Var,
+    /// The generator of G2.
+    pub h: PG::G2Var,
+    /// \beta times the above generator of G2.
+    pub beta_h: PG::G2Var,
+    /// The degree bounds supported by the key, each with its shifting advice.
+    pub degree_bounds_and_shift_powers: Option<Vec<(usize, PG::G1Var)>>,
+    /// The maximum degree supported by the universal parameters that the key
+    /// was derived from.
+    pub max_degree: usize,
+    /// The maximum degree supported by the trimmed parameters that the key is
+    /// a part of.
+    pub supported_degree: usize,
+}
+
+impl<E: PairingEngine, PG: PairingVar<E, E::Fq>> VerifierKeyVar<E, PG> {
+    /// Find the appropriate shift for the degree bound.
+    pub fn get_shift_power(&self, bound: usize) -> Option<PG::G1Var> {
+        self.degree_bounds_and_shift_powers
+            .as_ref()
+            .and_then(|v| v.iter().find(|(d, _)| *d == bound).map(|(_, s)| s.clone()))
+    }
+}
+
+impl<E: PairingEngine, PG: PairingVar<E, E::Fq>> AllocVar<marlin_pc::VerifierKey<E>, E::Fq>
+    for VerifierKeyVar<E, PG>
+{
+    fn new_variable<T: Borrow<marlin_pc::VerifierKey<E>>>(
+        cs: impl Into<Namespace<E::Fq>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+
+        let vk = f()?;
+        let vk = vk.borrow();
+        let g = new_g1::<E, PG>(ns!(cs, "g"), vk.vk.g, mode)?;
+        let gamma_g = new_g1::<E, PG>(ns!(cs, "gamma_g"), vk.vk.gamma_g, mode)?;
+        let h = new_g2::<E, PG>(ns!(cs, "h"), vk.vk.h, mode)?;
+        let beta_h = new_g2::<E, PG>(ns!(cs, "beta_h"), vk.vk.beta_h, mode)?;
+        let degree_bounds_and_shift_powers = vk
+            .degree_bounds_and_shift_powers
+            .as_ref()
+            .map(|v| {
+                v.iter()
+                    .map(|(d, s)| Ok((*d, new_g1::<E, PG>(ns!(cs, "shift_power"), *s, mode)?)))
+                    .collect::<Result<Vec<_>, SynthesisError>>()
+            })
+            .transpose()?;
+
+        Ok(Self {
+            g,
+            gamma_g,
+            h,
+            beta_h,
+            degree_bounds_and_shift_powers,
+            max_degree: vk.max_degree,
+            supported_degree: vk.supported_degree,
+        })
+    }
+}
+
+/// Matches the uncompressed canonical serialization of
+/// `marlin_pc::VerifierKey`.
+impl<E: PairingEngine, PG: PairingVar<E, E::Fq>> ToBytesGadget<E::Fq> for VerifierKeyVar<E, PG> {
+    fn to_bytes(&self) -> Result<Vec<UInt8<E::Fq>>, SynthesisError> {
+        let mut bytes = uncompressed_point_bytes(self.g.to_bytes()?)?;
+        bytes.extend(uncompressed_point_bytes(self.gamma_g.to_bytes()?)?);
+        bytes.extend(uncompressed_point_bytes(self.h.to_bytes()?)?);
+        bytes.extend(uncompressed_point_bytes(self.beta_h.to_bytes()?)?);
+        let le_bytes = |n: usize| UInt8::constant_vec(&(n as u64).to_le_bytes());
+        match &self.degree_bounds_and_shift_powers {
+            Some(degree_bounds_and_shift_powers) => {
+                bytes.push(UInt8::constant(1));
+                bytes.extend(le_bytes(degree_bounds_and_shift_powers.len()));
+                for (degree_bound, shift_power) in degree_bounds_and_shift_powers {
+                    bytes.extend(le_bytes(*degree_bound));
+                    bytes.extend(uncompressed_point_bytes(shift_power.to_bytes()?)?);
+                }
+            }
+            None => bytes.push(UInt8::constant(0)),
+        }
+        bytes.extend(le_bytes(self.max_degree));
+        bytes.extend(le_bytes(self.supported_degree));
+        Ok(bytes)
+    }
+}
+
+/// The uncompressed canonical serialization of a point, from its `ToBytes`
+/// encoding `bytes`. `ToBytes` writes each base field element of the
+/// coordinates with every byte of its representation, and the infinity flag
+/// in a byte of its own. The serialization writes only the bytes that the
+/// bits of the field take, and keeps the flag in bit 6 of the last of them,
+/// or of a byte of its own if bit 6 is taken.
+fn uncompressed_point_bytes<CF: PrimeField>(
+    mut bytes: Vec<UInt8<CF>>,
+) -> Result<Vec<UInt8<CF>>, SynthesisError> {
+    let infinity = bytes.pop().unwrap().to_bits_le()?[0].clone();
+    let repr_len = 8 * <CF::BigInt as BigInteger>::NUM_LIMBS;
+    let elem_len = (CF::size_in_bits() + 7) / 8;
+    let mut bytes = bytes
+        .chunks(repr_len)
+        .flat_map(|elem| elem[..elem_len].to_vec())
+        .collect::<Vec<_>>();
+    let mut flag_bits = match CF::size_in_bits() % 8 {
+        0 | 7 => vec![Boolean::FALSE; 8],
+        _ => bytes.pop().unwrap().to_bits_le()?,
+    };
+    flag_bits[6] = infinity;
+    bytes.push(UInt8::from_bits_le(&flag_bits));
+    Ok(bytes)
+}
+
+/// An allocated version of `marlin_pc::Commitment`.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct CommitmentVar<E: PairingEngine, PG: PairingVar<E, E::Fq>> {
+    /// A KZG10 commitment to the polynomial.
+    pub comm: PG::G1Var,
+    /// A KZG10 commitment to the shifted polynomial, if the polynomial
+    /// enforces a strict degree bound.
+    pub shifted_comm: Option<PG::G1Var>,
+}
+
+impl<E: PairingEngine, PG: PairingVar<E, E::Fq>> AllocVar<marlin_pc::Commitment<E>, E::Fq>
+    for CommitmentVar<E, PG>
+{
+    fn new_variable<T: Borrow<marlin_pc::Commitment<E>>>(
+        cs: impl Into<Namespace<E::Fq>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+
+        let commitment = f()?;
+        let commitment = commitment.borrow();
+        let comm = new_g1::<E, PG>(ns!(cs, "comm"), commitment.comm.0, mode)?;
+        let shifted_comm = commitment
+            .shifted_comm
+            .as_ref()
+            .map(|c| new_g1::<E, PG>(ns!(cs, "shifted_comm"), c.0, mode))
+            .transpose()?;
+
+        Ok(Self { comm, shifted_comm })
+    }
+}
+
+impl<E: PairingEngine, PG: PairingVar<E, E::Fq>> ToBytesGadget<E::Fq> for CommitmentVar<E, PG> {
+    fn to_bytes(&self) -> Result<Vec<UInt8<E::Fq>>, SynthesisError> {
+        let mut bytes = self.comm.to_bytes()?;
+        match &self.shifted_comm {
+            Some(shifted_comm) => {
+                bytes.push(UInt8::constant(1));
+                bytes.extend(shifted_comm.to_bytes()?);
+            }
+            None => {
+                bytes.push(UInt8::constant(0));
+                let empty = to_bytes![E::G1Affine::zero()].unwrap();
+                bytes.extend(UInt8::constant_vec(&empty));
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+/// An allocated version of `LabeledCommitment<marlin_pc::Commitment>`.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct LabeledCommitmentVar<E: PairingEngine, PG: PairingVar<E, E::Fq>> {
+    /// The label of the committed polynomial.
+    pub label: String,
+    /// The commitment.
+    pub commitment: CommitmentVar<E, PG>,
+    /// The strict degree bound enforced by the commitment, if any.
+    pub degree_bound: Option<usize>,
+}
+
+/// An allocated version of `kzg10::Proof`.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct ProofVar<E: PairingEngine, PG: PairingVar<E, E::Fq>> {
+    /// The witness of the evaluation.
+    pub w: PG::G1Var,
+    /// The evaluation of the random hiding polynomial, if the commitment is hiding.
+    pub random_v: Option<NonNativeFieldVar<E::Fr, E::Fq>>,
+}
+
+/// An allocated version of `BatchLCProof` for `MarlinKZG10`, with one KZG10
+/// proof per query point.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct BatchLCProofVar<E: PairingEngine, PG: PairingVar<E, E::Fq>> {
+    /// The KZG10 proofs, in ascending order of the label of their query point.
+    pub proofs: Vec<ProofVar<E, PG>>,
+}
+
+impl<E: PairingEngine, PG: PairingVar<E, E::Fq>>
+    AllocVar<
+        BatchLCProof<E::Fr, DensePolynomial<E::Fr>, MarlinKZG10<E, DensePolynomial<E::Fr>>>,
+        E::Fq,
+    > for BatchLCProofVar<E, PG>
+{
+    fn new_variable<
+        T: Borrow<BatchLCProof<E::Fr, DensePolynomial<E::Fr>, MarlinKZG10<E, DensePolynomial<E::Fr>>>>,
+    >(
+        cs: impl Into<Namespace<E::Fq>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+
+        let proof = f()?;
+        let proofs = proof
+            .borrow()
+            .proof
+            .iter()
+            .map(|proof| {
+                let w = new_g1::<E, PG>(ns!(cs, "w"), proof.w, mode)?;
+                let random_v = proof
+                    .random_v
+                    .map(|v| NonNativeFieldVar::new_variable(ns!(cs, "random_v"), || Ok(v), mode))
+                    .transpose()?;
+                Ok(ProofVar { w, random_v })
+            })
+            .collect::<Result<_, SynthesisError>>()?;
+
+        Ok(Self { proofs })
+    }
+}
+
+impl<E: PairingEngine, PG: PairingVar<E, E::Fq>> ToBytesGadget<E::Fq> for BatchLCProofVar<E, PG> {
+    fn to_bytes(&self) -> Result<Vec<UInt8<E::Fq>>, SynthesisError> {
+        let mut bytes = Vec::new();
+        for proof in &self.proofs {
+            bytes.extend(proof.w.to_bytes()?);
+            if let Some(random_v) = &proof.random_v {
+                bytes.extend(random_v.to_bytes()?);
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+/// The contribution of a linear combination to the opening check.
+#[allow(clippy::type_complexity)]
+struct LCInfo<'a, E: PairingEngine> {
+    /// The commitment terms, as `(coefficient, polynomial label)` pairs.
+    terms: Vec<(NonNativeFieldVar<E::Fr, E::Fq>, &'a str)>,
+    /// The sum of the coefficients of the constant terms, which is moved over
+    /// to the claimed evaluation.
+    constant: NonNativeFieldVar<E::Fr, E::Fq>,
+    /// The degree bound and label of the single polynomial of a degree-bounded
+    /// linear combination.
+    degree_bound: Option<(usize, &'a str)>,
+}
+
+impl<E: PairingEngine, PG: PairingVar<E, E::Fq>>
+    PCVerifierGadget<E::Fr, MarlinKZG10<E, DensePolynomial<E::Fr>>, E::Fq>
+    for MarlinKZG10Gadget<E, PG>
+{
+    type VerifierKeyVar = VerifierKeyVar<E, PG>;
+    type CommitmentVar = CommitmentVar<E, PG>;
+    type LabeledCommitmentVar = LabeledCommitmentVar<E, PG>;
+    type BatchLCProofVar = BatchLCProofVar<E, PG>;
+
+    fn create_labeled_commitment(
+        label: String,
+        commitment: Self::CommitmentVar,
+        degree_bound: Option<usize>,
+    ) -> Self::LabeledCommitmentVar {
+        LabeledCommitmentVar {
+            label,
+            commitment,
+            degree_bound,
+        }
+    }
+
+    /// Checks the opening proofs exactly as `MarlinKZG10::check_combinations`
+    /// does, except that the KZG10 checks at the different query points are
+    /// folded, with `rand_data.batching_rands`, into a single product of two
+    /// pairings.
+    fn check_combinations(
+        vk: &Self::VerifierKeyVar,
+        linear_combinations: &[LinearCombinationVar<E::Fr, E::Fq>],
+        commitments: &[Self::LabeledCommitmentVar],
+        query_set: &QuerySetVar<E::Fr, E::Fq>,
+        evaluations: &EvaluationsVar<E::Fr, E::Fq>,
+        proof: &Self::BatchLCProofVar,
+        rand_data: &PCCheckRandomDataVar<E::Fr, E::Fq>,
+    ) -> Result<Boolean<E::Fq>, SynthesisError> {
+        let check_time = start_timer!(|| "MarlinKZG10Gadget::CheckCombinations");
+        let label_comm_map = commitments
+            .iter()
+            .map(|c| (c.label.as_str(), c))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut lc_info = BTreeMap::new();
+        for lc in linear_combinations {
+            let mut terms = Vec::new();
+            let mut constant = NonNativeFieldVar::zero();
+            let mut degree_bound = None;
+            for (coeff, term) in &lc.terms {
+                let coeff_is_one = matches!(coeff, LinearCombinationCoeffVar::One);
+                let coeff = match coeff {
+                    LinearCombinationCoeffVar::One => NonNativeFieldVar::one(),
+                    LinearCombinationCoeffVar::MinusOne => NonNativeFieldVar::one().negate()?,
+                    LinearCombinationCoeffVar::Var(c) => c.clone(),
+                };
+                match term {
+                    LCTerm::One => constant += coeff,
+                    LCTerm::PolyLabel(label) => {
+                        let cur_comm = label_comm_map
+                            .get(label.as_str())
+                            .ok_or(SynthesisError::AssignmentMissing)?;
+                        if cur_comm.degree_bound.is_some()
+                            != cur_comm.commitment.shifted_comm.is_some()
+                        {
+                            return Err(SynthesisError::Unsatisfiable);
+                        }
+                        if let Some(bound) = cur_comm.degree_bound {
+                            if lc.terms.len() != 1 || !coeff_is_one {
+                                return Err(SynthesisError::Unsatisfiable);
+                            }
+                            degree_bound = Some((bound, label.as_str()));
+                        }
+                        terms.push((coeff, label.as_str()));
+                    }
+                }
+            }
+            lc_info.insert(
+                lc.label.as_str(),
+                LCInfo::<E> {
+                    terms,
+                    constant,
+                    degree_bound,
+                },
+            );
+        }
+
+        let mut query_to_labels_map = BTreeMap::new();
+        for (label, point) in &query_set.0 {
+            query_to_labels_map
+                .entry(point.name.as_str())
+                .or_insert((&point.value, BTreeSet::new()))
+                .1
+                .insert(label.as_str());
+        }
+        if proof.proofs.len() != query_to_labels_map.len()
+            || rand_data.batching_rands.len() + 1 < query_to_labels_map.len()
+        {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let opening_challenge = &rand_data.opening_challenges[0];
+
+        // The KZG10 check for `(comm, point, value, (w, random_v))` is
+        //   e(comm - value * g - random_v * gamma_g + point * w, h) = e(w, beta_h).
+        // We fold the checks at all query points, each scaled by its batching
+        // randomizer, into `e(lhs, h) * e(-rhs, beta_h) = 1`, and accumulate
+        // the scalar of every group element that appears in `lhs`.
+        let zero = NonNativeFieldVar::<E::Fr, E::Fq>::zero();
+        let mut comm_scalars = BTreeMap::new();
+        let mut shifted_comm_scalars = BTreeMap::new();
+        let mut shift_power_scalars = BTreeMap::new();
+        let mut g_scalar = zero.clone();
+        let mut gamma_g_scalar = zero.clone();
+        let mut w_scalars = Vec::new();
+        for (i, ((point, labels), kzg_proof)) in
+            query_to_labels_map.values().zip(&proof.proofs).enumerate()
+        {
+            let randomizer = match i {
+                0 => NonNativeFieldVar::one(),
+                _ => rand_data.batching_rands[i - 1].clone(),
+            };
+            let mut challenge = randomizer.clone();
+            for label in labels {
+                let info = lc_info
+                    .get(label)
+                    .ok_or(SynthesisError::AssignmentMissing)?;
+                let key = LabeledPointVar {
+                    name: label.to_string(),
+                    value: (*point).clone(),
+                };
+                let value = evaluations
+                    .0
+                    .get(&key)
+                    .ok_or(SynthesisError::AssignmentMissing)?
+                    - &info.constant;
+
+                for (coeff, comm_label) in &info.terms {
+                    *comm_scalars
+                        .entry(*comm_label)
+                        .or_insert_with(|| zero.clone()) += &(&challenge * coeff);
+                }
+                g_scalar -= &(&challenge * &value);
+                challenge *= opening_challenge;
+
+                if let Some((degree_bound, comm_label)) = info.degree_bound {
+                    *shifted_comm_scalars
+                        .entry(comm_label)
+                        .or_insert_with(|| zero.clone()) += &challenge;
+                    *shift_power_scalars
+                        .entry(degree_bound)
+                        .or_insert_with(|| zero.clone()) -= &(&challenge * &value);
+                    challenge *= opening_challenge;
+                }
+            }
+            if let Some(random_v) = &kzg_proof.random_v {
+                gamma_g_scalar -= &(&randomizer * random_v);
+            }
+            w_scalars.push((&kzg_proof.w, &randomizer * *point, randomizer));
+        }
+
+        let mut lhs = PG::G1Var::zero();
+        let mut rhs = PG::G1Var::zero();
+        for (comm_label, scalar) in comm_scalars {
+            let comm = &label_comm_map[comm_label].commitment.comm;
+            lhs += comm.scalar_mul_le(scalar.to_bits_le()?.iter())?;
+        }
+        for (comm_label, scalar) in shifted_comm_scalars {
+            let shifted_comm = label_comm_map[comm_label]
+                .commitment
+                .shifted_comm
+                .as_ref()
+                .unwrap();
+            lhs += shifted_comm.scalar_mul_le(scalar.to_bits_le()?.iter())?;
+        }
+        for (degree_bound, scalar) in shift_power_scalars {
+            let shift_power = vk
+                .get_shift_power(degree_bound)
+                .ok_or(SynthesisError::Unsatisfiable)?;
+            lhs += shift_power.scalar_mul_le(scalar.to_bits_le()?.iter())?;
+        }
+        lhs += vk.g.scalar_mul_le(g_scalar.to_bits_le()?.iter())?;
+        lhs += vk
+            .gamma_g
+            .scalar_mul_le(gamma_g_scalar.to_bits_le()?.iter())?;
+        for (w, lhs_scalar, rhs_scalar) in w_scalars {
+            lhs += w.scalar_mul_le(lhs_scalar.to_bits_le()?.iter())?;
+            rhs += w.scalar_mul_le(rhs_scalar.to_bits_le()?.iter())?;
+        }
+
+        let lhs = PG::prepare_g1(&lhs)?;
+        let rhs = PG::prepare_g1(&rhs.negate()?)?;
+        let h = PG::prepare_g2(&vk.h)?;
+        let beta_h = PG::prepare_g2(&vk.beta_h)?;
+        let result = PG::product_of_pairings(&[lhs, rhs], &[h, beta_h])?.is_one()?;
+        end_timer!(check_time);
+        Ok(result)
+    }
+}
+
+fn new_g1<E: PairingEngine, PG: PairingVar<E, E::Fq>>(
+    cs: impl Into<Namespace<E::Fq>>,
+    point: E::G1Affine,
+    mode: AllocationMode,
+) -> Result<PG::G1Var, SynthesisError> {
+    <PG::G1Var as AllocVar<E::G1Projective, _>>::new_variable(
+        cs,
+        || Ok(point.into_projective()),
+        mode,
+    )
+}
+
+fn new_g2<E: PairingEngine, PG: PairingVar<E, E::Fq>>(
+    cs: impl Into<Namespace<E::Fq>>,
+    point: E::G2Affine,
+    mode: AllocationMode,
+) -> Result<PG::G2Var, SynthesisError> {
+    <PG::G2Var as AllocVar<E::G2Projective, _>>::new_variable(
+        cs,
+        || Ok(point.into_projective()),
+        mode,
+    )
+}
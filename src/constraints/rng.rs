@@ -0,0 +1,445 @@
+use crate::rng::{
+    encode_label, poseidon_parameters, FiatShamirRng, PoseidonFiatShamirRng,
+    ShortChallengeFiatShamirRng, SimpleHashFiatShamirRng,
+};
+use crate::Vec;
+use ark_crypto_primitives::prf::blake2s::constraints::evaluate_blake2s;
+use ark_ff::{to_bytes, BigInteger, FpParameters, PrimeField};
+use ark_nonnative_field::NonNativeFieldVar;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::SynthesisError;
+use ark_sponge::constraints::CryptographicSpongeVar;
+use ark_sponge::poseidon::constraints::PoseidonSpongeVar;
+use ark_sponge::Absorb;
+use ark_std::marker::PhantomData;
+use blake2::Blake2s;
+use rand_chacha::ChaChaRng;
+
+/// A gadget that replays, inside a constraint system over `CF`, the transcript
+/// of the `FiatShamirRng` `FS` that derives challenges in `F`. Since the
+/// gadget only derives challenges of 128 bits, `FS` is a
+/// `ShortChallengeFiatShamirRng`.
+pub trait FiatShamirRngVar<F: PrimeField, CF: PrimeField, FS: FiatShamirRng>: Sized {
+    /// Create a new `Self` with an initial input, as `FS::initialize` does.
+    fn initialize(initial_input: &[UInt8<CF>]) -> Result<Self, SynthesisError>;
+
+    /// Absorb new inputs into state, as `FS::absorb` does.
+    fn absorb(&mut self, new_input: &[UInt8<CF>]) -> Result<(), SynthesisError>;
+
+    /// Absorb field elements into state, as `FS::absorb_field_elements` does.
+    /// By default, they are absorbed as their byte encoding.
+    fn absorb_nonnative_field_elements(
+        &mut self,
+        elems: &[NonNativeFieldVar<F, CF>],
+    ) -> Result<(), SynthesisError> {
+        let mut bytes = Vec::new();
+        for elem in elems {
+            bytes.extend(elem.to_bytes()?);
+        }
+        self.absorb(&bytes)
+    }
+
+    /// Squeeze `num` challenges, each sampled as `F::from(u128::rand(fs_rng))`,
+    /// along with their little-endian bit decompositions.
+    #[allow(clippy::type_complexity)]
+    fn squeeze_128_bits_field_elements(
+        &mut self,
+        num: usize,
+    ) -> Result<(Vec<NonNativeFieldVar<F, CF>>, Vec<Vec<Boolean<CF>>>), SynthesisError>;
+}
+
+/// The gadget version of `Transcript`, which binds every message and every
+/// challenge to the same labels as the native transcript.
+pub struct TranscriptVar<F, CF, FS, FSG>
+where
+    F: PrimeField,
+    CF: PrimeField,
+    FS: FiatShamirRng,
+    FSG: FiatShamirRngVar<F, CF, FS>,
+{
+    fs_rng: FSG,
+    #[doc(hidden)]
+    f: PhantomData<(F, CF, FS)>,
+}
+
+impl<F, CF, FS, FSG> TranscriptVar<F, CF, FS, FSG>
+where
+    F: PrimeField,
+    CF: PrimeField,
+    FS: FiatShamirRng,
+    FSG: FiatShamirRngVar<F, CF, FS>,
+{
+    /// Start a transcript for the protocol identified by `protocol_label`.
+    pub fn new(protocol_label: &[u8]) -> Result<Self, SynthesisError> {
+        Ok(Self {
+            fs_rng: FSG::initialize(&UInt8::constant_vec(&encode_label(protocol_label)))?,
+            f: PhantomData,
+        })
+    }
+
+    /// Append the bytes of `message` under `label`.
+    pub fn append_message(
+        &mut self,
+        label: &[u8],
+        message: &[UInt8<CF>],
+    ) -> Result<(), SynthesisError> {
+        let mut bytes =
+            UInt8::constant_vec(&to_bytes![encode_label(label), message.len() as u64].unwrap());
+        bytes.extend_from_slice(message);
+        self.fs_rng.absorb(&bytes)
+    }
+
+    /// Append the field elements `elems` under `label`.
+    pub fn append_nonnative_field_elements(
+        &mut self,
+        label: &[u8],
+        elems: &[NonNativeFieldVar<F, CF>],
+    ) -> Result<(), SynthesisError> {
+        self.fs_rng.absorb(&UInt8::constant_vec(
+            &to_bytes![encode_label(label), elems.len() as u64].unwrap(),
+        ))?;
+        if !elems.is_empty() {
+            self.fs_rng.absorb_nonnative_field_elements(elems)?;
+        }
+        Ok(())
+    }
+
+    /// Draw `num` challenges of 128 bits under `label`, along with their
+    /// little-endian bit decompositions.
+    #[allow(clippy::type_complexity)]
+    pub fn challenge_128_bits_field_elements(
+        &mut self,
+        label: &[u8],
+        num: usize,
+    ) -> Result<(Vec<NonNativeFieldVar<F, CF>>, Vec<Vec<Boolean<CF>>>), SynthesisError> {
+        self.fs_rng.absorb(&UInt8::constant_vec(
+            &to_bytes![encode_label(label), num as u64].unwrap(),
+        ))?;
+        self.fs_rng.squeeze_128_bits_field_elements(num)
+    }
+
+    /// Draw a single challenge of 128 bits under `label`.
+    pub fn challenge_128_bits_field_element(
+        &mut self,
+        label: &[u8],
+    ) -> Result<NonNativeFieldVar<F, CF>, SynthesisError> {
+        let (mut elems, _) = self.challenge_128_bits_field_elements(label, 1)?;
+        Ok(elems.pop().unwrap())
+    }
+}
+
+/// The gadget for
+/// `ShortChallengeFiatShamirRng<SimpleHashFiatShamirRng<Blake2s, ChaChaRng>>`.
+/// The seed is
+/// hashed with the Blake2s gadget, and challenges are read off the ChaCha20
+/// keystream that `ChaChaRng::from_seed(seed)` would produce.
+pub struct SimpleHashFiatShamirRngVar<F: PrimeField, CF: PrimeField> {
+    seed: Vec<UInt8<CF>>,
+    /// The ChaCha20 key, i.e. `seed` read as little-endian words.
+    key: Vec<UInt32<CF>>,
+    /// The counter of the next keystream block.
+    counter: u64,
+    /// The words of the current keystream block that are yet to be consumed.
+    buffer: Vec<UInt32<CF>>,
+    #[doc(hidden)]
+    field: PhantomData<F>,
+}
+
+impl<F: PrimeField, CF: PrimeField> SimpleHashFiatShamirRngVar<F, CF> {
+    fn from_preimage(preimage: &[UInt8<CF>]) -> Result<Self, SynthesisError> {
+        let key = evaluate_blake2s(&preimage.to_bits_le()?)?;
+        let seed = key
+            .iter()
+            .flat_map(|word| word.to_bits_le())
+            .collect::<Vec<_>>()
+            .chunks(8)
+            .map(UInt8::from_bits_le)
+            .collect();
+        Ok(Self {
+            seed,
+            key,
+            counter: 0,
+            buffer: Vec::new(),
+            field: PhantomData,
+        })
+    }
+
+    fn next_u32(&mut self) -> Result<UInt32<CF>, SynthesisError> {
+        if self.buffer.is_empty() {
+            let mut block = chacha20_block(&self.key, self.counter)?;
+            block.reverse();
+            self.buffer = block;
+            self.counter += 1;
+        }
+        Ok(self.buffer.pop().unwrap())
+    }
+}
+
+impl<F: PrimeField, CF: PrimeField>
+    FiatShamirRngVar<
+        F,
+        CF,
+        ShortChallengeFiatShamirRng<SimpleHashFiatShamirRng<Blake2s, ChaChaRng>>,
+    > for SimpleHashFiatShamirRngVar<F, CF>
+{
+    /// `self.seed = H(initial_input)`.
+    fn initialize(initial_input: &[UInt8<CF>]) -> Result<Self, SynthesisError> {
+        Self::from_preimage(initial_input)
+    }
+
+    /// `self.seed = H(new_input || self.seed)`.
+    fn absorb(&mut self, new_input: &[UInt8<CF>]) -> Result<(), SynthesisError> {
+        let mut preimage = new_input.to_vec();
+        preimage.extend_from_slice(&self.seed);
+        *self = Self::from_preimage(&preimage)?;
+        Ok(())
+    }
+
+    fn squeeze_128_bits_field_elements(
+        &mut self,
+        num: usize,
+    ) -> Result<(Vec<NonNativeFieldVar<F, CF>>, Vec<Vec<Boolean<CF>>>), SynthesisError> {
+        let mut elems = Vec::with_capacity(num);
+        let mut elems_bits = Vec::with_capacity(num);
+        for _ in 0..num {
+            // `u128::rand` reads two `u64`s, each made of two consecutive
+            // `u32`s, least significant first.
+            let mut bits = Vec::with_capacity(128);
+            for _ in 0..4 {
+                bits.extend(self.next_u32()?.to_bits_le());
+            }
+            elems.push(nonnative_from_bits_le(&bits)?);
+            elems_bits.push(bits);
+        }
+        Ok((elems, elems_bits))
+    }
+}
+
+/// The gadget for `ShortChallengeFiatShamirRng<PoseidonFiatShamirRng<CF>>`.
+pub struct PoseidonFiatShamirRngVar<F: PrimeField, CF: PrimeField + Absorb> {
+    sponge: PoseidonSpongeVar<CF>,
+    #[doc(hidden)]
+    field: PhantomData<F>,
+}
+
+impl<F: PrimeField, CF: PrimeField + Absorb>
+    FiatShamirRngVar<F, CF, ShortChallengeFiatShamirRng<PoseidonFiatShamirRng<CF>>>
+    for PoseidonFiatShamirRngVar<F, CF>
+{
+    fn initialize(initial_input: &[UInt8<CF>]) -> Result<Self, SynthesisError> {
+        let mut rng = Self {
+            sponge: PoseidonSpongeVar::new(initial_input.cs(), &poseidon_parameters()),
+            field: PhantomData,
+        };
+        rng.absorb(initial_input)?;
+        Ok(rng)
+    }
+
+    fn absorb(&mut self, new_input: &[UInt8<CF>]) -> Result<(), SynthesisError> {
+        let bytes_per_elem = CF::Params::CAPACITY as usize / 8;
+        let mut elems = Vec::with_capacity(1 + new_input.len() / bytes_per_elem);
+        elems.push(FpVar::constant(CF::from(new_input.len() as u64)));
+        for chunk in new_input.chunks(bytes_per_elem) {
+            elems.push(Boolean::le_bits_to_fp_var(&chunk.to_bits_le()?)?);
+        }
+        self.sponge.absorb(&elems)
+    }
+
+    fn absorb_nonnative_field_elements(
+        &mut self,
+        elems: &[NonNativeFieldVar<F, CF>],
+    ) -> Result<(), SynthesisError> {
+        let mut elems_bits = Vec::with_capacity(elems.len());
+        for elem in elems {
+            let mut bits = elem.to_bits_le()?;
+            bits.truncate(F::size_in_bits());
+            elems_bits.push(bits);
+        }
+
+        let elems = if F::characteristic() == CF::characteristic() {
+            elems_bits
+                .iter()
+                .map(|bits| Boolean::le_bits_to_fp_var(bits))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            elems_bits
+                .concat()
+                .chunks(CF::Params::CAPACITY as usize)
+                .map(Boolean::le_bits_to_fp_var)
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        self.sponge.absorb(&elems)
+    }
+
+    fn squeeze_128_bits_field_elements(
+        &mut self,
+        num: usize,
+    ) -> Result<(Vec<NonNativeFieldVar<F, CF>>, Vec<Vec<Boolean<CF>>>), SynthesisError> {
+        let mut elems = Vec::with_capacity(num);
+        let mut elems_bits = Vec::with_capacity(num);
+        for squeezed in self.sponge.squeeze_field_elements(num)? {
+            let bits = squeezed.to_bits_le()?[..128].to_vec();
+            elems.push(nonnative_from_bits_le(&bits)?);
+            elems_bits.push(bits);
+        }
+        Ok((elems, elems_bits))
+    }
+}
+
+/// Convert little-endian bits into the non-native field element they encode.
+/// The bits must encode an integer smaller than the modulus of `F`.
+pub(crate) fn nonnative_from_bits_le<F: PrimeField, CF: PrimeField>(
+    bits: &[Boolean<CF>],
+) -> Result<NonNativeFieldVar<F, CF>, SynthesisError> {
+    let value = || {
+        F::from_repr(F::BigInt::from_bits_le(&bits.value()?)).ok_or(SynthesisError::Unsatisfiable)
+    };
+
+    let cs = bits.cs();
+    if cs.is_none() {
+        return Ok(NonNativeFieldVar::constant(value()?));
+    }
+
+    let elem = NonNativeFieldVar::new_witness(cs, value)?;
+    let elem_bits = elem.to_bits_le()?;
+    elem_bits[..bits.len()].enforce_equal(bits)?;
+    for bit in &elem_bits[bits.len()..] {
+        bit.enforce_equal(&Boolean::FALSE)?;
+    }
+    Ok(elem)
+}
+
+/// The "expand 32-byte k" constants of ChaCha.
+const CHACHA_CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+/// Compute the ChaCha20 keystream block number `counter` for `key` and the
+/// all-zero stream id, as `rand_chacha` does.
+fn chacha20_block<CF: PrimeField>(
+    key: &[UInt32<CF>],
+    counter: u64,
+) -> Result<Vec<UInt32<CF>>, SynthesisError> {
+    let mut initial_state: Vec<_> = CHACHA_CONSTANTS
+        .iter()
+        .map(|c| UInt32::constant(*c))
+        .collect();
+    initial_state.extend_from_slice(key);
+    initial_state.push(UInt32::constant(counter as u32));
+    initial_state.push(UInt32::constant((counter >> 32) as u32));
+    initial_state.push(UInt32::constant(0));
+    initial_state.push(UInt32::constant(0));
+
+    let mut state = initial_state.clone();
+    for _ in 0..10 {
+        // Column rounds
+        quarter_round(&mut state, 0, 4, 8, 12)?;
+        quarter_round(&mut state, 1, 5, 9, 13)?;
+        quarter_round(&mut state, 2, 6, 10, 14)?;
+        quarter_round(&mut state, 3, 7, 11, 15)?;
+        // Diagonal rounds
+        quarter_round(&mut state, 0, 5, 10, 15)?;
+        quarter_round(&mut state, 1, 6, 11, 12)?;
+        quarter_round(&mut state, 2, 7, 8, 13)?;
+        quarter_round(&mut state, 3, 4, 9, 14)?;
+    }
+
+    state
+        .into_iter()
+        .zip(initial_state)
+        .map(|(word, initial_word)| UInt32::addmany(&[word, initial_word]))
+        .collect()
+}
+
+fn quarter_round<CF: PrimeField>(
+    state: &mut [UInt32<CF>],
+    a: usize,
+    b: usize,
+    c: usize,
+    d: usize,
+) -> Result<(), SynthesisError> {
+    // `rotr(32 - n)` is a left rotation by `n`.
+    state[a] = UInt32::addmany(&[state[a].clone(), state[b].clone()])?;
+    state[d] = state[d].xor(&state[a])?.rotr(16);
+    state[c] = UInt32::addmany(&[state[c].clone(), state[d].clone()])?;
+    state[b] = state[b].xor(&state[c])?.rotr(20);
+    state[a] = UInt32::addmany(&[state[a].clone(), state[b].clone()])?;
+    state[d] = state[d].xor(&state[a])?.rotr(24);
+    state[c] = UInt32::addmany(&[state[c].clone(), state[d].clone()])?;
+    state[b] = state[b].xor(&state[c])?.rotr(25);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::{to_bytes, UniformRand};
+    use ark_mnt4_298::{Fq, Fr};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    type FS = ShortChallengeFiatShamirRng<SimpleHashFiatShamirRng<Blake2s, ChaChaRng>>;
+    type FSVar = SimpleHashFiatShamirRngVar<Fr, Fq>;
+
+    #[test]
+    fn simple_hash_fs_rng_gadget_matches_native() {
+        let rng = &mut ark_std::test_rng();
+        let cs = ConstraintSystem::<Fq>::new_ref();
+
+        let initial_input = to_bytes![Fr::rand(rng), Fq::rand(rng)].unwrap();
+        let new_input = to_bytes![Fr::rand(rng)].unwrap();
+
+        let mut fs_rng = FS::initialize(&initial_input);
+        let mut fs_rng_var = <FSVar as FiatShamirRngVar<_, _, FS>>::initialize(
+            &UInt8::new_witness_vec(cs.clone(), &initial_input).unwrap(),
+        )
+        .unwrap();
+
+        // Squeeze across a keystream block boundary before absorbing again.
+        let expected: Vec<Fr> = (0..5).map(|_| u128::rand(&mut fs_rng).into()).collect();
+        let (elems, _) = fs_rng_var.squeeze_128_bits_field_elements(5).unwrap();
+        assert_eq!(elems.value().unwrap(), expected);
+
+        fs_rng.absorb(&new_input);
+        fs_rng_var
+            .absorb(&UInt8::new_witness_vec(cs.clone(), &new_input).unwrap())
+            .unwrap();
+        let expected: Fr = u128::rand(&mut fs_rng).into();
+        let (elems, bits) = fs_rng_var.squeeze_128_bits_field_elements(1).unwrap();
+        assert_eq!(elems[0].value().unwrap(), expected);
+        assert_eq!(bits[0].len(), 128);
+
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn poseidon_fs_rng_gadget_matches_native() {
+        type FS = ShortChallengeFiatShamirRng<PoseidonFiatShamirRng<Fq>>;
+        type FSVar = PoseidonFiatShamirRngVar<Fr, Fq>;
+
+        let rng = &mut ark_std::test_rng();
+        let cs = ConstraintSystem::<Fq>::new_ref();
+
+        let initial_input = to_bytes![Fr::rand(rng), Fq::rand(rng)].unwrap();
+        let elems: Vec<Fr> = (0..3).map(|_| Fr::rand(rng)).collect();
+
+        let mut fs_rng = FS::initialize(&initial_input);
+        let mut fs_rng_var = <FSVar as FiatShamirRngVar<_, _, FS>>::initialize(
+            &UInt8::new_witness_vec(cs.clone(), &initial_input).unwrap(),
+        )
+        .unwrap();
+
+        fs_rng.absorb_field_elements(&elems);
+        let elems_var: Vec<_> = elems
+            .iter()
+            .map(|elem| NonNativeFieldVar::new_witness(cs.clone(), || Ok(elem)).unwrap())
+            .collect();
+        fs_rng_var
+            .absorb_nonnative_field_elements(&elems_var)
+            .unwrap();
+
+        let expected: Vec<Fr> = fs_rng.squeeze_128_bits_field_elements(3);
+        let (elems, bits) = fs_rng_var.squeeze_128_bits_field_elements(3).unwrap();
+        assert_eq!(elems.value().unwrap(), expected);
+        assert_eq!(bits[0].len(), 128);
+
+        assert!(cs.is_satisfied().unwrap());
+    }
+}
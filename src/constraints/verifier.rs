@@ -0,0 +1,217 @@
+use crate::ahp::AHPForR1CS;
+use crate::constraints::{
+    ahp::AHPForR1CSGadget,
+    data_structures::{IndexVerifierKeyVar, ProofVar, ProverMsgVar, PublicInputVar},
+    pc::PCVerifierGadget,
+    rng::{FiatShamirRngVar, TranscriptVar},
+};
+use crate::rng::FiatShamirRng;
+use crate::{format, Marlin, ToString, Vec};
+use ark_ff::PrimeField;
+use ark_nonnative_field::NonNativeFieldVar;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::PolynomialCommitment;
+use ark_poly_commit::{EvaluationsVar, LabeledPointVar, PCCheckRandomDataVar};
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::SynthesisError;
+use ark_std::{collections::BTreeSet, marker::PhantomData, vec};
+use hashbrown::HashMap;
+
+/// The Marlin verifier, expressed as constraints over `CF`. It checks proofs
+/// produced by `Marlin<F, PC, FS>`, using the polynomial commitment gadget
+/// `PCG` and the Fiat-Shamir gadget `FSG`.
+pub struct MarlinVerifierGadget<F, CF, PC, FS, PCG, FSG>
+where
+    F: PrimeField,
+    CF: PrimeField,
+    PC: PolynomialCommitment<F, DensePolynomial<F>>,
+    FS: FiatShamirRng,
+    PCG: PCVerifierGadget<F, PC, CF>,
+    FSG: FiatShamirRngVar<F, CF, FS>,
+{
+    #[doc(hidden)]
+    f: PhantomData<(F, CF)>,
+    #[doc(hidden)]
+    pc: PhantomData<(PC, PCG)>,
+    #[doc(hidden)]
+    fs: PhantomData<(FS, FSG)>,
+}
+
+impl<F, CF, PC, FS, PCG, FSG> MarlinVerifierGadget<F, CF, PC, FS, PCG, FSG>
+where
+    F: PrimeField,
+    CF: PrimeField,
+    PC: PolynomialCommitment<F, DensePolynomial<F>>,
+    FS: FiatShamirRng,
+    PCG: PCVerifierGadget<F, PC, CF>,
+    FSG: FiatShamirRngVar<F, CF, FS>,
+{
+    /// The gadget version of `Marlin::verify`: outputs a `Boolean` that is
+    /// true if and only if `proof` is valid for `index_vk` and `public_input`.
+    /// Indices with lookups or product gates are not supported.
+    pub fn verify(
+        index_vk: &IndexVerifierKeyVar<F, CF, PC, PCG>,
+        public_input: &PublicInputVar<F, CF>,
+        proof: &ProofVar<F, CF, PC, PCG>,
+    ) -> Result<Boolean<CF>, SynthesisError> {
+        let verifier_time = start_timer!(|| "MarlinVerifierGadget::Verify");
+
+        // Neither the lookup argument nor product gates are supported in
+        // constraints.
+        if index_vk.index_info.has_lookups() || index_vk.index_info.has_product_gates() {
+            return Err(SynthesisError::MalformedVerifyingKey);
+        }
+
+        let public_input = &public_input.input;
+
+        if proof.commitments.len() != 3 || proof.prover_messages.len() != 3 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let mut transcript =
+            TranscriptVar::<F, CF, FS, FSG>::new(Marlin::<F, PC, FS>::PROTOCOL_NAME)?;
+        transcript.append_message(b"index verifier key", &index_vk.to_bytes()?)?;
+        transcript.append_nonnative_field_elements(b"public input", public_input)?;
+
+        // --------------------------------------------------------------------
+        // First round
+
+        let first_comms = &proof.commitments[0];
+        append_round(&mut transcript, 1, first_comms, &proof.prover_messages[0])?;
+
+        let (_, verifier_state) =
+            AHPForR1CSGadget::verifier_first_round(index_vk.index_info, &mut transcript)?;
+        // --------------------------------------------------------------------
+
+        // --------------------------------------------------------------------
+        // Second round
+        let second_comms = &proof.commitments[1];
+        append_round(&mut transcript, 2, second_comms, &proof.prover_messages[1])?;
+
+        let (_, verifier_state) =
+            AHPForR1CSGadget::verifier_second_round(verifier_state, &mut transcript)?;
+        // --------------------------------------------------------------------
+
+        // --------------------------------------------------------------------
+        // Third round
+        let third_comms = &proof.commitments[2];
+        append_round(&mut transcript, 3, third_comms, &proof.prover_messages[2])?;
+
+        let verifier_state =
+            AHPForR1CSGadget::verifier_third_round(verifier_state, &mut transcript)?;
+        // --------------------------------------------------------------------
+
+        // Collect degree bounds for commitments. Indexed polynomials have *no*
+        // degree bounds because we know the committed index polynomial has the
+        // correct degree.
+        let index_info = index_vk.index_info;
+        let degree_bounds = vec![None; index_vk.index_comms.len()]
+            .into_iter()
+            .chain(AHPForR1CS::prover_first_round_degree_bounds(&index_info))
+            .chain(AHPForR1CS::prover_second_round_degree_bounds(&index_info))
+            .chain(AHPForR1CS::prover_third_round_degree_bounds(&index_info))
+            .collect::<Vec<_>>();
+
+        // Gather commitments in one vector.
+        let commitments: Vec<_> = index_vk
+            .iter()
+            .chain(first_comms)
+            .chain(second_comms)
+            .chain(third_comms)
+            .cloned()
+            .zip(AHPForR1CS::<F>::polynomial_labels(&index_info))
+            .zip(degree_bounds)
+            .map(|((c, l), d)| PCG::create_labeled_commitment(l, c, d))
+            .collect();
+
+        let (query_set, verifier_state) = AHPForR1CSGadget::verifier_query_set(verifier_state);
+
+        transcript.append_nonnative_field_elements(b"evaluations", &proof.evaluations)?;
+        let (opening_challenges, opening_challenges_bits) =
+            transcript.challenge_128_bits_field_elements(b"opening challenge", 1)?;
+
+        let mut evaluations = HashMap::new();
+        let mut evaluation_labels = Vec::new();
+        for (poly_label, point) in &query_set.0 {
+            let key = LabeledPointVar {
+                name: poly_label.clone(),
+                value: point.value.clone(),
+            };
+            if AHPForR1CS::<F>::LC_WITH_ZERO_EVAL.contains(&poly_label.as_ref()) {
+                evaluations.insert(key, NonNativeFieldVar::zero());
+            } else {
+                evaluation_labels.push(key);
+            }
+        }
+        evaluation_labels.sort_by(|a, b| a.name.cmp(&b.name));
+        for (q, eval) in evaluation_labels.into_iter().zip(&proof.evaluations) {
+            evaluations.insert(q, eval.clone());
+        }
+        let evaluations = EvaluationsVar(evaluations);
+
+        let lc_s = AHPForR1CSGadget::construct_linear_combinations(
+            public_input,
+            &evaluations,
+            &verifier_state,
+        )?;
+
+        // The randomizers that batch the opening checks at different points
+        // are not part of the native transcript. They are squeezed only once
+        // the evaluation proofs that they randomize have been absorbed.
+        let num_points = query_set
+            .0
+            .iter()
+            .map(|(_, point)| point.name.to_string())
+            .collect::<BTreeSet<_>>()
+            .len();
+        transcript.append_message(b"evaluation proof", &proof.pc_batch_proof.to_bytes()?)?;
+        let (batching_rands, batching_rands_bits) = transcript
+            .challenge_128_bits_field_elements(b"batching randomizers", num_points - 1)?;
+
+        let rand_data = PCCheckRandomDataVar {
+            opening_challenges,
+            opening_challenges_bits,
+            batching_rands,
+            batching_rands_bits,
+        };
+
+        let evaluations_are_correct = PCG::check_combinations(
+            &index_vk.verifier_key,
+            &lc_s,
+            &commitments,
+            &query_set,
+            &evaluations,
+            &proof.pc_batch_proof,
+            &rand_data,
+        )?;
+
+        end_timer!(verifier_time);
+        Ok(evaluations_are_correct)
+    }
+}
+
+/// Append the commitments and the message sent by the prover in the given
+/// round to the transcript, as `Marlin::append_round` does.
+fn append_round<F, CF, FS, FSG>(
+    transcript: &mut TranscriptVar<F, CF, FS, FSG>,
+    round: usize,
+    comms: &[impl ToBytesGadget<CF>],
+    prover_msg: &ProverMsgVar<F, CF>,
+) -> Result<(), SynthesisError>
+where
+    F: PrimeField,
+    CF: PrimeField,
+    FS: FiatShamirRng,
+    FSG: FiatShamirRngVar<F, CF, FS>,
+{
+    let mut bytes = Vec::new();
+    for comm in comms {
+        bytes.extend(comm.to_bytes()?);
+    }
+    transcript.append_message(format!("round-{} commitments", round).as_bytes(), &bytes)?;
+    let elems = match prover_msg {
+        ProverMsgVar::EmptyMessage => &[][..],
+        ProverMsgVar::FieldElements(elems) => &elems[..],
+    };
+    transcript.append_nonnative_field_elements(format!("prover msg {}", round).as_bytes(), elems)
+}
@@ -1,6 +1,6 @@
 use crate::ahp::indexer::*;
 use crate::ahp::prover::ProverMsg;
-use crate::Vec;
+use crate::{String, Vec};
 use ark_ff::PrimeField;
 use ark_poly::univariate::DensePolynomial;
 use ark_poly_commit::{BatchLCProof, PolynomialCommitment};
@@ -9,6 +9,7 @@ use ark_std::{
     format,
     io::{Read, Write},
 };
+use digest::Digest;
 
 /* ************************************************************************* */
 /* ************************************************************************* */
@@ -38,7 +39,17 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>> ark_ff::ToB
 {
     fn write<W: Write>(&self, mut w: W) -> ark_std::io::Result<()> {
         self.index_info.write(&mut w)?;
-        self.index_comms.write(&mut w)
+        self.index_comms.write(&mut w)?;
+        // The verifier key has no `ToBytes` encoding in general, so its
+        // canonical serialization is written instead. It covers the degree
+        // bounds that the key supports as well as its group elements, which
+        // are left uncompressed so that the verifier gadget can compute it.
+        self.verifier_key
+            .serialize_uncompressed(&mut w)
+            .map_err(|err| match err {
+                SerializationError::IoError(err) => err,
+                err => ark_std::io::Error::new(ark_std::io::ErrorKind::InvalidData, err),
+            })
     }
 }
 
@@ -59,6 +70,24 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>> IndexVerifi
     pub fn iter(&self) -> impl Iterator<Item = &PC::Commitment> {
         self.index_comms.iter()
     }
+
+    /// A stable identifier of `self`: the hash under `D` of the canonical
+    /// serializations of `index_info`, `index_comms` and `verifier_key`.
+    /// Verifier keys of the same circuit have different digests if they were
+    /// derived from different universal parameters or indexer options.
+    pub fn digest<D: Digest>(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.index_info
+            .serialize(&mut bytes)
+            .expect("failed to serialize the index info");
+        self.index_comms
+            .serialize(&mut bytes)
+            .expect("failed to serialize the index commitments");
+        self.verifier_key
+            .serialize(&mut bytes)
+            .expect("failed to serialize the verifier key");
+        D::digest(&bytes).to_vec()
+    }
 }
 
 /* ************************************************************************* */
@@ -92,6 +121,9 @@ where
     }
 }
 
+/// The prover and verifier keys for a specific index, as output by the indexer.
+pub type IndexKeys<F, PC> = (IndexProverKey<F, PC>, IndexVerifierKey<F, PC>);
+
 /* ************************************************************************* */
 /* ************************************************************************* */
 /* ************************************************************************* */
@@ -107,6 +139,10 @@ pub struct Proof<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>>
     pub prover_messages: Vec<ProverMsg<F>>,
     /// An evaluation proof from the polynomial commitment.
     pub pc_proof: BatchLCProof<F, DensePolynomial<F>, PC>,
+    /// The digest of the verifier key that the proof was made for, if the
+    /// prover attached it. It is not part of the Fiat-Shamir transcript, and
+    /// only lets a verifier reject proofs for a different circuit early.
+    pub vk_digest: Option<Vec<u8>>,
 }
 
 impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>> Proof<F, PC> {
@@ -122,11 +158,25 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>> Proof<F, PC
             evaluations,
             prover_messages,
             pc_proof,
+            vk_digest: None,
         }
     }
 
+    /// Attach `vk_digest`, the digest of the verifier key that `self` was made
+    /// for, as computed by `IndexVerifierKey::digest`.
+    pub fn with_vk_digest(mut self, vk_digest: Vec<u8>) -> Self {
+        self.vk_digest = Some(vk_digest);
+        self
+    }
+
     /// Prints information about the size of the proof.
     pub fn print_size_info(&self) {
+        add_to_trace!(|| "Statistics about proof", || self.size_info());
+    }
+
+    /// A breakdown of the size of the proof: the number and size in bytes of
+    /// its commitments, evaluation proofs, evaluations and prover messages.
+    pub fn size_info(&self) -> String {
         use ark_poly_commit::PCCommitment;
 
         let mut num_comms_without_degree_bounds = 0;
@@ -159,7 +209,7 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>> Proof<F, PC
             .sum();
         let prover_msg_size_in_bytes = self.prover_messages.serialized_size();
         let arg_size = self.serialized_size();
-        let stats = format!(
+        format!(
             "Argument size in bytes: {}\n\n\
              Number of commitments without degree bounds: {}\n\
              Size (in bytes) of commitments without degree bounds: {}\n\
@@ -182,7 +232,6 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>> Proof<F, PC
             evals_size_in_bytes,
             num_prover_messages,
             prover_msg_size_in_bytes,
-        );
-        add_to_trace!(|| "Statistics about proof", || stats);
+        )
     }
 }
@@ -1,14 +1,22 @@
 use crate::ahp::Error as AHPError;
+use crate::srs::PowersOfTauError;
 
 /// A `enum` specifying the possible failure modes of the `SNARK`.
 #[derive(Debug)]
 pub enum Error<E> {
     /// The index is too large for the universal public parameters.
     IndexTooLarge,
+    /// The verifier keys of a batch were not derived from the same universal
+    /// parameters, so their proofs cannot be verified together.
+    IncompatibleVerifierKeys,
     /// There was an error in the underlying holographic IOP.
     AHPError(AHPError),
     /// There was an error in the underlying polynomial commitment.
     PolynomialCommitmentError(E),
+    /// The universal parameters are not well formed.
+    MalformedSRS,
+    /// The output of a powers-of-tau ceremony could not be imported.
+    PowersOfTauError(PowersOfTauError),
 }
 
 impl<E> From<AHPError> for Error<E> {
@@ -17,6 +25,12 @@ impl<E> From<AHPError> for Error<E> {
     }
 }
 
+impl<E> From<PowersOfTauError> for Error<E> {
+    fn from(err: PowersOfTauError) -> Self {
+        Error::PowersOfTauError(err)
+    }
+}
+
 impl<E> Error<E> {
     /// Convert an error in the underlying polynomial commitment scheme
     /// to a `Error`.
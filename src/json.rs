@@ -0,0 +1,566 @@
+use crate::ahp::indexer::IndexInfo;
+use crate::ahp::prover::ProverMsg;
+use crate::{AHPForR1CS, ArithmetizationLayout, IndexVerifierKey, Proof, String, ToString, Vec};
+use ark_ff::{BigInteger, FromBytes, PrimeField};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::PolynomialCommitment;
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystemRef, LinearCombination, SynthesisError, Variable,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use serde::{Deserialize, Serialize};
+
+/// The ways in which exporting or importing a JSON or hex artifact can fail.
+#[derive(Debug)]
+pub enum JsonError {
+    /// The JSON is malformed, or does not have the expected fields.
+    Json(serde_json::Error),
+    /// An artifact or one of its elements could not be serialized or
+    /// deserialized.
+    Serialization(SerializationError),
+    /// A string that should be hex is not.
+    InvalidHex(String),
+    /// A field element is not smaller than the modulus.
+    InvalidFieldElement(String),
+    /// The arithmetization layout is neither `joint` nor `per_matrix`.
+    UnknownArithmetization(String),
+    /// The labels of the index commitments are not those of the index that
+    /// the key describes.
+    UnexpectedLabel {
+        /// The label of the polynomial that the index has at this position.
+        expected: Option<String>,
+        /// The label found in the JSON.
+        found: Option<String>,
+    },
+    /// A constraint refers to a variable that the constraint system does not
+    /// have.
+    UnknownVariable(usize),
+    /// An assignment does not have as many values as the constraint system
+    /// has variables of its kind.
+    WrongAssignmentLength {
+        /// The number of variables of the constraint system.
+        expected: usize,
+        /// The number of values of the assignment.
+        found: usize,
+    },
+}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(err: serde_json::Error) -> Self {
+        JsonError::Json(err)
+    }
+}
+
+impl From<SerializationError> for JsonError {
+    fn from(err: SerializationError) -> Self {
+        JsonError::Serialization(err)
+    }
+}
+
+/// A value together with the label of the polynomial that it belongs to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabeledValue {
+    /// The label of the polynomial.
+    pub label: String,
+    /// The value, in hex.
+    pub value: String,
+}
+
+/// The evaluation of a polynomial or linear combination at a point.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvaluationJson {
+    /// The label of the polynomial or linear combination.
+    pub label: String,
+    /// The label of the point that it is evaluated at: `beta`, `gamma`,
+    /// `shifted_gamma` or `one`.
+    pub point: String,
+    /// The evaluation, as a big-endian integer in hex.
+    pub value: String,
+}
+
+/// The JSON form of a `Proof`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofJson {
+    /// The commitments that the prover sends in each round, labeled by
+    /// polynomial, each as the hex of its canonical serialization.
+    pub commitments: Vec<Vec<LabeledValue>>,
+    /// The evaluations of the polynomials and linear combinations that the
+    /// verifier queries, in the order of `Proof::evaluations`.
+    pub evaluations: Vec<EvaluationJson>,
+    /// The field elements that the prover sends in each round, or `null` for
+    /// the rounds in which it only sends oracles.
+    pub prover_messages: Vec<Option<Vec<String>>>,
+    /// The hex of the canonical serialization of the evaluation proof of the
+    /// polynomial commitment.
+    pub pc_proof: String,
+    /// The hex of the digest of the verifier key, if the prover attached it.
+    pub vk_digest: Option<String>,
+}
+
+/// The JSON form of an `IndexInfo`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexInfoJson {
+    /// The total number of variables in the constraint system.
+    pub num_variables: usize,
+    /// The number of constraints.
+    pub num_constraints: usize,
+    /// The number of non-zero entries covered by each arithmetization.
+    pub num_non_zero: usize,
+    /// The number of formatted public inputs, including the "one" variable.
+    pub num_instance_variables: usize,
+    /// The layout of the arithmetization: `joint` or `per_matrix`.
+    pub arithmetization: String,
+    /// The number of entries of the combined lookup table.
+    pub lookup_table_size: usize,
+    /// The number of factors of the product gates.
+    pub product_gate_degree: usize,
+    /// The number of evaluation queries that proofs stay zero-knowledge for.
+    pub zk_bound: usize,
+}
+
+/// The JSON form of an `IndexVerifierKey`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifierKeyJson {
+    /// The size and shape of the index.
+    pub index_info: IndexInfoJson,
+    /// The commitments to the index polynomials, labeled by polynomial, each
+    /// as the hex of its canonical serialization.
+    pub index_comms: Vec<LabeledValue>,
+    /// The hex of the canonical serialization of the verifier key of the
+    /// polynomial commitment.
+    pub verifier_key: String,
+}
+
+/// A term of a linear combination: a variable times a coefficient.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TermJson {
+    /// The index of the variable: `0` for the constant one, `1` to
+    /// `num_public_inputs` for the public inputs, and the following ones for
+    /// the witness variables.
+    pub variable: usize,
+    /// The coefficient, as a big-endian integer in hex.
+    pub coefficient: String,
+}
+
+/// A constraint `a * b = c` between three linear combinations.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstraintJson {
+    /// The terms of the left factor.
+    pub a: Vec<TermJson>,
+    /// The terms of the right factor.
+    pub b: Vec<TermJson>,
+    /// The terms of the product.
+    pub c: Vec<TermJson>,
+}
+
+/// The JSON form of an R1CS constraint system, which `JsonCircuit` turns into
+/// a circuit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct R1csJson {
+    /// The number of public inputs, not counting the constant one.
+    pub num_public_inputs: usize,
+    /// The number of witness variables.
+    pub num_witness_variables: usize,
+    /// The constraints, in order.
+    pub constraints: Vec<ConstraintJson>,
+}
+
+/// The JSON form of an assignment to the variables of an R1CS constraint
+/// system, each value as a big-endian integer in hex.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssignmentJson {
+    /// The values of the public inputs.
+    pub public_inputs: Vec<String>,
+    /// The values of the witness variables.
+    pub witness: Vec<String>,
+}
+
+impl<F> From<&IndexInfo<F>> for IndexInfoJson {
+    fn from(info: &IndexInfo<F>) -> Self {
+        Self {
+            num_variables: info.num_variables,
+            num_constraints: info.num_constraints,
+            num_non_zero: info.num_non_zero,
+            num_instance_variables: info.num_instance_variables,
+            arithmetization: match info.arithmetization {
+                ArithmetizationLayout::Joint => "joint".into(),
+                ArithmetizationLayout::PerMatrix => "per_matrix".into(),
+            },
+            lookup_table_size: info.lookup_table_size,
+            product_gate_degree: info.product_gate_degree,
+            zk_bound: info.zk_bound,
+        }
+    }
+}
+
+impl IndexInfoJson {
+    /// The `IndexInfo` that `self` describes.
+    pub fn to_index_info<F>(&self) -> Result<IndexInfo<F>, JsonError> {
+        let arithmetization = match self.arithmetization.as_str() {
+            "joint" => ArithmetizationLayout::Joint,
+            "per_matrix" => ArithmetizationLayout::PerMatrix,
+            other => return Err(JsonError::UnknownArithmetization(other.to_string())),
+        };
+        Ok(IndexInfo::new(
+            self.num_variables,
+            self.num_constraints,
+            self.num_non_zero,
+            self.num_instance_variables,
+            arithmetization,
+            self.lookup_table_size,
+            self.product_gate_degree,
+            self.zk_bound,
+        ))
+    }
+}
+
+/// Encode `bytes` as a `0x`-prefixed hex string.
+pub fn encode_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + 2 * bytes.len());
+    hex.push_str("0x");
+    for byte in bytes {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    hex
+}
+
+/// Decode a hex string, with or without a `0x` prefix.
+pub fn decode_hex(hex: &str) -> Result<Vec<u8>, JsonError> {
+    let digits = hex.strip_prefix("0x").unwrap_or(hex);
+    if digits.len() % 2 == 1 || !digits.is_ascii() {
+        return Err(JsonError::InvalidHex(hex.to_string()));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|_| JsonError::InvalidHex(hex.to_string()))
+        })
+        .collect()
+}
+
+/// Decode field elements encoded as big-endian integers in hex, such as the
+/// public inputs of an `AssignmentJson`.
+pub fn decode_field_elements<F: PrimeField>(hex: &[String]) -> Result<Vec<F>, JsonError> {
+    hex.iter().map(|elem| decode_field_element(elem)).collect()
+}
+
+/// Encode `elem` as its integer value, in big-endian hex.
+fn encode_field_element<F: PrimeField>(elem: &F) -> String {
+    encode_hex(&elem.into_repr().to_bytes_be())
+}
+
+/// Decode a field element encoded by `encode_field_element`, rejecting
+/// values that are not smaller than the modulus.
+fn decode_field_element<F: PrimeField>(hex: &str) -> Result<F, JsonError> {
+    let mut bytes = decode_hex(hex)?;
+    let size = <F::BigInt as BigInteger>::NUM_LIMBS * 8;
+    let invalid = || JsonError::InvalidFieldElement(hex.to_string());
+    if bytes.len() > size {
+        return Err(invalid());
+    }
+    bytes.reverse();
+    bytes.resize(size, 0);
+    let repr = F::BigInt::read(&bytes[..]).map_err(|_| invalid())?;
+    F::from_repr(repr).ok_or_else(invalid)
+}
+
+fn encode_canonical<T: CanonicalSerialize>(value: &T) -> Result<String, JsonError> {
+    let mut bytes = Vec::new();
+    value.serialize(&mut bytes)?;
+    Ok(encode_hex(&bytes))
+}
+
+fn decode_canonical<T: CanonicalDeserialize>(hex: &str) -> Result<T, JsonError> {
+    let bytes = decode_hex(hex)?;
+    let mut reader = &bytes[..];
+    let value = T::deserialize(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(JsonError::InvalidHex(hex.to_string()));
+    }
+    Ok(value)
+}
+
+impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>> Proof<F, PC> {
+    /// The JSON form of `self`, a proof for the index described by `info`,
+    /// which labels the commitments and evaluations by polynomial.
+    pub fn to_json_value(&self, info: &IndexInfo<F>) -> Result<ProofJson, JsonError> {
+        let num_index_polys = AHPForR1CS::<F>::indexer_polynomials(info).count();
+        let mut labels = AHPForR1CS::<F>::polynomial_labels(info).skip(num_index_polys);
+        let commitments = self
+            .commitments
+            .iter()
+            .map(|round| {
+                round
+                    .iter()
+                    .map(|comm| {
+                        Ok(LabeledValue {
+                            label: labels.next().unwrap_or_default(),
+                            value: encode_canonical(comm)?,
+                        })
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, JsonError>>()?;
+        let evaluations = AHPForR1CS::<F>::evaluation_labels(info)
+            .into_iter()
+            .zip(&self.evaluations)
+            .map(|((label, point), eval)| EvaluationJson {
+                label,
+                point,
+                value: encode_field_element(eval),
+            })
+            .collect();
+        let prover_messages = self
+            .prover_messages
+            .iter()
+            .map(|msg| match msg {
+                ProverMsg::EmptyMessage => None,
+                ProverMsg::FieldElements(elems) => {
+                    Some(elems.iter().map(encode_field_element).collect())
+                }
+            })
+            .collect();
+        Ok(ProofJson {
+            commitments,
+            evaluations,
+            prover_messages,
+            pc_proof: encode_canonical(&self.pc_proof)?,
+            vk_digest: self.vk_digest.as_deref().map(encode_hex),
+        })
+    }
+
+    /// The proof that `json` describes. The labels are only descriptive: the
+    /// commitments and evaluations are taken in order.
+    pub fn from_json_value(json: &ProofJson) -> Result<Self, JsonError> {
+        let commitments = json
+            .commitments
+            .iter()
+            .map(|round| round.iter().map(|c| decode_canonical(&c.value)).collect())
+            .collect::<Result<_, _>>()?;
+        let evaluations = json
+            .evaluations
+            .iter()
+            .map(|eval| decode_field_element(&eval.value))
+            .collect::<Result<_, _>>()?;
+        let prover_messages = json
+            .prover_messages
+            .iter()
+            .map(|msg| match msg {
+                None => Ok(ProverMsg::EmptyMessage),
+                Some(elems) => elems
+                    .iter()
+                    .map(|elem| decode_field_element(elem))
+                    .collect::<Result<_, _>>()
+                    .map(ProverMsg::FieldElements),
+            })
+            .collect::<Result<_, _>>()?;
+        let mut proof = Proof::new(
+            commitments,
+            evaluations,
+            prover_messages,
+            decode_canonical(&json.pc_proof)?,
+        );
+        if let Some(vk_digest) = &json.vk_digest {
+            proof = proof.with_vk_digest(decode_hex(vk_digest)?);
+        }
+        Ok(proof)
+    }
+
+    /// Export `self`, a proof for the index described by `info`, as JSON.
+    pub fn to_json(&self, info: &IndexInfo<F>) -> Result<String, JsonError> {
+        Ok(serde_json::to_string_pretty(&self.to_json_value(info)?)?)
+    }
+
+    /// Import a proof exported by `Proof::to_json`.
+    pub fn from_json(json: &str) -> Result<Self, JsonError> {
+        Self::from_json_value(&serde_json::from_str(json)?)
+    }
+
+    /// The hex of the canonical serialization of `self`.
+    pub fn to_hex(&self) -> Result<String, JsonError> {
+        encode_canonical(self)
+    }
+
+    /// Import a proof exported by `Proof::to_hex`.
+    pub fn from_hex(hex: &str) -> Result<Self, JsonError> {
+        decode_canonical(hex)
+    }
+}
+
+impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>> IndexVerifierKey<F, PC> {
+    /// The JSON form of `self`, which gives the size of the index by name and
+    /// labels the index commitments by polynomial.
+    pub fn to_json_value(&self) -> Result<VerifierKeyJson, JsonError> {
+        let index_comms = AHPForR1CS::<F>::indexer_polynomials(&self.index_info)
+            .zip(&self.index_comms)
+            .map(|(label, comm)| {
+                Ok(LabeledValue {
+                    label,
+                    value: encode_canonical(comm)?,
+                })
+            })
+            .collect::<Result<_, JsonError>>()?;
+        Ok(VerifierKeyJson {
+            index_info: (&self.index_info).into(),
+            index_comms,
+            verifier_key: encode_canonical(&self.verifier_key)?,
+        })
+    }
+
+    /// The verifier key that `json` describes. The index commitments have to
+    /// carry the labels of the polynomials of the index, in order.
+    pub fn from_json_value(json: &VerifierKeyJson) -> Result<Self, JsonError> {
+        let index_info = json.index_info.to_index_info()?;
+        let mut expected = AHPForR1CS::<F>::indexer_polynomials(&index_info);
+        let mut found = json.index_comms.iter();
+        let mut index_comms = Vec::new();
+        loop {
+            match (expected.next(), found.next()) {
+                (None, None) => break,
+                (Some(label), Some(comm)) if label == comm.label => {
+                    index_comms.push(decode_canonical(&comm.value)?);
+                }
+                (expected, found) => {
+                    return Err(JsonError::UnexpectedLabel {
+                        expected,
+                        found: found.map(|comm| comm.label.clone()),
+                    })
+                }
+            }
+        }
+        Ok(Self {
+            index_info,
+            index_comms,
+            verifier_key: decode_canonical(&json.verifier_key)?,
+        })
+    }
+
+    /// Export `self` as JSON.
+    pub fn to_json(&self) -> Result<String, JsonError> {
+        Ok(serde_json::to_string_pretty(&self.to_json_value()?)?)
+    }
+
+    /// Import a verifier key exported by `IndexVerifierKey::to_json`.
+    pub fn from_json(json: &str) -> Result<Self, JsonError> {
+        Self::from_json_value(&serde_json::from_str(json)?)
+    }
+
+    /// The hex of the canonical serialization of `self`.
+    pub fn to_hex(&self) -> Result<String, JsonError> {
+        encode_canonical(self)
+    }
+
+    /// Import a verifier key exported by `IndexVerifierKey::to_hex`.
+    pub fn from_hex(hex: &str) -> Result<Self, JsonError> {
+        decode_canonical(hex)
+    }
+}
+
+/// A circuit made of the constraints of an `R1csJson`, and of an assignment
+/// to its variables when it is used to prove.
+#[derive(Clone, Debug)]
+pub struct JsonCircuit<F: PrimeField> {
+    num_public_inputs: usize,
+    num_witness_variables: usize,
+    constraints: Vec<[Vec<(F, usize)>; 3]>,
+    assignment: Option<(Vec<F>, Vec<F>)>,
+}
+
+impl<F: PrimeField> JsonCircuit<F> {
+    /// The circuit that `json` describes, without an assignment, which is
+    /// enough to index it.
+    pub fn from_json_value(json: &R1csJson) -> Result<Self, JsonError> {
+        let num_variables = 1 + json.num_public_inputs + json.num_witness_variables;
+        let decode_lc = |terms: &[TermJson]| {
+            terms
+                .iter()
+                .map(|term| {
+                    if term.variable >= num_variables {
+                        return Err(JsonError::UnknownVariable(term.variable));
+                    }
+                    Ok((decode_field_element(&term.coefficient)?, term.variable))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let constraints = json
+            .constraints
+            .iter()
+            .map(|cons| {
+                Ok([
+                    decode_lc(&cons.a)?,
+                    decode_lc(&cons.b)?,
+                    decode_lc(&cons.c)?,
+                ])
+            })
+            .collect::<Result<_, JsonError>>()?;
+        Ok(Self {
+            num_public_inputs: json.num_public_inputs,
+            num_witness_variables: json.num_witness_variables,
+            constraints,
+            assignment: None,
+        })
+    }
+
+    /// Import a constraint system in the JSON form of `R1csJson`.
+    pub fn from_json(json: &str) -> Result<Self, JsonError> {
+        Self::from_json_value(&serde_json::from_str(json)?)
+    }
+
+    /// Assign the values of `assignment` to the variables of `self`, to prove
+    /// that they satisfy its constraints.
+    pub fn with_assignment(mut self, assignment: &AssignmentJson) -> Result<Self, JsonError> {
+        let check_length = |expected: usize, values: &[String]| {
+            if values.len() != expected {
+                return Err(JsonError::WrongAssignmentLength {
+                    expected,
+                    found: values.len(),
+                });
+            }
+            decode_field_elements(values)
+        };
+        self.assignment = Some((
+            check_length(self.num_public_inputs, &assignment.public_inputs)?,
+            check_length(self.num_witness_variables, &assignment.witness)?,
+        ));
+        Ok(self)
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for JsonCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let (public_inputs, witness) = match self.assignment {
+            Some((public_inputs, witness)) => (Some(public_inputs), Some(witness)),
+            None => (None, None),
+        };
+        let value = |values: &Option<Vec<F>>, i: usize| {
+            values
+                .as_ref()
+                .map(|values| values[i])
+                .ok_or(SynthesisError::AssignmentMissing)
+        };
+
+        let mut variables =
+            Vec::with_capacity(1 + self.num_public_inputs + self.num_witness_variables);
+        variables.push(Variable::One);
+        for i in 0..self.num_public_inputs {
+            variables.push(cs.new_input_variable(|| value(&public_inputs, i))?);
+        }
+        for i in 0..self.num_witness_variables {
+            variables.push(cs.new_witness_variable(|| value(&witness, i))?);
+        }
+
+        let lc = |terms: &[(F, usize)]| {
+            LinearCombination(
+                terms
+                    .iter()
+                    .map(|(coeff, var)| (*coeff, variables[*var]))
+                    .collect(),
+            )
+        };
+        for [a, b, c] in &self.constraints {
+            cs.enforce_constraint(lc(a), lc(b), lc(c))?;
+        }
+        Ok(())
+    }
+}
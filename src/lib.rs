@@ -3,10 +3,10 @@
 //!
 //! # Note
 //!
-//! Currently, Marlin only supports R1CS instances where the number of inputs
-//! is the same as the number of constraints (i.e., where the constraint
-//! matrices are square). Furthermore, Marlin only supports instances where the
-//! public inputs are of size one less than a power of 2 (i.e., 2^n - 1).
+//! The constraint matrices need not be square: the constraints and the
+//! variables are indexed by separate domains, so tall and wide circuits are
+//! not padded to a square shape. Public inputs may be of any length: they are
+//! padded internally to the size of the input domain.
 #![deny(unused_import_braces, unused_qualifications, trivial_casts)]
 #![deny(trivial_numeric_casts)]
 #![deny(stable_features, unreachable_pub, non_shorthand_field_patterns)]
@@ -18,14 +18,19 @@
 #[macro_use]
 extern crate ark_std;
 
-use ark_ff::{to_bytes, PrimeField, UniformRand};
-use ark_poly::{univariate::DensePolynomial, EvaluationDomain, GeneralEvaluationDomain};
-use ark_poly_commit::Evaluations;
+use ark_ff::PrimeField;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::{Evaluations, LinearCombination, QuerySet};
 use ark_poly_commit::{LabeledCommitment, PCUniversalParams, PolynomialCommitment};
 use ark_relations::r1cs::ConstraintSynthesizer;
 use ark_std::rand::RngCore;
+use digest::Digest;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 use ark_std::{
+    cfg_iter,
     collections::BTreeMap,
     format,
     marker::PhantomData,
@@ -43,17 +48,60 @@ macro_rules! eprintln {
 /// Implements a Fiat-Shamir based Rng that allows one to incrementally update
 /// the seed based on new messages in the proof transcript.
 pub mod rng;
-use rng::FiatShamirRng;
-pub use rng::SimpleHashFiatShamirRng;
+use rng::{AbiEncoding, BytesEncoding, TranscriptEncoding};
+use rng::{FiatShamirRng, PointCommitment, Transcript};
+pub use rng::{KeccakFiatShamirRng, PoseidonFiatShamirRng};
+pub use rng::{ShortChallengeFiatShamirRng, SimpleHashFiatShamirRng};
 
 mod error;
 pub use error::*;
 
+/// Implements batch verification of many proofs, possibly for different indices
+/// derived from the same SRS, by folding their polynomial commitment checks
+/// into a single randomized check.
+pub mod batch;
+pub use batch::BatchVerifiablePC;
+
+/// Implements updatable universal parameters for KZG-based polynomial
+/// commitments, so that the SRS can be produced by a multi-party ceremony, and
+/// their import from the output of powers-of-tau ceremonies.
+pub mod srs;
+pub use srs::{PowersOfTauPC, UpdatablePC};
+
 mod data_structures;
 pub use data_structures::*;
 
+/// Implements a versioned, self-describing format for keys and proofs, which
+/// records what they were written for and checks it when they are read.
+pub mod versioned;
+pub use versioned::VersionedPC;
+
+/// Implements human-readable JSON and hex encodings of proofs and verifier
+/// keys, which label their elements by polynomial.
+#[cfg(feature = "json")]
+pub mod json;
+
+/// Generates Solidity contracts that verify Marlin proofs over BN254 on the
+/// EVM, and encodes proofs as their calldata.
+#[cfg(feature = "solidity")]
+pub mod solidity;
+
+/// Implements R1CS gadgets for the Marlin verifier, so that Marlin proofs can
+/// be verified inside other constraint systems.
+#[cfg(feature = "r1cs")]
+pub mod constraints;
+
 /// Implements an Algebraic Holographic Proof (AHP) for the R1CS indexed relation.
 pub mod ahp;
+pub use ahp::ccs::{CcsConstraintSynthesizer, ProductGates};
+use ahp::constraint_systems::{Circuit, WithGates, WithLookups, R1CS};
+pub use ahp::indexer::{ArithmetizationLayout, IndexerConfig};
+pub use ahp::lookup::{LookupConstraintSynthesizer, LookupTables};
+use ahp::prover::ProverMsg;
+pub use ahp::prover::ZkMode;
+#[cfg(feature = "std")]
+pub use ahp::spill::DiskCache;
+use ahp::spill::Spiller;
 pub use ahp::AHPForR1CS;
 use ahp::EvaluationsProvider;
 
@@ -82,7 +130,25 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
         num_non_zero: usize,
         rng: &mut R,
     ) -> Result<UniversalSRS<F, PC>, Error<PC::Error>> {
-        let max_degree = AHPForR1CS::<F>::max_degree(num_constraints, num_variables, num_non_zero)?;
+        Self::universal_setup_with_zk_bound(num_constraints, num_variables, num_non_zero, 1, rng)
+    }
+
+    /// Generate the universal prover and verifier keys for the argument
+    /// system, for indices whose proofs stay zero-knowledge for `zk_bound`
+    /// evaluation queries to each oracle, as set in `IndexerConfig::zk_bound`.
+    pub fn universal_setup_with_zk_bound<R: RngCore>(
+        num_constraints: usize,
+        num_variables: usize,
+        num_non_zero: usize,
+        zk_bound: usize,
+        rng: &mut R,
+    ) -> Result<UniversalSRS<F, PC>, Error<PC::Error>> {
+        let max_degree = AHPForR1CS::<F>::max_degree_with_zk_bound(
+            num_constraints,
+            num_variables,
+            num_non_zero,
+            zk_bound,
+        )?;
         let setup_time = start_timer!(|| {
             format!(
             "Marlin::UniversalSetup with max_degree {}, computed for a maximum of {} constraints, {} vars, {} non_zero",
@@ -100,20 +166,81 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
     pub fn index<C: ConstraintSynthesizer<F>>(
         srs: &UniversalSRS<F, PC>,
         c: C,
-    ) -> Result<(IndexProverKey<F, PC>, IndexVerifierKey<F, PC>), Error<PC::Error>> {
+    ) -> Result<IndexKeys<F, PC>, Error<PC::Error>> {
+        Self::index_with_config(srs, c, IndexerConfig::default())
+    }
+
+    /// Generate the index-specific prover and verifier keys, with the indexer
+    /// options in `config`.
+    ///
+    /// With `ArithmetizationLayout::PerMatrix`, `num_non_zero` only has to
+    /// bound the non-zero entries of the densest matrix, but the SRS must
+    /// support polynomials of degree about `3 * num_non_zero`. Such an SRS is
+    /// obtained by passing `3 * num_non_zero` to `universal_setup`.
+    pub fn index_with_config<C: ConstraintSynthesizer<F>>(
+        srs: &UniversalSRS<F, PC>,
+        c: C,
+        config: IndexerConfig,
+    ) -> Result<IndexKeys<F, PC>, Error<PC::Error>> {
+        Self::index_circuit(srs, R1CS(c), config)
+    }
+
+    /// Generate the index-specific prover and verifier keys for a circuit
+    /// that looks values up in fixed tables, with the indexer options in
+    /// `config`.
+    ///
+    /// The SRS must support polynomials of degree about three times the size
+    /// of the lookup domain, that is, the larger of the number of constraints
+    /// and the total size of the tables. The verifier gadget does not support
+    /// such indices.
+    pub fn index_with_lookups<C: LookupConstraintSynthesizer<F>>(
+        srs: &UniversalSRS<F, PC>,
+        c: C,
+        config: IndexerConfig,
+    ) -> Result<IndexKeys<F, PC>, Error<PC::Error>> {
+        Self::index_circuit(srs, WithLookups(c), config)
+    }
+
+    /// Generate the index-specific prover and verifier keys for a circuit
+    /// with product gates, with the indexer options in `config`.
+    ///
+    /// With gates of `d` factors, the SRS must support polynomials of degree
+    /// about `(d + 1)` times the size of `H`, rather than three times. Such an
+    /// SRS is obtained by passing `(d + 1) * num_constraints / 3` constraints
+    /// to `universal_setup`. The verifier gadget does not support such
+    /// indices.
+    pub fn index_with_gates<C: CcsConstraintSynthesizer<F>>(
+        srs: &UniversalSRS<F, PC>,
+        c: C,
+        config: IndexerConfig,
+    ) -> Result<IndexKeys<F, PC>, Error<PC::Error>> {
+        Self::index_circuit(srs, WithGates(c), config)
+    }
+
+    fn index_circuit<C: Circuit<F>>(
+        srs: &UniversalSRS<F, PC>,
+        c: C,
+        config: IndexerConfig,
+    ) -> Result<IndexKeys<F, PC>, Error<PC::Error>> {
         let index_time = start_timer!(|| "Marlin::Index");
 
         // TODO: Add check that c is in the correct mode.
-        let index = AHPForR1CS::index(c)?;
+        let index = AHPForR1CS::index_circuit(c, config)?;
         if srs.max_degree() < index.max_degree() {
             Err(Error::IndexTooLarge)?;
         }
 
         let coeff_support = AHPForR1CS::get_degree_bounds(&index.index_info);
-        // Marlin only needs degree 2 random polynomials
-        let supported_hiding_bound = 1;
+        // Each oracle is queried at most `zk_bound` times, but the grand
+        // product of the lookup argument is queried at three points.
+        let zk_bound = index.index_info.zk_bound;
+        let supported_hiding_bound = if index.index_info.has_lookups() {
+            zk_bound + 2
+        } else {
+            zk_bound
+        };
         let (committer_key, verifier_key) = PC::trim(
-            &srs,
+            srs,
             index.max_degree(),
             supported_hiding_bound,
             Some(&coeff_support),
@@ -153,14 +280,117 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
         c: C,
         zk_rng: &mut R,
     ) -> Result<Proof<F, PC>, Error<PC::Error>> {
+        Self::prove_circuit::<BytesEncoding, _, _>(
+            index_pk,
+            R1CS(c),
+            ZkMode::ZeroKnowledge,
+            Spiller::unbounded(),
+            zk_rng,
+        )
+    }
+
+    /// Create a zkSNARK asserting that the constraint system is satisfied, and
+    /// that the values it looks up are in their tables.
+    pub fn prove_with_lookups<C: LookupConstraintSynthesizer<F>, R: RngCore>(
+        index_pk: &IndexProverKey<F, PC>,
+        c: C,
+        zk_rng: &mut R,
+    ) -> Result<Proof<F, PC>, Error<PC::Error>> {
+        Self::prove_circuit::<BytesEncoding, _, _>(
+            index_pk,
+            WithLookups(c),
+            ZkMode::ZeroKnowledge,
+            Spiller::unbounded(),
+            zk_rng,
+        )
+    }
+
+    /// Create a zkSNARK asserting that the constraint system, including its
+    /// product gates, is satisfied.
+    pub fn prove_with_gates<C: CcsConstraintSynthesizer<F>, R: RngCore>(
+        index_pk: &IndexProverKey<F, PC>,
+        c: C,
+        zk_rng: &mut R,
+    ) -> Result<Proof<F, PC>, Error<PC::Error>> {
+        Self::prove_circuit::<BytesEncoding, _, _>(
+            index_pk,
+            WithGates(c),
+            ZkMode::ZeroKnowledge,
+            Spiller::unbounded(),
+            zk_rng,
+        )
+    }
+
+    /// Create a SNARK asserting that the constraint system is satisfied, that
+    /// is zero-knowledge only if `zk_mode` is `ZkMode::ZeroKnowledge`.
+    ///
+    /// Proofs created with `ZkMode::NonZeroKnowledge` reveal information about
+    /// the witness, but are cheaper to create, do not use `zk_rng`, and are
+    /// accepted by `Marlin::verify` like any other proof.
+    pub fn prove_with_zk_mode<C: ConstraintSynthesizer<F>, R: RngCore>(
+        index_pk: &IndexProverKey<F, PC>,
+        c: C,
+        zk_mode: ZkMode,
+        zk_rng: &mut R,
+    ) -> Result<Proof<F, PC>, Error<PC::Error>> {
+        Self::prove_circuit::<BytesEncoding, _, _>(
+            index_pk,
+            R1CS(c),
+            zk_mode,
+            Spiller::unbounded(),
+            zk_rng,
+        )
+    }
+
+    /// Create a zkSNARK asserting that the constraint system is satisfied,
+    /// caching the vectors that the prover keeps between rounds on disk.
+    ///
+    /// The prover multiplies the constraint matrices by the assignment in
+    /// chunks, and writes the vectors that do not fit in `cache` to its
+    /// directory until the round that needs them, where they are read back
+    /// into memory. This does not bound the memory of the prover: the index,
+    /// the constraint system that the prover synthesizes, the polynomials of
+    /// each round and the vectors that it reads back are in memory as with
+    /// `Marlin::prove`. The cached vectors include the witness, which is
+    /// written unencrypted; see `DiskCache`.
+    #[cfg(feature = "std")]
+    pub fn prove_with_disk_cache<C: ConstraintSynthesizer<F>, R: RngCore>(
+        index_pk: &IndexProverKey<F, PC>,
+        c: C,
+        cache: DiskCache,
+        zk_rng: &mut R,
+    ) -> Result<Proof<F, PC>, Error<PC::Error>> {
+        let spiller = Spiller::with_cache(cache);
+        Self::prove_circuit::<BytesEncoding, _, _>(
+            index_pk,
+            R1CS(c),
+            ZkMode::ZeroKnowledge,
+            spiller,
+            zk_rng,
+        )
+    }
+
+    fn prove_circuit<E, C, R>(
+        index_pk: &IndexProverKey<F, PC>,
+        c: C,
+        zk_mode: ZkMode,
+        spiller: Spiller,
+        zk_rng: &mut R,
+    ) -> Result<Proof<F, PC>, Error<PC::Error>>
+    where
+        E: TranscriptEncoding<FS, PC::Commitment>,
+        C: Circuit<F>,
+        R: RngCore,
+    {
         let prover_time = start_timer!(|| "Marlin::Prover");
         // Add check that c is in the correct mode.
 
-        let prover_init_state = AHPForR1CS::prover_init(&index_pk.index, c)?;
+        let mut prover_init_state = AHPForR1CS::prover_init_circuit(&index_pk.index, c, spiller)?;
+        prover_init_state.set_zk_mode(zk_mode);
         let public_input = prover_init_state.public_input();
-        let mut fs_rng = FS::initialize(
-            &to_bytes![&Self::PROTOCOL_NAME, &index_pk.index_vk, &public_input].unwrap(),
-        );
+        let mut transcript = Transcript::<FS>::new(Self::PROTOCOL_NAME);
+        transcript.append_message(b"index verifier key", &index_pk.index_vk);
+        transcript.append_field_elements(b"public input", &public_input);
 
         // --------------------------------------------------------------------
         // First round
@@ -172,36 +402,46 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
         let (first_comms, first_comm_rands) = PC::commit(
             &index_pk.committer_key,
             prover_first_oracles.iter(),
-            Some(zk_rng),
+            hiding_rng(zk_mode, zk_rng),
         )
         .map_err(Error::from_pc_err)?;
         end_timer!(first_round_comm_time);
 
-        fs_rng.absorb(&to_bytes![first_comms, prover_first_msg].unwrap());
+        Self::append_round::<E>(
+            &mut transcript,
+            1,
+            first_comms.iter().map(LabeledCommitment::commitment),
+            &prover_first_msg,
+        );
 
         let (verifier_first_msg, verifier_state) =
-            AHPForR1CS::verifier_first_round(index_pk.index_vk.index_info, &mut fs_rng)?;
+            AHPForR1CS::verifier_first_round(index_pk.index_vk.index_info, &mut transcript)?;
         // --------------------------------------------------------------------
 
         // --------------------------------------------------------------------
         // Second round
 
         let (prover_second_msg, prover_second_oracles, prover_state) =
-            AHPForR1CS::prover_second_round(&verifier_first_msg, prover_state, zk_rng);
+            AHPForR1CS::prover_second_round(&verifier_first_msg, prover_state, zk_rng)?;
 
         let second_round_comm_time = start_timer!(|| "Committing to second round polys");
         let (second_comms, second_comm_rands) = PC::commit(
             &index_pk.committer_key,
             prover_second_oracles.iter(),
-            Some(zk_rng),
+            hiding_rng(zk_mode, zk_rng),
         )
         .map_err(Error::from_pc_err)?;
         end_timer!(second_round_comm_time);
 
-        fs_rng.absorb(&to_bytes![second_comms, prover_second_msg].unwrap());
+        Self::append_round::<E>(
+            &mut transcript,
+            2,
+            second_comms.iter().map(LabeledCommitment::commitment),
+            &prover_second_msg,
+        );
 
         let (verifier_second_msg, verifier_state) =
-            AHPForR1CS::verifier_second_round(verifier_state, &mut fs_rng);
+            AHPForR1CS::verifier_second_round(verifier_state, &mut transcript);
         // --------------------------------------------------------------------
 
         // --------------------------------------------------------------------
@@ -213,14 +453,19 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
         let (third_comms, third_comm_rands) = PC::commit(
             &index_pk.committer_key,
             prover_third_oracles.iter(),
-            Some(zk_rng),
+            hiding_rng(zk_mode, zk_rng),
         )
         .map_err(Error::from_pc_err)?;
         end_timer!(third_round_comm_time);
 
-        fs_rng.absorb(&to_bytes![third_comms, prover_third_msg].unwrap());
+        Self::append_round::<E>(
+            &mut transcript,
+            3,
+            third_comms.iter().map(LabeledCommitment::commitment),
+            &prover_third_msg,
+        );
 
-        let verifier_state = AHPForR1CS::verifier_third_round(verifier_state, &mut fs_rng);
+        let verifier_state = AHPForR1CS::verifier_third_round(verifier_state, &mut transcript);
         // --------------------------------------------------------------------
 
         // Gather prover polynomials in one vector.
@@ -243,7 +488,9 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
             .index_vk
             .iter()
             .cloned()
-            .zip(&AHPForR1CS::<F>::INDEXER_POLYNOMIALS)
+            .zip(AHPForR1CS::<F>::indexer_polynomials(
+                &index_pk.index.index_info,
+            ))
             .map(|(c, l)| LabeledCommitment::new(l.to_string(), c, None))
             .chain(first_comms.iter().cloned())
             .chain(second_comms.iter().cloned())
@@ -262,7 +509,7 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
 
         // Compute the AHP verifier's query set.
         let (query_set, verifier_state) =
-            AHPForR1CS::verifier_query_set(verifier_state, &mut fs_rng);
+            AHPForR1CS::verifier_query_set(verifier_state, &mut transcript);
         let lc_s = AHPForR1CS::construct_linear_combinations(
             &public_input,
             &polynomials,
@@ -270,24 +517,36 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
         )?;
 
         let eval_time = start_timer!(|| "Evaluating linear combinations over query set");
-        let mut evaluations = Vec::new();
-        for (label, (_, point)) in &query_set {
-            let lc = lc_s
-                .iter()
-                .find(|lc| &lc.label == label)
-                .ok_or(ahp::Error::MissingEval(label.to_string()))?;
-            let eval = polynomials.get_lc_eval(&lc, *point)?;
-            if !AHPForR1CS::<F>::LC_WITH_ZERO_EVAL.contains(&lc.label.as_ref()) {
-                evaluations.push((label.to_string(), eval));
-            }
-        }
+        let polynomials_by_label: BTreeMap<_, _> = polynomials
+            .iter()
+            .map(|p| (p.label().as_str(), p.polynomial()))
+            .collect();
+        let evaluations = cfg_iter!(query_set)
+            .map(|(label, (_, point))| {
+                let lc = lc_s
+                    .iter()
+                    .find(|lc| &lc.label == label)
+                    .ok_or(ahp::Error::MissingEval(label.to_string()))?;
+                let eval = polynomials_by_label.get_lc_eval(lc, *point)?;
+                Ok(
+                    if AHPForR1CS::<F>::LC_WITH_ZERO_EVAL.contains(&lc.label.as_ref()) {
+                        // The prover's own polynomials must satisfy every check.
+                        debug_assert!(eval.is_zero(), "{} does not vanish", lc.label);
+                        None
+                    } else {
+                        Some((label.to_string(), eval))
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, ahp::Error>>()?;
+        let mut evaluations: Vec<_> = evaluations.into_iter().flatten().collect();
 
         evaluations.sort_by(|a, b| a.0.cmp(&b.0));
         let evaluations = evaluations.into_iter().map(|x| x.1).collect::<Vec<F>>();
         end_timer!(eval_time);
 
-        fs_rng.absorb(&evaluations);
-        let opening_challenge: F = u128::rand(&mut fs_rng).into();
+        transcript.append_field_elements(b"evaluations", &evaluations);
+        let opening_challenge: F = E::opening_challenge(&mut transcript, b"opening challenge");
 
         let pc_proof = PC::open_combinations(
             &index_pk.committer_key,
@@ -297,7 +556,7 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
             &query_set,
             opening_challenge,
             &comm_rands,
-            Some(zk_rng),
+            hiding_rng(zk_mode, zk_rng),
         )
         .map_err(Error::from_pc_err)?;
 
@@ -317,48 +576,101 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
         public_input: &[F],
         proof: &Proof<F, PC>,
         rng: &mut R,
+    ) -> Result<bool, Error<PC::Error>> {
+        Self::verify_with_encoding::<BytesEncoding, _>(index_vk, public_input, proof, rng)
+    }
+
+    fn verify_with_encoding<E: TranscriptEncoding<FS, PC::Commitment>, R: RngCore>(
+        index_vk: &IndexVerifierKey<F, PC>,
+        public_input: &[F],
+        proof: &Proof<F, PC>,
+        rng: &mut R,
     ) -> Result<bool, Error<PC::Error>> {
         let verifier_time = start_timer!(|| "Marlin::Verify");
 
-        let public_input = {
-            let domain_x = GeneralEvaluationDomain::<F>::new(public_input.len() + 1).unwrap();
+        let PCCheck {
+            lc_s,
+            commitments,
+            query_set,
+            evaluations,
+            opening_challenge,
+        } = Self::replay_transcript::<E>(index_vk, public_input, proof)?;
 
-            let mut unpadded_input = public_input.to_vec();
-            unpadded_input.resize(
-                core::cmp::max(public_input.len(), domain_x.size() - 1),
-                F::zero(),
-            );
+        let evaluations_are_correct = PC::check_combinations(
+            &index_vk.verifier_key,
+            &lc_s,
+            &commitments,
+            &query_set,
+            &evaluations,
+            &proof.pc_proof,
+            opening_challenge,
+            rng,
+        )
+        .map_err(Error::from_pc_err)?;
 
-            unpadded_input
-        };
+        if !evaluations_are_correct {
+            eprintln!("PC::Check failed");
+        }
+        end_timer!(verifier_time, || format!(
+            " PC::Check for AHP Verifier linear equations: {}",
+            evaluations_are_correct
+        ));
+        Ok(evaluations_are_correct)
+    }
+
+    /// Verify `proof` as `Marlin::verify` does, but first reject it if it
+    /// carries the digest of a verifier key other than `index_vk` under `D`.
+    /// Proofs without a digest are verified as usual.
+    pub fn verify_with_vk_digest<D: Digest, R: RngCore>(
+        index_vk: &IndexVerifierKey<F, PC>,
+        public_input: &[F],
+        proof: &Proof<F, PC>,
+        rng: &mut R,
+    ) -> Result<bool, Error<PC::Error>> {
+        if let Some(vk_digest) = &proof.vk_digest {
+            if *vk_digest != index_vk.digest::<D>() {
+                return Ok(false);
+            }
+        }
+        Self::verify(index_vk, public_input, proof, rng)
+    }
 
-        let mut fs_rng =
-            FS::initialize(&to_bytes![&Self::PROTOCOL_NAME, &index_vk, &public_input].unwrap());
+    /// Replay the Fiat-Shamir transcript of `proof` and gather everything that
+    /// the polynomial commitment scheme needs to check the prover's evaluations.
+    fn replay_transcript<E: TranscriptEncoding<FS, PC::Commitment>>(
+        index_vk: &IndexVerifierKey<F, PC>,
+        public_input: &[F],
+        proof: &Proof<F, PC>,
+    ) -> Result<PCCheck<F, PC>, Error<PC::Error>> {
+        let mut transcript = Transcript::<FS>::new(Self::PROTOCOL_NAME);
+        transcript.append_message(b"index verifier key", index_vk);
+        transcript.append_field_elements(b"public input", public_input);
 
         // --------------------------------------------------------------------
         // First round
 
         let first_comms = &proof.commitments[0];
-        fs_rng.absorb(&to_bytes![first_comms, proof.prover_messages[0]].unwrap());
+        Self::append_round::<E>(&mut transcript, 1, first_comms, &proof.prover_messages[0]);
 
         let (_, verifier_state) =
-            AHPForR1CS::verifier_first_round(index_vk.index_info, &mut fs_rng)?;
+            AHPForR1CS::verifier_first_round(index_vk.index_info, &mut transcript)?;
         // --------------------------------------------------------------------
 
         // --------------------------------------------------------------------
         // Second round
         let second_comms = &proof.commitments[1];
-        fs_rng.absorb(&to_bytes![second_comms, proof.prover_messages[1]].unwrap());
+        Self::append_round::<E>(&mut transcript, 2, second_comms, &proof.prover_messages[1]);
 
-        let (_, verifier_state) = AHPForR1CS::verifier_second_round(verifier_state, &mut fs_rng);
+        let (_, verifier_state) =
+            AHPForR1CS::verifier_second_round(verifier_state, &mut transcript);
         // --------------------------------------------------------------------
 
         // --------------------------------------------------------------------
         // Third round
         let third_comms = &proof.commitments[2];
-        fs_rng.absorb(&to_bytes![third_comms, proof.prover_messages[2]].unwrap());
+        Self::append_round::<E>(&mut transcript, 3, third_comms, &proof.prover_messages[2]);
 
-        let verifier_state = AHPForR1CS::verifier_third_round(verifier_state, &mut fs_rng);
+        let verifier_state = AHPForR1CS::verifier_third_round(verifier_state, &mut transcript);
         // --------------------------------------------------------------------
 
         // Collect degree bounds for commitments. Indexed polynomials have *no*
@@ -379,16 +691,16 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
             .chain(second_comms)
             .chain(third_comms)
             .cloned()
-            .zip(AHPForR1CS::<F>::polynomial_labels())
+            .zip(AHPForR1CS::<F>::polynomial_labels(&index_info))
             .zip(degree_bounds)
             .map(|((c, l), d)| LabeledCommitment::new(l, c, d))
             .collect();
 
         let (query_set, verifier_state) =
-            AHPForR1CS::verifier_query_set(verifier_state, &mut fs_rng);
+            AHPForR1CS::verifier_query_set(verifier_state, &mut transcript);
 
-        fs_rng.absorb(&proof.evaluations);
-        let opening_challenge: F = u128::rand(&mut fs_rng).into();
+        transcript.append_field_elements(b"evaluations", &proof.evaluations);
+        let opening_challenge: F = E::opening_challenge(&mut transcript, b"opening challenge");
 
         let mut evaluations = Evaluations::new();
         let mut evaluation_labels = Vec::new();
@@ -404,31 +716,91 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, FS: FiatSha
             evaluations.insert(q, *eval);
         }
 
-        let lc_s = AHPForR1CS::construct_linear_combinations(
-            &public_input,
-            &evaluations,
-            &verifier_state,
-        )?;
+        let lc_s =
+            AHPForR1CS::construct_linear_combinations(public_input, &evaluations, &verifier_state)?;
 
-        let evaluations_are_correct = PC::check_combinations(
-            &index_vk.verifier_key,
-            &lc_s,
-            &commitments,
-            &query_set,
-            &evaluations,
-            &proof.pc_proof,
+        Ok(PCCheck {
+            lc_s,
+            commitments,
+            query_set,
+            evaluations,
             opening_challenge,
-            rng,
+        })
+    }
+
+    /// Append the commitments and the message sent by the prover in the given
+    /// round to the transcript.
+    fn append_round<'a, E: TranscriptEncoding<FS, PC::Commitment>>(
+        transcript: &mut Transcript<FS>,
+        round: usize,
+        comms: impl IntoIterator<Item = &'a PC::Commitment>,
+        prover_msg: &ProverMsg<F>,
+    ) where
+        PC::Commitment: 'a,
+    {
+        let comms: Vec<_> = comms.into_iter().collect();
+        let label = format!("round-{} commitments", round);
+        E::append_commitments(transcript, label.as_bytes(), &comms);
+        let elems = match prover_msg {
+            ProverMsg::EmptyMessage => &[][..],
+            ProverMsg::FieldElements(elems) => &elems[..],
+        };
+        transcript.append_field_elements(format!("prover msg {}", round).as_bytes(), elems);
+    }
+}
+
+impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>>
+    Marlin<F, PC, KeccakFiatShamirRng>
+where
+    PC::Commitment: PointCommitment,
+{
+    /// Create a SNARK asserting that the constraint system is satisfied, as
+    /// `Marlin::prove_with_zk_mode` does, but absorb the commitments of each
+    /// round as the ABI encoding of their points rather than as their
+    /// `ToBytes` encoding.
+    ///
+    /// These are the proofs that the generated Solidity verifier checks.
+    pub fn prove_with_abi_commitments<C: ConstraintSynthesizer<F>, R: RngCore>(
+        index_pk: &IndexProverKey<F, PC>,
+        c: C,
+        zk_mode: ZkMode,
+        zk_rng: &mut R,
+    ) -> Result<Proof<F, PC>, Error<PC::Error>> {
+        Self::prove_circuit::<AbiEncoding, _, _>(
+            index_pk,
+            R1CS(c),
+            zk_mode,
+            Spiller::unbounded(),
+            zk_rng,
         )
-        .map_err(Error::from_pc_err)?;
+    }
 
-        if !evaluations_are_correct {
-            eprintln!("PC::Check failed");
-        }
-        end_timer!(verifier_time, || format!(
-            " PC::Check for AHP Verifier linear equations: {}",
-            evaluations_are_correct
-        ));
-        Ok(evaluations_are_correct)
+    /// Verify a proof made with `Marlin::prove_with_abi_commitments`.
+    pub fn verify_with_abi_commitments<R: RngCore>(
+        index_vk: &IndexVerifierKey<F, PC>,
+        public_input: &[F],
+        proof: &Proof<F, PC>,
+        rng: &mut R,
+    ) -> Result<bool, Error<PC::Error>> {
+        Self::verify_with_encoding::<AbiEncoding, _>(index_vk, public_input, proof, rng)
+    }
+}
+
+/// The inputs to `PC::check_combinations` that the verifier derives from a
+/// proof once its Fiat-Shamir transcript has been replayed.
+struct PCCheck<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>> {
+    lc_s: Vec<LinearCombination<F>>,
+    commitments: Vec<LabeledCommitment<PC::Commitment>>,
+    query_set: QuerySet<F>,
+    evaluations: Evaluations<F, F>,
+    opening_challenge: F,
+}
+
+/// The randomness used by the commitment scheme, which does not hide the
+/// committed polynomials outside of zero-knowledge mode.
+fn hiding_rng<R: RngCore>(zk_mode: ZkMode, rng: &mut R) -> Option<&mut dyn RngCore> {
+    match zk_mode {
+        ZkMode::ZeroKnowledge => Some(rng),
+        ZkMode::NonZeroKnowledge => None,
     }
 }
@@ -0,0 +1,245 @@
+use crate::rng::{FiatShamirRng, PointCommitment};
+use crate::Vec;
+use ark_ff::{to_bytes, BigInteger, PrimeField, ToBytes};
+use ark_std::rand::{Error, RngCore};
+use digest::Digest;
+use sha3::Keccak256;
+
+/// The Keccak-256 hash of `bytes`, as computed by the `keccak256` builtin of
+/// the EVM. It differs from SHA3-256 in its padding.
+pub(crate) fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+/// A `FiatShamirRng` backed by Keccak-256, which a verifier on the EVM can
+/// replay with the `keccak256` builtin.
+///
+/// Like `SimpleHashFiatShamirRng`, it absorbs new inputs by setting
+/// `seed = keccak256(new_input || seed)`. Field elements are absorbed in the
+/// encoding of `abi.encodePacked` on `uint256` words, as 32-byte big-endian
+/// integers, and so are the coordinates of the points of commitments passed
+/// to `absorb_point_commitments`.
+///
+/// Its output is the stream of blocks `keccak256(seed || counter)`, for a
+/// 64-bit big-endian counter that starts at zero after each absorption.
+/// Each challenge is a block read as a big-endian integer and reduced modulo
+/// the field, so it spans the whole field, while each 128-bit challenge is
+/// the high 128 bits of a block.
+pub struct KeccakFiatShamirRng {
+    seed: [u8; 32],
+    counter: u64,
+}
+
+impl KeccakFiatShamirRng {
+    /// The current seed.
+    pub fn seed(&self) -> [u8; 32] {
+        self.seed
+    }
+
+    /// Absorb `prefix`, the size of the ABI encoding of the coordinates of
+    /// the points of `comms`, and that encoding.
+    pub fn absorb_point_commitments<'a, C: 'a + PointCommitment>(
+        &mut self,
+        prefix: &[u8],
+        comms: impl IntoIterator<Item = &'a C>,
+    ) {
+        let elems: Vec<_> = comms
+            .into_iter()
+            .flat_map(|comm| comm.to_base_field_elements())
+            .collect();
+        let message = abi_encode(&elems);
+        self.absorb(&to_bytes![prefix, message.len() as u64, message].unwrap());
+    }
+
+    /// The next block of the output stream.
+    fn next_block(&mut self) -> [u8; 32] {
+        let mut bytes = [0u8; 40];
+        bytes[..32].copy_from_slice(&self.seed);
+        bytes[32..].copy_from_slice(&self.counter.to_be_bytes());
+        self.counter += 1;
+        keccak256(&bytes)
+    }
+}
+
+impl RngCore for KeccakFiatShamirRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_be_bytes(bytes)
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(32) {
+            chunk.copy_from_slice(&self.next_block()[..chunk.len()]);
+        }
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl FiatShamirRng for KeccakFiatShamirRng {
+    /// Create a new `Self` with `self.seed = keccak256(initial_input)`.
+    #[inline]
+    fn initialize<'a, T: 'a + ToBytes>(initial_input: &'a T) -> Self {
+        let mut bytes = Vec::new();
+        initial_input
+            .write(&mut bytes)
+            .expect("failed to convert to bytes");
+        Self {
+            seed: keccak256(&bytes),
+            counter: 0,
+        }
+    }
+
+    /// Refresh `self.seed` with new material, by setting
+    /// `self.seed = keccak256(new_input || self.seed)`.
+    #[inline]
+    fn absorb<'a, T: 'a + ToBytes>(&mut self, new_input: &'a T) {
+        let mut bytes = Vec::new();
+        new_input
+            .write(&mut bytes)
+            .expect("failed to convert to bytes");
+        bytes.extend_from_slice(&self.seed);
+        self.seed = keccak256(&bytes);
+        self.counter = 0;
+    }
+
+    /// Absorb the ABI encoding of `elems`.
+    fn absorb_field_elements<F: PrimeField>(&mut self, elems: &[F]) {
+        self.absorb(&abi_encode(elems));
+    }
+
+    /// Squeeze each element as the high 128 bits of the next block of the
+    /// output stream, read as a big-endian integer.
+    fn squeeze_128_bits_field_elements<F: PrimeField>(&mut self, num: usize) -> Vec<F> {
+        (0..num)
+            .map(|_| {
+                let mut high = [0u8; 16];
+                high.copy_from_slice(&self.next_block()[..16]);
+                u128::from_be_bytes(high).into()
+            })
+            .collect()
+    }
+
+    /// Squeeze each challenge as the next block of the output stream, read as
+    /// a big-endian integer and reduced modulo the field.
+    fn squeeze_challenges<F: PrimeField>(&mut self, num: usize) -> Vec<F> {
+        (0..num)
+            .map(|_| F::from_be_bytes_mod_order(&self.next_block()))
+            .collect()
+    }
+}
+
+/// The encoding of `elems` by `abi.encodePacked` on `uint256` words: each
+/// element as a big-endian integer, padded to a multiple of 32 bytes.
+pub(crate) fn abi_encode<F: PrimeField>(elems: &[F]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for elem in elems {
+        let bytes = elem.into_repr().to_bytes_be();
+        let padding = (32 - bytes.len() % 32) % 32;
+        encoded.resize(encoded.len() + padding, 0);
+        encoded.extend(bytes);
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Bn254, Fr, G1Affine};
+    use ark_ec::AffineCurve;
+    use ark_poly_commit::{kzg10, marlin_pc, PCCommitment};
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn keccak256_known_answers() {
+        assert_eq!(
+            hex(&keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex(&keccak256(b"abc")),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+        assert_eq!(
+            hex(&keccak256(b"transfer(address,uint256)")[..4]),
+            "a9059cbb"
+        );
+        // Inputs whose padding fits in a single byte, and that span several
+        // blocks of 136 bytes.
+        assert_eq!(
+            hex(&keccak256(&[0x61; 135])),
+            "34367dc248bbd832f4e3e69dfaac2f92638bd0bbd18f2912ba4ef454919cf446"
+        );
+        assert_eq!(
+            hex(&keccak256(&[0x61; 272])),
+            "cf7fcd4f705ee749930d19ca84561a9bf62516bd90a471545fa2f49fdc7e63c8"
+        );
+    }
+
+    #[test]
+    fn keccak_fs_rng_known_answers() {
+        let challenge_hex = |elem: Fr| format!("{:0>64}", elem.into_repr()).to_lowercase();
+
+        // Field elements are absorbed as big-endian words, and challenges
+        // are blocks reduced modulo the field.
+        let mut fs_rng = KeccakFiatShamirRng::initialize(&b"marlin".to_vec());
+        fs_rng.absorb_field_elements(&[Fr::from(1u64), Fr::from(2u64)]);
+        let challenges: Vec<Fr> = fs_rng.squeeze_challenges(2);
+        assert_eq!(
+            challenge_hex(challenges[0]),
+            "0e424dd89320e2954f033332ebecf9bd1ee794b7012e7bc63dbb39bbc9311eb5"
+        );
+        assert_eq!(
+            challenge_hex(challenges[1]),
+            "2ed0fd0b044e7f004a146e23a37b559cd5d5cc09c955d08587adb0294d78c760"
+        );
+
+        // Commitments are absorbed as the coordinates of their points, with
+        // the point at infinity as `(0, 0)`.
+        let g = kzg10::Commitment::<Bn254>(G1Affine::prime_subgroup_generator());
+        let comms = [
+            marlin_pc::Commitment {
+                comm: g,
+                shifted_comm: Some(g),
+            },
+            marlin_pc::Commitment {
+                comm: kzg10::Commitment::empty(),
+                shifted_comm: None,
+            },
+        ];
+        fs_rng.absorb_point_commitments(b"comms", &comms);
+        let challenge: Vec<Fr> = fs_rng.squeeze_challenges(1);
+        assert_eq!(
+            challenge_hex(challenge[0]),
+            "02c4ed069ff6ef84f54c57c31a9f11c264d7c886acc5fa207ba83b725150a6a1"
+        );
+
+        // Short challenges are the high 128 bits of the blocks.
+        let mut fs_rng = KeccakFiatShamirRng::initialize(&b"marlin".to_vec());
+        fs_rng.absorb_field_elements(&[Fr::from(1u64), Fr::from(2u64)]);
+        let challenges: Vec<Fr> = fs_rng.squeeze_128_bits_field_elements(2);
+        assert_eq!(
+            challenge_hex(challenges[0]),
+            "000000000000000000000000000000003ea69c4b745282bf075378e96d6e521a"
+        );
+        assert_eq!(
+            challenge_hex(challenges[1]),
+            "000000000000000000000000000000002ed0fd0b044e7f004a146e23a37b559c"
+        );
+    }
+}
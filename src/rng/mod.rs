@@ -0,0 +1,225 @@
+use crate::Vec;
+use ark_ec::PairingEngine;
+use ark_ff::{to_bytes, FromBytes, PrimeField, ToBytes, ToConstraintField, UniformRand, Zero};
+use ark_poly_commit::{kzg10, marlin_pc, LabeledCommitment, PCCommitment};
+use ark_std::convert::From;
+use ark_std::marker::PhantomData;
+use ark_std::rand::{Error, RngCore, SeedableRng};
+use digest::Digest;
+
+mod keccak;
+pub use keccak::*;
+
+mod poseidon;
+pub use poseidon::*;
+
+mod transcript;
+pub use transcript::*;
+
+/// An RNG suitable for Fiat-Shamir transforms
+pub trait FiatShamirRng: RngCore {
+    /// Create a new `Self` with an initial input
+    fn initialize<'a, T: 'a + ToBytes>(initial_input: &'a T) -> Self;
+    /// Absorb new inputs into state
+    fn absorb<'a, T: 'a + ToBytes>(&mut self, new_input: &'a T);
+
+    /// Absorb field elements into state. By default, they are absorbed as
+    /// their `ToBytes` encoding.
+    fn absorb_field_elements<F: PrimeField>(&mut self, elems: &[F]) {
+        self.absorb(&to_bytes![elems].unwrap());
+    }
+
+    /// Squeeze `num` field elements whose canonical representations are
+    /// 128 bits long. By default, each is sampled as `u128::rand(self)`.
+    fn squeeze_128_bits_field_elements<F: PrimeField>(&mut self, num: usize) -> Vec<F> {
+        (0..num).map(|_| u128::rand(self).into()).collect()
+    }
+
+    /// Squeeze `num` challenges for the rounds of the AHP verifier. By
+    /// default, each is a uniformly random field element, sampled as
+    /// `F::rand(self)`.
+    fn squeeze_challenges<F: PrimeField>(&mut self, num: usize) -> Vec<F> {
+        (0..num).map(|_| F::rand(self)).collect()
+    }
+}
+
+/// A commitment that consists of points of an elliptic curve, which
+/// `KeccakFiatShamirRng` can absorb as the coordinates of these points rather
+/// than as its `ToBytes` encoding.
+pub trait PointCommitment: ToBytes {
+    /// The base field of the curve.
+    type BaseField: PrimeField;
+
+    /// The affine coordinates of the points of the commitment, with the point
+    /// at infinity as `(0, 0)`. An optional point is preceded by a flag that
+    /// is one if it is present, and is the point at infinity otherwise.
+    fn to_base_field_elements(&self) -> Vec<Self::BaseField>;
+}
+
+impl<E: PairingEngine> PointCommitment for kzg10::Commitment<E>
+where
+    E::G1Affine: ToConstraintField<E::Fq>,
+{
+    type BaseField = E::Fq;
+
+    fn to_base_field_elements(&self) -> Vec<E::Fq> {
+        if self.0.is_zero() {
+            return vec![E::Fq::zero(); 2];
+        }
+        let mut coordinates = self.0.to_field_elements().unwrap();
+        coordinates.truncate(2);
+        coordinates
+    }
+}
+
+impl<E: PairingEngine> PointCommitment for marlin_pc::Commitment<E>
+where
+    E::G1Affine: ToConstraintField<E::Fq>,
+{
+    type BaseField = E::Fq;
+
+    fn to_base_field_elements(&self) -> Vec<E::Fq> {
+        let mut elems = self.comm.to_base_field_elements();
+        let (flag, shifted_comm) = match &self.shifted_comm {
+            Some(shifted_comm) => (1u64, *shifted_comm),
+            None => (0, kzg10::Commitment::empty()),
+        };
+        elems.push(flag.into());
+        elems.extend(shifted_comm.to_base_field_elements());
+        elems
+    }
+}
+
+impl<C: PCCommitment + PointCommitment> PointCommitment for LabeledCommitment<C> {
+    type BaseField = C::BaseField;
+
+    fn to_base_field_elements(&self) -> Vec<C::BaseField> {
+        self.commitment().to_base_field_elements()
+    }
+}
+
+/// A simple `FiatShamirRng` that refreshes its seed by hashing together the previous seed
+/// and the new seed material.
+pub struct SimpleHashFiatShamirRng<D: Digest, R: RngCore + SeedableRng> {
+    r: R,
+    seed: [u8; 32],
+    #[doc(hidden)]
+    digest: PhantomData<D>,
+}
+
+impl<D: Digest, R: RngCore + SeedableRng> RngCore for SimpleHashFiatShamirRng<D, R> {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.r.next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.r.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.r.fill_bytes(dest);
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        Ok(self.r.fill_bytes(dest))
+    }
+}
+
+impl<D: Digest, R: RngCore + SeedableRng> FiatShamirRng for SimpleHashFiatShamirRng<D, R>
+where
+    R::Seed: From<[u8; 32]>,
+{
+    /// Create a new `Self` by initializing with a fresh seed.
+    /// `self.seed = H(initial_input)`.
+    #[inline]
+    fn initialize<'a, T: 'a + ToBytes>(initial_input: &'a T) -> Self {
+        let mut bytes = Vec::new();
+        initial_input
+            .write(&mut bytes)
+            .expect("failed to convert to bytes");
+        let seed = FromBytes::read(D::digest(&bytes).as_ref()).expect("failed to get [u8; 32]");
+        let r = R::from_seed(<R::Seed>::from(seed));
+        Self {
+            r,
+            seed: seed,
+            digest: PhantomData,
+        }
+    }
+
+    /// Refresh `self.seed` with new material. Achieved by setting
+    /// `self.seed = H(new_input || self.seed)`.
+    #[inline]
+    fn absorb<'a, T: 'a + ToBytes>(&mut self, new_input: &'a T) {
+        let mut bytes = Vec::new();
+        new_input
+            .write(&mut bytes)
+            .expect("failed to convert to bytes");
+        bytes.extend_from_slice(&self.seed);
+        self.seed = FromBytes::read(D::digest(&bytes).as_ref()).expect("failed to get [u8; 32]");
+        self.r = R::from_seed(<R::Seed>::from(self.seed));
+    }
+}
+
+/// A `FiatShamirRng` that behaves as `FS`, except that the challenges of the
+/// AHP verifier are squeezed as 128-bit field elements, as the opening
+/// challenge always is.
+///
+/// The verifier gadget in `constraints` can only derive challenges of 128
+/// bits, so proofs that are to be verified recursively must be made with this
+/// rng. Its transcripts are not those of `FS`: a proof made with one does not
+/// verify with the other.
+pub struct ShortChallengeFiatShamirRng<FS: FiatShamirRng>(FS);
+
+impl<FS: FiatShamirRng> RngCore for ShortChallengeFiatShamirRng<FS> {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest);
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+impl<FS: FiatShamirRng> FiatShamirRng for ShortChallengeFiatShamirRng<FS> {
+    #[inline]
+    fn initialize<'a, T: 'a + ToBytes>(initial_input: &'a T) -> Self {
+        Self(FS::initialize(initial_input))
+    }
+
+    #[inline]
+    fn absorb<'a, T: 'a + ToBytes>(&mut self, new_input: &'a T) {
+        self.0.absorb(new_input);
+    }
+
+    #[inline]
+    fn absorb_field_elements<F: PrimeField>(&mut self, elems: &[F]) {
+        self.0.absorb_field_elements(elems);
+    }
+
+    #[inline]
+    fn squeeze_128_bits_field_elements<F: PrimeField>(&mut self, num: usize) -> Vec<F> {
+        self.0.squeeze_128_bits_field_elements(num)
+    }
+
+    /// Squeeze `num` challenges with `FS::squeeze_128_bits_field_elements`.
+    #[inline]
+    fn squeeze_challenges<F: PrimeField>(&mut self, num: usize) -> Vec<F> {
+        self.0.squeeze_128_bits_field_elements(num)
+    }
+}
@@ -0,0 +1,528 @@
+use crate::rng::FiatShamirRng;
+use crate::Vec;
+use ark_ff::{BigInteger, BitIteratorBE, FpParameters, PrimeField, ToBytes};
+use ark_sponge::poseidon::{PoseidonParameters, PoseidonSponge};
+use ark_sponge::{Absorb, CryptographicSponge, FieldBasedCryptographicSponge};
+use ark_std::rand::{Error, RngCore};
+
+/// The number of elements in the state of the Poseidon permutation.
+const STATE_SIZE: usize = 3;
+
+/// The security level, in bits, that the round numbers are chosen for.
+const SECURITY_LEVEL: u32 = 128;
+
+/// The admissible S-box exponents, in order of preference.
+const ALPHAS: [u64; 6] = [3, 5, 7, 11, 13, 17];
+
+/// A `FiatShamirRng` backed by a Poseidon sponge over the field `CF`.
+///
+/// Field elements are absorbed natively: elements of `CF` itself are absorbed
+/// as they are, and elements of any other prime field are absorbed by packing
+/// their bits into elements of `CF`. Byte inputs, such as commitments, are
+/// prefixed with their length and packed into elements of `CF` in the same
+/// way. A recursive verifier whose constraint field is `CF` can thus replay
+/// the transcript at the cost of a few Poseidon permutations per round.
+pub struct PoseidonFiatShamirRng<CF: PrimeField + Absorb> {
+    sponge: PoseidonSponge<CF>,
+}
+
+impl<CF: PrimeField + Absorb> PoseidonFiatShamirRng<CF> {
+    /// Squeeze a single element of `CF` and output its little-endian bits.
+    fn squeeze_bits_le(&mut self) -> Vec<bool> {
+        self.sponge.squeeze_native_field_elements(1)[0]
+            .into_repr()
+            .to_bits_le()
+    }
+}
+
+impl<CF: PrimeField + Absorb> RngCore for PoseidonFiatShamirRng<CF> {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        bits_le_to_u128(&self.squeeze_bits_le()[..64]) as u64
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let bytes_per_elem = CF::Params::CAPACITY as usize / 8;
+        for chunk in dest.chunks_mut(bytes_per_elem) {
+            let elem = self.sponge.squeeze_native_field_elements(1)[0];
+            chunk.copy_from_slice(&elem.into_repr().to_bytes_le()[..chunk.len()]);
+        }
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<CF: PrimeField + Absorb> FiatShamirRng for PoseidonFiatShamirRng<CF> {
+    /// Create a new `Self` by absorbing `initial_input` into a fresh sponge.
+    #[inline]
+    fn initialize<'a, T: 'a + ToBytes>(initial_input: &'a T) -> Self {
+        let mut rng = Self {
+            sponge: PoseidonSponge::new(&poseidon_parameters()),
+        };
+        rng.absorb(initial_input);
+        rng
+    }
+
+    /// Absorb the length of the byte encoding of `new_input`, followed by
+    /// the encoding itself packed into elements of `CF`.
+    #[inline]
+    fn absorb<'a, T: 'a + ToBytes>(&mut self, new_input: &'a T) {
+        let mut bytes = Vec::new();
+        new_input
+            .write(&mut bytes)
+            .expect("failed to convert to bytes");
+
+        let bytes_per_elem = CF::Params::CAPACITY as usize / 8;
+        let mut elems = Vec::with_capacity(1 + bytes.len() / bytes_per_elem);
+        elems.push(CF::from(bytes.len() as u64));
+        elems.extend(
+            bytes
+                .chunks(bytes_per_elem)
+                .map(CF::from_le_bytes_mod_order),
+        );
+        self.sponge.absorb(&elems);
+    }
+
+    #[inline]
+    fn absorb_field_elements<F: PrimeField>(&mut self, elems: &[F]) {
+        let elems = if F::characteristic() == CF::characteristic() {
+            elems
+                .iter()
+                .map(|elem| CF::from_le_bytes_mod_order(&elem.into_repr().to_bytes_le()))
+                .collect()
+        } else {
+            let bits = elems
+                .iter()
+                .flat_map(|elem| {
+                    let mut bits = elem.into_repr().to_bits_le();
+                    bits.truncate(F::size_in_bits());
+                    bits
+                })
+                .collect::<Vec<_>>();
+            bits.chunks(CF::Params::CAPACITY as usize)
+                .map(|chunk| CF::from_repr(CF::BigInt::from_bits_le(chunk)).unwrap())
+                .collect::<Vec<_>>()
+        };
+        self.sponge.absorb(&elems);
+    }
+
+    /// Squeeze `num` elements of `CF`, and keep the 128 least significant
+    /// bits of each.
+    #[inline]
+    fn squeeze_128_bits_field_elements<F: PrimeField>(&mut self, num: usize) -> Vec<F> {
+        (0..num)
+            .map(|_| bits_le_to_u128(&self.squeeze_bits_le()[..128]).into())
+            .collect()
+    }
+}
+
+fn bits_le_to_u128(bits: &[bool]) -> u128 {
+    bits.iter()
+        .rev()
+        .fold(0u128, |acc, bit| (acc << 1) | u128::from(*bit))
+}
+
+/// The parameters of the Poseidon sponge over `F` that `PoseidonFiatShamirRng`
+/// uses: a state of three elements, of which two make up the rate, and the
+/// smallest S-box exponent in `ALPHAS` that is a permutation of `F`. The round
+/// numbers are those that the Poseidon reference scripts give for 128-bit
+/// security (see `round_numbers`), and the round constants and the (Cauchy)
+/// MDS matrix are generated with the Grain LFSR as in the reference script
+/// `generate_parameters_grain.sage`.
+///
+/// # Panics
+///
+/// Panics if `F` has fewer than `SECURITY_LEVEL` bits, or if none of the
+/// exponents in `ALPHAS` is coprime to `|F| - 1`.
+pub fn poseidon_parameters<F: PrimeField>() -> PoseidonParameters<F> {
+    assert!(
+        F::size_in_bits() >= SECURITY_LEVEL as usize,
+        "field too small for Poseidon"
+    );
+    let alpha = ALPHAS
+        .iter()
+        .copied()
+        .find(|alpha| modulus_minus_one_rem::<F>(*alpha) != 0)
+        .expect("no admissible Poseidon S-box for this field");
+    let (full_rounds, partial_rounds) = round_numbers(alpha);
+    let (ark, mds) = grain_constants(full_rounds, partial_rounds);
+    PoseidonParameters::new(full_rounds, partial_rounds, alpha, mds, ark)
+}
+
+/// The number of full and partial rounds that `calc_round_numbers.py`, the
+/// round number script of the Poseidon reference implementation, outputs for
+/// the S-box `x^alpha`, a state of three elements and a field of at least
+/// `SECURITY_LEVEL` bits, including its security margin of two full rounds and
+/// 7.5% more partial rounds.
+///
+/// For such fields, only the statistical bound (six full rounds) and the
+/// interpolation bound `R_F + R_P >= 1 + ceil(M log_alpha(2)) +
+/// ceil(log_alpha(t))` are tight, so the cheapest round numbers follow from
+/// these two in integer arithmetic.
+fn round_numbers(alpha: u64) -> (u32, u32) {
+    const STATISTICAL_FULL_ROUNDS: u32 = 6;
+
+    // The smallest `k` such that `alpha^k >= bound`, where `None` stands for
+    // `2^SECURITY_LEVEL`.
+    let ceil_log = |bound: Option<u128>| {
+        let mut power = 1u128;
+        let mut k = 0;
+        loop {
+            match (power.checked_mul(alpha as u128), bound) {
+                (None, _) => return k + 1,
+                (Some(next), Some(bound)) if next >= bound => return k + 1,
+                (Some(next), _) => power = next,
+            }
+            k += 1;
+        }
+    };
+    let interpolation_rounds = 1 + ceil_log(None) + ceil_log(Some(STATE_SIZE as u128));
+    let partial_rounds = interpolation_rounds - STATISTICAL_FULL_ROUNDS;
+
+    // The security margin: two more full rounds, and 7.5% more partial rounds
+    // rounded up.
+    let scaled = 43 * partial_rounds;
+    let partial_rounds = scaled / 40 + u32::from(scaled > scaled / 40 * 40);
+    (STATISTICAL_FULL_ROUNDS + 2, partial_rounds)
+}
+
+/// Generate the round constants and the MDS matrix of the Poseidon
+/// permutation with the given round numbers and an S-box `x^alpha`, as
+/// `generate_parameters_grain.sage` does: the round constants are sampled
+/// first, and the Cauchy MDS matrix is then resampled until it has no
+/// invariant subspace (see `is_secure_mds`).
+#[allow(clippy::type_complexity)]
+fn grain_constants<F: PrimeField>(
+    full_rounds: u32,
+    partial_rounds: u32,
+) -> (Vec<Vec<F>>, Vec<Vec<F>>) {
+    let mut lfsr = GrainLfsr::new(
+        F::size_in_bits() as u64,
+        STATE_SIZE as u64,
+        full_rounds as u64,
+        partial_rounds as u64,
+    );
+
+    let ark = (0..full_rounds + partial_rounds)
+        .map(|_| {
+            (0..STATE_SIZE)
+                .map(|_| lfsr.next_field_element_rejection_sampling())
+                .collect()
+        })
+        .collect();
+
+    let mds = loop {
+        let mds = lfsr.next_cauchy_matrix();
+        if is_secure_mds(&mds) {
+            break mds;
+        }
+    };
+    (ark, mds.iter().map(|row| row.to_vec()).collect())
+}
+
+type Matrix<F> = [[F; STATE_SIZE]; STATE_SIZE];
+
+/// Whether the powers `M, M^2, ..., M^{2t}` of `mds` all have an irreducible
+/// characteristic polynomial. Such powers have no invariant subspace, which
+/// rules out the infinitely long subspace trails that the three checks of
+/// `generate_parameters_grain.sage` look for.
+fn is_secure_mds<F: PrimeField>(mds: &Matrix<F>) -> bool {
+    let mut power = *mds;
+    for _ in 0..2 * STATE_SIZE {
+        if !is_irreducible_cubic(&char_poly(&power)) {
+            return false;
+        }
+        power = mat_mul(&power, mds);
+    }
+    true
+}
+
+fn mat_mul<F: PrimeField>(a: &Matrix<F>, b: &Matrix<F>) -> Matrix<F> {
+    let mut c = [[F::zero(); STATE_SIZE]; STATE_SIZE];
+    for (i, row) in c.iter_mut().enumerate() {
+        for (j, entry) in row.iter_mut().enumerate() {
+            *entry = (0..STATE_SIZE).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    c
+}
+
+/// The coefficients `[c_0, c_1, c_2]` of the characteristic polynomial
+/// `x^3 + c_2 x^2 + c_1 x + c_0` of `m`.
+fn char_poly<F: PrimeField>(m: &Matrix<F>) -> [F; 3] {
+    let minor = |i: usize, j: usize| m[i][i] * m[j][j] - m[i][j] * m[j][i];
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    [
+        -det,
+        minor(0, 1) + minor(0, 2) + minor(1, 2),
+        -(m[0][0] + m[1][1] + m[2][2]),
+    ]
+}
+
+/// Whether the monic cubic `f` is irreducible over `F`, i.e. has no root in
+/// `F`, i.e. `gcd(x^|F| - x, f) = 1`.
+fn is_irreducible_cubic<F: PrimeField>(f: &[F; 3]) -> bool {
+    // Multiplication in `F[x]/(f)`, on the coefficients of `1, x, x^2`.
+    let mul = |a: &[F; 3], b: &[F; 3]| {
+        let mut prod = [F::zero(); 5];
+        for i in 0..3 {
+            for j in 0..3 {
+                prod[i + j] += a[i] * b[j];
+            }
+        }
+        for k in (3..5).rev() {
+            let lead = prod[k];
+            for i in 0..3 {
+                prod[k - 3 + i] -= lead * f[i];
+            }
+        }
+        [prod[0], prod[1], prod[2]]
+    };
+
+    let x = [F::zero(), F::one(), F::zero()];
+    let mut x_pow = [F::one(), F::zero(), F::zero()];
+    for bit in BitIteratorBE::without_leading_zeros(F::characteristic()) {
+        x_pow = mul(&x_pow, &x_pow);
+        if bit {
+            x_pow = mul(&x_pow, &x);
+        }
+    }
+    x_pow[1] -= F::one();
+
+    // `gcd(f, g)` for `g = x^|F| - x mod f`, which is 1 exactly when the last
+    // nonzero remainder is a constant.
+    let mut a = f.to_vec();
+    a.push(F::one());
+    let mut b = x_pow.to_vec();
+    loop {
+        while b.last() == Some(&F::zero()) {
+            b.pop();
+        }
+        match b.len() {
+            0 => return a.len() == 1,
+            1 => return true,
+            _ => {}
+        }
+        let lead_inv = b.last().unwrap().inverse().unwrap();
+        while a.len() >= b.len() {
+            let coeff = *a.last().unwrap() * lead_inv;
+            let shift = a.len() - b.len();
+            for (i, b_i) in b.iter().enumerate() {
+                a[shift + i] -= coeff * b_i;
+            }
+            a.pop();
+        }
+        core::mem::swap(&mut a, &mut b);
+    }
+}
+
+/// Compute `(|F| - 1) mod m`.
+fn modulus_minus_one_rem<F: PrimeField>(m: u64) -> u64 {
+    let m = m as u128;
+    let rem = F::characteristic()
+        .iter()
+        .rev()
+        .fold(0u128, |acc, limb| ((acc << 64) | *limb as u128) % m);
+    ((rem + m - 1) % m) as u64
+}
+
+/// The Grain LFSR that the Poseidon paper uses to generate parameters.
+struct GrainLfsr {
+    state: [bool; 80],
+    head: usize,
+}
+
+impl GrainLfsr {
+    fn new(field_size: u64, state_size: u64, full_rounds: u64, partial_rounds: u64) -> Self {
+        let mut state = [false; 80];
+        // b_0, b_1: the field is a prime field.
+        state[1] = true;
+        // b_2..b_5: the S-box is `x^alpha`, so these bits stay unset.
+        write_bits_be(&mut state[6..18], field_size);
+        write_bits_be(&mut state[18..30], state_size);
+        write_bits_be(&mut state[30..40], full_rounds);
+        write_bits_be(&mut state[40..50], partial_rounds);
+        for bit in &mut state[50..] {
+            *bit = true;
+        }
+
+        let mut lfsr = Self { state, head: 0 };
+        for _ in 0..160 {
+            lfsr.update();
+        }
+        lfsr
+    }
+
+    fn update(&mut self) -> bool {
+        let new_bit = [0, 13, 23, 38, 51, 62]
+            .iter()
+            .fold(false, |acc, i| acc ^ self.state[(self.head + i) % 80]);
+        self.state[self.head] = new_bit;
+        self.head = (self.head + 1) % 80;
+        new_bit
+    }
+
+    /// Output `num` bits, discarding every output bit that is not preceded
+    /// by a set bit.
+    fn next_bits(&mut self, num: usize) -> Vec<bool> {
+        let mut bits = Vec::with_capacity(num);
+        while bits.len() < num {
+            let keep = self.update();
+            let bit = self.update();
+            if keep {
+                bits.push(bit);
+            }
+        }
+        bits
+    }
+
+    fn next_field_element_rejection_sampling<F: PrimeField>(&mut self) -> F {
+        loop {
+            let bits = self.next_bits(F::size_in_bits());
+            if let Some(elem) = F::from_repr(F::BigInt::from_bits_be(&bits)) {
+                return elem;
+            }
+        }
+    }
+
+    fn next_field_element_mod_p<F: PrimeField>(&mut self) -> F {
+        let bits = self.next_bits(F::size_in_bits());
+        F::from_le_bytes_mod_order(&F::BigInt::from_bits_be(&bits).to_bytes_le())
+    }
+
+    /// Sample the Cauchy matrix `(x_i + y_j)^-1` for `2t` distinct elements
+    /// `x_0, ..., y_{t-1}`, resampling all of them until they are distinct
+    /// and no `x_i + y_j` is zero.
+    fn next_cauchy_matrix<F: PrimeField>(&mut self) -> Matrix<F> {
+        loop {
+            let elems = loop {
+                let elems: Vec<F> = (0..2 * STATE_SIZE)
+                    .map(|_| self.next_field_element_mod_p())
+                    .collect();
+                let distinct = elems
+                    .iter()
+                    .enumerate()
+                    .all(|(i, elem)| !elems[..i].contains(elem));
+                if distinct {
+                    break elems;
+                }
+            };
+            let (xs, ys) = elems.split_at(STATE_SIZE);
+
+            let mut mds = [[F::zero(); STATE_SIZE]; STATE_SIZE];
+            let mut degenerate = false;
+            for (row, x) in mds.iter_mut().zip(xs) {
+                for (entry, y) in row.iter_mut().zip(ys) {
+                    match (*x + y).inverse() {
+                        Some(inv) => *entry = inv,
+                        None => degenerate = true,
+                    }
+                }
+            }
+            if !degenerate {
+                return mds;
+            }
+        }
+    }
+}
+
+fn write_bits_be(dest: &mut [bool], value: u64) {
+    let len = dest.len();
+    for (i, bit) in dest.iter_mut().enumerate() {
+        *bit = (value >> (len - 1 - i)) & 1 == 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_ff::{One, Zero};
+
+    fn from_hex(hex: &str) -> Fr {
+        let bytes: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .rev()
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        Fr::from_le_bytes_mod_order(&bytes)
+    }
+
+    #[test]
+    fn grain_constants_match_reference() {
+        // The output of `generate_parameters_grain.sage 1 0 254 3 8 57` for
+        // the BN254 scalar field, as used by circomlib.
+        let (ark, mds) = grain_constants::<Fr>(8, 57);
+        assert_eq!(ark.len(), 65);
+        assert_eq!(
+            ark[0],
+            [
+                "0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e",
+                "00f1445235f2148c5986587169fc1bcd887b08d4d00868df5696fff40956e864",
+                "08dff3487e8ac99e1f29a058d0fa80b930c728730b7ab36ce879f3890ecf73f5",
+            ]
+            .iter()
+            .map(|hex| from_hex(hex))
+            .collect::<Vec<_>>()
+        );
+        let expected_mds = [
+            [
+                "109b7f411ba0e4c9b2b70caf5c36a7b194be7c11ad24378bfedb68592ba8118b",
+                "16ed41e13bb9c0c66ae119424fddbcbc9314dc9fdbdeea55d6c64543dc4903e0",
+                "2b90bba00fca0589f617e7dcbfe82e0df706ab640ceb247b791a93b74e36736d",
+            ],
+            [
+                "2969f27eed31a480b9c36c764379dbca2cc8fdd1415c3dded62940bcde0bd771",
+                "2e2419f9ec02ec394c9871c832963dc1b89d743c8c7b964029b2311687b1fe23",
+                "101071f0032379b697315876690f053d148d4e109f5fb065c8aacc55a0f89bfa",
+            ],
+            [
+                "143021ec686a3f330d5f9e654638065ce6cd79e28c5b3753326244ee65a1b1a7",
+                "176cc029695ad02582a70eff08a6fd99d057e12e58e7d7b6b16cdfabc8ee2911",
+                "19a3fc0a56702bf417ba7fee3802593fa644470307043f7773279cd71d25d5e0",
+            ],
+        ];
+        for (row, expected) in mds.iter().zip(&expected_mds) {
+            assert_eq!(
+                row,
+                &expected.iter().map(|hex| from_hex(hex)).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn matrices_with_invariant_subspaces_are_insecure() {
+        let mut m = [[Fr::zero(); STATE_SIZE]; STATE_SIZE];
+        m[0][0] = Fr::from(2u64);
+        m[1][1] = Fr::from(3u64);
+        m[2][2] = Fr::from(4u64);
+        assert!(!is_secure_mds(&m));
+        // The cube of the companion matrix of x^3 - 2 is a scalar matrix.
+        m = [[Fr::zero(); STATE_SIZE]; STATE_SIZE];
+        m[1][0] = Fr::one();
+        m[2][1] = Fr::one();
+        m[0][2] = Fr::from(2u64);
+        assert!(!is_secure_mds(&m));
+    }
+
+    #[test]
+    fn round_numbers_match_reference() {
+        // The output of `calc_round_numbers.py` for t = 3 and 128-bit security.
+        assert_eq!(round_numbers(3), (8, 83));
+        assert_eq!(round_numbers(5), (8, 56));
+        assert_eq!(round_numbers(7), (8, 46));
+        assert_eq!(round_numbers(17), (8, 31));
+    }
+}
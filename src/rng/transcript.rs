@@ -0,0 +1,166 @@
+use crate::rng::{FiatShamirRng, KeccakFiatShamirRng, PointCommitment};
+use crate::Vec;
+use ark_ff::{to_bytes, PrimeField, ToBytes};
+
+/// A Fiat-Shamir transcript in the style of Merlin: every message appended to
+/// it and every challenge drawn from it is bound to a label.
+///
+/// Labels and messages are absorbed together with their lengths, so two
+/// different sequences of labeled messages never absorb the same input. The
+/// challenges of a protocol thus depend on the meaning of each message, and
+/// not only on its bytes.
+pub struct Transcript<FS: FiatShamirRng> {
+    fs_rng: FS,
+}
+
+impl<FS: FiatShamirRng> Transcript<FS> {
+    /// Start a transcript for the protocol identified by `protocol_label`.
+    pub fn new(protocol_label: &[u8]) -> Self {
+        Self {
+            fs_rng: FS::initialize(&encode_label(protocol_label)),
+        }
+    }
+
+    /// Append `message` under `label`. The message is absorbed as its
+    /// `ToBytes` encoding.
+    pub fn append_message<T: ToBytes>(&mut self, label: &[u8], message: &T) {
+        let message = to_bytes![message].unwrap();
+        self.fs_rng
+            .absorb(&to_bytes![encode_label(label), message.len() as u64, message].unwrap());
+    }
+
+    /// Append the field elements `elems` under `label`. The elements are
+    /// absorbed with `FiatShamirRng::absorb_field_elements`.
+    pub fn append_field_elements<F: PrimeField>(&mut self, label: &[u8], elems: &[F]) {
+        self.fs_rng
+            .absorb(&to_bytes![encode_label(label), elems.len() as u64].unwrap());
+        if !elems.is_empty() {
+            self.fs_rng.absorb_field_elements(elems);
+        }
+    }
+
+    /// Draw `num` challenges for the rounds of the AHP verifier under
+    /// `label`, with `FiatShamirRng::squeeze_challenges`.
+    pub fn challenge_field_elements<F: PrimeField>(&mut self, label: &[u8], num: usize) -> Vec<F> {
+        self.fs_rng
+            .absorb(&to_bytes![encode_label(label), num as u64].unwrap());
+        self.fs_rng.squeeze_challenges(num)
+    }
+
+    /// Draw a single challenge for a round of the AHP verifier under `label`.
+    pub fn challenge_field_element<F: PrimeField>(&mut self, label: &[u8]) -> F {
+        self.challenge_field_elements(label, 1)[0]
+    }
+
+    /// Draw `num` challenges of 128 bits under `label`.
+    pub fn challenge_128_bits_field_elements<F: PrimeField>(
+        &mut self,
+        label: &[u8],
+        num: usize,
+    ) -> Vec<F> {
+        self.fs_rng
+            .absorb(&to_bytes![encode_label(label), num as u64].unwrap());
+        self.fs_rng.squeeze_128_bits_field_elements(num)
+    }
+
+    /// Draw a single challenge of 128 bits under `label`.
+    pub fn challenge_128_bits_field_element<F: PrimeField>(&mut self, label: &[u8]) -> F {
+        self.challenge_128_bits_field_elements(label, 1)[0]
+    }
+
+    /// The underlying `FiatShamirRng`.
+    #[cfg(feature = "solidity")]
+    pub(crate) fn fs_rng(&self) -> &FS {
+        &self.fs_rng
+    }
+}
+
+impl Transcript<KeccakFiatShamirRng> {
+    /// Append the commitments `comms` under `label`. The commitments are
+    /// absorbed as the ABI encoding of the coordinates of their points, with
+    /// `KeccakFiatShamirRng::absorb_point_commitments`.
+    pub fn append_point_commitments<'a, C: 'a + PointCommitment>(
+        &mut self,
+        label: &[u8],
+        comms: impl IntoIterator<Item = &'a C>,
+    ) {
+        self.fs_rng
+            .absorb_point_commitments(&encode_label(label), comms);
+    }
+}
+
+/// How `Marlin` appends the commitments of the prover to its transcript, and
+/// draws the challenge with which the verifier batches the openings.
+pub(crate) trait TranscriptEncoding<FS: FiatShamirRng, C> {
+    /// Append `comms` under `label`.
+    fn append_commitments(transcript: &mut Transcript<FS>, label: &[u8], comms: &[&C]);
+
+    /// Draw the opening challenge under `label`. By default, it has 128 bits.
+    fn opening_challenge<F: PrimeField>(transcript: &mut Transcript<FS>, label: &[u8]) -> F {
+        transcript.challenge_128_bits_field_element(label)
+    }
+}
+
+/// Commitments are appended as their `ToBytes` encoding, which any
+/// `FiatShamirRng` can absorb.
+pub(crate) struct BytesEncoding;
+
+impl<FS: FiatShamirRng, C: ToBytes> TranscriptEncoding<FS, C> for BytesEncoding {
+    fn append_commitments(transcript: &mut Transcript<FS>, label: &[u8], comms: &[&C]) {
+        transcript.append_message(label, &comms);
+    }
+}
+
+/// Commitments are appended as the ABI encoding of their points, and the
+/// opening challenge spans the whole field, as the generated Solidity
+/// verifier derives them.
+pub(crate) struct AbiEncoding;
+
+impl<C: PointCommitment> TranscriptEncoding<KeccakFiatShamirRng, C> for AbiEncoding {
+    fn append_commitments(
+        transcript: &mut Transcript<KeccakFiatShamirRng>,
+        label: &[u8],
+        comms: &[&C],
+    ) {
+        transcript.append_point_commitments(label, comms.iter().copied());
+    }
+
+    fn opening_challenge<F: PrimeField>(
+        transcript: &mut Transcript<KeccakFiatShamirRng>,
+        label: &[u8],
+    ) -> F {
+        transcript.challenge_field_element(label)
+    }
+}
+
+/// Encode `label` as its length, followed by its bytes.
+pub(crate) fn encode_label(label: &[u8]) -> Vec<u8> {
+    to_bytes![label.len() as u64, label].unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SimpleHashFiatShamirRng;
+    use ark_bls12_381::Fr;
+    use blake2::Blake2s;
+    use rand_chacha::ChaChaRng;
+
+    type FS = SimpleHashFiatShamirRng<Blake2s, ChaChaRng>;
+
+    fn challenge(protocol: &[u8], label: &[u8], message: &[u8]) -> Fr {
+        let mut transcript = Transcript::<FS>::new(protocol);
+        transcript.append_message(label, &message);
+        transcript.challenge_128_bits_field_element(b"challenge")
+    }
+
+    #[test]
+    fn challenges_depend_on_labels() {
+        let expected = challenge(b"protocol", b"label", b"message");
+        assert_eq!(challenge(b"protocol", b"label", b"message"), expected);
+        assert_ne!(challenge(b"protocol", b"other label", b"message"), expected);
+        assert_ne!(challenge(b"other protocol", b"label", b"message"), expected);
+        // Moving bytes from the label to the message changes the challenge.
+        assert_ne!(challenge(b"protocol", b"labelm", b"essage"), expected);
+    }
+}
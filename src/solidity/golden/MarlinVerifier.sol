@@ -0,0 +1,498 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Generated by ark-marlin for a single index. Do not edit.
+pragma solidity ^0.8.4;
+
+/// @title Marlin verifier
+/// @notice Verifies Marlin proofs for the index that this contract was
+/// generated for, made with the MarlinKZG10 polynomial commitment over BN254
+/// and the Keccak-256 Fiat-Shamir transform.
+contract MarlinVerifier {
+    uint256 internal constant R_MOD = 0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001;
+    uint256 internal constant Q_MOD = 0x30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47;
+    uint256 internal constant NUM_INPUTS = 2;
+    uint256 internal constant LOG_CONSTRAINT_DOMAIN_SIZE = 7;
+    uint256 internal constant LOG_VARIABLE_DOMAIN_SIZE = 5;
+    uint256 internal constant LOG_H_SIZE = 7;
+    uint256 internal constant LOG_K_SIZE = 9;
+    uint256 internal constant LOG_X_SIZE = 2;
+    uint256 internal constant K_SIZE_INV = 0x304c1c4ba7c10759a3741d93a64097b0f99fce54557c93d8fb40049926080001;
+    uint256 internal constant X_SIZE_INV = 0x244b3ad628e5381f4a3c3448e1210245de26ee365b4b146cf2e9782ef4000001;
+    uint256 internal constant X_GENERATOR = 0x30644e72e131a029048b6e193fd841045cea24f6fd736bec231204708f703636;
+    uint256 internal constant G_X = 0x10ff4ab61e6109f64b103c45770a0c2ec238622df2dddbf4aee2683203a35b7f;
+    uint256 internal constant G_Y = 0x10ff352f0d2acf65fcd3a0517bb3dba40d462da489a9041ffd6e02a1f1ef6100;
+    uint256 internal constant GAMMA_G_X = 0x1056c7e53024cf23717b29148bac7616334da324b11dfea00892fd628e8beb9b;
+    uint256 internal constant GAMMA_G_Y = 0x2635b81eca551e3dce46c882340ae5e3a0ce165eb3ba4c63f6a646026c1d0fbf;
+    uint256 internal constant G_1_SHIFT_X = 0x0840aa20e4d8894c54b92aa564ec37500c1ae936bbe4f1ca5ee923c73b61d3f9;
+    uint256 internal constant G_1_SHIFT_Y = 0x192f815df4e801a710919138c9502b401578bf18d78b58a0096b909012aed1e4;
+    uint256 internal constant G_2_SHIFT_X = 0x020417904b00d1d729bfe08f407ac9e8e5a0c546e0754d5cf779231e73c2d9ab;
+    uint256 internal constant G_2_SHIFT_Y = 0x15e4c97dd0f96d4f1e221ae553bad52c7aafd3e5bec209cdfb95385b8f60cda4;
+    uint256 internal constant ROW_X = 0x2e38bb716fcdb210eef9717631701e565a3a639a10e380465a5a38929b70bcd6;
+    uint256 internal constant ROW_Y = 0x17a7d11b8506a053f96e4ff7b69fd3f376367489e1c501782964107d4561f0e0;
+    uint256 internal constant COL_X = 0x1af381f4dce4aab4f63755cde479f0a88b83697af0ec9676e508b7463dfdfc76;
+    uint256 internal constant COL_Y = 0x27358f7d4c6ec3cfc281e845ddc2ce38617f84324c7c0805b3e3779728408aa1;
+    uint256 internal constant A_VAL_X = 0x220304ee7d40daafc5e2ee87e530e0d984a35f89aa1f5129f6f93d9f9c5eecad;
+    uint256 internal constant A_VAL_Y = 0x1dc64c96a8233b1efa838fe1055a7867824451f9e1437b11de08d160e9fdfe14;
+    uint256 internal constant B_VAL_X = 0x07d778f28aa26e4f7e182429999c89a9ab6924c26a5f9a788ea58aba53f83402;
+    uint256 internal constant B_VAL_Y = 0x27de1dc3f5837de82bdb7558e9de47568842a19703328af5593c92275a452356;
+    uint256 internal constant C_VAL_X = 0x2c43013b8f006538817a1d66b010a130057aa9186d9c1652a91d8f8b2ee7d6d3;
+    uint256 internal constant C_VAL_Y = 0x0e9028ee4f9bf8f254a7d7645be32b66874cd4dcb0d94756a517aec231684427;
+    uint256 internal constant ROW_COL_X = 0x16c6fd1af6636ae194d015b73bd1a41f8632eda3832c363a30a746219c3e7970;
+    uint256 internal constant ROW_COL_Y = 0x1c6e6a3fecbbecf3190b0510a17a44fcac66218f02d42b4b5bf385ad13de50a6;
+    uint256 internal constant H_X_IM = 0x0c724e1809536ec488aaae8b9d889d17994312eff04a726f3f50d7c7c877e9c8;
+    uint256 internal constant H_X_RE = 0x2aed435a016112962ed82d424afc495542043a4aec0edb793e83d014c7ebeb89;
+    uint256 internal constant H_Y_IM = 0x026af33b14421f71dd26554f1913dfa9f75e0af4ef15218de9be7a5399d4f44d;
+    uint256 internal constant H_Y_RE = 0x20c3029a0004aa1762fd18ebcbb0c62d5812a2c4a2e93c91a08549a2b821cd7e;
+    uint256 internal constant BETA_H_X_IM = 0x068aa54311120ae9e7b6e2e97a81e2965bfb3e094ec5ad7655fc3fe0b351cc6a;
+    uint256 internal constant BETA_H_X_RE = 0x0eaeebc89285a17a92907fa9965b099f51b4cbd0b1d7c8a69690afc80611aca2;
+    uint256 internal constant BETA_H_Y_IM = 0x070a598756aa16dfa0ae279ae26965b1c6fed951789b8d34b50b01e26847cf2e;
+    uint256 internal constant BETA_H_Y_RE = 0x01d54d82b6a6663b4859fc452f327509d6e2812789babae8c65f835e44cea576;
+
+    bytes32 internal constant VK_SEED = 0xcdc27f440ea0cd9df7d5e0a1640fb88fc0894dc44abce5076538874899d1a65b;
+    bytes internal constant PUBLIC_INPUT_PREFIX = hex"0c000000000000007075626c696320696e7075740200000000000000";
+    bytes internal constant ROUND_1_PREFIX = hex"1300000000000000726f756e642d3120636f6d6d69746d656e74738002000000000000";
+    bytes internal constant PROVER_MSG_1_PREFIX = hex"0c0000000000000070726f766572206d736720310000000000000000";
+    bytes internal constant ALPHA_PREFIX = hex"0500000000000000616c7068610100000000000000";
+    bytes internal constant ETA_A_PREFIX = hex"05000000000000006574615f610100000000000000";
+    bytes internal constant ETA_B_PREFIX = hex"05000000000000006574615f620100000000000000";
+    bytes internal constant ETA_C_PREFIX = hex"05000000000000006574615f630100000000000000";
+    bytes internal constant ROUND_2_PREFIX = hex"1300000000000000726f756e642d3220636f6d6d69746d656e7473e001000000000000";
+    bytes internal constant PROVER_MSG_2_PREFIX = hex"0c0000000000000070726f766572206d736720320000000000000000";
+    bytes internal constant BETA_PREFIX = hex"0400000000000000626574610100000000000000";
+    bytes internal constant ROUND_3_PREFIX = hex"1300000000000000726f756e642d3320636f6d6d69746d656e74734001000000000000";
+    bytes internal constant PROVER_MSG_3_PREFIX = hex"0c0000000000000070726f766572206d736720330000000000000000";
+    bytes internal constant GAMMA_PREFIX = hex"050000000000000067616d6d610100000000000000";
+    bytes internal constant EVALUATIONS_PREFIX = hex"0b000000000000006576616c756174696f6e730400000000000000";
+    bytes internal constant OPENING_PREFIX = hex"11000000000000006f70656e696e67206368616c6c656e67650100000000000000";
+
+    /// @dev The layout of a proof: the commitments of each round, with the
+    /// shifted commitments of `g_1` and `g_2` after them, the evaluations in
+    /// the order of their labels, and an opening proof at `beta` and at
+    /// `gamma`, each as the witness and the hiding evaluation.
+    uint256 internal constant W = 0;
+    uint256 internal constant Z_A = 2;
+    uint256 internal constant Z_B = 4;
+    uint256 internal constant MASK_POLY = 6;
+    uint256 internal constant T = 8;
+    uint256 internal constant G_1 = 10;
+    uint256 internal constant H_1 = 14;
+    uint256 internal constant G_2 = 16;
+    uint256 internal constant H_2 = 20;
+    uint256 internal constant EVALUATIONS = 22;
+    uint256 internal constant G_1_AT_BETA = 22;
+    uint256 internal constant G_2_AT_GAMMA = 23;
+    uint256 internal constant T_AT_BETA = 24;
+    uint256 internal constant Z_B_AT_BETA = 25;
+    uint256 internal constant OPENING_PROOFS = 26;
+    uint256 internal constant PROOF_AT_BETA = 26;
+    uint256 internal constant PROOF_AT_GAMMA = 29;
+    uint256 internal constant PROOF_LENGTH = 32;
+
+    /// @dev The challenges of the verifier, and the randomizer of the batched
+    /// pairing check.
+    struct Challenges {
+        uint256 alpha;
+        uint256 etaA;
+        uint256 etaB;
+        uint256 etaC;
+        uint256 beta;
+        uint256 gamma;
+        uint256 xi;
+        uint256 rho;
+    }
+
+    /// @dev A point of G1, with the point at infinity as `(0, 0)`.
+    struct G1Point {
+        uint256 x;
+        uint256 y;
+    }
+
+    /// @notice Verify `proof` for the public input `input`, as encoded by
+    /// `ark_marlin::solidity::encode_calldata`.
+    function verify(uint256[] calldata input, uint256[] calldata proof) external view returns (bool) {
+        if (input.length != NUM_INPUTS || proof.length != PROOF_LENGTH) {
+            return false;
+        }
+        for (uint256 i = 0; i < NUM_INPUTS; i++) {
+            if (input[i] >= R_MOD) {
+                return false;
+            }
+        }
+        for (uint256 i = 0; i < EVALUATIONS; i++) {
+            if (proof[i] >= Q_MOD) {
+                return false;
+            }
+        }
+        for (uint256 i = EVALUATIONS; i < OPENING_PROOFS; i++) {
+            if (proof[i] >= R_MOD) {
+                return false;
+            }
+        }
+        for (uint256 i = OPENING_PROOFS; i < PROOF_LENGTH; i += 3) {
+            if (proof[i] >= Q_MOD || proof[i + 1] >= Q_MOD || proof[i + 2] >= R_MOD) {
+                return false;
+            }
+        }
+        Challenges memory c = replayTranscript(input, proof);
+        return checkOpenings(input, proof, c);
+    }
+
+    // ------------------------------------------------------------------------
+    // Transcript
+    // ------------------------------------------------------------------------
+
+    /// @dev Replay the Fiat-Shamir transcript of
+    /// `Marlin::verify_with_abi_commitments`.
+    function replayTranscript(uint256[] calldata input, uint256[] calldata proof)
+        internal
+        pure
+        returns (Challenges memory c)
+    {
+        bytes32 seed = VK_SEED;
+        seed = absorb(seed, PUBLIC_INPUT_PREFIX);
+        if (NUM_INPUTS > 0) {
+            seed = absorb(seed, encodeFieldElements(input, 0, NUM_INPUTS));
+        }
+
+        bytes memory round = abi.encodePacked(
+            ROUND_1_PREFIX,
+            encodeCommitment(proof, W, false),
+            encodeCommitment(proof, Z_A, false),
+            encodeCommitment(proof, Z_B, false),
+            encodeCommitment(proof, MASK_POLY, false)
+        );
+        seed = absorb(seed, round);
+        seed = absorb(seed, PROVER_MSG_1_PREFIX);
+        (seed, c.alpha) = challengeOutsideH(seed, ALPHA_PREFIX);
+        (seed, c.etaA) = challenge(seed, ETA_A_PREFIX);
+        (seed, c.etaB) = challenge(seed, ETA_B_PREFIX);
+        (seed, c.etaC) = challenge(seed, ETA_C_PREFIX);
+
+        round = abi.encodePacked(
+            ROUND_2_PREFIX,
+            encodeCommitment(proof, T, false),
+            encodeCommitment(proof, G_1, true),
+            encodeCommitment(proof, H_1, false)
+        );
+        seed = absorb(seed, round);
+        seed = absorb(seed, PROVER_MSG_2_PREFIX);
+        (seed, c.beta) = challengeOutsideH(seed, BETA_PREFIX);
+
+        round = abi.encodePacked(
+            ROUND_3_PREFIX,
+            encodeCommitment(proof, G_2, true),
+            encodeCommitment(proof, H_2, false)
+        );
+        seed = absorb(seed, round);
+        seed = absorb(seed, PROVER_MSG_3_PREFIX);
+        (seed, c.gamma) = challenge(seed, GAMMA_PREFIX);
+
+        seed = absorb(seed, EVALUATIONS_PREFIX);
+        seed = absorb(seed, encodeFieldElements(proof, EVALUATIONS, OPENING_PROOFS - EVALUATIONS));
+        (seed, c.xi) = challenge(seed, OPENING_PREFIX);
+
+        // The randomizer of the batched pairing check has to depend on the
+        // opening proofs, which the transcript does not cover.
+        bytes memory openings = abi.encodePacked(
+            proof[PROOF_AT_BETA],
+            proof[PROOF_AT_BETA + 1],
+            proof[PROOF_AT_BETA + 2],
+            proof[PROOF_AT_GAMMA],
+            proof[PROOF_AT_GAMMA + 1],
+            proof[PROOF_AT_GAMMA + 2]
+        );
+        c.rho = uint256(keccak256(abi.encodePacked(seed, openings))) >> 128;
+    }
+
+    /// @dev Set `seed` to `keccak256(data || seed)`.
+    function absorb(bytes32 seed, bytes memory data) internal pure returns (bytes32) {
+        return keccak256(abi.encodePacked(data, seed));
+    }
+
+    /// @dev Absorb `prefix`, which ends with the number of challenges, and
+    /// squeeze a challenge.
+    function challenge(bytes32 seed, bytes memory prefix) internal pure returns (bytes32, uint256) {
+        seed = absorb(seed, prefix);
+        return (seed, uint256(keccak256(abi.encodePacked(seed, uint64(0)))) % R_MOD);
+    }
+
+    /// @dev Squeeze challenges until one lies outside of the domain `H`.
+    function challengeOutsideH(bytes32 seed, bytes memory prefix) internal pure returns (bytes32, uint256) {
+        uint256 x;
+        while (true) {
+            (seed, x) = challenge(seed, prefix);
+            if (vanishing(x, LOG_H_SIZE) != 0) {
+                break;
+            }
+        }
+        return (seed, x);
+    }
+
+    /// @dev The encoding of `len` field elements of `elems`, starting at
+    /// `start`.
+    function encodeFieldElements(uint256[] calldata elems, uint256 start, uint256 len)
+        internal
+        pure
+        returns (bytes memory encoded)
+    {
+        for (uint256 i = start; i < start + len; i++) {
+            encoded = abi.encodePacked(encoded, elems[i]);
+        }
+    }
+
+    /// @dev The encoding of the commitment at `proof[i]`: its coordinates,
+    /// and a flag followed by the coordinates of its shifted commitment at
+    /// `proof[i + 2]` if `shifted` is set, or by the point at infinity.
+    function encodeCommitment(uint256[] calldata proof, uint256 i, bool shifted)
+        internal
+        pure
+        returns (bytes memory)
+    {
+        if (shifted) {
+            return abi.encodePacked(proof[i], proof[i + 1], uint256(1), proof[i + 2], proof[i + 3]);
+        }
+        return abi.encodePacked(proof[i], proof[i + 1], uint256(0), uint256(0), uint256(0));
+    }
+
+    // ------------------------------------------------------------------------
+    // Linear combinations and the batched KZG check
+    // ------------------------------------------------------------------------
+
+    /// @dev Check the openings of the linear combinations of
+    /// `AHPForR1CS::construct_linear_combinations` with a single pairing
+    /// check, as `MarlinKZG10::check_combinations` does.
+    function checkOpenings(uint256[] calldata input, uint256[] calldata proof, Challenges memory c)
+        internal
+        view
+        returns (bool)
+    {
+        (G1Point memory atBeta, uint256 evalAtBeta) = combinedAtBeta(input, proof, c);
+        (G1Point memory atGamma, uint256 evalAtGamma) = combinedAtGamma(proof, c);
+
+        // e(-(W_beta + rho W_gamma), beta H)
+        //   * e(C_beta + beta W_beta + rho (C_gamma + gamma W_gamma)
+        //       - (v_beta + rho v_gamma) G - (r_beta + rho r_gamma) gamma G, H) = 1
+        G1Point memory wBeta = point(proof, PROOF_AT_BETA);
+        G1Point memory wGamma = point(proof, PROOF_AT_GAMMA);
+        G1Point memory totalC = add(atBeta, mul(wBeta, c.beta));
+        totalC = add(totalC, mul(add(atGamma, mul(wGamma, c.gamma)), c.rho));
+        uint256 scalar = addmod(evalAtBeta, mulmod(c.rho, evalAtGamma, R_MOD), R_MOD);
+        totalC = add(totalC, mul(G1Point(G_X, G_Y), R_MOD - scalar));
+        scalar = addmod(proof[PROOF_AT_BETA + 2], mulmod(c.rho, proof[PROOF_AT_GAMMA + 2], R_MOD), R_MOD);
+        totalC = add(totalC, mul(G1Point(GAMMA_G_X, GAMMA_G_Y), R_MOD - scalar));
+        return pairingCheck(negate(add(wBeta, mul(wGamma, c.rho))), totalC);
+    }
+
+    /// @dev The combination of the commitments and evaluations opened at
+    /// `beta`, in the order of their labels: `g_1` with its degree bound,
+    /// `outer_sumcheck`, `t` and `z_b`.
+    function combinedAtBeta(uint256[] calldata input, uint256[] calldata proof, Challenges memory c)
+        internal
+        view
+        returns (G1Point memory comm, uint256 eval)
+    {
+        comm = degreeBounded(proof, G_1, G_1_AT_BETA, G1Point(G_1_SHIFT_X, G_1_SHIFT_Y), c.xi);
+        eval = proof[G_1_AT_BETA];
+
+        uint256 xiPower = mulmod(c.xi, c.xi, R_MOD);
+        (G1Point memory outer, uint256 outerEval) = outerSumcheck(input, proof, c);
+        comm = add(comm, mul(outer, xiPower));
+        eval = addmod(eval, mulmod(outerEval, xiPower, R_MOD), R_MOD);
+
+        xiPower = mulmod(xiPower, c.xi, R_MOD);
+        comm = add(comm, mul(point(proof, T), xiPower));
+        eval = addmod(eval, mulmod(proof[T_AT_BETA], xiPower, R_MOD), R_MOD);
+
+        xiPower = mulmod(xiPower, c.xi, R_MOD);
+        comm = add(comm, mul(point(proof, Z_B), xiPower));
+        eval = addmod(eval, mulmod(proof[Z_B_AT_BETA], xiPower, R_MOD), R_MOD);
+    }
+
+    /// @dev The combination of the commitments and evaluations opened at
+    /// `gamma`, in the order of their labels: `g_2` with its degree bound and
+    /// `inner_sumcheck`.
+    function combinedAtGamma(uint256[] calldata proof, Challenges memory c)
+        internal
+        view
+        returns (G1Point memory comm, uint256 eval)
+    {
+        comm = degreeBounded(proof, G_2, G_2_AT_GAMMA, G1Point(G_2_SHIFT_X, G_2_SHIFT_Y), c.xi);
+        uint256 xi2 = mulmod(c.xi, c.xi, R_MOD);
+        (G1Point memory inner, uint256 innerEval) = innerSumcheck(proof, c);
+        comm = add(comm, mul(inner, xi2));
+        eval = addmod(proof[G_2_AT_GAMMA], mulmod(innerEval, xi2, R_MOD), R_MOD);
+    }
+
+    /// @dev The commitment and evaluation of the linear combination
+    /// `outer_sumcheck`, whose constant terms are moved to its evaluation:
+    ///   mask_poly + r (eta_a + eta_c z_b) z_a - t s_C v_X w - v_H h_1
+    ///   + r eta_b z_b - t s_C x(beta) - beta g_1(beta)
+    /// where `r = r(alpha, beta) s_R` and `s_R`, `s_C` select the constraint
+    /// and variable domains within `H`.
+    function outerSumcheck(uint256[] calldata input, uint256[] calldata proof, Challenges memory c)
+        internal
+        view
+        returns (G1Point memory comm, uint256 eval)
+    {
+        uint256 r = rAlphaBeta(c.alpha, c.beta);
+        uint256 tSelector = mulmod(proof[T_AT_BETA], selector(c.beta, LOG_VARIABLE_DOMAIN_SIZE), R_MOD);
+
+        comm = point(proof, MASK_POLY);
+        uint256 scalar = mulmod(r, addmod(c.etaA, mulmod(c.etaC, proof[Z_B_AT_BETA], R_MOD), R_MOD), R_MOD);
+        comm = add(comm, mul(point(proof, Z_A), scalar));
+        scalar = mulmod(tSelector, vanishing(c.beta, LOG_X_SIZE), R_MOD);
+        comm = add(comm, mul(point(proof, W), R_MOD - scalar));
+        comm = add(comm, mul(point(proof, H_1), R_MOD - vanishing(c.beta, LOG_H_SIZE)));
+
+        scalar = mulmod(r, mulmod(c.etaB, proof[Z_B_AT_BETA], R_MOD), R_MOD);
+        scalar = addmod(scalar, R_MOD - mulmod(tSelector, inputAtBeta(input, c.beta), R_MOD), R_MOD);
+        scalar = addmod(scalar, R_MOD - mulmod(c.beta, proof[G_1_AT_BETA], R_MOD), R_MOD);
+        eval = (R_MOD - scalar) % R_MOD;
+    }
+
+    /// @dev The unnormalized bivariate Lagrange polynomial of the constraint
+    /// domain at `(alpha, beta)`, times the selector of the constraint domain
+    /// at `beta`.
+    function rAlphaBeta(uint256 alpha, uint256 beta) internal view returns (uint256 r) {
+        if (alpha != beta) {
+            r = addmod(
+                vanishing(alpha, LOG_CONSTRAINT_DOMAIN_SIZE),
+                R_MOD - vanishing(beta, LOG_CONSTRAINT_DOMAIN_SIZE),
+                R_MOD
+            );
+            r = mulmod(r, inverse(addmod(alpha, R_MOD - beta, R_MOD)), R_MOD);
+        } else {
+            uint256 size = 1 << LOG_CONSTRAINT_DOMAIN_SIZE;
+            r = mulmod(size, expmod(alpha, size - 1), R_MOD);
+        }
+        r = mulmod(r, selector(beta, LOG_CONSTRAINT_DOMAIN_SIZE), R_MOD);
+    }
+
+    /// @dev The evaluation at `beta` of the polynomial `x` that interpolates
+    /// the formatted public input `(1, input)` over the domain `X`.
+    function inputAtBeta(uint256[] calldata input, uint256 beta) internal view returns (uint256 xAtBeta) {
+        // L_i(beta) = v_X(beta) omega^i / (|X| (beta - omega^i))
+        uint256 scale = mulmod(vanishing(beta, LOG_X_SIZE), X_SIZE_INV, R_MOD);
+        uint256 omega = 1;
+        for (uint256 i = 0; i <= NUM_INPUTS; i++) {
+            uint256 lagrange = mulmod(
+                mulmod(scale, omega, R_MOD),
+                inverse(addmod(beta, R_MOD - omega, R_MOD)),
+                R_MOD
+            );
+            xAtBeta = addmod(xAtBeta, mulmod(lagrange, i == 0 ? 1 : input[i - 1], R_MOD), R_MOD);
+            omega = mulmod(omega, X_GENERATOR, R_MOD);
+        }
+    }
+
+    /// @dev The commitment and evaluation of the linear combination
+    /// `inner_sumcheck`, whose constant term is moved to its evaluation:
+    ///   v_R(alpha) v_C(beta) sum_M eta_M val_M - v_K(gamma) h_2
+    ///   - (beta alpha - alpha row - beta col + row_col) s
+    /// where `s = gamma g_2(gamma) + t(beta) / |K|`.
+    function innerSumcheck(uint256[] calldata proof, Challenges memory c)
+        internal
+        view
+        returns (G1Point memory comm, uint256 eval)
+    {
+        uint256 scalar = mulmod(
+            vanishing(c.alpha, LOG_CONSTRAINT_DOMAIN_SIZE),
+            vanishing(c.beta, LOG_VARIABLE_DOMAIN_SIZE),
+            R_MOD
+        );
+        comm = mul(G1Point(A_VAL_X, A_VAL_Y), mulmod(scalar, c.etaA, R_MOD));
+        comm = add(comm, mul(G1Point(B_VAL_X, B_VAL_Y), mulmod(scalar, c.etaB, R_MOD)));
+        comm = add(comm, mul(G1Point(C_VAL_X, C_VAL_Y), mulmod(scalar, c.etaC, R_MOD)));
+        comm = add(comm, mul(point(proof, H_2), R_MOD - vanishing(c.gamma, LOG_K_SIZE)));
+
+        uint256 s = addmod(
+            mulmod(c.gamma, proof[G_2_AT_GAMMA], R_MOD),
+            mulmod(proof[T_AT_BETA], K_SIZE_INV, R_MOD),
+            R_MOD
+        );
+        comm = add(comm, mul(G1Point(ROW_X, ROW_Y), mulmod(c.alpha, s, R_MOD)));
+        comm = add(comm, mul(G1Point(COL_X, COL_Y), mulmod(c.beta, s, R_MOD)));
+        comm = add(comm, mul(G1Point(ROW_COL_X, ROW_COL_Y), R_MOD - s));
+        eval = mulmod(mulmod(c.beta, c.alpha, R_MOD), s, R_MOD);
+    }
+
+    /// @dev `C + xi (C' - v S)` for the commitment `C` at `proof[i]`, its
+    /// shifted commitment `C'`, its evaluation `v` at `proof[e]` and the shift
+    /// power `S` of its degree bound.
+    function degreeBounded(uint256[] calldata proof, uint256 i, uint256 e, G1Point memory shift, uint256 xi)
+        internal
+        view
+        returns (G1Point memory)
+    {
+        G1Point memory adjusted = add(point(proof, i + 2), mul(shift, R_MOD - proof[e]));
+        return add(point(proof, i), mul(adjusted, xi));
+    }
+
+    // ------------------------------------------------------------------------
+    // Field and group arithmetic
+    // ------------------------------------------------------------------------
+
+    /// @dev `x^n - 1` for `n = 2^logN`, the vanishing polynomial of the
+    /// subgroup of `n` elements.
+    function vanishing(uint256 x, uint256 logN) internal pure returns (uint256) {
+        for (uint256 i = 0; i < logN; i++) {
+            x = mulmod(x, x, R_MOD);
+        }
+        return addmod(x, R_MOD - 1, R_MOD);
+    }
+
+    /// @dev The selector of the subgroup of `2^logN` elements within `H` at
+    /// `x`: `|D| v_H(x) / (|H| v_D(x))`, or one if it is all of `H`.
+    function selector(uint256 x, uint256 logN) internal view returns (uint256) {
+        if (logN == LOG_H_SIZE) {
+            return 1;
+        }
+        uint256 numerator = mulmod(1 << logN, vanishing(x, LOG_H_SIZE), R_MOD);
+        uint256 denominator = mulmod(1 << LOG_H_SIZE, vanishing(x, logN), R_MOD);
+        return mulmod(numerator, inverse(denominator), R_MOD);
+    }
+
+    function inverse(uint256 x) internal view returns (uint256) {
+        require(x != 0, "inverse of zero");
+        return expmod(x, R_MOD - 2);
+    }
+
+    function expmod(uint256 base, uint256 exponent) internal view returns (uint256 result) {
+        bytes memory input = abi.encodePacked(uint256(32), uint256(32), uint256(32), base, exponent, R_MOD);
+        (bool success, bytes memory output) = address(0x05).staticcall(input);
+        require(success, "modexp failed");
+        result = abi.decode(output, (uint256));
+    }
+
+    function point(uint256[] calldata proof, uint256 i) internal pure returns (G1Point memory) {
+        return G1Point(proof[i], proof[i + 1]);
+    }
+
+    function add(G1Point memory p, G1Point memory q) internal view returns (G1Point memory r) {
+        (bool success, bytes memory output) = address(0x06).staticcall(abi.encode(p.x, p.y, q.x, q.y));
+        require(success, "ecAdd failed");
+        (r.x, r.y) = abi.decode(output, (uint256, uint256));
+    }
+
+    function mul(G1Point memory p, uint256 s) internal view returns (G1Point memory r) {
+        (bool success, bytes memory output) = address(0x07).staticcall(abi.encode(p.x, p.y, s));
+        require(success, "ecMul failed");
+        (r.x, r.y) = abi.decode(output, (uint256, uint256));
+    }
+
+    function negate(G1Point memory p) internal pure returns (G1Point memory) {
+        if (p.x == 0 && p.y == 0) {
+            return p;
+        }
+        return G1Point(p.x, Q_MOD - p.y);
+    }
+
+    /// @dev Whether `e(a, beta H) e(b, H) = 1`.
+    function pairingCheck(G1Point memory a, G1Point memory b) internal view returns (bool) {
+        bytes memory input = abi.encodePacked(
+            abi.encode(a.x, a.y, BETA_H_X_IM, BETA_H_X_RE, BETA_H_Y_IM, BETA_H_Y_RE),
+            abi.encode(b.x, b.y, H_X_IM, H_X_RE, H_Y_IM, H_Y_RE)
+        );
+        (bool success, bytes memory output) = address(0x08).staticcall(input);
+        return success && output.length == 32 && abi.decode(output, (uint256)) == 1;
+    }
+}
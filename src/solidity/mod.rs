@@ -0,0 +1,383 @@
+use crate::ahp::prover::ProverMsg;
+use crate::rng::{encode_label, keccak256, KeccakFiatShamirRng, Transcript};
+use crate::{AHPForR1CS, ArithmetizationLayout, IndexVerifierKey, Marlin, Proof, String, Vec};
+use ark_bn254::{Bn254, Fq, Fr, G1Affine, G2Affine};
+use ark_ff::{to_bytes, BigInteger, Field, One, PrimeField, Zero};
+use ark_poly::{univariate::DensePolynomial, EvaluationDomain, GeneralEvaluationDomain};
+use ark_poly_commit::marlin_pc::MarlinKZG10;
+use core::fmt::Write;
+
+mod reference;
+
+/// The polynomial commitment scheme that generated verifiers check proofs of.
+pub type SolidityPC = MarlinKZG10<Bn254, DensePolynomial<Fr>>;
+
+/// The argument system whose proofs generated verifiers check, once made
+/// with `SolidityMarlin::prove_with_abi_commitments`.
+pub type SolidityMarlin = Marlin<Fr, SolidityPC, KeccakFiatShamirRng>;
+
+/// A word of calldata: a big-endian 256-bit integer.
+pub type Word = [u8; 32];
+
+/// The number of words of an encoded proof.
+pub const PROOF_LENGTH: usize = 32;
+
+/// The signature of the `verify` function of generated verifiers.
+pub const VERIFY_SIGNATURE: &str = "verify(uint256[],uint256[])";
+
+/// The Solidity source that generated verifiers are filled into.
+const TEMPLATE: &str = include_str!("template.sol");
+
+/// The ways in which generating a verifier or encoding a proof can fail.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SolidityError {
+    /// The index uses a feature that generated verifiers do not support:
+    /// the per-matrix arithmetization, product gates or lookups.
+    UnsupportedIndex(&'static str),
+    /// A domain of the index is not a subgroup of a power-of-two order.
+    UnsupportedDomain,
+    /// The verifier key cannot enforce the degree bound of `g_1` or `g_2`.
+    MissingDegreeBound(usize),
+    /// The proof does not have the shape of a proof for a supported index.
+    MalformedProof,
+}
+
+/// A verifier for a single index, which can be written as a Solidity
+/// contract, and which runs the logic of that contract in Rust as a
+/// reference model.
+///
+/// Generated verifiers support the joint arithmetization without product
+/// gates or lookups, and check proofs made with
+/// `SolidityMarlin::prove_with_abi_commitments`.
+#[derive(Clone, Debug)]
+pub struct SolidityVerifier {
+    /// The seed of the transcript once the verifier key has been absorbed.
+    vk_seed: [u8; 32],
+    num_inputs: usize,
+    log_constraint_domain_size: u32,
+    log_variable_domain_size: u32,
+    log_h_size: u32,
+    log_k_size: u32,
+    log_x_size: u32,
+    x_generator: Fr,
+    /// The commitments to `row`, `col`, `a_val`, `b_val`, `c_val` and
+    /// `row_col`, in this order.
+    index_comms: Vec<G1Affine>,
+    g: G1Affine,
+    gamma_g: G1Affine,
+    h: G2Affine,
+    beta_h: G2Affine,
+    g_1_shift: G1Affine,
+    g_2_shift: G1Affine,
+}
+
+impl SolidityVerifier {
+    /// The verifier for the index of `vk`.
+    pub fn new(vk: &IndexVerifierKey<Fr, SolidityPC>) -> Result<Self, SolidityError> {
+        let info = &vk.index_info;
+        if info.arithmetization != ArithmetizationLayout::Joint {
+            return Err(SolidityError::UnsupportedIndex(
+                "per-matrix arithmetization",
+            ));
+        }
+        if info.has_product_gates() {
+            return Err(SolidityError::UnsupportedIndex("product gates"));
+        }
+        if info.has_lookups() {
+            return Err(SolidityError::UnsupportedIndex("lookups"));
+        }
+
+        let log_size = |size: usize| {
+            let domain =
+                GeneralEvaluationDomain::<Fr>::new(size).ok_or(SolidityError::UnsupportedDomain)?;
+            match domain {
+                GeneralEvaluationDomain::Radix2(radix2) => Ok((domain, radix2.log_size_of_group)),
+                _ => Err(SolidityError::UnsupportedDomain),
+            }
+        };
+        let (_, log_constraint_domain_size) = log_size(info.num_constraints)?;
+        let (_, log_variable_domain_size) = log_size(info.num_variables)?;
+        let (_, log_k_size) = log_size(info.num_non_zero)?;
+        let (x_domain, log_x_size) = log_size(info.num_instance_variables)?;
+
+        let shift = |bound: Option<usize>| {
+            let bound = bound.expect("g_1 and g_2 have degree bounds");
+            vk.verifier_key
+                .get_shift_power(bound)
+                .ok_or(SolidityError::MissingDegreeBound(bound))
+        };
+        let g_1_shift = shift(
+            AHPForR1CS::<Fr>::prover_second_round_degree_bounds(info)
+                .nth(1)
+                .flatten(),
+        )?;
+        let g_2_shift = shift(
+            AHPForR1CS::<Fr>::prover_third_round_degree_bounds(info)
+                .next()
+                .flatten(),
+        )?;
+
+        let mut transcript = Transcript::<KeccakFiatShamirRng>::new(SolidityMarlin::PROTOCOL_NAME);
+        transcript.append_message(b"index verifier key", vk);
+
+        let kzg_vk = &vk.verifier_key.vk;
+        Ok(Self {
+            vk_seed: transcript.fs_rng().seed(),
+            num_inputs: info.num_instance_variables - 1,
+            log_constraint_domain_size,
+            log_variable_domain_size,
+            log_h_size: core::cmp::max(log_constraint_domain_size, log_variable_domain_size),
+            log_k_size,
+            log_x_size,
+            x_generator: x_domain.element(1),
+            index_comms: vk.index_comms.iter().map(|c| c.comm.0).collect(),
+            g: kzg_vk.g,
+            gamma_g: kzg_vk.gamma_g,
+            h: kzg_vk.h,
+            beta_h: kzg_vk.beta_h,
+            g_1_shift,
+            g_2_shift,
+        })
+    }
+
+    /// The Solidity source of the verifier, as a contract named `name`.
+    pub fn contract(&self, name: &str) -> String {
+        let mut constants = String::new();
+        let mut uint = |name: &str, value: String| {
+            writeln!(
+                constants,
+                "    uint256 internal constant {} = {};",
+                name, value
+            )
+            .unwrap();
+        };
+        uint("R_MOD", hex_word(&field_word(-Fr::one()), 1));
+        uint("Q_MOD", hex_word(&field_word(-Fq::one()), 1));
+        uint("NUM_INPUTS", self.num_inputs.to_string());
+        uint(
+            "LOG_CONSTRAINT_DOMAIN_SIZE",
+            self.log_constraint_domain_size.to_string(),
+        );
+        uint(
+            "LOG_VARIABLE_DOMAIN_SIZE",
+            self.log_variable_domain_size.to_string(),
+        );
+        uint("LOG_H_SIZE", self.log_h_size.to_string());
+        uint("LOG_K_SIZE", self.log_k_size.to_string());
+        uint("LOG_X_SIZE", self.log_x_size.to_string());
+        uint(
+            "K_SIZE_INV",
+            hex_word(&field_word(size_inverse(self.log_k_size)), 0),
+        );
+        uint(
+            "X_SIZE_INV",
+            hex_word(&field_word(size_inverse(self.log_x_size)), 0),
+        );
+        uint("X_GENERATOR", hex_word(&field_word(self.x_generator), 0));
+
+        let mut g1 = |name: &str, point: &G1Affine| {
+            let [x, y] = g1_words(point);
+            uint(&format!("{}_X", name), hex_word(&x, 0));
+            uint(&format!("{}_Y", name), hex_word(&y, 0));
+        };
+        g1("G", &self.g);
+        g1("GAMMA_G", &self.gamma_g);
+        g1("G_1_SHIFT", &self.g_1_shift);
+        g1("G_2_SHIFT", &self.g_2_shift);
+        for (name, comm) in ["ROW", "COL", "A_VAL", "B_VAL", "C_VAL", "ROW_COL"]
+            .iter()
+            .zip(&self.index_comms)
+        {
+            g1(name, comm);
+        }
+        for (name, point) in [("H", &self.h), ("BETA_H", &self.beta_h)].iter() {
+            let [x_im, x_re, y_im, y_re] = g2_words(point);
+            uint(&format!("{}_X_IM", name), hex_word(&x_im, 0));
+            uint(&format!("{}_X_RE", name), hex_word(&x_re, 0));
+            uint(&format!("{}_Y_IM", name), hex_word(&y_im, 0));
+            uint(&format!("{}_Y_RE", name), hex_word(&y_re, 0));
+        }
+
+        writeln!(
+            constants,
+            "\n    bytes32 internal constant VK_SEED = {};",
+            hex_word(&self.vk_seed, 0)
+        )
+        .unwrap();
+        for (name, prefix) in self.prefixes() {
+            writeln!(
+                constants,
+                "    bytes internal constant {} = hex\"{}\";",
+                name,
+                to_hex(&prefix)
+            )
+            .unwrap();
+        }
+
+        TEMPLATE
+            .replace("{{NAME}}", name)
+            .replace("{{CONSTANTS}}", constants.trim_end())
+    }
+
+    /// The bytes that the transcript absorbs before each message and each
+    /// challenge: the label, and the number of elements or the size of the
+    /// message.
+    fn prefixes(&self) -> Vec<(&'static str, Vec<u8>)> {
+        let prefix = |label: &[u8], len: usize| to_bytes![encode_label(label), len as u64].unwrap();
+        let round = |i: usize, num_comms: usize| {
+            let label = format!("round-{} commitments", i);
+            prefix(label.as_bytes(), num_comms * COMMITMENT_SIZE)
+        };
+        let prover_msg = |i: usize| prefix(format!("prover msg {}", i).as_bytes(), 0);
+        vec![
+            (
+                "PUBLIC_INPUT_PREFIX",
+                prefix(b"public input", self.num_inputs),
+            ),
+            ("ROUND_1_PREFIX", round(1, 4)),
+            ("PROVER_MSG_1_PREFIX", prover_msg(1)),
+            ("ALPHA_PREFIX", prefix(b"alpha", 1)),
+            ("ETA_A_PREFIX", prefix(b"eta_a", 1)),
+            ("ETA_B_PREFIX", prefix(b"eta_b", 1)),
+            ("ETA_C_PREFIX", prefix(b"eta_c", 1)),
+            ("ROUND_2_PREFIX", round(2, 3)),
+            ("PROVER_MSG_2_PREFIX", prover_msg(2)),
+            ("BETA_PREFIX", prefix(b"beta", 1)),
+            ("ROUND_3_PREFIX", round(3, 2)),
+            ("PROVER_MSG_3_PREFIX", prover_msg(3)),
+            ("GAMMA_PREFIX", prefix(b"gamma", 1)),
+            (
+                "EVALUATIONS_PREFIX",
+                prefix(b"evaluations", NUM_EVALUATIONS),
+            ),
+            ("OPENING_PREFIX", prefix(b"opening challenge", 1)),
+        ]
+    }
+}
+
+/// The number of evaluations in a proof.
+const NUM_EVALUATIONS: usize = 4;
+
+/// The size of the encoding of a commitment in the transcript: the
+/// coordinates of the point, a flag for the shifted commitment, and the
+/// coordinates of the shifted commitment or of the point at infinity.
+const COMMITMENT_SIZE: usize = 5 * 32;
+
+/// The encoding of `public_input` as calldata words.
+pub fn encode_public_input(public_input: &[Fr]) -> Vec<Word> {
+    public_input.iter().map(|x| field_word(*x)).collect()
+}
+
+/// The encoding of `proof` as the calldata words that generated verifiers
+/// take, as laid out in their `PROOF_LENGTH` constants: the commitments of
+/// each round, followed by the shifted commitments of `g_1` and `g_2`, the
+/// evaluations, and the opening proofs at `beta` and `gamma`.
+pub fn encode_proof(proof: &Proof<Fr, SolidityPC>) -> Result<Vec<Word>, SolidityError> {
+    let shapes: [&[bool]; 3] = [&[false; 4], &[false, true, false], &[true, false]];
+    let well_formed = proof.commitments.len() == 3
+        && proof
+            .prover_messages
+            .iter()
+            .all(|msg| matches!(msg, ProverMsg::EmptyMessage))
+        && proof.evaluations.len() == NUM_EVALUATIONS
+        && proof.pc_proof.proof.len() == 2;
+    if !well_formed {
+        return Err(SolidityError::MalformedProof);
+    }
+
+    let mut words = Vec::with_capacity(PROOF_LENGTH);
+    for (round, shifted) in proof.commitments.iter().zip(shapes.iter()) {
+        if round.len() != shifted.len() {
+            return Err(SolidityError::MalformedProof);
+        }
+        for (comm, shifted) in round.iter().zip(shifted.iter()) {
+            words.extend_from_slice(&g1_words(&comm.comm.0));
+            match (&comm.shifted_comm, shifted) {
+                (Some(shifted_comm), true) => words.extend_from_slice(&g1_words(&shifted_comm.0)),
+                (None, false) => {}
+                _ => return Err(SolidityError::MalformedProof),
+            }
+        }
+    }
+    words.extend(proof.evaluations.iter().map(|eval| field_word(*eval)));
+    for opening in &proof.pc_proof.proof {
+        words.extend_from_slice(&g1_words(&opening.w));
+        words.push(field_word(opening.random_v.unwrap_or_else(Fr::zero)));
+    }
+    Ok(words)
+}
+
+/// The calldata of a call to `verify` on a generated verifier, for `proof`
+/// and `public_input`.
+pub fn encode_calldata(
+    public_input: &[Fr],
+    proof: &Proof<Fr, SolidityPC>,
+) -> Result<Vec<u8>, SolidityError> {
+    let input = encode_public_input(public_input);
+    let proof = encode_proof(proof)?;
+    let mut calldata = keccak256(VERIFY_SIGNATURE.as_bytes())[..4].to_vec();
+    calldata.extend_from_slice(&usize_word(2 * 32));
+    calldata.extend_from_slice(&usize_word((3 + input.len()) * 32));
+    for array in &[input, proof] {
+        calldata.extend_from_slice(&usize_word(array.len()));
+        for word in array {
+            calldata.extend_from_slice(word);
+        }
+    }
+    Ok(calldata)
+}
+
+/// The inverse of `2^log_size` in `Fr`.
+fn size_inverse(log_size: u32) -> Fr {
+    Fr::from(1u64 << log_size).inverse().unwrap()
+}
+
+fn usize_word(n: usize) -> Word {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&(n as u64).to_be_bytes());
+    word
+}
+
+/// The big-endian encoding of the canonical representation of `elem`.
+fn field_word<F: PrimeField>(elem: F) -> Word {
+    let mut word = [0u8; 32];
+    word.copy_from_slice(&elem.into_repr().to_bytes_be());
+    word
+}
+
+/// The coordinates of `point`, with the point at infinity as `(0, 0)` as in
+/// the EVM precompiles.
+fn g1_words(point: &G1Affine) -> [Word; 2] {
+    if point.infinity {
+        [[0; 32]; 2]
+    } else {
+        [field_word(point.x), field_word(point.y)]
+    }
+}
+
+/// The coordinates of `point` in the order that the pairing precompile
+/// takes them: the imaginary part of each coordinate before its real part.
+fn g2_words(point: &G2Affine) -> [Word; 4] {
+    [
+        field_word(point.x.c1),
+        field_word(point.x.c0),
+        field_word(point.y.c1),
+        field_word(point.y.c0),
+    ]
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// `word + addend` as a `0x`-prefixed hex literal.
+fn hex_word(word: &Word, addend: u8) -> String {
+    let mut word = *word;
+    let mut carry = addend;
+    for byte in word.iter_mut().rev() {
+        let (sum, overflow) = byte.overflowing_add(carry);
+        *byte = sum;
+        carry = overflow as u8;
+    }
+    format!("0x{}", to_hex(&word))
+}
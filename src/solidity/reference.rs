@@ -0,0 +1,461 @@
+//! A model of the generated contracts in Rust, which follows their logic
+//! step by step on the same calldata words, so that tests can check it
+//! against `Marlin::verify_with_abi_commitments` without an EVM.
+
+use super::{SolidityVerifier, Word, PROOF_LENGTH, VERIFY_SIGNATURE};
+use crate::rng::keccak256;
+use crate::Vec;
+use ark_bn254::{Bn254, Fq, Fq12, Fr, G1Affine, G1Projective};
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{BigInteger256, Field, One, PrimeField, Zero};
+use ark_std::convert::TryFrom;
+
+// The layout of a proof, as in the template.
+const W: usize = 0;
+const Z_A: usize = 2;
+const Z_B: usize = 4;
+const MASK_POLY: usize = 6;
+const T: usize = 8;
+const G_1: usize = 10;
+const H_1: usize = 14;
+const G_2: usize = 16;
+const H_2: usize = 20;
+const EVALUATIONS: usize = 22;
+const G_1_AT_BETA: usize = 22;
+const G_2_AT_GAMMA: usize = 23;
+const T_AT_BETA: usize = 24;
+const Z_B_AT_BETA: usize = 25;
+const OPENING_PROOFS: usize = 26;
+const PROOF_AT_BETA: usize = 26;
+const PROOF_AT_GAMMA: usize = 29;
+
+/// The challenges of the verifier, and the randomizer of the batched
+/// pairing check.
+struct Challenges {
+    alpha: Fr,
+    eta_a: Fr,
+    eta_b: Fr,
+    eta_c: Fr,
+    beta: Fr,
+    gamma: Fr,
+    xi: Fr,
+    rho: Fr,
+}
+
+/// A proof whose words have been checked to be in range, with its points
+/// decoded. Decoding fails where the contract would revert when it passes a
+/// point to a precompile.
+struct DecodedProof<'a> {
+    words: &'a [Word],
+    scalars: Vec<Fr>,
+}
+
+impl<'a> DecodedProof<'a> {
+    fn point(&self, i: usize) -> Option<G1Affine> {
+        let (x, y) = (
+            Fq::from_repr(word_repr(&self.words[i]))?,
+            Fq::from_repr(word_repr(&self.words[i + 1]))?,
+        );
+        if x.is_zero() && y.is_zero() {
+            return Some(G1Affine::zero());
+        }
+        let point = G1Affine::new(x, y, false);
+        if point.is_on_curve() {
+            Some(point)
+        } else {
+            None
+        }
+    }
+
+    fn scalar(&self, i: usize) -> Fr {
+        self.scalars[i - EVALUATIONS]
+    }
+}
+
+impl SolidityVerifier {
+    /// Whether the generated contract accepts `calldata`, as made by
+    /// `encode_calldata`. Calls on which the contract reverts are rejected.
+    pub fn verify_calldata(&self, calldata: &[u8]) -> bool {
+        if calldata.len() < 4 || calldata[..4] != keccak256(VERIFY_SIGNATURE.as_bytes())[..4] {
+            return false;
+        }
+        let args = &calldata[4..];
+        match (decode_array(args, 0), decode_array(args, 1)) {
+            (Some(input), Some(proof)) => self.verify_words(&input, &proof),
+            _ => false,
+        }
+    }
+
+    /// Whether the generated contract accepts the proof `proof` for the
+    /// public input `input`, as encoded by `encode_proof` and
+    /// `encode_public_input`.
+    pub fn verify_words(&self, input: &[Word], proof: &[Word]) -> bool {
+        if input.len() != self.num_inputs || proof.len() != PROOF_LENGTH {
+            return false;
+        }
+        let input = match input
+            .iter()
+            .map(|word| Fr::from_repr(word_repr(word)))
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(input) => input,
+            None => return false,
+        };
+        let in_range = proof[..EVALUATIONS]
+            .iter()
+            .all(|word| Fq::from_repr(word_repr(word)).is_some())
+            && proof[OPENING_PROOFS..].chunks(3).all(|opening| {
+                Fq::from_repr(word_repr(&opening[0])).is_some()
+                    && Fq::from_repr(word_repr(&opening[1])).is_some()
+            });
+        let scalars = proof[EVALUATIONS..OPENING_PROOFS]
+            .iter()
+            .chain(&[proof[PROOF_AT_BETA + 2], proof[PROOF_AT_GAMMA + 2]])
+            .map(|word| Fr::from_repr(word_repr(word)))
+            .collect::<Option<Vec<_>>>();
+        let scalars = match scalars {
+            Some(scalars) if in_range => scalars,
+            _ => return false,
+        };
+        let proof = DecodedProof {
+            words: proof,
+            scalars,
+        };
+        let c = self.replay_transcript(&input, &proof);
+        self.check_openings(&input, &proof, &c).unwrap_or(false)
+    }
+
+    /// Replay the Fiat-Shamir transcript of
+    /// `Marlin::verify_with_abi_commitments`.
+    fn replay_transcript(&self, input: &[Fr], proof: &DecodedProof) -> Challenges {
+        let prefixes = self.prefixes();
+        let prefix = |name: &str| {
+            &prefixes
+                .iter()
+                .find(|(prefix_name, _)| *prefix_name == name)
+                .expect("every prefix is generated")
+                .1[..]
+        };
+        let commitment = |i: usize, shifted: bool| {
+            let mut encoded = [proof.words[i], proof.words[i + 1]].concat();
+            if shifted {
+                encoded.extend_from_slice(&super::usize_word(1));
+                encoded.extend_from_slice(&[proof.words[i + 2], proof.words[i + 3]].concat());
+            } else {
+                encoded.extend_from_slice(&[0; 3 * 32]);
+            }
+            encoded
+        };
+        let round = |name: &str, comms: &[(usize, bool)]| {
+            let mut encoded = prefix(name).to_vec();
+            for (i, shifted) in comms {
+                encoded.extend(commitment(*i, *shifted));
+            }
+            encoded
+        };
+
+        let mut seed = self.vk_seed;
+        seed = absorb(seed, prefix("PUBLIC_INPUT_PREFIX"));
+        if !input.is_empty() {
+            seed = absorb(seed, &encode_field_elements(input));
+        }
+
+        let round_1 = round(
+            "ROUND_1_PREFIX",
+            &[(W, false), (Z_A, false), (Z_B, false), (MASK_POLY, false)],
+        );
+        seed = absorb(seed, &round_1);
+        seed = absorb(seed, prefix("PROVER_MSG_1_PREFIX"));
+        let alpha = self.challenge_outside_h(&mut seed, prefix("ALPHA_PREFIX"));
+        let eta_a = challenge(&mut seed, prefix("ETA_A_PREFIX"));
+        let eta_b = challenge(&mut seed, prefix("ETA_B_PREFIX"));
+        let eta_c = challenge(&mut seed, prefix("ETA_C_PREFIX"));
+
+        let round_2 = round("ROUND_2_PREFIX", &[(T, false), (G_1, true), (H_1, false)]);
+        seed = absorb(seed, &round_2);
+        seed = absorb(seed, prefix("PROVER_MSG_2_PREFIX"));
+        let beta = self.challenge_outside_h(&mut seed, prefix("BETA_PREFIX"));
+
+        let round_3 = round("ROUND_3_PREFIX", &[(G_2, true), (H_2, false)]);
+        seed = absorb(seed, &round_3);
+        seed = absorb(seed, prefix("PROVER_MSG_3_PREFIX"));
+        let gamma = challenge(&mut seed, prefix("GAMMA_PREFIX"));
+
+        seed = absorb(seed, prefix("EVALUATIONS_PREFIX"));
+        seed = absorb(
+            seed,
+            &encode_field_elements(&proof.scalars[..OPENING_PROOFS - EVALUATIONS]),
+        );
+        let xi = challenge(&mut seed, prefix("OPENING_PREFIX"));
+
+        let mut openings = seed.to_vec();
+        for i in PROOF_AT_BETA..PROOF_LENGTH {
+            openings.extend_from_slice(&proof.words[i]);
+        }
+        let rho = high_half(&keccak256(&openings));
+
+        Challenges {
+            alpha,
+            eta_a,
+            eta_b,
+            eta_c,
+            beta,
+            gamma,
+            xi,
+            rho,
+        }
+    }
+
+    /// Squeeze challenges until one lies outside of the domain `H`.
+    fn challenge_outside_h(&self, seed: &mut [u8; 32], prefix: &[u8]) -> Fr {
+        loop {
+            let x = challenge(seed, prefix);
+            if !vanishing(x, self.log_h_size).is_zero() {
+                break x;
+            }
+        }
+    }
+
+    /// Check the openings with a single pairing check. Returns `None` where
+    /// the contract would revert.
+    fn check_openings(&self, input: &[Fr], proof: &DecodedProof, c: &Challenges) -> Option<bool> {
+        let (at_beta, eval_at_beta) = self.combined_at_beta(input, proof, c)?;
+        let (at_gamma, eval_at_gamma) = self.combined_at_gamma(proof, c)?;
+
+        let w_beta = proof.point(PROOF_AT_BETA)?.into_projective();
+        let w_gamma = proof.point(PROOF_AT_GAMMA)?.into_projective();
+        let mut total_c = at_beta + mul(w_beta, c.beta);
+        total_c += mul(at_gamma + mul(w_gamma, c.gamma), c.rho);
+        total_c -= mul(
+            self.g.into_projective(),
+            eval_at_beta + c.rho * eval_at_gamma,
+        );
+        let random_v = proof.scalars[OPENING_PROOFS - EVALUATIONS]
+            + c.rho * proof.scalars[OPENING_PROOFS - EVALUATIONS + 1];
+        total_c -= mul(self.gamma_g.into_projective(), random_v);
+        let total_w = -(w_beta + mul(w_gamma, c.rho));
+
+        let product = Bn254::pairing(total_w, self.beta_h) * Bn254::pairing(total_c, self.h);
+        Some(product == Fq12::one())
+    }
+
+    /// The combination of the commitments and evaluations opened at `beta`.
+    fn combined_at_beta(
+        &self,
+        input: &[Fr],
+        proof: &DecodedProof,
+        c: &Challenges,
+    ) -> Option<(G1Projective, Fr)> {
+        let mut comm = degree_bounded(proof, G_1, G_1_AT_BETA, self.g_1_shift, c.xi)?;
+        let mut eval = proof.scalar(G_1_AT_BETA);
+
+        let mut xi_power = c.xi * c.xi;
+        let (outer, outer_eval) = self.outer_sumcheck(input, proof, c)?;
+        comm += mul(outer, xi_power);
+        eval += outer_eval * xi_power;
+
+        xi_power *= c.xi;
+        comm += mul(proof.point(T)?.into_projective(), xi_power);
+        eval += proof.scalar(T_AT_BETA) * xi_power;
+
+        xi_power *= c.xi;
+        comm += mul(proof.point(Z_B)?.into_projective(), xi_power);
+        eval += proof.scalar(Z_B_AT_BETA) * xi_power;
+        Some((comm, eval))
+    }
+
+    /// The combination of the commitments and evaluations opened at `gamma`.
+    fn combined_at_gamma(
+        &self,
+        proof: &DecodedProof,
+        c: &Challenges,
+    ) -> Option<(G1Projective, Fr)> {
+        let comm = degree_bounded(proof, G_2, G_2_AT_GAMMA, self.g_2_shift, c.xi)?;
+        let xi_2 = c.xi * c.xi;
+        let (inner, inner_eval) = self.inner_sumcheck(proof, c)?;
+        Some((
+            comm + mul(inner, xi_2),
+            proof.scalar(G_2_AT_GAMMA) + inner_eval * xi_2,
+        ))
+    }
+
+    /// The commitment and evaluation of `outer_sumcheck`.
+    fn outer_sumcheck(
+        &self,
+        input: &[Fr],
+        proof: &DecodedProof,
+        c: &Challenges,
+    ) -> Option<(G1Projective, Fr)> {
+        let r = self.r_alpha_beta(c.alpha, c.beta)?;
+        let t_selector =
+            proof.scalar(T_AT_BETA) * self.selector(c.beta, self.log_variable_domain_size)?;
+        let z_b_at_beta = proof.scalar(Z_B_AT_BETA);
+
+        let mut comm = proof.point(MASK_POLY)?.into_projective();
+        comm += mul(
+            proof.point(Z_A)?.into_projective(),
+            r * (c.eta_a + c.eta_c * z_b_at_beta),
+        );
+        comm -= mul(
+            proof.point(W)?.into_projective(),
+            t_selector * vanishing(c.beta, self.log_x_size),
+        );
+        comm -= mul(
+            proof.point(H_1)?.into_projective(),
+            vanishing(c.beta, self.log_h_size),
+        );
+
+        let constant = r * c.eta_b * z_b_at_beta
+            - t_selector * self.input_at_beta(input, c.beta)?
+            - c.beta * proof.scalar(G_1_AT_BETA);
+        Some((comm, -constant))
+    }
+
+    /// The unnormalized bivariate Lagrange polynomial of the constraint
+    /// domain at `(alpha, beta)`, times the selector of the constraint domain
+    /// at `beta`.
+    fn r_alpha_beta(&self, alpha: Fr, beta: Fr) -> Option<Fr> {
+        let log_size = self.log_constraint_domain_size;
+        let r = if alpha != beta {
+            (vanishing(alpha, log_size) - vanishing(beta, log_size)) * (alpha - beta).inverse()?
+        } else {
+            let size = 1u64 << log_size;
+            Fr::from(size) * alpha.pow([size - 1])
+        };
+        Some(r * self.selector(beta, log_size)?)
+    }
+
+    /// The evaluation at `beta` of the polynomial that interpolates `(1, input)`
+    /// over the domain `X`.
+    fn input_at_beta(&self, input: &[Fr], beta: Fr) -> Option<Fr> {
+        let scale =
+            vanishing(beta, self.log_x_size) * Fr::from(1u64 << self.log_x_size).inverse()?;
+        let mut omega = Fr::one();
+        let mut x_at_beta = Fr::zero();
+        for x in ark_std::iter::once(&Fr::one()).chain(input) {
+            x_at_beta += scale * omega * (beta - omega).inverse()? * x;
+            omega *= self.x_generator;
+        }
+        Some(x_at_beta)
+    }
+
+    /// The commitment and evaluation of `inner_sumcheck`.
+    fn inner_sumcheck(&self, proof: &DecodedProof, c: &Challenges) -> Option<(G1Projective, Fr)> {
+        let [row, col, a_val, b_val, c_val, row_col] =
+            [0, 1, 2, 3, 4, 5].map(|i| self.index_comms[i].into_projective());
+        let scalar = vanishing(c.alpha, self.log_constraint_domain_size)
+            * vanishing(c.beta, self.log_variable_domain_size);
+        let mut comm = mul(a_val, scalar * c.eta_a)
+            + mul(b_val, scalar * c.eta_b)
+            + mul(c_val, scalar * c.eta_c);
+        comm -= mul(
+            proof.point(H_2)?.into_projective(),
+            vanishing(c.gamma, self.log_k_size),
+        );
+
+        let k_size_inv = Fr::from(1u64 << self.log_k_size).inverse()?;
+        let s = c.gamma * proof.scalar(G_2_AT_GAMMA) + proof.scalar(T_AT_BETA) * k_size_inv;
+        comm += mul(row, c.alpha * s) + mul(col, c.beta * s) - mul(row_col, s);
+        Some((comm, c.beta * c.alpha * s))
+    }
+
+    /// The selector of the subgroup of `2^log_size` elements within `H` at
+    /// `x`.
+    fn selector(&self, x: Fr, log_size: u32) -> Option<Fr> {
+        if log_size == self.log_h_size {
+            return Some(Fr::one());
+        }
+        let numerator = Fr::from(1u64 << log_size) * vanishing(x, self.log_h_size);
+        let denominator = Fr::from(1u64 << self.log_h_size) * vanishing(x, log_size);
+        Some(numerator * denominator.inverse()?)
+    }
+}
+
+/// `C + xi (C' - v S)` for the commitment `C` at `proof[i]`, its shifted
+/// commitment `C'`, its evaluation `v` at `proof[e]` and the shift power `S`.
+fn degree_bounded(
+    proof: &DecodedProof,
+    i: usize,
+    e: usize,
+    shift: G1Affine,
+    xi: Fr,
+) -> Option<G1Projective> {
+    let adjusted =
+        proof.point(i + 2)?.into_projective() - mul(shift.into_projective(), proof.scalar(e));
+    Some(proof.point(i)?.into_projective() + mul(adjusted, xi))
+}
+
+fn mul(point: G1Projective, scalar: Fr) -> G1Projective {
+    point.mul(scalar.into_repr())
+}
+
+/// `x^n - 1` for `n = 2^log_size`.
+fn vanishing(mut x: Fr, log_size: u32) -> Fr {
+    for _ in 0..log_size {
+        x.square_in_place();
+    }
+    x - Fr::one()
+}
+
+/// Set `seed` to `keccak256(data || seed)`.
+fn absorb(seed: [u8; 32], data: &[u8]) -> [u8; 32] {
+    let mut bytes = data.to_vec();
+    bytes.extend_from_slice(&seed);
+    keccak256(&bytes)
+}
+
+/// Absorb `prefix` and squeeze a challenge.
+fn challenge(seed: &mut [u8; 32], prefix: &[u8]) -> Fr {
+    *seed = absorb(*seed, prefix);
+    let mut bytes = seed.to_vec();
+    bytes.extend_from_slice(&0u64.to_be_bytes());
+    Fr::from_be_bytes_mod_order(&keccak256(&bytes))
+}
+
+/// The top 128 bits of a big-endian word.
+fn high_half(word: &Word) -> Fr {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&word[..16]);
+    u128::from_be_bytes(bytes).into()
+}
+
+fn encode_field_elements(elems: &[Fr]) -> Vec<u8> {
+    elems
+        .iter()
+        .flat_map(|elem| super::field_word(*elem))
+        .collect()
+}
+
+/// The integer whose big-endian encoding is `word`.
+fn word_repr(word: &Word) -> BigInteger256 {
+    let mut limbs = [0u64; 4];
+    for (limb, bytes) in limbs.iter_mut().zip(word.rchunks(8)) {
+        let mut limb_bytes = [0u8; 8];
+        limb_bytes.copy_from_slice(bytes);
+        *limb = u64::from_be_bytes(limb_bytes);
+    }
+    BigInteger256::new(limbs)
+}
+
+/// The `index`-th argument of `args`, a dynamic `uint256[]`.
+fn decode_array(args: &[u8], index: usize) -> Option<Vec<Word>> {
+    let read = |offset: usize| -> Option<usize> {
+        let word = args.get(offset..offset.checked_add(32)?)?;
+        if word[..24].iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&word[24..]);
+        usize::try_from(u64::from_be_bytes(bytes)).ok()
+    };
+    let offset = read(32 * index)?;
+    let len = read(offset)?;
+    (0..len)
+        .map(|i| {
+            let start = offset.checked_add(32)?.checked_add(i.checked_mul(32)?)?;
+            let mut word = [0u8; 32];
+            word.copy_from_slice(args.get(start..start + 32)?);
+            Some(word)
+        })
+        .collect()
+}
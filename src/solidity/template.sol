@@ -0,0 +1,443 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Generated by ark-marlin for a single index. Do not edit.
+pragma solidity ^0.8.4;
+
+/// @title Marlin verifier
+/// @notice Verifies Marlin proofs for the index that this contract was
+/// generated for, made with the MarlinKZG10 polynomial commitment over BN254
+/// and the Keccak-256 Fiat-Shamir transform.
+contract {{NAME}} {
+{{CONSTANTS}}
+
+    /// @dev The layout of a proof: the commitments of each round, with the
+    /// shifted commitments of `g_1` and `g_2` after them, the evaluations in
+    /// the order of their labels, and an opening proof at `beta` and at
+    /// `gamma`, each as the witness and the hiding evaluation.
+    uint256 internal constant W = 0;
+    uint256 internal constant Z_A = 2;
+    uint256 internal constant Z_B = 4;
+    uint256 internal constant MASK_POLY = 6;
+    uint256 internal constant T = 8;
+    uint256 internal constant G_1 = 10;
+    uint256 internal constant H_1 = 14;
+    uint256 internal constant G_2 = 16;
+    uint256 internal constant H_2 = 20;
+    uint256 internal constant EVALUATIONS = 22;
+    uint256 internal constant G_1_AT_BETA = 22;
+    uint256 internal constant G_2_AT_GAMMA = 23;
+    uint256 internal constant T_AT_BETA = 24;
+    uint256 internal constant Z_B_AT_BETA = 25;
+    uint256 internal constant OPENING_PROOFS = 26;
+    uint256 internal constant PROOF_AT_BETA = 26;
+    uint256 internal constant PROOF_AT_GAMMA = 29;
+    uint256 internal constant PROOF_LENGTH = 32;
+
+    /// @dev The challenges of the verifier, and the randomizer of the batched
+    /// pairing check.
+    struct Challenges {
+        uint256 alpha;
+        uint256 etaA;
+        uint256 etaB;
+        uint256 etaC;
+        uint256 beta;
+        uint256 gamma;
+        uint256 xi;
+        uint256 rho;
+    }
+
+    /// @dev A point of G1, with the point at infinity as `(0, 0)`.
+    struct G1Point {
+        uint256 x;
+        uint256 y;
+    }
+
+    /// @notice Verify `proof` for the public input `input`, as encoded by
+    /// `ark_marlin::solidity::encode_calldata`.
+    function verify(uint256[] calldata input, uint256[] calldata proof) external view returns (bool) {
+        if (input.length != NUM_INPUTS || proof.length != PROOF_LENGTH) {
+            return false;
+        }
+        for (uint256 i = 0; i < NUM_INPUTS; i++) {
+            if (input[i] >= R_MOD) {
+                return false;
+            }
+        }
+        for (uint256 i = 0; i < EVALUATIONS; i++) {
+            if (proof[i] >= Q_MOD) {
+                return false;
+            }
+        }
+        for (uint256 i = EVALUATIONS; i < OPENING_PROOFS; i++) {
+            if (proof[i] >= R_MOD) {
+                return false;
+            }
+        }
+        for (uint256 i = OPENING_PROOFS; i < PROOF_LENGTH; i += 3) {
+            if (proof[i] >= Q_MOD || proof[i + 1] >= Q_MOD || proof[i + 2] >= R_MOD) {
+                return false;
+            }
+        }
+        Challenges memory c = replayTranscript(input, proof);
+        return checkOpenings(input, proof, c);
+    }
+
+    // ------------------------------------------------------------------------
+    // Transcript
+    // ------------------------------------------------------------------------
+
+    /// @dev Replay the Fiat-Shamir transcript of
+    /// `Marlin::verify_with_abi_commitments`.
+    function replayTranscript(uint256[] calldata input, uint256[] calldata proof)
+        internal
+        pure
+        returns (Challenges memory c)
+    {
+        bytes32 seed = VK_SEED;
+        seed = absorb(seed, PUBLIC_INPUT_PREFIX);
+        if (NUM_INPUTS > 0) {
+            seed = absorb(seed, encodeFieldElements(input, 0, NUM_INPUTS));
+        }
+
+        bytes memory round = abi.encodePacked(
+            ROUND_1_PREFIX,
+            encodeCommitment(proof, W, false),
+            encodeCommitment(proof, Z_A, false),
+            encodeCommitment(proof, Z_B, false),
+            encodeCommitment(proof, MASK_POLY, false)
+        );
+        seed = absorb(seed, round);
+        seed = absorb(seed, PROVER_MSG_1_PREFIX);
+        (seed, c.alpha) = challengeOutsideH(seed, ALPHA_PREFIX);
+        (seed, c.etaA) = challenge(seed, ETA_A_PREFIX);
+        (seed, c.etaB) = challenge(seed, ETA_B_PREFIX);
+        (seed, c.etaC) = challenge(seed, ETA_C_PREFIX);
+
+        round = abi.encodePacked(
+            ROUND_2_PREFIX,
+            encodeCommitment(proof, T, false),
+            encodeCommitment(proof, G_1, true),
+            encodeCommitment(proof, H_1, false)
+        );
+        seed = absorb(seed, round);
+        seed = absorb(seed, PROVER_MSG_2_PREFIX);
+        (seed, c.beta) = challengeOutsideH(seed, BETA_PREFIX);
+
+        round = abi.encodePacked(
+            ROUND_3_PREFIX,
+            encodeCommitment(proof, G_2, true),
+            encodeCommitment(proof, H_2, false)
+        );
+        seed = absorb(seed, round);
+        seed = absorb(seed, PROVER_MSG_3_PREFIX);
+        (seed, c.gamma) = challenge(seed, GAMMA_PREFIX);
+
+        seed = absorb(seed, EVALUATIONS_PREFIX);
+        seed = absorb(seed, encodeFieldElements(proof, EVALUATIONS, OPENING_PROOFS - EVALUATIONS));
+        (seed, c.xi) = challenge(seed, OPENING_PREFIX);
+
+        // The randomizer of the batched pairing check has to depend on the
+        // opening proofs, which the transcript does not cover.
+        bytes memory openings = abi.encodePacked(
+            proof[PROOF_AT_BETA],
+            proof[PROOF_AT_BETA + 1],
+            proof[PROOF_AT_BETA + 2],
+            proof[PROOF_AT_GAMMA],
+            proof[PROOF_AT_GAMMA + 1],
+            proof[PROOF_AT_GAMMA + 2]
+        );
+        c.rho = uint256(keccak256(abi.encodePacked(seed, openings))) >> 128;
+    }
+
+    /// @dev Set `seed` to `keccak256(data || seed)`.
+    function absorb(bytes32 seed, bytes memory data) internal pure returns (bytes32) {
+        return keccak256(abi.encodePacked(data, seed));
+    }
+
+    /// @dev Absorb `prefix`, which ends with the number of challenges, and
+    /// squeeze a challenge.
+    function challenge(bytes32 seed, bytes memory prefix) internal pure returns (bytes32, uint256) {
+        seed = absorb(seed, prefix);
+        return (seed, uint256(keccak256(abi.encodePacked(seed, uint64(0)))) % R_MOD);
+    }
+
+    /// @dev Squeeze challenges until one lies outside of the domain `H`.
+    function challengeOutsideH(bytes32 seed, bytes memory prefix) internal pure returns (bytes32, uint256) {
+        uint256 x;
+        while (true) {
+            (seed, x) = challenge(seed, prefix);
+            if (vanishing(x, LOG_H_SIZE) != 0) {
+                break;
+            }
+        }
+        return (seed, x);
+    }
+
+    /// @dev The encoding of `len` field elements of `elems`, starting at
+    /// `start`.
+    function encodeFieldElements(uint256[] calldata elems, uint256 start, uint256 len)
+        internal
+        pure
+        returns (bytes memory encoded)
+    {
+        for (uint256 i = start; i < start + len; i++) {
+            encoded = abi.encodePacked(encoded, elems[i]);
+        }
+    }
+
+    /// @dev The encoding of the commitment at `proof[i]`: its coordinates,
+    /// and a flag followed by the coordinates of its shifted commitment at
+    /// `proof[i + 2]` if `shifted` is set, or by the point at infinity.
+    function encodeCommitment(uint256[] calldata proof, uint256 i, bool shifted)
+        internal
+        pure
+        returns (bytes memory)
+    {
+        if (shifted) {
+            return abi.encodePacked(proof[i], proof[i + 1], uint256(1), proof[i + 2], proof[i + 3]);
+        }
+        return abi.encodePacked(proof[i], proof[i + 1], uint256(0), uint256(0), uint256(0));
+    }
+
+    // ------------------------------------------------------------------------
+    // Linear combinations and the batched KZG check
+    // ------------------------------------------------------------------------
+
+    /// @dev Check the openings of the linear combinations of
+    /// `AHPForR1CS::construct_linear_combinations` with a single pairing
+    /// check, as `MarlinKZG10::check_combinations` does.
+    function checkOpenings(uint256[] calldata input, uint256[] calldata proof, Challenges memory c)
+        internal
+        view
+        returns (bool)
+    {
+        (G1Point memory atBeta, uint256 evalAtBeta) = combinedAtBeta(input, proof, c);
+        (G1Point memory atGamma, uint256 evalAtGamma) = combinedAtGamma(proof, c);
+
+        // e(-(W_beta + rho W_gamma), beta H)
+        //   * e(C_beta + beta W_beta + rho (C_gamma + gamma W_gamma)
+        //       - (v_beta + rho v_gamma) G - (r_beta + rho r_gamma) gamma G, H) = 1
+        G1Point memory wBeta = point(proof, PROOF_AT_BETA);
+        G1Point memory wGamma = point(proof, PROOF_AT_GAMMA);
+        G1Point memory totalC = add(atBeta, mul(wBeta, c.beta));
+        totalC = add(totalC, mul(add(atGamma, mul(wGamma, c.gamma)), c.rho));
+        uint256 scalar = addmod(evalAtBeta, mulmod(c.rho, evalAtGamma, R_MOD), R_MOD);
+        totalC = add(totalC, mul(G1Point(G_X, G_Y), R_MOD - scalar));
+        scalar = addmod(proof[PROOF_AT_BETA + 2], mulmod(c.rho, proof[PROOF_AT_GAMMA + 2], R_MOD), R_MOD);
+        totalC = add(totalC, mul(G1Point(GAMMA_G_X, GAMMA_G_Y), R_MOD - scalar));
+        return pairingCheck(negate(add(wBeta, mul(wGamma, c.rho))), totalC);
+    }
+
+    /// @dev The combination of the commitments and evaluations opened at
+    /// `beta`, in the order of their labels: `g_1` with its degree bound,
+    /// `outer_sumcheck`, `t` and `z_b`.
+    function combinedAtBeta(uint256[] calldata input, uint256[] calldata proof, Challenges memory c)
+        internal
+        view
+        returns (G1Point memory comm, uint256 eval)
+    {
+        comm = degreeBounded(proof, G_1, G_1_AT_BETA, G1Point(G_1_SHIFT_X, G_1_SHIFT_Y), c.xi);
+        eval = proof[G_1_AT_BETA];
+
+        uint256 xiPower = mulmod(c.xi, c.xi, R_MOD);
+        (G1Point memory outer, uint256 outerEval) = outerSumcheck(input, proof, c);
+        comm = add(comm, mul(outer, xiPower));
+        eval = addmod(eval, mulmod(outerEval, xiPower, R_MOD), R_MOD);
+
+        xiPower = mulmod(xiPower, c.xi, R_MOD);
+        comm = add(comm, mul(point(proof, T), xiPower));
+        eval = addmod(eval, mulmod(proof[T_AT_BETA], xiPower, R_MOD), R_MOD);
+
+        xiPower = mulmod(xiPower, c.xi, R_MOD);
+        comm = add(comm, mul(point(proof, Z_B), xiPower));
+        eval = addmod(eval, mulmod(proof[Z_B_AT_BETA], xiPower, R_MOD), R_MOD);
+    }
+
+    /// @dev The combination of the commitments and evaluations opened at
+    /// `gamma`, in the order of their labels: `g_2` with its degree bound and
+    /// `inner_sumcheck`.
+    function combinedAtGamma(uint256[] calldata proof, Challenges memory c)
+        internal
+        view
+        returns (G1Point memory comm, uint256 eval)
+    {
+        comm = degreeBounded(proof, G_2, G_2_AT_GAMMA, G1Point(G_2_SHIFT_X, G_2_SHIFT_Y), c.xi);
+        uint256 xi2 = mulmod(c.xi, c.xi, R_MOD);
+        (G1Point memory inner, uint256 innerEval) = innerSumcheck(proof, c);
+        comm = add(comm, mul(inner, xi2));
+        eval = addmod(proof[G_2_AT_GAMMA], mulmod(innerEval, xi2, R_MOD), R_MOD);
+    }
+
+    /// @dev The commitment and evaluation of the linear combination
+    /// `outer_sumcheck`, whose constant terms are moved to its evaluation:
+    ///   mask_poly + r (eta_a + eta_c z_b) z_a - t s_C v_X w - v_H h_1
+    ///   + r eta_b z_b - t s_C x(beta) - beta g_1(beta)
+    /// where `r = r(alpha, beta) s_R` and `s_R`, `s_C` select the constraint
+    /// and variable domains within `H`.
+    function outerSumcheck(uint256[] calldata input, uint256[] calldata proof, Challenges memory c)
+        internal
+        view
+        returns (G1Point memory comm, uint256 eval)
+    {
+        uint256 r = rAlphaBeta(c.alpha, c.beta);
+        uint256 tSelector = mulmod(proof[T_AT_BETA], selector(c.beta, LOG_VARIABLE_DOMAIN_SIZE), R_MOD);
+
+        comm = point(proof, MASK_POLY);
+        uint256 scalar = mulmod(r, addmod(c.etaA, mulmod(c.etaC, proof[Z_B_AT_BETA], R_MOD), R_MOD), R_MOD);
+        comm = add(comm, mul(point(proof, Z_A), scalar));
+        scalar = mulmod(tSelector, vanishing(c.beta, LOG_X_SIZE), R_MOD);
+        comm = add(comm, mul(point(proof, W), R_MOD - scalar));
+        comm = add(comm, mul(point(proof, H_1), R_MOD - vanishing(c.beta, LOG_H_SIZE)));
+
+        scalar = mulmod(r, mulmod(c.etaB, proof[Z_B_AT_BETA], R_MOD), R_MOD);
+        scalar = addmod(scalar, R_MOD - mulmod(tSelector, inputAtBeta(input, c.beta), R_MOD), R_MOD);
+        scalar = addmod(scalar, R_MOD - mulmod(c.beta, proof[G_1_AT_BETA], R_MOD), R_MOD);
+        eval = (R_MOD - scalar) % R_MOD;
+    }
+
+    /// @dev The unnormalized bivariate Lagrange polynomial of the constraint
+    /// domain at `(alpha, beta)`, times the selector of the constraint domain
+    /// at `beta`.
+    function rAlphaBeta(uint256 alpha, uint256 beta) internal view returns (uint256 r) {
+        if (alpha != beta) {
+            r = addmod(
+                vanishing(alpha, LOG_CONSTRAINT_DOMAIN_SIZE),
+                R_MOD - vanishing(beta, LOG_CONSTRAINT_DOMAIN_SIZE),
+                R_MOD
+            );
+            r = mulmod(r, inverse(addmod(alpha, R_MOD - beta, R_MOD)), R_MOD);
+        } else {
+            uint256 size = 1 << LOG_CONSTRAINT_DOMAIN_SIZE;
+            r = mulmod(size, expmod(alpha, size - 1), R_MOD);
+        }
+        r = mulmod(r, selector(beta, LOG_CONSTRAINT_DOMAIN_SIZE), R_MOD);
+    }
+
+    /// @dev The evaluation at `beta` of the polynomial `x` that interpolates
+    /// the formatted public input `(1, input)` over the domain `X`.
+    function inputAtBeta(uint256[] calldata input, uint256 beta) internal view returns (uint256 xAtBeta) {
+        // L_i(beta) = v_X(beta) omega^i / (|X| (beta - omega^i))
+        uint256 scale = mulmod(vanishing(beta, LOG_X_SIZE), X_SIZE_INV, R_MOD);
+        uint256 omega = 1;
+        for (uint256 i = 0; i <= NUM_INPUTS; i++) {
+            uint256 lagrange = mulmod(
+                mulmod(scale, omega, R_MOD),
+                inverse(addmod(beta, R_MOD - omega, R_MOD)),
+                R_MOD
+            );
+            xAtBeta = addmod(xAtBeta, mulmod(lagrange, i == 0 ? 1 : input[i - 1], R_MOD), R_MOD);
+            omega = mulmod(omega, X_GENERATOR, R_MOD);
+        }
+    }
+
+    /// @dev The commitment and evaluation of the linear combination
+    /// `inner_sumcheck`, whose constant term is moved to its evaluation:
+    ///   v_R(alpha) v_C(beta) sum_M eta_M val_M - v_K(gamma) h_2
+    ///   - (beta alpha - alpha row - beta col + row_col) s
+    /// where `s = gamma g_2(gamma) + t(beta) / |K|`.
+    function innerSumcheck(uint256[] calldata proof, Challenges memory c)
+        internal
+        view
+        returns (G1Point memory comm, uint256 eval)
+    {
+        uint256 scalar = mulmod(
+            vanishing(c.alpha, LOG_CONSTRAINT_DOMAIN_SIZE),
+            vanishing(c.beta, LOG_VARIABLE_DOMAIN_SIZE),
+            R_MOD
+        );
+        comm = mul(G1Point(A_VAL_X, A_VAL_Y), mulmod(scalar, c.etaA, R_MOD));
+        comm = add(comm, mul(G1Point(B_VAL_X, B_VAL_Y), mulmod(scalar, c.etaB, R_MOD)));
+        comm = add(comm, mul(G1Point(C_VAL_X, C_VAL_Y), mulmod(scalar, c.etaC, R_MOD)));
+        comm = add(comm, mul(point(proof, H_2), R_MOD - vanishing(c.gamma, LOG_K_SIZE)));
+
+        uint256 s = addmod(
+            mulmod(c.gamma, proof[G_2_AT_GAMMA], R_MOD),
+            mulmod(proof[T_AT_BETA], K_SIZE_INV, R_MOD),
+            R_MOD
+        );
+        comm = add(comm, mul(G1Point(ROW_X, ROW_Y), mulmod(c.alpha, s, R_MOD)));
+        comm = add(comm, mul(G1Point(COL_X, COL_Y), mulmod(c.beta, s, R_MOD)));
+        comm = add(comm, mul(G1Point(ROW_COL_X, ROW_COL_Y), R_MOD - s));
+        eval = mulmod(mulmod(c.beta, c.alpha, R_MOD), s, R_MOD);
+    }
+
+    /// @dev `C + xi (C' - v S)` for the commitment `C` at `proof[i]`, its
+    /// shifted commitment `C'`, its evaluation `v` at `proof[e]` and the shift
+    /// power `S` of its degree bound.
+    function degreeBounded(uint256[] calldata proof, uint256 i, uint256 e, G1Point memory shift, uint256 xi)
+        internal
+        view
+        returns (G1Point memory)
+    {
+        G1Point memory adjusted = add(point(proof, i + 2), mul(shift, R_MOD - proof[e]));
+        return add(point(proof, i), mul(adjusted, xi));
+    }
+
+    // ------------------------------------------------------------------------
+    // Field and group arithmetic
+    // ------------------------------------------------------------------------
+
+    /// @dev `x^n - 1` for `n = 2^logN`, the vanishing polynomial of the
+    /// subgroup of `n` elements.
+    function vanishing(uint256 x, uint256 logN) internal pure returns (uint256) {
+        for (uint256 i = 0; i < logN; i++) {
+            x = mulmod(x, x, R_MOD);
+        }
+        return addmod(x, R_MOD - 1, R_MOD);
+    }
+
+    /// @dev The selector of the subgroup of `2^logN` elements within `H` at
+    /// `x`: `|D| v_H(x) / (|H| v_D(x))`, or one if it is all of `H`.
+    function selector(uint256 x, uint256 logN) internal view returns (uint256) {
+        if (logN == LOG_H_SIZE) {
+            return 1;
+        }
+        uint256 numerator = mulmod(1 << logN, vanishing(x, LOG_H_SIZE), R_MOD);
+        uint256 denominator = mulmod(1 << LOG_H_SIZE, vanishing(x, logN), R_MOD);
+        return mulmod(numerator, inverse(denominator), R_MOD);
+    }
+
+    function inverse(uint256 x) internal view returns (uint256) {
+        require(x != 0, "inverse of zero");
+        return expmod(x, R_MOD - 2);
+    }
+
+    function expmod(uint256 base, uint256 exponent) internal view returns (uint256 result) {
+        bytes memory input = abi.encodePacked(uint256(32), uint256(32), uint256(32), base, exponent, R_MOD);
+        (bool success, bytes memory output) = address(0x05).staticcall(input);
+        require(success, "modexp failed");
+        result = abi.decode(output, (uint256));
+    }
+
+    function point(uint256[] calldata proof, uint256 i) internal pure returns (G1Point memory) {
+        return G1Point(proof[i], proof[i + 1]);
+    }
+
+    function add(G1Point memory p, G1Point memory q) internal view returns (G1Point memory r) {
+        (bool success, bytes memory output) = address(0x06).staticcall(abi.encode(p.x, p.y, q.x, q.y));
+        require(success, "ecAdd failed");
+        (r.x, r.y) = abi.decode(output, (uint256, uint256));
+    }
+
+    function mul(G1Point memory p, uint256 s) internal view returns (G1Point memory r) {
+        (bool success, bytes memory output) = address(0x07).staticcall(abi.encode(p.x, p.y, s));
+        require(success, "ecMul failed");
+        (r.x, r.y) = abi.decode(output, (uint256, uint256));
+    }
+
+    function negate(G1Point memory p) internal pure returns (G1Point memory) {
+        if (p.x == 0 && p.y == 0) {
+            return p;
+        }
+        return G1Point(p.x, Q_MOD - p.y);
+    }
+
+    /// @dev Whether `e(a, beta H) e(b, H) = 1`.
+    function pairingCheck(G1Point memory a, G1Point memory b) internal view returns (bool) {
+        bytes memory input = abi.encodePacked(
+            abi.encode(a.x, a.y, BETA_H_X_IM, BETA_H_X_RE, BETA_H_Y_IM, BETA_H_Y_RE),
+            abi.encode(b.x, b.y, H_X_IM, H_X_RE, H_Y_IM, H_Y_RE)
+        );
+        (bool success, bytes memory output) = address(0x08).staticcall(input);
+        return success && output.length == 32 && abi.decode(output, (uint256)) == 1;
+    }
+}
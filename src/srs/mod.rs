@@ -0,0 +1,414 @@
+use crate::rng::{FiatShamirRng, Transcript};
+use crate::{Error, Marlin, UniversalSRS, Vec};
+use ark_ec::{msm::VariableBaseMSM, AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{to_bytes, Field, One, PrimeField, UniformRand, Zero};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::{kzg10, marlin_pc::MarlinKZG10, sonic_pc::SonicKZG10, PolynomialCommitment};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    rand::RngCore,
+};
+use derivative::Derivative;
+
+mod powers_of_tau;
+pub use powers_of_tau::*;
+
+/// A polynomial commitment scheme whose universal parameters can be updated by
+/// any number of parties in turn, so that they are sound as long as one of
+/// the parties discards its secret.
+pub trait UpdatablePC<F: PrimeField>: PolynomialCommitment<F, DensePolynomial<F>> {
+    /// The proof that an update was computed correctly from the parameters
+    /// it updates, by a party that knows its secret.
+    type UpdateProof: Clone + CanonicalSerialize + CanonicalDeserialize;
+
+    /// Mix fresh secret randomness from `rng` into `pp`. The secret is not
+    /// retained.
+    fn update_universal_params<FS: FiatShamirRng, R: RngCore>(
+        pp: &Self::UniversalParams,
+        rng: &mut R,
+    ) -> (Self::UniversalParams, Self::UpdateProof);
+
+    /// Check that `pp` results from applying `updates`, in order, to
+    /// `initial_pp`, and that it is well formed.
+    fn verify_universal_params_updates<FS: FiatShamirRng, R: RngCore>(
+        initial_pp: &Self::UniversalParams,
+        updates: &[Self::UpdateProof],
+        pp: &Self::UniversalParams,
+        rng: &mut R,
+    ) -> bool;
+
+    /// Check with randomized pairing checks that `pp` is well formed: that its
+    /// powers are consecutive powers of one secret, consistent with its G2
+    /// elements, and that it has every power that `trim` takes for degree
+    /// bounds.
+    fn verify_universal_params<R: RngCore>(pp: &Self::UniversalParams, rng: &mut R) -> bool;
+}
+
+/// The proof of an update of KZG10 universal parameters, which multiplies
+/// their secret `beta` by `tau` and their secret `gamma` by `rho`.
+#[derive(Derivative, CanonicalSerialize, CanonicalDeserialize)]
+#[derivative(Clone(bound = ""), Debug(bound = ""))]
+pub struct KZG10UpdateProof<E: PairingEngine> {
+    /// `beta * G` after the update.
+    pub beta_g: E::G1Affine,
+    /// `gamma * G` after the update.
+    pub gamma_g: E::G1Affine,
+    /// `tau * H`.
+    pub tau_h: E::G2Affine,
+    /// `rho * H`.
+    pub rho_h: E::G2Affine,
+    /// The commitments of the Schnorr proofs of knowledge of `tau` and `rho`.
+    pub pok_commitments: (E::G2Affine, E::G2Affine),
+    /// The responses of the Schnorr proofs of knowledge of `tau` and `rho`.
+    pub pok_responses: (E::Fr, E::Fr),
+}
+
+/// The label of the transcript from which proofs of knowledge of update
+/// secrets draw their challenges.
+const UPDATE_PROTOCOL_NAME: &[u8] = b"KZG10-SRS-UPDATE";
+
+/// The challenge of the proofs of knowledge in `proof`, which binds them to
+/// the parameters being updated and to the result of the update.
+fn pok_challenge<E: PairingEngine, FS: FiatShamirRng>(
+    prev_beta_g: &E::G1Affine,
+    prev_gamma_g: &E::G1Affine,
+    proof: &KZG10UpdateProof<E>,
+) -> E::Fr {
+    let mut transcript = Transcript::<FS>::new(UPDATE_PROTOCOL_NAME);
+    transcript.append_message(b"previous beta g", prev_beta_g);
+    transcript.append_message(b"previous gamma g", prev_gamma_g);
+    transcript.append_message(b"beta g", &proof.beta_g);
+    transcript.append_message(b"gamma g", &proof.gamma_g);
+    transcript.append_message(b"tau h", &proof.tau_h);
+    transcript.append_message(b"rho h", &proof.rho_h);
+    transcript.append_message(b"tau pok commitment", &proof.pok_commitments.0);
+    transcript.append_message(b"rho pok commitment", &proof.pok_commitments.1);
+    transcript.challenge_128_bits_field_element(b"pok challenge")
+}
+
+/// Multiply the `i`-th element of `elems` by `scalar^i`, or by
+/// `scalar^i * factor` if `factor` is given.
+fn scale_by_powers<G: AffineCurve>(
+    elems: impl Iterator<Item = (usize, G)>,
+    scalar: G::ScalarField,
+    factor: Option<G::ScalarField>,
+) -> Vec<(usize, G)> {
+    let factor = factor.unwrap_or_else(G::ScalarField::one);
+    let (indices, scaled): (Vec<_>, Vec<_>) = elems
+        .map(|(i, elem)| (i, elem.mul(scalar.pow([i as u64]) * factor)))
+        .unzip();
+    indices
+        .into_iter()
+        .zip(G::Projective::batch_normalization_into_affine(&scaled))
+        .collect()
+}
+
+fn update_kzg10_params<E: PairingEngine, FS: FiatShamirRng, R: RngCore>(
+    pp: &kzg10::UniversalParams<E>,
+    rng: &mut R,
+) -> (kzg10::UniversalParams<E>, KZG10UpdateProof<E>) {
+    let update_time = start_timer!(|| "Updating KZG10 universal parameters");
+    let mut nonzero_rand = || loop {
+        let s = E::Fr::rand(rng);
+        if !s.is_zero() {
+            break s;
+        }
+    };
+    let (tau, rho) = (nonzero_rand(), nonzero_rand());
+    let (tau_r, rho_r) = (nonzero_rand(), nonzero_rand());
+    let tau_inv = tau.inverse().unwrap();
+
+    let powers_of_g = scale_by_powers(pp.powers_of_g.iter().copied().enumerate(), tau, None)
+        .into_iter()
+        .map(|(_, g)| g)
+        .collect::<Vec<_>>();
+    let powers_of_gamma_g = scale_by_powers(
+        pp.powers_of_gamma_g.iter().map(|(i, g)| (*i, *g)),
+        tau,
+        Some(rho),
+    )
+    .into_iter()
+    .collect::<BTreeMap<_, _>>();
+    let neg_powers_of_h = scale_by_powers(
+        pp.neg_powers_of_h.iter().map(|(i, h)| (*i, *h)),
+        tau_inv,
+        None,
+    )
+    .into_iter()
+    .collect::<BTreeMap<_, _>>();
+    let beta_h = pp.beta_h.mul(tau).into_affine();
+
+    let mut proof = KZG10UpdateProof {
+        beta_g: powers_of_g[1],
+        gamma_g: powers_of_gamma_g[&0],
+        tau_h: pp.h.mul(tau).into_affine(),
+        rho_h: pp.h.mul(rho).into_affine(),
+        pok_commitments: (pp.h.mul(tau_r).into_affine(), pp.h.mul(rho_r).into_affine()),
+        pok_responses: (E::Fr::zero(), E::Fr::zero()),
+    };
+    let c = pok_challenge::<E, FS>(&pp.powers_of_g[1], &pp.powers_of_gamma_g[&0], &proof);
+    proof.pok_responses = (tau_r + c * tau, rho_r + c * rho);
+
+    let new_pp = kzg10::UniversalParams {
+        powers_of_g,
+        powers_of_gamma_g,
+        h: pp.h,
+        beta_h,
+        neg_powers_of_h,
+        prepared_h: pp.prepared_h.clone(),
+        prepared_beta_h: beta_h.into(),
+    };
+    end_timer!(update_time);
+    (new_pp, proof)
+}
+
+/// Whether `lhs` and `rhs` have the same pairing.
+fn pairings_match<E: PairingEngine>(
+    lhs: (E::G1Affine, E::G2Affine),
+    rhs: (E::G1Affine, E::G2Affine),
+) -> bool {
+    E::product_of_pairings(&[
+        (lhs.0.into(), lhs.1.into()),
+        ((-rhs.0).into(), rhs.1.into()),
+    ])
+    .is_one()
+}
+
+/// Whether `prepared` is the preparation of `h` for pairings.
+fn is_prepared<E: PairingEngine>(prepared: &E::G2Prepared, h: E::G2Affine) -> bool {
+    to_bytes![prepared].ok() == to_bytes![E::G2Prepared::from(h)].ok()
+}
+
+/// A random linear combination of `elems`, with the coefficients `coeffs`.
+fn combine<G: AffineCurve>(elems: &[G], coeffs: &[G::ScalarField]) -> G {
+    let coeffs = coeffs.iter().map(|c| c.into_repr()).collect::<Vec<_>>();
+    VariableBaseMSM::multi_scalar_mul(elems, &coeffs).into_affine()
+}
+
+/// The values of `map` if its keys are `0, 1, ..., map.len() - 1`.
+fn consecutive_values<T: Copy>(map: &BTreeMap<usize, T>) -> Option<Vec<T>> {
+    map.iter()
+        .enumerate()
+        .map(|(i, (key, value))| (i == *key).then_some(*value))
+        .collect()
+}
+
+/// Whether `pp` has as many powers as `kzg10::KZG10::setup` produces: one more
+/// power of `gamma * G` than of `G`, and, if `negative_g2_powers` is set, as
+/// many negative powers of `H` as powers of `G`, which `SonicKZG10` takes for
+/// its degree bounds.
+fn kzg10_params_have_all_powers<E: PairingEngine>(
+    pp: &kzg10::UniversalParams<E>,
+    negative_g2_powers: bool,
+) -> bool {
+    let num_neg_powers_of_h = if negative_g2_powers {
+        pp.powers_of_g.len()
+    } else {
+        0
+    };
+    pp.powers_of_gamma_g.len() == pp.powers_of_g.len() + 1
+        && pp.neg_powers_of_h.len() == num_neg_powers_of_h
+}
+
+/// Check with randomized pairing checks that the powers in `pp` are
+/// consecutive powers of the secret of `pp.beta_h`, and that its prepared G2
+/// elements are those of `pp.h` and `pp.beta_h`. Neither secret may be zero,
+/// and `gamma` may not be one.
+fn kzg10_params_are_well_formed<E: PairingEngine, R: RngCore>(
+    pp: &kzg10::UniversalParams<E>,
+    rng: &mut R,
+) -> bool {
+    let check_time = start_timer!(|| "Checking KZG10 universal parameters");
+    let powers_of_gamma_g = consecutive_values(&pp.powers_of_gamma_g);
+    let neg_powers_of_h = consecutive_values(&pp.neg_powers_of_h);
+    let (powers_of_gamma_g, neg_powers_of_h) = match (powers_of_gamma_g, neg_powers_of_h) {
+        (Some(gamma_g), Some(neg_h)) => (gamma_g, neg_h),
+        _ => return false,
+    };
+    let g = &pp.powers_of_g;
+    if g.len() < 2
+        || g[0].is_zero()
+        || pp.h.is_zero()
+        || pp.beta_h.is_zero()
+        || powers_of_gamma_g.len() < 2
+        || powers_of_gamma_g[0].is_zero()
+        || powers_of_gamma_g[0] == g[0]
+        || neg_powers_of_h.first().map_or(false, |h| *h != pp.h)
+        || !is_prepared::<E>(&pp.prepared_h, pp.h)
+        || !is_prepared::<E>(&pp.prepared_beta_h, pp.beta_h)
+    {
+        return false;
+    }
+
+    // For random `r_i`, `sum_i r_i * beta^(i + 1) * G` must pair with `H` as
+    // `sum_i r_i * beta^i * G` pairs with `beta * H`. This holds for every
+    // consecutive pair of powers at once, except with negligible probability.
+    let mut rand_coeffs = |n: usize| {
+        (0..n)
+            .map(|_| E::Fr::from(u128::rand(rng)))
+            .collect::<Vec<_>>()
+    };
+    let shifted_pairs_match = |powers: &[E::G1Affine], r: &[E::Fr]| {
+        let n = powers.len() - 1;
+        pairings_match::<E>(
+            (combine(&powers[1..], r), pp.h),
+            (combine(&powers[..n], r), pp.beta_h),
+        )
+    };
+    let r = rand_coeffs(g.len() - 1);
+    let mut well_formed = shifted_pairs_match(g, &r);
+    let r = rand_coeffs(powers_of_gamma_g.len() - 1);
+    well_formed &= shifted_pairs_match(&powers_of_gamma_g, &r);
+    if neg_powers_of_h.len() > 1 {
+        // `beta^(-i) * H` pairs with `G` as `beta^(-(i + 1)) * H` pairs with
+        // `beta * G`.
+        let n = neg_powers_of_h.len() - 1;
+        let r = rand_coeffs(n);
+        well_formed &= pairings_match::<E>(
+            (g[0], combine(&neg_powers_of_h[..n], &r)),
+            (g[1], combine(&neg_powers_of_h[1..], &r)),
+        );
+    }
+    end_timer!(check_time);
+    well_formed
+}
+
+fn verify_kzg10_updates<E: PairingEngine, FS: FiatShamirRng, R: RngCore>(
+    initial_pp: &kzg10::UniversalParams<E>,
+    updates: &[KZG10UpdateProof<E>],
+    pp: &kzg10::UniversalParams<E>,
+    rng: &mut R,
+) -> bool {
+    let verify_time = start_timer!(|| format!("Verifying {} KZG10 updates", updates.len()));
+    let h = pp.h;
+    let (mut beta_g, mut gamma_g) = match (
+        initial_pp.powers_of_g.get(1),
+        initial_pp.powers_of_gamma_g.get(&0),
+    ) {
+        (Some(beta_g), Some(gamma_g)) => (*beta_g, *gamma_g),
+        _ => return false,
+    };
+    for update in updates {
+        let c = pok_challenge::<E, FS>(&beta_g, &gamma_g, update);
+        let (tau_r_h, rho_r_h) = update.pok_commitments;
+        let (tau_z, rho_z) = update.pok_responses;
+        let valid = !update.tau_h.is_zero()
+            && !update.rho_h.is_zero()
+            && h.mul(tau_z) == tau_r_h.into_projective() + update.tau_h.mul(c)
+            && h.mul(rho_z) == rho_r_h.into_projective() + update.rho_h.mul(c)
+            && pairings_match::<E>((update.beta_g, h), (beta_g, update.tau_h))
+            && pairings_match::<E>((update.gamma_g, h), (gamma_g, update.rho_h));
+        if !valid {
+            end_timer!(verify_time);
+            return false;
+        }
+        beta_g = update.beta_g;
+        gamma_g = update.gamma_g;
+    }
+
+    let valid = h == initial_pp.h
+        && pp.powers_of_g.len() == initial_pp.powers_of_g.len()
+        && pp.powers_of_gamma_g.len() == initial_pp.powers_of_gamma_g.len()
+        && pp.neg_powers_of_h.len() == initial_pp.neg_powers_of_h.len()
+        && pp.powers_of_g[0] == initial_pp.powers_of_g[0]
+        && pp.powers_of_g[1] == beta_g
+        && pp.powers_of_gamma_g.get(&0) == Some(&gamma_g)
+        && kzg10_params_are_well_formed(pp, rng);
+    end_timer!(verify_time);
+    valid
+}
+
+impl<E: PairingEngine> UpdatablePC<E::Fr> for MarlinKZG10<E, DensePolynomial<E::Fr>> {
+    type UpdateProof = KZG10UpdateProof<E>;
+
+    fn update_universal_params<FS: FiatShamirRng, R: RngCore>(
+        pp: &Self::UniversalParams,
+        rng: &mut R,
+    ) -> (Self::UniversalParams, Self::UpdateProof) {
+        update_kzg10_params::<E, FS, R>(pp, rng)
+    }
+
+    fn verify_universal_params_updates<FS: FiatShamirRng, R: RngCore>(
+        initial_pp: &Self::UniversalParams,
+        updates: &[Self::UpdateProof],
+        pp: &Self::UniversalParams,
+        rng: &mut R,
+    ) -> bool {
+        verify_kzg10_updates::<E, FS, R>(initial_pp, updates, pp, rng)
+    }
+
+    fn verify_universal_params<R: RngCore>(pp: &Self::UniversalParams, rng: &mut R) -> bool {
+        kzg10_params_have_all_powers(pp, false) && kzg10_params_are_well_formed(pp, rng)
+    }
+}
+
+impl<E: PairingEngine> UpdatablePC<E::Fr> for SonicKZG10<E, DensePolynomial<E::Fr>> {
+    type UpdateProof = KZG10UpdateProof<E>;
+
+    fn update_universal_params<FS: FiatShamirRng, R: RngCore>(
+        pp: &Self::UniversalParams,
+        rng: &mut R,
+    ) -> (Self::UniversalParams, Self::UpdateProof) {
+        update_kzg10_params::<E, FS, R>(pp, rng)
+    }
+
+    fn verify_universal_params_updates<FS: FiatShamirRng, R: RngCore>(
+        initial_pp: &Self::UniversalParams,
+        updates: &[Self::UpdateProof],
+        pp: &Self::UniversalParams,
+        rng: &mut R,
+    ) -> bool {
+        verify_kzg10_updates::<E, FS, R>(initial_pp, updates, pp, rng)
+    }
+
+    fn verify_universal_params<R: RngCore>(pp: &Self::UniversalParams, rng: &mut R) -> bool {
+        kzg10_params_have_all_powers(pp, true) && kzg10_params_are_well_formed(pp, rng)
+    }
+}
+
+impl<F: PrimeField, PC: UpdatablePC<F>, FS: FiatShamirRng> Marlin<F, PC, FS> {
+    /// Contribute to a setup ceremony: mix fresh secret randomness from `rng`
+    /// into `srs`, and return the updated SRS with a proof of the update.
+    ///
+    /// The SRS output by `Marlin::universal_setup` can start a ceremony. The
+    /// result of the ceremony is sound as long as one of its contributors
+    /// used a secret `rng`, even if every other party knows its trapdoor.
+    pub fn update_universal_srs<R: RngCore>(
+        srs: &UniversalSRS<F, PC>,
+        rng: &mut R,
+    ) -> (UniversalSRS<F, PC>, PC::UpdateProof) {
+        PC::update_universal_params::<FS, R>(srs, rng)
+    }
+
+    /// Verify that `srs` is the result of a ceremony that started from
+    /// `initial_srs` and applied `updates` in order, each of which is proven
+    /// by a party that knew its secret. `rng` is used for the randomized
+    /// well-formedness check of `srs`.
+    pub fn verify_universal_srs_updates<R: RngCore>(
+        initial_srs: &UniversalSRS<F, PC>,
+        updates: &[PC::UpdateProof],
+        srs: &UniversalSRS<F, PC>,
+        rng: &mut R,
+    ) -> bool {
+        PC::verify_universal_params_updates::<FS, R>(initial_srs, updates, srs, rng)
+    }
+
+    /// Check with randomized pairing checks that `srs` is well formed, so that
+    /// the keys that `Marlin::index` derives from it are sound. An SRS from an
+    /// untrusted source should be checked before it is used.
+    pub fn verify_srs<R: RngCore>(
+        srs: &UniversalSRS<F, PC>,
+        rng: &mut R,
+    ) -> Result<(), Error<PC::Error>> {
+        let verify_time = start_timer!(|| "Marlin::VerifySRS");
+        let well_formed = PC::verify_universal_params(srs, rng);
+        end_timer!(verify_time);
+        if well_formed {
+            Ok(())
+        } else {
+            Err(Error::MalformedSRS)
+        }
+    }
+}
@@ -0,0 +1,805 @@
+use super::{kzg10_params_are_well_formed, scale_by_powers};
+use crate::rng::FiatShamirRng;
+#[cfg(feature = "kzg-ceremony")]
+use crate::String;
+use crate::{AHPForR1CS, Error, Marlin, UniversalSRS, Vec};
+use ark_ec::{AffineCurve, PairingEngine};
+use ark_ff::{One, PrimeField, UniformRand, Zero};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::{kzg10, marlin_pc::MarlinKZG10, sonic_pc::SonicKZG10, PolynomialCommitment};
+use ark_std::{
+    collections::BTreeMap,
+    io::{self, Read},
+    rand::RngCore,
+    vec,
+};
+
+/// The ways in which importing a powers-of-tau transcript can fail.
+#[derive(Debug)]
+pub enum PowersOfTauError {
+    /// The transcript could not be read.
+    Io(io::Error),
+    /// The transcript could not be parsed.
+    InvalidFormat,
+    /// A group element is not encoded correctly, is not on the curve or not in
+    /// its prime-order subgroup, or is the identity.
+    InvalidPoint,
+    /// The transcript has fewer powers than the universal parameters need.
+    NotEnoughPowers {
+        /// The number of powers that are needed.
+        needed: usize,
+        /// The number of powers in the transcript.
+        available: usize,
+    },
+    /// The base field of the curve leaves no room for the flags of the
+    /// transcript's point encoding.
+    UnsupportedCurve,
+    /// The powers are not consecutive powers of a single secret.
+    InconsistentPowers,
+    /// The powers of `alpha` are those of `tau`, so that `alpha = 1` and the
+    /// parameters would have no independent `gamma`.
+    DependentAlphaPowers,
+}
+
+impl From<io::Error> for PowersOfTauError {
+    fn from(err: io::Error) -> Self {
+        PowersOfTauError::Io(err)
+    }
+}
+
+/// How the group elements of a powers-of-tau transcript are encoded.
+///
+/// Both encodings write the base field elements of a point big-endian, with
+/// the highest-degree coefficient of extension field elements first, and keep
+/// flags in the most significant bits of the first byte.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PointEncoding {
+    /// Only the `x` coordinate is written, with a flag for the sign of `y`,
+    /// as in perpetual powers-of-tau response files and KZG ceremony
+    /// transcripts.
+    Compressed,
+    /// Both coordinates are written, as in perpetual powers-of-tau challenge
+    /// files.
+    Uncompressed,
+}
+
+/// The consecutive powers of a secret `tau` that a powers-of-tau ceremony
+/// produces, from which universal parameters can be built without trusting
+/// whoever runs `Marlin::universal_setup`.
+#[derive(Clone, Debug)]
+pub struct PowersOfTau<E: PairingEngine> {
+    /// `tau^i * G` for a generator `G` of G1.
+    pub powers_of_g: Vec<E::G1Affine>,
+    /// `tau^i * H` for a generator `H` of G2.
+    pub powers_of_h: Vec<E::G2Affine>,
+    /// `alpha * tau^i * G` for a second secret `alpha`, if the ceremony
+    /// produces one. Without them, a fresh `alpha` is sampled when the
+    /// universal parameters are built.
+    pub alpha_powers_of_g: Option<Vec<E::G1Affine>>,
+}
+
+/// The sizes of the base field elements of `E` and of their encodings.
+fn base_field_size<E: PairingEngine>() -> (usize, usize) {
+    let num_bits = E::Fq::size_in_bits();
+    ((num_bits + 7) / 8, num_bits)
+}
+
+/// Decode a group element from its powers-of-tau encoding, by rewriting it
+/// into the encoding of `ark-serialize`.
+///
+/// When the base field has three spare bits, as for BLS12-381, the flags are
+/// those of the ZCash encoding: whether the point is compressed, whether it is
+/// the identity, and whether `y` is the larger of `y` and `-y`. When it only
+/// has two, as for BN254, the first one is the sign of `y`.
+fn decode_point<E: PairingEngine, G: AffineCurve>(
+    bytes: &[u8],
+    encoding: PointEncoding,
+) -> Result<G, PowersOfTauError> {
+    let (size, num_bits) = base_field_size::<E>();
+    let expected_len = match encoding {
+        PointEncoding::Compressed => G::zero().serialized_size(),
+        PointEncoding::Uncompressed => G::zero().uncompressed_size(),
+    };
+    if bytes.len() != expected_len {
+        return Err(PowersOfTauError::InvalidPoint);
+    }
+    let (is_infinity, is_greatest, flags) = match 8 * size - num_bits {
+        0 | 1 => return Err(PowersOfTauError::UnsupportedCurve),
+        2 => (bytes[0] & 0x40 != 0, bytes[0] & 0x80 != 0, 0xc0),
+        _ => {
+            if (bytes[0] & 0x80 != 0) != (encoding == PointEncoding::Compressed) {
+                return Err(PowersOfTauError::InvalidPoint);
+            }
+            (bytes[0] & 0x40 != 0, bytes[0] & 0x20 != 0, 0xe0)
+        }
+    };
+    if is_infinity {
+        return Err(PowersOfTauError::InvalidPoint);
+    }
+
+    let mut bytes = bytes.to_vec();
+    bytes[0] &= !flags;
+    let num_coords = match encoding {
+        PointEncoding::Compressed => 1,
+        PointEncoding::Uncompressed => 2,
+    };
+    // `ark-serialize` writes the coefficients of each coordinate lowest
+    // degree first, each little-endian.
+    let mut ark_bytes = bytes
+        .chunks(bytes.len() / num_coords)
+        .flat_map(|coord| coord.chunks(size).rev().flat_map(|c| c.iter().rev()))
+        .copied()
+        .collect::<Vec<_>>();
+    let point = match encoding {
+        PointEncoding::Compressed => {
+            if is_greatest {
+                *ark_bytes.last_mut().unwrap() |= 0x80;
+            }
+            G::deserialize(&ark_bytes[..]).map_err(|_| PowersOfTauError::InvalidPoint)?
+        }
+        PointEncoding::Uncompressed => {
+            // The uncompressed deserialization does not check that the point
+            // is on the curve, but recovering it from its `x` coordinate does.
+            let point = G::deserialize_uncompressed(&ark_bytes[..])
+                .map_err(|_| PowersOfTauError::InvalidPoint)?;
+            ark_bytes.clear();
+            point.serialize(&mut ark_bytes).unwrap();
+            let recovered =
+                G::deserialize(&ark_bytes[..]).map_err(|_| PowersOfTauError::InvalidPoint)?;
+            if recovered != point {
+                return Err(PowersOfTauError::InvalidPoint);
+            }
+            point
+        }
+    };
+    Ok(point)
+}
+
+/// Read the first `num` of the `total` group elements of a section of a
+/// powers-of-tau file, and skip the others.
+fn read_points<E: PairingEngine, G: AffineCurve, R: Read>(
+    mut reader: R,
+    num: usize,
+    total: usize,
+    encoding: PointEncoding,
+    num_base_field_elems: usize,
+) -> Result<Vec<G>, PowersOfTauError> {
+    if num > total {
+        return Err(PowersOfTauError::NotEnoughPowers {
+            needed: num,
+            available: total,
+        });
+    }
+    let point_size = match encoding {
+        PointEncoding::Compressed => 1,
+        PointEncoding::Uncompressed => 2,
+    } * num_base_field_elems
+        * base_field_size::<E>().0;
+    let mut bytes = vec![0u8; point_size];
+    let points = (0..num)
+        .map(|_| {
+            reader.read_exact(&mut bytes)?;
+            decode_point::<E, G>(&bytes, encoding)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let skipped = ((total - num) * point_size) as u64;
+    if io::copy(&mut reader.by_ref().take(skipped), &mut io::sink())? != skipped {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(points)
+}
+
+impl<E: PairingEngine> PowersOfTau<E> {
+    /// Read the first `num_g1_powers` powers of G1 and `num_g2_powers` powers
+    /// of G2 from a perpetual powers-of-tau file of a ceremony for
+    /// `2^power` powers. The powers of `alpha` are read along with those of
+    /// G1, with one more of them, as `powers_of_gamma_g` needs.
+    ///
+    /// Response files use the compressed encoding, and challenge files the
+    /// uncompressed one. Both start with a hash of the previous file, after
+    /// which they hold `2^(power + 1) - 1` powers of `tau` in G1, `2^power`
+    /// powers of `tau` in G2, and `2^power` powers of `alpha * tau` and of
+    /// `beta * tau` in G1.
+    pub fn read_ppot<R: Read>(
+        mut reader: R,
+        power: usize,
+        encoding: PointEncoding,
+        num_g1_powers: usize,
+        num_g2_powers: usize,
+    ) -> Result<Self, PowersOfTauError> {
+        let read_time = start_timer!(|| "Reading perpetual powers-of-tau file");
+        let num_powers = 1 << power;
+        let mut hash = [0u8; 64];
+        reader.read_exact(&mut hash)?;
+        let powers_of_g =
+            read_points::<E, _, _>(&mut reader, num_g1_powers, 2 * num_powers - 1, encoding, 1)?;
+        let powers_of_h =
+            read_points::<E, _, _>(&mut reader, num_g2_powers, num_powers, encoding, 2)?;
+        let alpha_powers_of_g =
+            read_points::<E, _, _>(&mut reader, num_g1_powers + 1, num_powers, encoding, 1)?;
+        end_timer!(read_time);
+        Ok(Self {
+            powers_of_g,
+            powers_of_h,
+            alpha_powers_of_g: Some(alpha_powers_of_g),
+        })
+    }
+
+    /// Read the first `num_g1_powers` powers of G1 and `num_g2_powers` powers
+    /// of G2 from the JSON transcript of a KZG ceremony, such as the Ethereum
+    /// one, in which each power is a hex string in the compressed encoding.
+    /// The powers are taken from the first of its transcripts that has enough
+    /// of them.
+    ///
+    /// These ceremonies have a single secret, so that the result has no powers
+    /// of `alpha`, and one more power of G1 must be read for the powers of
+    /// `gamma * G` that are derived from them.
+    #[cfg(feature = "kzg-ceremony")]
+    pub fn from_kzg_ceremony_json(
+        json: &str,
+        num_g1_powers: usize,
+        num_g2_powers: usize,
+    ) -> Result<Self, PowersOfTauError> {
+        let ceremony: ceremony_json::Ceremony =
+            serde_json::from_str(json).map_err(|_| PowersOfTauError::InvalidFormat)?;
+        let powers = ceremony
+            .transcripts
+            .iter()
+            .map(|transcript| &transcript.powers_of_tau)
+            .find(|powers| {
+                powers.g1_powers.len() >= num_g1_powers && powers.g2_powers.len() >= num_g2_powers
+            })
+            .ok_or_else(|| PowersOfTauError::NotEnoughPowers {
+                needed: num_g1_powers,
+                available: ceremony
+                    .transcripts
+                    .iter()
+                    .map(|t| t.powers_of_tau.g1_powers.len())
+                    .max()
+                    .unwrap_or(0),
+            })?;
+        fn decode_hex_points<E: PairingEngine, G: AffineCurve>(
+            points: &[String],
+        ) -> Result<Vec<G>, PowersOfTauError> {
+            points
+                .iter()
+                .map(|point| {
+                    let bytes = ceremony_json::decode_hex(point)?;
+                    decode_point::<E, G>(&bytes, PointEncoding::Compressed)
+                })
+                .collect()
+        }
+        Ok(Self {
+            powers_of_g: decode_hex_points::<E, _>(&powers.g1_powers[..num_g1_powers])?,
+            powers_of_h: decode_hex_points::<E, _>(&powers.g2_powers[..num_g2_powers])?,
+            alpha_powers_of_g: None,
+        })
+    }
+
+    /// Build KZG10 universal parameters for polynomials of degree up to
+    /// `max_degree`, and check them with randomized pairing checks.
+    ///
+    /// If `negative_g2_powers` is set, the G2 generator of the parameters is
+    /// `tau^max_degree * H`, so that the lower powers of `tau` in G2 are its
+    /// negative powers.
+    ///
+    /// The powers of `alpha` are the powers of `gamma * G`. Without them,
+    /// `gamma` is sampled from `rng`, which must then be secret: whoever knows
+    /// `gamma` can open hiding commitments to any value.
+    fn kzg10_params<R: RngCore>(
+        &self,
+        max_degree: usize,
+        negative_g2_powers: bool,
+        rng: &mut R,
+    ) -> Result<kzg10::UniversalParams<E>, PowersOfTauError> {
+        let check_len = |needed: usize, available: usize| {
+            if needed > available {
+                Err(PowersOfTauError::NotEnoughPowers { needed, available })
+            } else {
+                Ok(())
+            }
+        };
+        check_len(max_degree + 1, self.powers_of_g.len())?;
+        let powers_of_gamma_g = match &self.alpha_powers_of_g {
+            Some(alpha_powers_of_g) => {
+                check_len(max_degree + 2, alpha_powers_of_g.len())?;
+                if alpha_powers_of_g[0] == self.powers_of_g[0] {
+                    return Err(PowersOfTauError::DependentAlphaPowers);
+                }
+                alpha_powers_of_g[..max_degree + 2]
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .collect()
+            }
+            None => {
+                check_len(max_degree + 2, self.powers_of_g.len())?;
+                let gamma = loop {
+                    let gamma = E::Fr::rand(rng);
+                    if !gamma.is_zero() && !gamma.is_one() {
+                        break gamma;
+                    }
+                };
+                let powers_of_g = self.powers_of_g[..max_degree + 2].iter().copied();
+                scale_by_powers(powers_of_g.enumerate(), E::Fr::one(), Some(gamma))
+                    .into_iter()
+                    .collect()
+            }
+        };
+        let (h_power, neg_powers_of_h) = if negative_g2_powers {
+            check_len(max_degree + 2, self.powers_of_h.len())?;
+            let neg_powers_of_h = (0..=max_degree)
+                .map(|i| (i, self.powers_of_h[max_degree - i]))
+                .collect();
+            (max_degree, neg_powers_of_h)
+        } else {
+            check_len(2, self.powers_of_h.len())?;
+            (0, BTreeMap::new())
+        };
+
+        let h = self.powers_of_h[h_power];
+        let beta_h = self.powers_of_h[h_power + 1];
+        let pp = kzg10::UniversalParams {
+            powers_of_g: self.powers_of_g[..=max_degree].to_vec(),
+            powers_of_gamma_g,
+            h,
+            beta_h,
+            neg_powers_of_h,
+            prepared_h: h.into(),
+            prepared_beta_h: beta_h.into(),
+        };
+        if kzg10_params_are_well_formed(&pp, rng) {
+            Ok(pp)
+        } else {
+            Err(PowersOfTauError::InconsistentPowers)
+        }
+    }
+}
+
+#[cfg(feature = "kzg-ceremony")]
+mod ceremony_json {
+    use super::PowersOfTauError;
+    use crate::{String, Vec};
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    pub(super) struct Ceremony {
+        pub(super) transcripts: Vec<Transcript>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Transcript {
+        pub(super) powers_of_tau: Powers,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct Powers {
+        #[serde(rename = "G1Powers")]
+        pub(super) g1_powers: Vec<String>,
+        #[serde(rename = "G2Powers")]
+        pub(super) g2_powers: Vec<String>,
+    }
+
+    /// Decode a hex string, with or without a `0x` prefix.
+    pub(super) fn decode_hex(hex: &str) -> Result<Vec<u8>, PowersOfTauError> {
+        let hex = hex.strip_prefix("0x").unwrap_or(hex);
+        if hex.len() % 2 == 1 {
+            return Err(PowersOfTauError::InvalidFormat);
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| PowersOfTauError::InvalidFormat)
+            })
+            .collect()
+    }
+}
+
+/// A KZG-based polynomial commitment scheme whose universal parameters can be
+/// built from the output of a powers-of-tau ceremony.
+pub trait PowersOfTauPC<F: PrimeField>: PolynomialCommitment<F, DensePolynomial<F>> {
+    /// The pairing engine of the commitment scheme.
+    type Engine: PairingEngine<Fr = F>;
+
+    /// The number of powers of `tau` in G2 that universal parameters for
+    /// polynomials of degree up to `max_degree` need.
+    fn num_g2_powers(max_degree: usize) -> usize;
+
+    /// Build universal parameters for polynomials of degree up to
+    /// `max_degree` from `powers`, and check their consistency with
+    /// randomized pairing checks. If `powers` has no powers of `alpha`, the
+    /// secret `gamma` of the parameters is sampled from `rng`.
+    fn universal_params_from_powers_of_tau<R: RngCore>(
+        powers: &PowersOfTau<Self::Engine>,
+        max_degree: usize,
+        rng: &mut R,
+    ) -> Result<Self::UniversalParams, PowersOfTauError>;
+}
+
+impl<E: PairingEngine> PowersOfTauPC<E::Fr> for MarlinKZG10<E, DensePolynomial<E::Fr>> {
+    type Engine = E;
+
+    fn num_g2_powers(_: usize) -> usize {
+        2
+    }
+
+    fn universal_params_from_powers_of_tau<R: RngCore>(
+        powers: &PowersOfTau<E>,
+        max_degree: usize,
+        rng: &mut R,
+    ) -> Result<Self::UniversalParams, PowersOfTauError> {
+        powers.kzg10_params(max_degree, false, rng)
+    }
+}
+
+impl<E: PairingEngine> PowersOfTauPC<E::Fr> for SonicKZG10<E, DensePolynomial<E::Fr>> {
+    type Engine = E;
+
+    /// `SonicKZG10` enforces degree bounds with negative powers of `tau` in
+    /// G2, which are taken relative to `tau^max_degree * H`.
+    fn num_g2_powers(max_degree: usize) -> usize {
+        max_degree + 2
+    }
+
+    fn universal_params_from_powers_of_tau<R: RngCore>(
+        powers: &PowersOfTau<E>,
+        max_degree: usize,
+        rng: &mut R,
+    ) -> Result<Self::UniversalParams, PowersOfTauError> {
+        powers.kzg10_params(max_degree, true, rng)
+    }
+}
+
+impl<F: PrimeField, PC: PowersOfTauPC<F>, FS: FiatShamirRng> Marlin<F, PC, FS> {
+    /// Build the universal parameters of the argument system from the output
+    /// of a powers-of-tau ceremony, truncated to the degree that indices of the
+    /// given size and `zk_bound` need, as in `Marlin::universal_setup_with_zk_bound`.
+    pub fn universal_setup_from_powers_of_tau<R: RngCore>(
+        powers: &PowersOfTau<PC::Engine>,
+        num_constraints: usize,
+        num_variables: usize,
+        num_non_zero: usize,
+        zk_bound: usize,
+        rng: &mut R,
+    ) -> Result<UniversalSRS<F, PC>, Error<PC::Error>> {
+        let max_degree = AHPForR1CS::<F>::max_degree_with_zk_bound(
+            num_constraints,
+            num_variables,
+            num_non_zero,
+            zk_bound,
+        )?;
+        Ok(PC::universal_params_from_powers_of_tau(
+            powers, max_degree, rng,
+        )?)
+    }
+
+    /// Build the universal parameters of the argument system from a perpetual
+    /// powers-of-tau file of a ceremony for `2^power` powers, reading only the
+    /// powers that indices of the given size and `zk_bound` need.
+    #[allow(clippy::too_many_arguments)]
+    pub fn universal_setup_from_ppot<Rd: Read, R: RngCore>(
+        reader: Rd,
+        power: usize,
+        encoding: PointEncoding,
+        num_constraints: usize,
+        num_variables: usize,
+        num_non_zero: usize,
+        zk_bound: usize,
+        rng: &mut R,
+    ) -> Result<UniversalSRS<F, PC>, Error<PC::Error>> {
+        let max_degree = AHPForR1CS::<F>::max_degree_with_zk_bound(
+            num_constraints,
+            num_variables,
+            num_non_zero,
+            zk_bound,
+        )?;
+        let powers = PowersOfTau::read_ppot(
+            reader,
+            power,
+            encoding,
+            max_degree + 1,
+            PC::num_g2_powers(max_degree),
+        )?;
+        Ok(PC::universal_params_from_powers_of_tau(
+            &powers, max_degree, rng,
+        )?)
+    }
+
+    /// Build the universal parameters of the argument system from the JSON
+    /// transcript of a KZG ceremony, reading only the powers that indices of
+    /// the given size and `zk_bound` need.
+    ///
+    /// These ceremonies have a single secret, so that the secret `gamma` of
+    /// the parameters is sampled from `rng`. Whoever knows `gamma` can open
+    /// hiding commitments to any value, so that parameters imported by
+    /// another party must be updated with `Marlin::update_universal_srs` by at
+    /// least one honest party before they are used.
+    #[cfg(feature = "kzg-ceremony")]
+    pub fn universal_setup_from_kzg_ceremony_json<R: RngCore>(
+        json: &str,
+        num_constraints: usize,
+        num_variables: usize,
+        num_non_zero: usize,
+        zk_bound: usize,
+        rng: &mut R,
+    ) -> Result<UniversalSRS<F, PC>, Error<PC::Error>> {
+        let max_degree = AHPForR1CS::<F>::max_degree_with_zk_bound(
+            num_constraints,
+            num_variables,
+            num_non_zero,
+            zk_bound,
+        )?;
+        let powers = PowersOfTau::from_kzg_ceremony_json(
+            json,
+            max_degree + 2,
+            PC::num_g2_powers(max_degree),
+        )?;
+        Ok(PC::universal_params_from_powers_of_tau(
+            &powers, max_degree, rng,
+        )?)
+    }
+}
+
+/// The inverse of `decode_point`.
+#[cfg(test)]
+pub(crate) fn encode_point<E: PairingEngine, G: AffineCurve>(
+    point: &G,
+    encoding: PointEncoding,
+) -> Vec<u8> {
+    let (size, num_bits) = base_field_size::<E>();
+    let mut ark_bytes = Vec::new();
+    let num_coords = match encoding {
+        PointEncoding::Compressed => {
+            point.serialize(&mut ark_bytes).unwrap();
+            1
+        }
+        PointEncoding::Uncompressed => {
+            point.serialize_uncompressed(&mut ark_bytes).unwrap();
+            2
+        }
+    };
+    let is_greatest =
+        encoding == PointEncoding::Compressed && ark_bytes.last().unwrap() & 0x80 != 0;
+    *ark_bytes.last_mut().unwrap() &= 0x3f;
+    let mut bytes = ark_bytes
+        .chunks(ark_bytes.len() / num_coords)
+        .flat_map(|coord| coord.chunks(size).rev().flat_map(|c| c.iter().rev()))
+        .copied()
+        .collect::<Vec<_>>();
+    if 8 * size - num_bits == 2 {
+        bytes[0] |= if is_greatest { 0x80 } else { 0 };
+    } else {
+        bytes[0] |= if encoding == PointEncoding::Compressed {
+            0x80
+        } else {
+            0
+        };
+        bytes[0] |= if is_greatest { 0x20 } else { 0 };
+    }
+    bytes
+}
+
+/// A perpetual powers-of-tau file for `2^power` powers of `tau`, with
+/// `beta = 2 * alpha`, without the trailing public key of response files.
+#[cfg(test)]
+pub(crate) fn ppot_file<E: PairingEngine>(
+    tau: E::Fr,
+    alpha: E::Fr,
+    power: usize,
+    encoding: PointEncoding,
+) -> Vec<u8> {
+    use ark_ec::ProjectiveCurve;
+    use ark_ff::Field;
+
+    let beta = alpha.double();
+    let num_powers = 1 << power;
+    let g = E::G1Affine::prime_subgroup_generator();
+    let h = E::G2Affine::prime_subgroup_generator();
+    let powers_of_tau = |n: usize, factor: E::Fr| (0..n).map(move |i| tau.pow([i as u64]) * factor);
+    let mut file = vec![0u8; 64];
+    for s in powers_of_tau(2 * num_powers - 1, E::Fr::one()) {
+        file.extend(encode_point::<E, _>(&g.mul(s).into_affine(), encoding));
+    }
+    for s in powers_of_tau(num_powers, E::Fr::one()) {
+        file.extend(encode_point::<E, _>(&h.mul(s).into_affine(), encoding));
+    }
+    for &factor in &[alpha, beta] {
+        for s in powers_of_tau(num_powers, factor) {
+            file.extend(encode_point::<E, _>(&g.mul(s).into_affine(), encoding));
+        }
+    }
+    file.extend(encode_point::<E, _>(&h.mul(beta).into_affine(), encoding));
+    file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::ProjectiveCurve;
+    use ark_ff::{Field, UniformRand};
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn decodes_bls12_381_generators() {
+        use ark_bls12_381::{Bls12_381, G1Affine, G2Affine};
+
+        let g1_compressed = decode_hex("97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb");
+        let g1_uncompressed = decode_hex("17f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb08b3f481e3aaa0f1a09e30ed741d8ae4fcf5e095d5d00af600db18cb2c04b3edd03cc744a2888ae40caa232946c5e7e1");
+        let g2_compressed = decode_hex("93e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8");
+
+        let g = G1Affine::prime_subgroup_generator();
+        let h = G2Affine::prime_subgroup_generator();
+        let decode_g1 =
+            |bytes: &[u8], encoding| decode_point::<Bls12_381, G1Affine>(bytes, encoding).unwrap();
+        assert_eq!(decode_g1(&g1_compressed, PointEncoding::Compressed), g);
+        assert_eq!(decode_g1(&g1_uncompressed, PointEncoding::Uncompressed), g);
+        assert_eq!(
+            decode_point::<Bls12_381, G2Affine>(&g2_compressed, PointEncoding::Compressed).unwrap(),
+            h
+        );
+        assert_eq!(
+            encode_point::<Bls12_381, _>(&h, PointEncoding::Compressed),
+            g2_compressed
+        );
+        assert_eq!(
+            encode_point::<Bls12_381, _>(&-g, PointEncoding::Compressed)[0],
+            0xb7
+        );
+
+        // The compression flag must match the encoding.
+        assert!(
+            decode_point::<Bls12_381, G1Affine>(&g1_compressed, PointEncoding::Uncompressed)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn decodes_bn254_generator() {
+        use ark_bn254::{Bn254, G1Affine};
+
+        let mut uncompressed = vec![0u8; 64];
+        uncompressed[31] = 1;
+        uncompressed[63] = 2;
+        let g = G1Affine::prime_subgroup_generator();
+        assert_eq!(
+            decode_point::<Bn254, G1Affine>(&uncompressed, PointEncoding::Uncompressed).unwrap(),
+            g
+        );
+        // `2` is smaller than `-2`, so that the sign flag is not set.
+        assert_eq!(
+            decode_point::<Bn254, G1Affine>(&uncompressed[..32], PointEncoding::Compressed)
+                .unwrap(),
+            g
+        );
+        let mut negated = uncompressed[..32].to_vec();
+        negated[0] |= 0x80;
+        assert_eq!(
+            decode_point::<Bn254, G1Affine>(&negated, PointEncoding::Compressed).unwrap(),
+            -g
+        );
+
+        // Points off the curve and the identity are rejected.
+        uncompressed[63] = 3;
+        assert!(
+            decode_point::<Bn254, G1Affine>(&uncompressed, PointEncoding::Uncompressed).is_err()
+        );
+        let mut identity = vec![0u8; 32];
+        identity[0] = 0x40;
+        assert!(decode_point::<Bn254, G1Affine>(&identity, PointEncoding::Compressed).is_err());
+
+        // So are encodings of the wrong length.
+        for bytes in [&[][..], &uncompressed[..31], &uncompressed[..33]] {
+            assert!(matches!(
+                decode_point::<Bn254, G1Affine>(bytes, PointEncoding::Compressed),
+                Err(PowersOfTauError::InvalidPoint)
+            ));
+        }
+    }
+
+    #[test]
+    fn reads_ppot_files() {
+        use ark_bn254::{Bn254, Fr, G1Affine};
+
+        let rng = &mut ark_std::test_rng();
+        let (tau, alpha) = (Fr::rand(rng), Fr::rand(rng));
+        let g = G1Affine::prime_subgroup_generator();
+        for &encoding in &[PointEncoding::Compressed, PointEncoding::Uncompressed] {
+            let file = ppot_file::<Bn254>(tau, alpha, 3, encoding);
+            let powers = PowersOfTau::<Bn254>::read_ppot(&file[..], 3, encoding, 5, 2).unwrap();
+            assert_eq!(powers.powers_of_g.len(), 5);
+            assert_eq!(powers.powers_of_g[4], g.mul(tau.pow([4])).into_affine());
+            assert_eq!(powers.powers_of_h.len(), 2);
+            let alpha_powers_of_g = powers.alpha_powers_of_g.unwrap();
+            assert_eq!(alpha_powers_of_g.len(), 6);
+            assert_eq!(
+                alpha_powers_of_g[5],
+                g.mul(alpha * tau.pow([5])).into_affine()
+            );
+
+            assert!(matches!(
+                PowersOfTau::<Bn254>::read_ppot(&file[..], 3, encoding, 16, 2),
+                Err(PowersOfTauError::NotEnoughPowers { .. })
+            ));
+            assert!(matches!(
+                PowersOfTau::<Bn254>::read_ppot(&file[..file.len() / 2], 3, encoding, 5, 2),
+                Err(PowersOfTauError::Io(_))
+            ));
+        }
+    }
+
+    #[cfg(feature = "kzg-ceremony")]
+    #[test]
+    fn reads_kzg_ceremony_json() {
+        use ark_bls12_381::{Bls12_381, G1Affine, G2Affine};
+
+        let rng = &mut ark_std::test_rng();
+        let tau = <Bls12_381 as PairingEngine>::Fr::rand(rng);
+        let hex_powers = |n: usize, generator: &dyn Fn(usize) -> Vec<u8>| {
+            (0..n)
+                .map(|i| {
+                    let hex = generator(i)
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect::<String>();
+                    format!("\"0x{}\"", hex)
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let g1 = |i: usize| {
+            let point = G1Affine::prime_subgroup_generator().mul(tau.pow([i as u64]));
+            encode_point::<Bls12_381, _>(&point.into_affine(), PointEncoding::Compressed)
+        };
+        let g2 = |i: usize| {
+            let point = G2Affine::prime_subgroup_generator().mul(tau.pow([i as u64]));
+            encode_point::<Bls12_381, _>(&point.into_affine(), PointEncoding::Compressed)
+        };
+        let json = format!(
+            r#"{{"transcripts":[{{"numG1Powers":4,"numG2Powers":2,"powersOfTau":{{"G1Powers":[{}],"G2Powers":[{}]}},"witness":{{}}}},{{"numG1Powers":16,"numG2Powers":3,"powersOfTau":{{"G1Powers":[{}],"G2Powers":[{}]}},"witness":{{}}}}]}}"#,
+            hex_powers(4, &g1),
+            hex_powers(2, &g2),
+            hex_powers(16, &g1),
+            hex_powers(3, &g2),
+        );
+
+        let powers = PowersOfTau::<Bls12_381>::from_kzg_ceremony_json(&json, 10, 2).unwrap();
+        assert_eq!(powers.powers_of_g.len(), 10);
+        assert!(powers.alpha_powers_of_g.is_none());
+        let pp = powers.kzg10_params(8, false, rng).unwrap();
+        assert_eq!(pp.powers_of_gamma_g.len(), 10);
+        assert_ne!(pp.powers_of_gamma_g[&0], pp.powers_of_g[0]);
+        assert!(kzg10_params_are_well_formed(&pp, rng));
+
+        // Parameters with `gamma = 1`, as the ceremony's powers alone would
+        // give, are rejected.
+        let mut dependent_powers = powers.clone();
+        dependent_powers.alpha_powers_of_g = Some(powers.powers_of_g.clone());
+        assert!(matches!(
+            dependent_powers.kzg10_params(8, false, rng),
+            Err(PowersOfTauError::DependentAlphaPowers)
+        ));
+        assert!(matches!(
+            PowersOfTau::<Bls12_381>::from_kzg_ceremony_json(&json, 17, 2),
+            Err(PowersOfTauError::NotEnoughPowers {
+                needed: 17,
+                available: 16
+            })
+        ));
+        assert!(matches!(
+            PowersOfTau::<Bls12_381>::from_kzg_ceremony_json("{}", 1, 1),
+            Err(PowersOfTauError::InvalidFormat)
+        ));
+        let empty_point = json.replace(&hex_powers(1, &g1), "\"0x\"");
+        assert!(matches!(
+            PowersOfTau::<Bls12_381>::from_kzg_ceremony_json(&empty_point, 10, 2),
+            Err(PowersOfTauError::InvalidPoint)
+        ));
+    }
+}
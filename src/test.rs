@@ -1,3 +1,4 @@
+use crate::{CcsConstraintSynthesizer, LookupConstraintSynthesizer, LookupTables, ProductGates};
 use ark_ff::Field;
 use ark_relations::{
     lc,
@@ -113,14 +114,98 @@ impl<F: Field> ConstraintSynthesizer<F> for OutlineTestCircuit<F> {
     }
 }
 
+#[derive(Clone)]
+/// Define a constraint system that checks values with lookups: each of
+/// `nibbles` is looked up in the table of the sixteen nibbles, and each of
+/// `powers` in that of the powers of two below sixteen. The public input is
+/// the sum of all values.
+struct LookupCircuit<F: Field> {
+    nibbles: Vec<u64>,
+    powers: Vec<u64>,
+    field_phantom: PhantomData<F>,
+}
+
+impl<F: Field> LookupConstraintSynthesizer<F> for LookupCircuit<F> {
+    fn generate_constraints_and_lookups(
+        self,
+        cs: ConstraintSystemRef<F>,
+        tables: &mut LookupTables<F>,
+    ) -> Result<(), SynthesisError> {
+        let nibble_table = tables.add_table((0..16u64).map(F::from).collect());
+        let power_table = tables.add_table([1u64, 2, 4, 8].iter().copied().map(F::from).collect());
+
+        let values = self.nibbles.iter().chain(&self.powers);
+        let sum = cs.new_input_variable(|| Ok(values.clone().map(|v| F::from(*v)).sum()))?;
+        let mut total = lc!();
+        for (i, value) in values.enumerate() {
+            let value = cs.new_witness_variable(|| Ok(F::from(*value)))?;
+            let table = if i < self.nibbles.len() {
+                nibble_table
+            } else {
+                power_table
+            };
+            tables.enforce_lookup(&cs, lc!() + value, table)?;
+            total = total + value;
+        }
+        cs.enforce_constraint(
+            total,
+            lc!() + ark_relations::r1cs::Variable::One,
+            lc!() + sum,
+        )?;
+
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone)]
+/// Define a constraint system with product gates: the public inputs are
+/// `x * y * z` and `x * y`, and the witness also holds `z * z`, which is
+/// enforced by an R1CS constraint.
+struct ProductCircuit<F: Field> {
+    x: F,
+    y: F,
+    z: F,
+}
+
+impl<F: Field> CcsConstraintSynthesizer<F> for ProductCircuit<F> {
+    fn generate_constraints_and_gates(
+        self,
+        cs: ConstraintSystemRef<F>,
+        gates: &mut ProductGates<F>,
+    ) -> Result<(), SynthesisError> {
+        let (x, y, z) = (self.x, self.y, self.z);
+        let xyz = cs.new_input_variable(|| Ok(x * y * z))?;
+        let xy = cs.new_input_variable(|| Ok(x * y))?;
+        let x = cs.new_witness_variable(|| Ok(x))?;
+        let y = cs.new_witness_variable(|| Ok(y))?;
+        let z_var = cs.new_witness_variable(|| Ok(z))?;
+        let zz = cs.new_witness_variable(|| Ok(z * z))?;
+
+        gates.enforce_product(&cs, vec![lc!() + x, lc!() + y, lc!() + z_var], lc!() + xyz)?;
+        gates.enforce_product(&cs, vec![lc!() + x, lc!() + y], lc!() + xy)?;
+        cs.enforce_constraint(lc!() + z_var, lc!() + z_var, lc!() + zz)?;
+
+        Ok(())
+    }
+}
+
 mod marlin {
     use super::*;
-    use crate::{Marlin, SimpleHashFiatShamirRng};
+    use crate::ahp::Error as AHPError;
+    use crate::rng::{AbiEncoding, BytesEncoding, Transcript};
+    use crate::srs::{ppot_file, PointEncoding, PowersOfTau, PowersOfTauError};
+    use crate::{ArithmetizationLayout, DiskCache, Error, IndexerConfig, ZkMode};
+    use crate::{IndexProverKey, Proof, UniversalSRS};
+    use crate::{IndexVerifierKey, KeccakFiatShamirRng, Marlin};
+    use crate::{PoseidonFiatShamirRng, ShortChallengeFiatShamirRng, SimpleHashFiatShamirRng};
 
     use ark_bls12_381::{Bls12_381, Fr};
-    use ark_ff::UniformRand;
+    use ark_bn254::{Bn254, Fr as Bn254Fr};
+    use ark_ff::{PrimeField, UniformRand, Zero};
     use ark_poly::univariate::DensePolynomial;
-    use ark_poly_commit::marlin_pc::MarlinKZG10;
+    use ark_poly_commit::{marlin_pc::MarlinKZG10, sonic_pc::SonicKZG10};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
     use ark_std::ops::MulAssign;
     use blake2::Blake2s;
     use rand_chacha::ChaChaRng;
@@ -150,6 +235,7 @@ mod marlin {
             };
 
             let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circ.clone()).unwrap();
+            assert_eq!(index_vk.index_info.num_constraints, num_constraints);
             println!("Called index");
 
             let proof = MarlinInst::prove(&index_pk, circ, rng).unwrap();
@@ -202,6 +288,265 @@ mod marlin {
         test_circuit(num_constraints, num_variables);
     }
 
+    #[test]
+    fn prove_and_verify_with_poseidon_fs() {
+        type MarlinPoseidonInst = Marlin<Fr, MultiPC, PoseidonFiatShamirRng<Fr>>;
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinPoseidonInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 25,
+            num_variables: 25,
+        };
+
+        let (index_pk, index_vk) = MarlinPoseidonInst::index(&universal_srs, circ).unwrap();
+        let proof = MarlinPoseidonInst::prove(&index_pk, circ, rng).unwrap();
+
+        assert!(MarlinPoseidonInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+        assert!(!MarlinPoseidonInst::verify(&index_vk, &[a, a], &proof, rng).unwrap());
+    }
+
+    #[test]
+    fn short_challenges_are_opt_in() {
+        use ark_ff::BigInteger;
+
+        type MarlinShortInst = Marlin<Fr, MultiPC, ShortChallengeFiatShamirRng<FS>>;
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 25,
+            num_variables: 25,
+        };
+        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+        let proof = MarlinInst::prove(&index_pk, circ, rng).unwrap();
+        let short_proof = MarlinShortInst::prove(&index_pk, circ, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+        assert!(MarlinShortInst::verify(&index_vk, &[c, d], &short_proof, rng).unwrap());
+        assert!(!MarlinInst::verify(&index_vk, &[c, d], &short_proof, rng).unwrap());
+
+        // The challenges of the AHP span the whole field, unless the rng is
+        // wrapped to keep them to 128 bits.
+        let beta_bits = |check: crate::PCCheck<Fr, MultiPC>| {
+            let (_, (_, beta)) = check
+                .query_set
+                .iter()
+                .find(|(_, (label, _))| label == "beta")
+                .unwrap();
+            beta.into_repr().num_bits()
+        };
+        let check =
+            MarlinInst::replay_transcript::<BytesEncoding>(&index_vk, &[c, d], &proof).unwrap();
+        assert!(beta_bits(check) > 128);
+        let check =
+            MarlinShortInst::replay_transcript::<BytesEncoding>(&index_vk, &[c, d], &short_proof)
+                .unwrap();
+        assert!(beta_bits(check) <= 128);
+    }
+
+    #[test]
+    fn prove_and_verify_with_keccak_fs() {
+        use ark_ff::BigInteger;
+
+        type Bn254PC = MarlinKZG10<Bn254, DensePolynomial<Bn254Fr>>;
+        type MarlinKeccakInst = Marlin<Bn254Fr, Bn254PC, KeccakFiatShamirRng>;
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinKeccakInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let a = Bn254Fr::from(3u64);
+        let b = Bn254Fr::from(5u64);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 25,
+            num_variables: 25,
+        };
+
+        let (index_pk, index_vk) = MarlinKeccakInst::index(&universal_srs, circ).unwrap();
+        let zk_mode = ZkMode::NonZeroKnowledge;
+        let proof =
+            MarlinKeccakInst::prove_with_abi_commitments(&index_pk, circ, zk_mode, rng).unwrap();
+        let verify = |input: &[Bn254Fr], rng: &mut _| {
+            MarlinKeccakInst::verify_with_abi_commitments(&index_vk, input, &proof, rng).unwrap()
+        };
+        assert!(verify(&[c, d], rng));
+        assert!(!verify(&[a, a], rng));
+        // The commitments are absorbed differently by `Marlin::verify`, whose
+        // opening challenge has 128 bits.
+        assert!(!MarlinKeccakInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+        let bytes_proof =
+            MarlinKeccakInst::prove_with_zk_mode(&index_pk, circ, zk_mode, rng).unwrap();
+        assert!(MarlinKeccakInst::verify(&index_vk, &[c, d], &bytes_proof, rng).unwrap());
+        let check =
+            MarlinKeccakInst::replay_transcript::<BytesEncoding>(&index_vk, &[c, d], &bytes_proof)
+                .unwrap();
+        assert!(check.opening_challenge.into_repr().num_bits() <= 128);
+
+        // Without blinding, the proof and thus its challenges are fixed by the
+        // setup and the witness.
+        let check =
+            MarlinKeccakInst::replay_transcript::<AbiEncoding>(&index_vk, &[c, d], &proof).unwrap();
+        let hex = |elem: Bn254Fr| format!("{:0>64}", elem.into_repr()).to_lowercase();
+        let point = |label: &str| {
+            let (_, (_, point)) = check
+                .query_set
+                .iter()
+                .find(|(_, (point_label, _))| point_label == label)
+                .unwrap();
+            hex(*point)
+        };
+        assert_eq!(
+            point("beta"),
+            "18b6e70edf95fb8b0794c71ebfd943a453593b0bd135519c307d4ef447f003fe"
+        );
+        assert_eq!(
+            point("gamma"),
+            "108620fcbd83b0466a1ebef378aeac049d927e08eb73ede6967c79eb7b7fd782"
+        );
+        assert_eq!(
+            hex(check.opening_challenge),
+            "29f4c3673089f78d9bb960432a06668913022375730077efcc67afa966ae1ade"
+        );
+    }
+
+    #[test]
+    fn batch_prove_and_verify() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let circuits: Vec<_> = (0..4)
+            .map(|_| Circuit {
+                a: Some(Fr::rand(rng)),
+                b: Some(Fr::rand(rng)),
+                num_constraints: 25,
+                num_variables: 25,
+            })
+            .collect();
+        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circuits[0]).unwrap();
+
+        let mut inputs = Vec::new();
+        let mut proofs = Vec::new();
+        for circ in &circuits {
+            let (a, b) = (circ.a.unwrap(), circ.b.unwrap());
+            inputs.push(vec![a * b, a * b * b]);
+            proofs.push(MarlinInst::prove(&index_pk, *circ, rng).unwrap());
+        }
+
+        let batch: Vec<_> = inputs
+            .iter()
+            .zip(&proofs)
+            .map(|(x, proof)| (x.as_slice(), proof))
+            .collect();
+        assert!(MarlinInst::batch_verify(&index_vk, &batch, rng)
+            .unwrap()
+            .is_empty());
+        println!("Called batch verifier");
+
+        println!("\nShould not verify (i.e. verifier messages should print below):");
+        let a = circuits[2].a.unwrap();
+        let wrong_input = vec![a, a];
+        let mut batch = batch;
+        batch[2].0 = &wrong_input;
+        assert_eq!(
+            MarlinInst::batch_verify(&index_vk, &batch, rng).unwrap(),
+            vec![2]
+        );
+
+        // A public input of the wrong length only rejects its own proof.
+        let short_input = vec![a];
+        batch[1].0 = &short_input;
+        assert_eq!(
+            MarlinInst::batch_verify(&index_vk, &batch, rng).unwrap(),
+            vec![1, 2]
+        );
+
+        // So does a proof with an evaluation missing.
+        let mut bytes = Vec::new();
+        proofs[0].serialize(&mut bytes).unwrap();
+        let mut truncated = Proof::deserialize(&bytes[..]).unwrap();
+        truncated.evaluations.pop();
+        batch[0].1 = &truncated;
+        assert_eq!(
+            MarlinInst::batch_verify(&index_vk, &batch, rng).unwrap(),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn batch_prove_and_verify_heterogeneous() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let mut keys = Vec::new();
+        let mut inputs = Vec::new();
+        let mut proofs = Vec::new();
+        for &(num_constraints, num_variables) in &[(25, 25), (50, 25), (25, 50)] {
+            let a = Fr::rand(rng);
+            let b = Fr::rand(rng);
+            let circ = Circuit {
+                a: Some(a),
+                b: Some(b),
+                num_constraints,
+                num_variables,
+            };
+            let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+            proofs.push(MarlinInst::prove(&index_pk, circ, rng).unwrap());
+            inputs.push(vec![a * b, a * b * b]);
+            keys.push(index_vk);
+        }
+
+        let mut batch: Vec<_> = keys
+            .iter()
+            .zip(&inputs)
+            .zip(&proofs)
+            .map(|((vk, x), proof)| (vk, x.as_slice(), proof))
+            .collect();
+        assert!(MarlinInst::batch_verify_heterogeneous(&batch, rng)
+            .unwrap()
+            .is_empty());
+        println!("Called batch verifier");
+
+        println!("\nShould not verify (i.e. verifier messages should print below):");
+        batch[1].0 = &keys[0];
+        assert_eq!(
+            MarlinInst::batch_verify_heterogeneous(&batch, rng).unwrap(),
+            vec![1]
+        );
+
+        let other_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+        let circ = Circuit {
+            a: None,
+            b: None,
+            num_constraints: 25,
+            num_variables: 25,
+        };
+        let (_, other_vk) = MarlinInst::index(&other_srs, circ).unwrap();
+        batch[1].0 = &other_vk;
+        assert!(matches!(
+            MarlinInst::batch_verify_heterogeneous(&batch, rng),
+            Err(Error::IncompatibleVerifierKeys)
+        ));
+    }
+
     #[test]
     /// Test on a constraint system that will trigger outlining.
     fn prove_and_test_outlining() {
@@ -227,4 +572,1332 @@ mod marlin {
         assert!(MarlinInst::verify(&index_vk, &inputs, &proof, rng).unwrap());
         println!("Called verifier");
     }
+
+    #[test]
+    fn verify_rejects_public_input_of_wrong_length() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 25,
+            num_variables: 25,
+        };
+
+        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+        // Two public inputs and the constant "one" do not fill a domain of
+        // size four, but the index records the actual count.
+        assert_eq!(index_vk.index_info.num_instance_variables, 3);
+
+        let proof = MarlinInst::prove(&index_pk, circ, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+
+        // Neither a truncated input nor a zero-extended one is accepted.
+        assert!(MarlinInst::verify(&index_vk, &[c], &proof, rng).is_err());
+        assert!(MarlinInst::verify(&index_vk, &[c, d, Fr::zero()], &proof, rng).is_err());
+    }
+
+    #[test]
+    fn prove_and_verify_with_per_matrix_arithmetization() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 25,
+            num_variables: 25,
+        };
+
+        let (_, joint_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+        let config = IndexerConfig {
+            arithmetization: ArithmetizationLayout::PerMatrix,
+            ..IndexerConfig::default()
+        };
+        let (index_pk, index_vk) =
+            MarlinInst::index_with_config(&universal_srs, circ, config).unwrap();
+        assert_eq!(
+            index_vk.index_info.arithmetization,
+            ArithmetizationLayout::PerMatrix
+        );
+        // Each matrix has a single entry per constraint, whereas their sum
+        // has up to three.
+        assert_eq!(index_vk.index_info.num_non_zero, 25);
+        assert!(joint_vk.index_info.num_non_zero > 25);
+        assert_eq!(index_vk.index_comms.len(), 12);
+
+        let proof = MarlinInst::prove(&index_pk, circ, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+        assert!(!MarlinInst::verify(&index_vk, &[a, a], &proof, rng).unwrap());
+    }
+
+    #[test]
+    fn prove_and_verify_with_larger_zk_bound() {
+        let rng = &mut ark_std::test_rng();
+
+        let zk_bound = 3;
+        let universal_srs =
+            MarlinInst::universal_setup_with_zk_bound(100, 25, 100, zk_bound, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 25,
+            num_variables: 25,
+        };
+
+        let config = IndexerConfig {
+            zk_bound,
+            ..IndexerConfig::default()
+        };
+        let (index_pk, index_vk) =
+            MarlinInst::index_with_config(&universal_srs, circ, config).unwrap();
+        assert_eq!(index_vk.index_info.zk_bound, zk_bound);
+
+        let proof = MarlinInst::prove(&index_pk, circ, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+        assert!(!MarlinInst::verify(&index_vk, &[a, a], &proof, rng).unwrap());
+
+        // The query bound is bound to the verifier key.
+        let (_, default_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+        assert!(!MarlinInst::verify(&default_vk, &[c, d], &proof, rng).unwrap_or(false));
+
+        // A query bound of zero is rejected rather than indexed.
+        let config = IndexerConfig {
+            zk_bound: 0,
+            ..IndexerConfig::default()
+        };
+        assert!(matches!(
+            MarlinInst::index_with_config(&universal_srs, circ, config),
+            Err(Error::AHPError(AHPError::InvalidZkBound))
+        ));
+    }
+
+    #[test]
+    fn prove_and_verify_without_zero_knowledge() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 25,
+            num_variables: 25,
+        };
+
+        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+        let proof =
+            MarlinInst::prove_with_zk_mode(&index_pk, circ, ZkMode::NonZeroKnowledge, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+        assert!(!MarlinInst::verify(&index_vk, &[a, a], &proof, rng).unwrap());
+
+        // Without blinding, the proof depends on the witness alone.
+        let other_proof =
+            MarlinInst::prove_with_zk_mode(&index_pk, circ, ZkMode::NonZeroKnowledge, rng).unwrap();
+        let (mut bytes, mut other_bytes) = (Vec::new(), Vec::new());
+        proof.serialize(&mut bytes).unwrap();
+        other_proof.serialize(&mut other_bytes).unwrap();
+        assert_eq!(bytes, other_bytes);
+    }
+
+    #[test]
+    fn prove_and_verify_with_disk_cache() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 100,
+            num_variables: 25,
+        };
+        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+
+        let cache_dir = std::env::temp_dir().join(format!("marlin-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        // An empty cache writes every vector to disk, a small one some of them.
+        for max_bytes in [0, 4096] {
+            let cache = DiskCache::new(max_bytes, &cache_dir);
+            let proof = MarlinInst::prove_with_disk_cache(&index_pk, circ, cache, rng).unwrap();
+            assert!(MarlinInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+            assert!(!MarlinInst::verify(&index_vk, &[a, a], &proof, rng).unwrap());
+            assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 0);
+        }
+
+        // Caching does not change the proof.
+        let cache = DiskCache::new(0, &cache_dir);
+        let proof =
+            MarlinInst::prove_with_disk_cache(&index_pk, circ, cache, &mut ark_std::test_rng())
+                .unwrap();
+        let other_proof = MarlinInst::prove(&index_pk, circ, &mut ark_std::test_rng()).unwrap();
+        let (mut bytes, mut other_bytes) = (Vec::new(), Vec::new());
+        proof.serialize(&mut bytes).unwrap();
+        other_proof.serialize(&mut other_bytes).unwrap();
+        assert_eq!(bytes, other_bytes);
+
+        // A directory that cannot be written to is reported.
+        let cache = DiskCache::new(0, cache_dir.join("missing"));
+        assert!(matches!(
+            MarlinInst::prove_with_disk_cache(&index_pk, circ, cache, rng),
+            Err(Error::AHPError(AHPError::SpillError(_)))
+        ));
+        std::fs::remove_dir(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn disk_cache_bounds_vectors_held_in_memory() {
+        use crate::AHPForR1CS;
+
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+        let circ = Circuit {
+            a: Some(Fr::rand(rng)),
+            b: Some(Fr::rand(rng)),
+            num_constraints: 100,
+            num_variables: 25,
+        };
+        let (index_pk, _) = MarlinInst::index(&universal_srs, circ).unwrap();
+
+        let cache_dir = std::env::temp_dir().join(format!("marlin-peak-{}", std::process::id()));
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        // The peak of the vectors held until the second round, which is when
+        // the prover holds the most of them.
+        let mut peak_held_bytes = |max_bytes: usize| {
+            let cache = DiskCache::new(max_bytes, &cache_dir);
+            let state =
+                AHPForR1CS::prover_init_with_disk_cache(&index_pk.index, circ, cache).unwrap();
+            let (_, _, state) = AHPForR1CS::prover_first_round(state, rng).unwrap();
+            let mut transcript = Transcript::<FS>::new(b"cache test");
+            let (verifier_first_msg, _) =
+                AHPForR1CS::verifier_first_round(index_pk.index_vk.index_info, &mut transcript)
+                    .unwrap();
+            let (_, _, state) =
+                AHPForR1CS::prover_second_round(&verifier_first_msg, state, rng).unwrap();
+            state.peak_held_bytes()
+        };
+
+        let unbounded_peak = peak_held_bytes(usize::MAX);
+        for max_bytes in [0, 1024, 4096, unbounded_peak / 2] {
+            assert!(max_bytes < unbounded_peak);
+            assert!(peak_held_bytes(max_bytes) <= max_bytes);
+        }
+        std::fs::remove_dir(&cache_dir).unwrap();
+    }
+
+    fn lookup_circuit(nibbles: &[u64], powers: &[u64]) -> LookupCircuit<Fr> {
+        LookupCircuit {
+            nibbles: nibbles.to_vec(),
+            powers: powers.to_vec(),
+            field_phantom: PhantomData,
+        }
+    }
+
+    #[test]
+    fn prove_and_verify_with_lookups() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 100, rng).unwrap();
+
+        let circ = lookup_circuit(&[0, 7, 15, 3, 3], &[1, 8]);
+        let (index_pk, index_vk) =
+            MarlinInst::index_with_lookups(&universal_srs, circ.clone(), IndexerConfig::default())
+                .unwrap();
+        // The dummy entry, the sixteen nibbles and the four powers.
+        assert_eq!(index_vk.index_info.lookup_table_size, 21);
+        assert_eq!(index_vk.index_comms.len(), 10);
+
+        let proof = MarlinInst::prove_with_lookups(&index_pk, circ, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[Fr::from(37u64)], &proof, rng).unwrap());
+        assert!(!MarlinInst::verify(&index_vk, &[Fr::from(38u64)], &proof, rng).unwrap());
+    }
+
+    #[test]
+    fn prove_and_verify_with_lookups_and_larger_zk_bound() {
+        let rng = &mut ark_std::test_rng();
+
+        let zk_bound = 2;
+        let universal_srs =
+            MarlinInst::universal_setup_with_zk_bound(100, 25, 100, zk_bound, rng).unwrap();
+
+        let circ = lookup_circuit(&[0, 7, 15, 3, 3], &[1, 8]);
+        let config = IndexerConfig {
+            zk_bound,
+            ..IndexerConfig::default()
+        };
+        let (index_pk, index_vk) =
+            MarlinInst::index_with_lookups(&universal_srs, circ.clone(), config).unwrap();
+
+        let proof = MarlinInst::prove_with_lookups(&index_pk, circ, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[Fr::from(37u64)], &proof, rng).unwrap());
+        assert!(!MarlinInst::verify(&index_vk, &[Fr::from(38u64)], &proof, rng).unwrap());
+    }
+
+    #[test]
+    fn prove_rejects_values_not_in_table() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 100, rng).unwrap();
+
+        let circ = lookup_circuit(&[0, 0, 0], &[1]);
+        let (index_pk, _) =
+            MarlinInst::index_with_lookups(&universal_srs, circ, IndexerConfig::default()).unwrap();
+
+        // Sixteen is not a nibble, and three, although a nibble, is not a power.
+        for circ in [
+            lookup_circuit(&[0, 16, 0], &[1]),
+            lookup_circuit(&[0, 0, 0], &[3]),
+        ] {
+            assert!(matches!(
+                MarlinInst::prove_with_lookups(&index_pk, circ, rng),
+                Err(Error::AHPError(AHPError::LookupValueNotInTable))
+            ));
+        }
+    }
+
+    #[test]
+    fn prove_and_verify_with_product_gates() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 100, rng).unwrap();
+
+        let (x, y, z) = (Fr::rand(rng), Fr::rand(rng), Fr::rand(rng));
+        let circ = ProductCircuit { x, y, z };
+        for (arithmetization, num_index_comms) in [
+            (ArithmetizationLayout::Joint, 9),
+            (ArithmetizationLayout::PerMatrix, 24),
+        ] {
+            let config = IndexerConfig {
+                arithmetization,
+                ..IndexerConfig::default()
+            };
+            let (index_pk, index_vk) =
+                MarlinInst::index_with_gates(&universal_srs, circ, config).unwrap();
+            // The two constraints that carry the cubic gate are merged into one.
+            assert_eq!(index_vk.index_info.product_gate_degree, 3);
+            assert_eq!(index_vk.index_info.num_constraints, 3);
+            assert_eq!(index_vk.index_comms.len(), num_index_comms);
+
+            let proof = MarlinInst::prove_with_gates(&index_pk, circ, rng).unwrap();
+            assert!(MarlinInst::verify(&index_vk, &[x * y * z, x * y], &proof, rng).unwrap());
+            assert!(!MarlinInst::verify(&index_vk, &[x * y, x * y * z], &proof, rng).unwrap());
+        }
+
+        // Gates need at least two factors.
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let x = cs.new_witness_variable(|| Ok(x)).unwrap();
+        assert!(matches!(
+            ProductGates::new().enforce_product(&cs, vec![lc!() + x], lc!() + x),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+    }
+
+    #[test]
+    fn prove_and_verify_with_coset_evals() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 100, rng).unwrap();
+
+        let (x, y, z) = (Fr::rand(rng), Fr::rand(rng), Fr::rand(rng));
+        let circ = ProductCircuit { x, y, z };
+        for arithmetization in [
+            ArithmetizationLayout::Joint,
+            ArithmetizationLayout::PerMatrix,
+        ] {
+            let config = IndexerConfig {
+                arithmetization,
+                ..IndexerConfig::default()
+            };
+            let (index_pk, index_vk) =
+                MarlinInst::index_with_gates(&universal_srs, circ, config).unwrap();
+            let coset_config = IndexerConfig {
+                precompute_coset_evals: true,
+                ..config
+            };
+            let (coset_index_pk, coset_index_vk) =
+                MarlinInst::index_with_gates(&universal_srs, circ, coset_config).unwrap();
+            assert!(coset_index_pk.serialized_size() > index_pk.serialized_size());
+            assert_eq!(coset_index_vk.serialized_size(), index_vk.serialized_size());
+
+            let proof =
+                MarlinInst::prove_with_gates(&coset_index_pk, circ, &mut ark_std::test_rng())
+                    .unwrap();
+            assert!(MarlinInst::verify(&index_vk, &[x * y * z, x * y], &proof, rng).unwrap());
+            assert!(!MarlinInst::verify(&index_vk, &[x * y, x * y * z], &proof, rng).unwrap());
+
+            // The prover computes the same `h_2` in either form.
+            let other_proof =
+                MarlinInst::prove_with_gates(&index_pk, circ, &mut ark_std::test_rng()).unwrap();
+            let (mut bytes, mut other_bytes) = (Vec::new(), Vec::new());
+            proof.serialize(&mut bytes).unwrap();
+            other_proof.serialize(&mut other_bytes).unwrap();
+            assert_eq!(bytes, other_bytes);
+        }
+    }
+
+    #[test]
+    fn verify_with_vk_digest() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 100,
+            num_variables: 25,
+        };
+        let other_circ = Circuit {
+            num_constraints: 50,
+            ..circ
+        };
+        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+        let (_, same_index_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+        let (other_index_pk, other_index_vk) =
+            MarlinInst::index(&universal_srs, other_circ).unwrap();
+        let config = IndexerConfig {
+            arithmetization: ArithmetizationLayout::PerMatrix,
+            ..IndexerConfig::default()
+        };
+        let (per_matrix_index_pk, per_matrix_index_vk) =
+            MarlinInst::index_with_config(&universal_srs, circ, config).unwrap();
+
+        // Indexing is deterministic, and both digests cover the indexer
+        // options.
+        let vk_digest = index_vk.digest::<Blake2s>();
+        assert_eq!(vk_digest, same_index_vk.digest::<Blake2s>());
+        assert_ne!(vk_digest, other_index_vk.digest::<Blake2s>());
+        assert_ne!(vk_digest, per_matrix_index_vk.digest::<Blake2s>());
+        let circuit_digest = index_pk.index.circuit_digest::<Blake2s>();
+        assert_ne!(
+            circuit_digest,
+            per_matrix_index_pk.index.circuit_digest::<Blake2s>()
+        );
+        assert_ne!(
+            circuit_digest,
+            other_index_pk.index.circuit_digest::<Blake2s>()
+        );
+
+        // Only the digest of the verifier key covers the universal parameters.
+        let other_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+        let (other_srs_index_pk, other_srs_index_vk) = MarlinInst::index(&other_srs, circ).unwrap();
+        assert_ne!(vk_digest, other_srs_index_vk.digest::<Blake2s>());
+        assert_eq!(
+            circuit_digest,
+            other_srs_index_pk.index.circuit_digest::<Blake2s>()
+        );
+
+        let proof = MarlinInst::prove(&index_pk, circ, rng)
+            .unwrap()
+            .with_vk_digest(vk_digest);
+        let mut bytes = Vec::new();
+        proof.serialize(&mut bytes).unwrap();
+        let proof = Proof::deserialize(&bytes[..]).unwrap();
+        assert!(
+            MarlinInst::verify_with_vk_digest::<Blake2s, _>(&index_vk, &[c, d], &proof, rng)
+                .unwrap()
+        );
+        assert!(!MarlinInst::verify_with_vk_digest::<Blake2s, _>(
+            &other_index_vk,
+            &[c, d],
+            &proof,
+            rng
+        )
+        .unwrap());
+
+        // Proofs without a digest are verified as usual.
+        let proof = MarlinInst::prove(&index_pk, circ, rng).unwrap();
+        assert!(
+            MarlinInst::verify_with_vk_digest::<Blake2s, _>(&index_vk, &[c, d], &proof, rng)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn transcript_binds_every_field_of_the_verifier_key() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 100,
+            num_variables: 25,
+        };
+        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+        let proof = MarlinInst::prove(&index_pk, circ, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+
+        let challenge = |index_vk: &IndexVerifierKey<Fr, MultiPC>| -> Fr {
+            let mut transcript = Transcript::<FS>::new(MarlinInst::PROTOCOL_NAME);
+            transcript.append_message(b"index verifier key", index_vk);
+            transcript.challenge_128_bits_field_element(b"challenge")
+        };
+        let tamperings: Vec<fn(&mut IndexVerifierKey<Fr, MultiPC>)> = vec![
+            |vk| vk.index_info.num_variables += 1,
+            |vk| vk.index_info.num_constraints += 1,
+            |vk| vk.index_info.num_non_zero += 1,
+            |vk| vk.index_info.num_instance_variables += 1,
+            |vk| vk.index_info.arithmetization = ArithmetizationLayout::PerMatrix,
+            |vk| vk.index_info.lookup_table_size += 1,
+            |vk| vk.index_info.product_gate_degree += 1,
+            |vk| vk.index_info.zk_bound += 1,
+            |vk| vk.index_comms.swap(0, 1),
+            |vk| vk.verifier_key.vk.g = -vk.verifier_key.vk.g,
+            |vk| vk.verifier_key.vk.gamma_g = -vk.verifier_key.vk.gamma_g,
+            |vk| vk.verifier_key.vk.h = -vk.verifier_key.vk.h,
+            |vk| vk.verifier_key.vk.beta_h = -vk.verifier_key.vk.beta_h,
+            |vk| {
+                vk.verifier_key
+                    .degree_bounds_and_shift_powers
+                    .as_mut()
+                    .unwrap()[0]
+                    .0 += 1
+            },
+            |vk| {
+                let shift_power = &mut vk
+                    .verifier_key
+                    .degree_bounds_and_shift_powers
+                    .as_mut()
+                    .unwrap()[0]
+                    .1;
+                *shift_power = -*shift_power;
+            },
+            |vk| vk.verifier_key.max_degree += 1,
+            |vk| vk.verifier_key.supported_degree -= 1,
+        ];
+        for tamper in tamperings {
+            let mut tampered_vk = index_vk.clone();
+            tamper(&mut tampered_vk);
+            assert_ne!(challenge(&tampered_vk), challenge(&index_vk));
+        }
+
+        // Fields that the checks of the verifier do not use are bound to the
+        // proof through the challenges.
+        let tamperings: Vec<fn(&mut IndexVerifierKey<Fr, MultiPC>)> = vec![
+            |vk| vk.index_info.zk_bound += 1,
+            |vk| vk.verifier_key.max_degree += 1,
+            |vk| vk.verifier_key.supported_degree -= 1,
+        ];
+        for tamper in tamperings {
+            let mut tampered_vk = index_vk.clone();
+            tamper(&mut tampered_vk);
+            assert!(!MarlinInst::verify(&tampered_vk, &[c, d], &proof, rng).unwrap());
+        }
+    }
+
+    #[test]
+    fn versioned_keys_and_proofs_round_trip() {
+        use crate::versioned::{
+            migrate, read_versioned, write_versioned, ArtifactKind, FormatError, Header, Migration,
+        };
+
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 100,
+            num_variables: 25,
+        };
+        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+        let proof = MarlinInst::prove(&index_pk, circ, rng).unwrap();
+
+        let mut srs_bytes = Vec::new();
+        write_versioned::<Fr, MultiPC, _, _>(
+            ArtifactKind::UniversalSRS,
+            &universal_srs,
+            &mut srs_bytes,
+        )
+        .unwrap();
+        let mut pk_bytes = Vec::new();
+        index_pk.write_versioned(&mut pk_bytes).unwrap();
+        let mut vk_bytes = Vec::new();
+        index_vk.write_versioned(&mut vk_bytes).unwrap();
+        let mut proof_bytes = Vec::new();
+        proof.write_versioned(&mut proof_bytes).unwrap();
+
+        let header = Header::read(&proof_bytes[..]).unwrap();
+        assert_eq!(
+            header,
+            Header::new::<Fr, MultiPC>(ArtifactKind::Proof, header.payload_size)
+        );
+        assert_eq!(header.payload_size as usize, proof.serialized_size());
+
+        let srs: UniversalSRS<Fr, MultiPC> =
+            read_versioned::<Fr, MultiPC, _, _>(ArtifactKind::UniversalSRS, &srs_bytes[..])
+                .unwrap();
+        let (_, same_index_vk) = MarlinInst::index(&srs, circ).unwrap();
+        let index_pk = IndexProverKey::<Fr, MultiPC>::read_versioned(&pk_bytes[..]).unwrap();
+        let index_vk = IndexVerifierKey::<Fr, MultiPC>::read_versioned(&vk_bytes[..]).unwrap();
+        assert_eq!(
+            index_vk.digest::<Blake2s>(),
+            same_index_vk.digest::<Blake2s>()
+        );
+        let proof = Proof::<Fr, MultiPC>::read_versioned(&proof_bytes[..]).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+        let proof = MarlinInst::prove(&index_pk, circ, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+
+        // Artifacts are only read as what they were written for.
+        type SonicPC = SonicKZG10<Bls12_381, DensePolynomial<Fr>>;
+        type Bn254PC = MarlinKZG10<Bn254, DensePolynomial<Bn254Fr>>;
+        assert!(matches!(
+            IndexVerifierKey::<Fr, MultiPC>::read_versioned(&proof_bytes[..]),
+            Err(FormatError::UnexpectedKind(3))
+        ));
+        assert!(matches!(
+            Proof::<Fr, SonicPC>::read_versioned(&proof_bytes[..]),
+            Err(FormatError::UnexpectedScheme(1))
+        ));
+        assert!(matches!(
+            Proof::<Bn254Fr, Bn254PC>::read_versioned(&proof_bytes[..]),
+            Err(FormatError::UnexpectedField(_))
+        ));
+
+        // Corrupted and truncated artifacts are rejected.
+        let corrupt = |i: usize| {
+            let mut bytes = proof_bytes.clone();
+            bytes[i] ^= 1;
+            Proof::<Fr, MultiPC>::read_versioned(&bytes[..])
+        };
+        assert!(matches!(corrupt(0), Err(FormatError::InvalidMagic)));
+        assert!(matches!(
+            corrupt(5),
+            Err(FormatError::UnsupportedVersion(257))
+        ));
+        assert!(matches!(corrupt(30), Err(FormatError::ChecksumMismatch)));
+        assert!(matches!(
+            corrupt(proof_bytes.len() - 1),
+            Err(FormatError::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            Proof::<Fr, MultiPC>::read_versioned(&proof_bytes[..proof_bytes.len() - 1]),
+            Err(FormatError::Io(_))
+        ));
+
+        // Payloads of older versions go through every later migration.
+        let migrations: [Migration; 2] = [
+            |_, mut payload| {
+                payload.push(2);
+                Ok(payload)
+            },
+            |_, mut payload| {
+                payload.push(3);
+                Ok(payload)
+            },
+        ];
+        let migrated = |version| migrate(ArtifactKind::Proof, version, vec![1], &migrations);
+        assert_eq!(migrated(1).unwrap(), vec![1, 2, 3]);
+        assert_eq!(migrated(2).unwrap(), vec![1, 3]);
+        assert_eq!(migrated(3).unwrap(), vec![1]);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json_keys_and_proofs_round_trip() {
+        use crate::json::{JsonError, ProofJson};
+
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 100,
+            num_variables: 25,
+        };
+        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circ).unwrap();
+        let proof = MarlinInst::prove(&index_pk, circ, rng)
+            .unwrap()
+            .with_vk_digest(index_vk.digest::<Blake2s>());
+
+        let vk_json = index_vk.to_json().unwrap();
+        let json: serde_json::Value = serde_json::from_str(&vk_json).unwrap();
+        assert_eq!(json["index_info"]["num_constraints"], 100);
+        assert_eq!(json["index_info"]["arithmetization"], "joint");
+        assert_eq!(json["index_comms"][0]["label"], "row");
+        let same_index_vk = IndexVerifierKey::<Fr, MultiPC>::from_json(&vk_json).unwrap();
+        assert_eq!(same_index_vk.to_json().unwrap(), vk_json);
+
+        let proof_json = proof.to_json(&index_vk.index_info).unwrap();
+        let json: ProofJson = serde_json::from_str(&proof_json).unwrap();
+        let first_round: Vec<_> = json.commitments[0].iter().map(|c| &c.label[..]).collect();
+        assert_eq!(first_round, ["w", "z_a", "z_b", "mask_poly"]);
+        assert!(json
+            .evaluations
+            .iter()
+            .any(|eval| eval.label == "z_b" && eval.point == "beta"));
+        let same_proof = Proof::<Fr, MultiPC>::from_json(&proof_json).unwrap();
+        assert_eq!(
+            same_proof.to_json(&index_vk.index_info).unwrap(),
+            proof_json
+        );
+        assert!(MarlinInst::verify(&same_index_vk, &[c, d], &same_proof, rng).unwrap());
+
+        let proof_hex = proof.to_hex().unwrap();
+        let same_proof = Proof::<Fr, MultiPC>::from_hex(&proof_hex).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[c, d], &same_proof, rng).unwrap());
+
+        // Field elements must be canonical, and the index commitments must be
+        // those of the index that the key describes.
+        let mut json = json;
+        json.evaluations[0].value = format!("0x{}", "ff".repeat(32));
+        assert!(matches!(
+            Proof::<Fr, MultiPC>::from_json_value(&json),
+            Err(JsonError::InvalidFieldElement(_))
+        ));
+        let mut vk_json = index_vk.to_json_value().unwrap();
+        vk_json.index_comms.swap(0, 1);
+        assert!(matches!(
+            IndexVerifierKey::<Fr, MultiPC>::from_json_value(&vk_json),
+            Err(JsonError::UnexpectedLabel { .. })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn prove_and_verify_json_r1cs() {
+        use crate::json::{
+            AssignmentJson, ConstraintJson, JsonCircuit, JsonError, R1csJson, TermJson,
+        };
+
+        let rng = &mut ark_std::test_rng();
+
+        // `x * y = z` for a public `z`, and `(x + 1) * 1 = x + 1`.
+        let term = |variable: usize| TermJson {
+            variable,
+            coefficient: "0x01".into(),
+        };
+        let mut r1cs = R1csJson {
+            num_public_inputs: 1,
+            num_witness_variables: 2,
+            constraints: vec![
+                ConstraintJson {
+                    a: vec![term(2)],
+                    b: vec![term(3)],
+                    c: vec![term(1)],
+                },
+                ConstraintJson {
+                    a: vec![term(2), term(0)],
+                    b: vec![term(0)],
+                    c: vec![term(2), term(0)],
+                },
+            ],
+        };
+        let assignment = AssignmentJson {
+            public_inputs: vec!["0x0f".into()],
+            witness: vec!["0x03".into(), "0x05".into()],
+        };
+
+        let universal_srs = MarlinInst::universal_setup(16, 16, 16, rng).unwrap();
+        let circuit = JsonCircuit::<Fr>::from_json(&serde_json::to_string(&r1cs).unwrap()).unwrap();
+        let (index_pk, index_vk) = MarlinInst::index(&universal_srs, circuit.clone()).unwrap();
+        assert_eq!(index_vk.index_info.num_instance_variables, 2);
+
+        let proof = MarlinInst::prove(
+            &index_pk,
+            circuit.clone().with_assignment(&assignment).unwrap(),
+            rng,
+        )
+        .unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[Fr::from(15u64)], &proof, rng).unwrap());
+        assert!(!MarlinInst::verify(&index_vk, &[Fr::from(16u64)], &proof, rng).unwrap());
+
+        // Proving needs an assignment of the right length, and constraints
+        // may only refer to variables of the system.
+        assert!(MarlinInst::prove(&index_pk, circuit.clone(), rng).is_err());
+        let short = AssignmentJson {
+            witness: vec!["0x03".into()],
+            ..assignment
+        };
+        assert!(matches!(
+            circuit.with_assignment(&short),
+            Err(JsonError::WrongAssignmentLength {
+                expected: 2,
+                found: 1
+            })
+        ));
+        r1cs.constraints[0].c[0].variable = 4;
+        assert!(matches!(
+            JsonCircuit::<Fr>::from_json_value(&r1cs),
+            Err(JsonError::UnknownVariable(4))
+        ));
+    }
+
+    #[test]
+    fn prove_and_verify_with_updated_srs() {
+        let rng = &mut ark_std::test_rng();
+
+        let initial_srs = MarlinInst::universal_setup(100, 25, 300, rng).unwrap();
+        let (srs, first_update) = MarlinInst::update_universal_srs(&initial_srs, rng);
+        let (srs, second_update) = MarlinInst::update_universal_srs(&srs, rng);
+        let updates = [first_update, second_update];
+        assert!(MarlinInst::verify_universal_srs_updates(
+            &initial_srs,
+            &updates,
+            &srs,
+            rng
+        ));
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 25,
+            num_variables: 25,
+        };
+
+        let (index_pk, index_vk) = MarlinInst::index(&srs, circ).unwrap();
+        let proof = MarlinInst::prove(&index_pk, circ, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+        assert!(!MarlinInst::verify(&index_vk, &[a, a], &proof, rng).unwrap());
+    }
+
+    #[test]
+    fn prove_and_verify_with_ppot_srs() {
+        type SonicInst = Marlin<Fr, SonicKZG10<Bls12_381, DensePolynomial<Fr>>, FS>;
+        let rng = &mut ark_std::test_rng();
+
+        let (tau, alpha) = (Fr::rand(rng), Fr::rand(rng));
+        let file = ppot_file::<Bls12_381>(tau, alpha, 9, PointEncoding::Compressed);
+        let srs = MarlinInst::universal_setup_from_ppot(
+            &file[..],
+            9,
+            PointEncoding::Compressed,
+            25,
+            25,
+            100,
+            1,
+            rng,
+        )
+        .unwrap();
+        let sonic_srs = SonicInst::universal_setup_from_ppot(
+            &file[..],
+            9,
+            PointEncoding::Compressed,
+            25,
+            25,
+            100,
+            1,
+            rng,
+        )
+        .unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints: 25,
+            num_variables: 25,
+        };
+
+        let (index_pk, index_vk) = MarlinInst::index(&srs, circ).unwrap();
+        let proof = MarlinInst::prove(&index_pk, circ, rng).unwrap();
+        assert!(MarlinInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+        assert!(!MarlinInst::verify(&index_vk, &[a, a], &proof, rng).unwrap());
+
+        let (index_pk, index_vk) = SonicInst::index(&sonic_srs, circ).unwrap();
+        let proof = SonicInst::prove(&index_pk, circ, rng).unwrap();
+        assert!(SonicInst::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+
+        // Powers of different secrets are rejected.
+        let mut powers =
+            PowersOfTau::<Bls12_381>::read_ppot(&file[..], 9, PointEncoding::Compressed, 300, 2)
+                .unwrap();
+        powers.powers_of_g.swap(1, 2);
+        assert!(matches!(
+            MarlinInst::universal_setup_from_powers_of_tau(&powers, 25, 25, 100, 1, rng),
+            Err(Error::PowersOfTauError(
+                PowersOfTauError::InconsistentPowers
+            ))
+        ));
+    }
+
+    #[test]
+    fn verify_srs_rejects_malformed_srs() {
+        use ark_bls12_381::{G1Affine, G2Affine};
+        type SonicInst = Marlin<Fr, SonicKZG10<Bls12_381, DensePolynomial<Fr>>, FS>;
+        let rng = &mut ark_std::test_rng();
+
+        let srs = MarlinInst::universal_setup(25, 25, 100, rng).unwrap();
+        let sonic_srs = SonicInst::universal_setup(25, 25, 100, rng).unwrap();
+        assert!(MarlinInst::verify_srs(&srs, rng).is_ok());
+        assert!(SonicInst::verify_srs(&sonic_srs, rng).is_ok());
+        let (updated_srs, _) = SonicInst::update_universal_srs(&sonic_srs, rng);
+        assert!(SonicInst::verify_srs(&updated_srs, rng).is_ok());
+
+        // Powers of G that are not consecutive powers of one secret.
+        let mut malformed_srs = srs.clone();
+        malformed_srs.powers_of_g.swap(5, 6);
+        assert!(matches!(
+            MarlinInst::verify_srs(&malformed_srs, rng),
+            Err(Error::MalformedSRS)
+        ));
+
+        // Powers of G that are inconsistent with `beta * H`.
+        let mut malformed_srs = srs.clone();
+        malformed_srs.beta_h = sonic_srs.beta_h;
+        malformed_srs.prepared_beta_h = sonic_srs.prepared_beta_h.clone();
+        assert!(matches!(
+            MarlinInst::verify_srs(&malformed_srs, rng),
+            Err(Error::MalformedSRS)
+        ));
+
+        // A prepared G2 element that is not that of `beta * H`.
+        let mut malformed_srs = srs.clone();
+        malformed_srs.prepared_beta_h = sonic_srs.prepared_beta_h.clone();
+        assert!(matches!(
+            MarlinInst::verify_srs(&malformed_srs, rng),
+            Err(Error::MalformedSRS)
+        ));
+
+        // Powers of `gamma * G` with `gamma = 1`, as a single-secret ceremony
+        // gives before it is updated.
+        let mut malformed_srs = srs.clone();
+        malformed_srs.powers_of_gamma_g = srs.powers_of_g.iter().copied().enumerate().collect();
+        malformed_srs.powers_of_g.pop();
+        assert!(matches!(
+            MarlinInst::verify_srs(&malformed_srs, rng),
+            Err(Error::MalformedSRS)
+        ));
+
+        // Powers of a zero secret, which are all the identity but the first.
+        let mut malformed_srs = srs.clone();
+        for g in malformed_srs.powers_of_g.iter_mut().skip(1) {
+            *g = G1Affine::zero();
+        }
+        for (_, g) in malformed_srs.powers_of_gamma_g.iter_mut().skip(1) {
+            *g = G1Affine::zero();
+        }
+        malformed_srs.beta_h = G2Affine::zero();
+        malformed_srs.prepared_beta_h = G2Affine::zero().into();
+        assert!(matches!(
+            MarlinInst::verify_srs(&malformed_srs, rng),
+            Err(Error::MalformedSRS)
+        ));
+
+        let mut malformed_srs = sonic_srs.clone();
+        let last = malformed_srs.powers_of_gamma_g.len() - 1;
+        let gamma_g = malformed_srs.powers_of_gamma_g[&0];
+        malformed_srs.powers_of_gamma_g.insert(last, gamma_g);
+        assert!(matches!(
+            SonicInst::verify_srs(&malformed_srs, rng),
+            Err(Error::MalformedSRS)
+        ));
+        let mut malformed_srs = sonic_srs.clone();
+        malformed_srs.powers_of_gamma_g.remove(&last);
+        assert!(matches!(
+            SonicInst::verify_srs(&malformed_srs, rng),
+            Err(Error::MalformedSRS)
+        ));
+
+        // Negative powers of H for degree bounds that are wrong or missing.
+        let mut malformed_srs = sonic_srs.clone();
+        let neg_h = malformed_srs.neg_powers_of_h[&2];
+        malformed_srs.neg_powers_of_h.insert(7, neg_h);
+        assert!(matches!(
+            SonicInst::verify_srs(&malformed_srs, rng),
+            Err(Error::MalformedSRS)
+        ));
+        let mut malformed_srs = sonic_srs.clone();
+        let last = malformed_srs.neg_powers_of_h.len() - 1;
+        malformed_srs.neg_powers_of_h.remove(&last);
+        assert!(matches!(
+            SonicInst::verify_srs(&malformed_srs, rng),
+            Err(Error::MalformedSRS)
+        ));
+    }
+
+    #[test]
+    fn verify_rejects_invalid_srs_updates() {
+        type SonicInst = Marlin<Fr, SonicKZG10<Bls12_381, DensePolynomial<Fr>>, FS>;
+        let rng = &mut ark_std::test_rng();
+
+        let initial_srs = SonicInst::universal_setup(100, 25, 300, rng).unwrap();
+        let (srs, first_update) = SonicInst::update_universal_srs(&initial_srs, rng);
+        let (srs, second_update) = SonicInst::update_universal_srs(&srs, rng);
+        let updates = [first_update.clone(), second_update.clone()];
+        assert!(SonicInst::verify_universal_srs_updates(
+            &initial_srs,
+            &updates,
+            &srs,
+            rng
+        ));
+
+        // Every contribution must be accounted for, in order.
+        let reordered = [second_update.clone(), first_update.clone()];
+        assert!(!SonicInst::verify_universal_srs_updates(
+            &initial_srs,
+            &updates[..1],
+            &srs,
+            rng
+        ));
+        assert!(!SonicInst::verify_universal_srs_updates(
+            &initial_srs,
+            &reordered,
+            &srs,
+            rng
+        ));
+
+        // An SRS with a known trapdoor cannot be passed off as the result.
+        let other_srs = SonicInst::universal_setup(100, 25, 300, rng).unwrap();
+        assert!(!SonicInst::verify_universal_srs_updates(
+            &initial_srs,
+            &updates,
+            &other_srs,
+            rng
+        ));
+
+        // Nor can an SRS whose powers are not consecutive.
+        let mut malformed_srs = srs.clone();
+        malformed_srs.powers_of_g.swap(2, 3);
+        assert!(!SonicInst::verify_universal_srs_updates(
+            &initial_srs,
+            &updates,
+            &malformed_srs,
+            rng
+        ));
+        let mut malformed_srs = srs.clone();
+        let neg_h = malformed_srs.neg_powers_of_h[&2];
+        malformed_srs.neg_powers_of_h.insert(3, neg_h);
+        assert!(!SonicInst::verify_universal_srs_updates(
+            &initial_srs,
+            &updates,
+            &malformed_srs,
+            rng
+        ));
+
+        // A proof of knowledge does not carry over to another update.
+        let mut forged_update = second_update;
+        forged_update.pok_responses = first_update.pok_responses;
+        assert!(!SonicInst::verify_universal_srs_updates(
+            &initial_srs,
+            &[first_update, forged_update],
+            &srs,
+            rng
+        ));
+    }
+}
+
+#[cfg(feature = "solidity")]
+mod solidity {
+    use super::*;
+    use crate::solidity::{
+        encode_calldata, encode_proof, encode_public_input, SolidityError, SolidityMarlin,
+        SolidityVerifier,
+    };
+    use crate::{ArithmetizationLayout, IndexerConfig, ZkMode};
+
+    use ark_bn254::Fr;
+    use ark_ff::{One, UniformRand};
+
+    const GOLDEN_FILE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/solidity/golden/MarlinVerifier.sol"
+    );
+
+    fn circuit(a: Fr, b: Fr, num_constraints: usize, num_variables: usize) -> Circuit<Fr> {
+        Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints,
+            num_variables,
+        }
+    }
+
+    #[test]
+    fn generated_verifier_matches_golden_file() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = SolidityMarlin::universal_setup(100, 25, 300, rng).unwrap();
+        let circ = circuit(Fr::one(), Fr::one(), 100, 25);
+        let (_, index_vk) = SolidityMarlin::index(&universal_srs, circ).unwrap();
+        let contract = SolidityVerifier::new(&index_vk)
+            .unwrap()
+            .contract("MarlinVerifier");
+
+        // Set `UPDATE_GOLDEN` to regenerate the golden file after an intended
+        // change of the template or of the transcript.
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(GOLDEN_FILE, &contract).unwrap();
+        }
+        let golden = std::fs::read_to_string(GOLDEN_FILE).unwrap();
+        assert!(
+            contract == golden,
+            "the generated verifier differs from {}",
+            GOLDEN_FILE
+        );
+    }
+
+    #[test]
+    fn reference_model_agrees_with_verify() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = SolidityMarlin::universal_setup(100, 100, 300, rng).unwrap();
+
+        for &(num_constraints, num_variables, zk_mode) in &[
+            (25, 25, ZkMode::ZeroKnowledge),
+            (100, 25, ZkMode::ZeroKnowledge),
+            (25, 100, ZkMode::NonZeroKnowledge),
+        ] {
+            let a = Fr::rand(rng);
+            let b = Fr::rand(rng);
+            let c = a * b;
+            let d = c * b;
+            let circ = circuit(a, b, num_constraints, num_variables);
+            let (index_pk, index_vk) = SolidityMarlin::index(&universal_srs, circ).unwrap();
+            let mut proof =
+                SolidityMarlin::prove_with_abi_commitments(&index_pk, circ, zk_mode, rng).unwrap();
+            let verifier = SolidityVerifier::new(&index_vk).unwrap();
+
+            assert!(
+                SolidityMarlin::verify_with_abi_commitments(&index_vk, &[c, d], &proof, rng)
+                    .unwrap()
+            );
+            let input = encode_public_input(&[c, d]);
+            let words = encode_proof(&proof).unwrap();
+            assert!(verifier.verify_words(&input, &words));
+            assert!(verifier.verify_calldata(&encode_calldata(&[c, d], &proof).unwrap()));
+
+            // A wrong public input.
+            assert!(
+                !SolidityMarlin::verify_with_abi_commitments(&index_vk, &[a, a], &proof, rng)
+                    .unwrap()
+            );
+            assert!(!verifier.verify_words(&encode_public_input(&[a, a]), &words));
+            assert!(!verifier.verify_words(&input[..1], &words));
+
+            // A wrong evaluation and a wrong commitment.
+            proof.evaluations[2] += Fr::one();
+            assert!(
+                !SolidityMarlin::verify_with_abi_commitments(&index_vk, &[c, d], &proof, rng)
+                    .unwrap()
+            );
+            assert!(!verifier.verify_words(&input, &encode_proof(&proof).unwrap()));
+            proof.evaluations[2] -= Fr::one();
+            proof.commitments[0].swap(0, 1);
+            assert!(
+                !SolidityMarlin::verify_with_abi_commitments(&index_vk, &[c, d], &proof, rng)
+                    .unwrap()
+            );
+            assert!(!verifier.verify_words(&input, &encode_proof(&proof).unwrap()));
+
+            // A wrong commitment or opening proof, and words out of range.
+            for &i in &[1, 12, 25, 28] {
+                let mut tampered = words.clone();
+                tampered[i][31] ^= 1;
+                assert!(!verifier.verify_words(&input, &tampered));
+                tampered[i] = [0xff; 32];
+                assert!(!verifier.verify_words(&input, &tampered));
+            }
+        }
+    }
+
+    #[test]
+    fn generator_rejects_unsupported_indices() {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = SolidityMarlin::universal_setup(100, 25, 300, rng).unwrap();
+        let circ = circuit(Fr::one(), Fr::one(), 25, 25);
+        let config = IndexerConfig {
+            arithmetization: ArithmetizationLayout::PerMatrix,
+            ..IndexerConfig::default()
+        };
+        let (index_pk, index_vk) =
+            SolidityMarlin::index_with_config(&universal_srs, circ, config).unwrap();
+        assert_eq!(
+            SolidityVerifier::new(&index_vk).unwrap_err(),
+            SolidityError::UnsupportedIndex("per-matrix arithmetization")
+        );
+
+        let zk_mode = ZkMode::ZeroKnowledge;
+        let proof =
+            SolidityMarlin::prove_with_abi_commitments(&index_pk, circ, zk_mode, rng).unwrap();
+        assert_eq!(encode_proof(&proof), Err(SolidityError::MalformedProof));
+    }
+}
+
+#[cfg(feature = "r1cs")]
+mod marlin_recursion {
+    use super::*;
+    use crate::constraints::{
+        FiatShamirRngVar, IndexVerifierKeyVar, MarlinKZG10Gadget, MarlinVerifierGadget,
+        PoseidonFiatShamirRngVar, ProofVar, PublicInputVar, SimpleHashFiatShamirRngVar,
+    };
+    use crate::rng::FiatShamirRng;
+    use crate::SimpleHashFiatShamirRng;
+    use crate::{ArithmetizationLayout, IndexerConfig, Proof};
+    use crate::{Marlin, PoseidonFiatShamirRng, ShortChallengeFiatShamirRng};
+
+    use ark_ff::{to_bytes, UniformRand};
+    use ark_mnt4_298::{constraints::PairingVar as MNT4PairingVar, Fq, Fr, MNT4_298};
+    use ark_poly::univariate::DensePolynomial;
+    use ark_poly_commit::marlin_pc::MarlinKZG10;
+    use ark_r1cs_std::{alloc::AllocVar, boolean::Boolean, eq::EqGadget, R1CSVar, ToBytesGadget};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use blake2::Blake2s;
+    use rand_chacha::ChaChaRng;
+
+    type MultiPC = MarlinKZG10<MNT4_298, DensePolynomial<Fr>>;
+    type MultiPCVar = MarlinKZG10Gadget<MNT4_298, MNT4PairingVar>;
+    type MarlinVerifierVar<FS, FSVar> =
+        MarlinVerifierGadget<Fr, Fq, MultiPC, FS, MultiPCVar, FSVar>;
+
+    fn verify_proof_in_circuit<FS: FiatShamirRng, FSVar: FiatShamirRngVar<Fr, Fq, FS>>(
+        num_constraints: usize,
+        num_variables: usize,
+        arithmetization: ArithmetizationLayout,
+    ) {
+        let rng = &mut ark_std::test_rng();
+
+        let universal_srs = Marlin::<Fr, MultiPC, FS>::universal_setup(32, 32, 64, rng).unwrap();
+
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c = a * b;
+        let d = c * b;
+        let circ = Circuit {
+            a: Some(a),
+            b: Some(b),
+            num_constraints,
+            num_variables,
+        };
+
+        let config = IndexerConfig {
+            arithmetization,
+            ..IndexerConfig::default()
+        };
+        let (index_pk, index_vk) =
+            Marlin::<Fr, MultiPC, FS>::index_with_config(&universal_srs, circ, config).unwrap();
+        let proof = Marlin::<Fr, MultiPC, FS>::prove(&index_pk, circ, rng).unwrap();
+        assert!(Marlin::<Fr, MultiPC, FS>::verify(&index_vk, &[c, d], &proof, rng).unwrap());
+
+        // The verifier key is a witness, whose bytes the circuit computes as
+        // the native verifier does.
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        let index_vk_var =
+            IndexVerifierKeyVar::<_, _, _, MultiPCVar>::new_witness(cs.clone(), || Ok(&index_vk))
+                .unwrap();
+        assert_eq!(
+            index_vk_var.to_bytes().unwrap().value().unwrap(),
+            to_bytes![index_vk].unwrap()
+        );
+        let public_input_var = PublicInputVar::new_input(cs.clone(), || Ok([c, d])).unwrap();
+        let proof_var =
+            ProofVar::<_, _, _, MultiPCVar>::new_witness(cs.clone(), || Ok(&proof)).unwrap();
+
+        let verified =
+            MarlinVerifierVar::<FS, FSVar>::verify(&index_vk_var, &public_input_var, &proof_var)
+                .unwrap();
+        assert!(verified.value().unwrap());
+        verified.enforce_equal(&Boolean::TRUE).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // On any public input and proof, the circuit reaches the same verdict
+        // as the native verifier.
+        let mut bytes = Vec::new();
+        proof.serialize(&mut bytes).unwrap();
+        let mut tampered = Proof::deserialize(&bytes[..]).unwrap();
+        tampered.evaluations[0] += Fr::from(1u64);
+        for (public_input, proof) in &[([c, d], &proof), ([a, a], &proof), ([c, d], &tampered)] {
+            let native =
+                Marlin::<Fr, MultiPC, FS>::verify(&index_vk, public_input, proof, rng).unwrap();
+
+            let cs = ConstraintSystem::<Fq>::new_ref();
+            let index_vk_var =
+                IndexVerifierKeyVar::<_, _, _, MultiPCVar>::new_constant(cs.clone(), &index_vk)
+                    .unwrap();
+            let public_input_var =
+                PublicInputVar::new_input(cs.clone(), || Ok(*public_input)).unwrap();
+            let proof_var =
+                ProofVar::<_, _, _, MultiPCVar>::new_witness(cs.clone(), || Ok(*proof)).unwrap();
+
+            let verified = MarlinVerifierVar::<FS, FSVar>::verify(
+                &index_vk_var,
+                &public_input_var,
+                &proof_var,
+            )
+            .unwrap();
+            assert_eq!(verified.value().unwrap(), native);
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn verify_proof_in_circuit_with_simple_hash_fs_and_tall_matrix() {
+        verify_proof_in_circuit::<
+            ShortChallengeFiatShamirRng<SimpleHashFiatShamirRng<Blake2s, ChaChaRng>>,
+            SimpleHashFiatShamirRngVar<Fr, Fq>,
+        >(16, 5, ArithmetizationLayout::Joint);
+    }
+
+    #[test]
+    fn verify_proof_in_circuit_with_poseidon_fs_and_squat_matrix() {
+        verify_proof_in_circuit::<
+            ShortChallengeFiatShamirRng<PoseidonFiatShamirRng<Fq>>,
+            PoseidonFiatShamirRngVar<Fr, Fq>,
+        >(4, 16, ArithmetizationLayout::Joint);
+    }
+
+    #[test]
+    fn verify_proof_in_circuit_with_per_matrix_arithmetization() {
+        verify_proof_in_circuit::<
+            ShortChallengeFiatShamirRng<SimpleHashFiatShamirRng<Blake2s, ChaChaRng>>,
+            SimpleHashFiatShamirRngVar<Fr, Fq>,
+        >(16, 5, ArithmetizationLayout::PerMatrix);
+    }
 }
@@ -0,0 +1,352 @@
+use crate::{IndexProverKey, IndexVerifierKey, Proof, Vec};
+use ark_ec::{AffineCurve, PairingEngine};
+use ark_ff::PrimeField;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::{marlin_pc::MarlinKZG10, sonic_pc::SonicKZG10, PolynomialCommitment};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::io::{self, Read, Write};
+
+/// The bytes that every versioned artifact starts with.
+pub const MAGIC: [u8; 4] = *b"MRLN";
+
+/// The version of the layout of the payloads that `write_versioned` writes.
+/// Artifacts of older versions are migrated to it when they are read.
+pub const FORMAT_VERSION: u16 = 1;
+
+/// The size of the header, in bytes.
+const HEADER_SIZE: usize = 24;
+
+/// The kinds of artifacts that can be written in the versioned format.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// Universal parameters, as output by `Marlin::universal_setup`.
+    UniversalSRS,
+    /// An `IndexProverKey`.
+    IndexProverKey,
+    /// An `IndexVerifierKey`.
+    IndexVerifierKey,
+    /// A `Proof`.
+    Proof,
+}
+
+impl ArtifactKind {
+    fn from_u8(kind: u8) -> Option<Self> {
+        match kind {
+            0 => Some(ArtifactKind::UniversalSRS),
+            1 => Some(ArtifactKind::IndexProverKey),
+            2 => Some(ArtifactKind::IndexVerifierKey),
+            3 => Some(ArtifactKind::Proof),
+            _ => None,
+        }
+    }
+}
+
+/// The ways in which reading or writing a versioned artifact can fail.
+#[derive(Debug)]
+pub enum FormatError {
+    /// The artifact could not be read or written.
+    Io(io::Error),
+    /// The payload could not be serialized or deserialized.
+    Serialization(SerializationError),
+    /// The artifact does not start with `MAGIC`.
+    InvalidMagic,
+    /// The artifact has a format version that this version of the crate does
+    /// not know how to read.
+    UnsupportedVersion(u16),
+    /// The artifact is of another kind, or of an unknown one.
+    UnexpectedKind(u8),
+    /// The artifact was written for another polynomial commitment scheme.
+    UnexpectedScheme(u8),
+    /// The artifact was written for another scalar field.
+    UnexpectedField(u32),
+    /// The artifact was written for another curve.
+    UnexpectedCurve(u32),
+    /// The checksum does not match the contents of the artifact.
+    ChecksumMismatch,
+}
+
+impl From<io::Error> for FormatError {
+    fn from(err: io::Error) -> Self {
+        FormatError::Io(err)
+    }
+}
+
+impl From<SerializationError> for FormatError {
+    fn from(err: SerializationError) -> Self {
+        FormatError::Serialization(err)
+    }
+}
+
+/// A polynomial commitment scheme whose artifacts can be written in the
+/// versioned format, which records the scheme and its curve.
+pub trait VersionedPC<F: PrimeField>: PolynomialCommitment<F, DensePolynomial<F>> {
+    /// The identifier of the scheme.
+    const SCHEME_ID: u8;
+
+    /// A fingerprint of the curve that the scheme is instantiated with.
+    fn curve_id() -> u32;
+}
+
+impl<E: PairingEngine> VersionedPC<E::Fr> for MarlinKZG10<E, DensePolynomial<E::Fr>> {
+    const SCHEME_ID: u8 = 1;
+
+    fn curve_id() -> u32 {
+        pairing_curve_id::<E>()
+    }
+}
+
+impl<E: PairingEngine> VersionedPC<E::Fr> for SonicKZG10<E, DensePolynomial<E::Fr>> {
+    const SCHEME_ID: u8 = 2;
+
+    fn curve_id() -> u32 {
+        pairing_curve_id::<E>()
+    }
+}
+
+/// The CRC-32 of the encodings of the generators of G1 and G2 of `E`.
+fn pairing_curve_id<E: PairingEngine>() -> u32 {
+    let mut bytes = Vec::new();
+    E::G1Affine::prime_subgroup_generator()
+        .serialize(&mut bytes)
+        .unwrap();
+    E::G2Affine::prime_subgroup_generator()
+        .serialize(&mut bytes)
+        .unwrap();
+    crc32(0, &bytes)
+}
+
+/// The CRC-32 of the little-endian encoding of the modulus of `F`.
+fn field_id<F: PrimeField>() -> u32 {
+    let bytes: Vec<u8> = F::characteristic()
+        .iter()
+        .flat_map(|limb| limb.to_le_bytes())
+        .collect();
+    crc32(0, &bytes)
+}
+
+/// Update `crc`, the CRC-32 (IEEE) of the bytes processed so far, with
+/// `bytes`.
+fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// The header of a versioned artifact, which describes its payload.
+///
+/// The header is made of `MAGIC`, followed by the fields below in this order,
+/// little-endian. The payload that follows it is the canonical serialization
+/// of the artifact, and is followed by the CRC-32 of the header and the
+/// payload.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// The format version of the payload.
+    pub version: u16,
+    /// The kind of the artifact.
+    pub kind: ArtifactKind,
+    /// The identifier of the polynomial commitment scheme, as in
+    /// `VersionedPC::SCHEME_ID`.
+    pub scheme_id: u8,
+    /// A fingerprint of the scalar field: the CRC-32 of its modulus.
+    pub field_id: u32,
+    /// A fingerprint of the curve, as in `VersionedPC::curve_id`.
+    pub curve_id: u32,
+    /// The size of the payload, in bytes.
+    pub payload_size: u64,
+}
+
+impl Header {
+    /// The header of an artifact of `kind` for `PC`, with a payload of
+    /// `payload_size` bytes in the current format version.
+    pub fn new<F: PrimeField, PC: VersionedPC<F>>(kind: ArtifactKind, payload_size: u64) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            kind,
+            scheme_id: PC::SCHEME_ID,
+            field_id: field_id::<F>(),
+            curve_id: PC::curve_id(),
+            payload_size,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[..4].copy_from_slice(&MAGIC);
+        bytes[4..6].copy_from_slice(&self.version.to_le_bytes());
+        bytes[6] = self.kind as u8;
+        bytes[7] = self.scheme_id;
+        bytes[8..12].copy_from_slice(&self.field_id.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.curve_id.to_le_bytes());
+        bytes[16..].copy_from_slice(&self.payload_size.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; HEADER_SIZE]) -> Result<Self, FormatError> {
+        if bytes[..4] != MAGIC {
+            return Err(FormatError::InvalidMagic);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let mut payload_size = [0u8; 8];
+        payload_size.copy_from_slice(&bytes[16..]);
+        Ok(Self {
+            version,
+            kind: ArtifactKind::from_u8(bytes[6]).ok_or(FormatError::UnexpectedKind(bytes[6]))?,
+            scheme_id: bytes[7],
+            field_id: u32_at(8),
+            curve_id: u32_at(12),
+            payload_size: u64::from_le_bytes(payload_size),
+        })
+    }
+
+    /// Read the header of a versioned artifact, without checking what it was
+    /// written for.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, FormatError> {
+        let mut bytes = [0u8; HEADER_SIZE];
+        reader.read_exact(&mut bytes)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Upgrade the payload of an artifact of the given kind from the format
+/// version that the migration is registered for to the next one.
+pub(crate) type Migration = fn(ArtifactKind, Vec<u8>) -> Result<Vec<u8>, FormatError>;
+
+/// The migrations between consecutive format versions: the `i`-th one
+/// upgrades payloads of version `i + 1`. A change to the layout of an artifact
+/// bumps `FORMAT_VERSION` and registers a migration from the previous layout
+/// here.
+const MIGRATIONS: [Migration; FORMAT_VERSION as usize - 1] = [];
+
+/// Upgrade `payload`, of the format version `version`, with the migrations
+/// registered from that version on.
+pub(crate) fn migrate(
+    kind: ArtifactKind,
+    version: u16,
+    payload: Vec<u8>,
+    migrations: &[Migration],
+) -> Result<Vec<u8>, FormatError> {
+    migrations[version as usize - 1..]
+        .iter()
+        .try_fold(payload, |payload, migration| migration(kind, payload))
+}
+
+/// Write `value`, an artifact of `kind` for `PC`, in the versioned format.
+pub fn write_versioned<F, PC, T, W>(
+    kind: ArtifactKind,
+    value: &T,
+    mut writer: W,
+) -> Result<(), FormatError>
+where
+    F: PrimeField,
+    PC: VersionedPC<F>,
+    T: CanonicalSerialize,
+    W: Write,
+{
+    let mut payload = Vec::with_capacity(value.serialized_size());
+    value.serialize(&mut payload)?;
+    let header = Header::new::<F, PC>(kind, payload.len() as u64).to_bytes();
+    let checksum = crc32(crc32(0, &header), &payload);
+    writer.write_all(&header)?;
+    writer.write_all(&payload)?;
+    writer.write_all(&checksum.to_le_bytes())?;
+    Ok(())
+}
+
+/// Read an artifact of `kind` for `PC` in the versioned format, after checking
+/// that it was written for the same kind, scheme, field and curve, and
+/// migrating it from older format versions.
+pub fn read_versioned<F, PC, T, R>(kind: ArtifactKind, mut reader: R) -> Result<T, FormatError>
+where
+    F: PrimeField,
+    PC: VersionedPC<F>,
+    T: CanonicalDeserialize,
+    R: Read,
+{
+    let mut header_bytes = [0u8; HEADER_SIZE];
+    reader.read_exact(&mut header_bytes)?;
+    let header = Header::from_bytes(&header_bytes)?;
+    let expected = Header::new::<F, PC>(kind, header.payload_size);
+    if header.kind != expected.kind {
+        return Err(FormatError::UnexpectedKind(header.kind as u8));
+    }
+    if header.scheme_id != expected.scheme_id {
+        return Err(FormatError::UnexpectedScheme(header.scheme_id));
+    }
+    if header.field_id != expected.field_id {
+        return Err(FormatError::UnexpectedField(header.field_id));
+    }
+    if header.curve_id != expected.curve_id {
+        return Err(FormatError::UnexpectedCurve(header.curve_id));
+    }
+
+    // The payload is read in chunks, so that a corrupted size does not
+    // allocate more memory than the artifact holds.
+    let mut payload = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let mut remaining = header.payload_size;
+    while remaining > 0 {
+        let len = core::cmp::min(remaining, chunk.len() as u64) as usize;
+        reader.read_exact(&mut chunk[..len])?;
+        payload.extend_from_slice(&chunk[..len]);
+        remaining -= len as u64;
+    }
+    let mut checksum = [0u8; 4];
+    reader.read_exact(&mut checksum)?;
+    if u32::from_le_bytes(checksum) != crc32(crc32(0, &header_bytes), &payload) {
+        return Err(FormatError::ChecksumMismatch);
+    }
+
+    let payload = migrate(kind, header.version, payload, &MIGRATIONS)?;
+    let mut payload = &payload[..];
+    let value = T::deserialize(&mut payload)?;
+    if !payload.is_empty() {
+        return Err(SerializationError::InvalidData.into());
+    }
+    Ok(value)
+}
+
+impl<F: PrimeField, PC: VersionedPC<F>> Proof<F, PC> {
+    /// Write `self` in the versioned format.
+    pub fn write_versioned<W: Write>(&self, writer: W) -> Result<(), FormatError> {
+        write_versioned::<F, PC, _, _>(ArtifactKind::Proof, self, writer)
+    }
+
+    /// Read a proof written by `Proof::write_versioned`.
+    pub fn read_versioned<R: Read>(reader: R) -> Result<Self, FormatError> {
+        read_versioned::<F, PC, _, _>(ArtifactKind::Proof, reader)
+    }
+}
+
+impl<F: PrimeField, PC: VersionedPC<F>> IndexVerifierKey<F, PC> {
+    /// Write `self` in the versioned format.
+    pub fn write_versioned<W: Write>(&self, writer: W) -> Result<(), FormatError> {
+        write_versioned::<F, PC, _, _>(ArtifactKind::IndexVerifierKey, self, writer)
+    }
+
+    /// Read a verifier key written by `IndexVerifierKey::write_versioned`.
+    pub fn read_versioned<R: Read>(reader: R) -> Result<Self, FormatError> {
+        read_versioned::<F, PC, _, _>(ArtifactKind::IndexVerifierKey, reader)
+    }
+}
+
+impl<F: PrimeField, PC: VersionedPC<F>> IndexProverKey<F, PC> {
+    /// Write `self` in the versioned format.
+    pub fn write_versioned<W: Write>(&self, writer: W) -> Result<(), FormatError> {
+        write_versioned::<F, PC, _, _>(ArtifactKind::IndexProverKey, self, writer)
+    }
+
+    /// Read a prover key written by `IndexProverKey::write_versioned`.
+    pub fn read_versioned<R: Read>(reader: R) -> Result<Self, FormatError> {
+        read_versioned::<F, PC, _, _>(ArtifactKind::IndexProverKey, reader)
+    }
+}
@@ -0,0 +1,146 @@
+//! End-to-end tests of the `marlin` command-line tool.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A scratch directory for the files of one test, removed when dropped.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("marlin-cli-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn write(&self, name: &str, contents: &str) -> String {
+        let path = self.path(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn path(&self, name: &str) -> String {
+        self.0.join(name).to_str().unwrap().to_string()
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run `marlin` with `args` and return its exit code.
+fn marlin(args: &[&str]) -> i32 {
+    let output = Command::new(env!("CARGO_BIN_EXE_marlin"))
+        .args(args)
+        .output()
+        .unwrap();
+    output.status.code().unwrap()
+}
+
+/// The constraint system `x * x = y * scale`, with `y` public.
+fn square_r1cs(scale: u64) -> String {
+    format!(
+        r#"{{
+            "num_public_inputs": 1,
+            "num_witness_variables": 1,
+            "constraints": [{{
+                "a": [{{ "variable": 2, "coefficient": "0x01" }}],
+                "b": [{{ "variable": 2, "coefficient": "0x01" }}],
+                "c": [{{ "variable": 1, "coefficient": "0x{:02x}" }}]
+            }}]
+        }}"#,
+        scale
+    )
+}
+
+#[test]
+fn setup_index_prove_and_verify() {
+    let dir = ScratchDir::new("end-to-end");
+    let r1cs = dir.write("r1cs.json", &square_r1cs(1));
+    let witness = dir.write(
+        "witness.json",
+        r#"{ "public_inputs": ["0x09"], "witness": ["0x03"] }"#,
+    );
+    let inputs = dir.write("inputs.json", r#"["0x09"]"#);
+    let wrong_inputs = dir.write("wrong-inputs.json", r#"["0x0a"]"#);
+    let (srs, pk, vk, proof) = (
+        dir.path("srs"),
+        dir.path("pk"),
+        dir.path("vk"),
+        dir.path("proof"),
+    );
+
+    let setup = [
+        "setup",
+        "--max-constraints",
+        "4",
+        "--max-variables",
+        "4",
+        "--max-non-zero",
+        "8",
+        "--out",
+        &srs,
+    ];
+    assert_eq!(marlin(&setup), 0);
+    let index = [
+        "index", "--r1cs", &r1cs, "--srs", &srs, "--pk", &pk, "--vk", &vk,
+    ];
+    assert_eq!(marlin(&index), 0);
+    let prove = [
+        "prove",
+        "--pk",
+        &pk,
+        "--r1cs",
+        &r1cs,
+        "--witness",
+        &witness,
+        "--out",
+        &proof,
+    ];
+    assert_eq!(marlin(&prove), 0);
+
+    let verify = |inputs: &str| {
+        marlin(&[
+            "verify",
+            "--vk",
+            &vk,
+            "--public-inputs",
+            inputs,
+            "--proof",
+            &proof,
+        ])
+    };
+    assert_eq!(verify(&inputs), 0);
+    assert_eq!(verify(&wrong_inputs), 1);
+    assert_eq!(verify(&dir.path("missing.json")), 2);
+
+    // Proving with a constraint system other than the indexed one fails.
+    let other_r1cs = dir.write("other-r1cs.json", &square_r1cs(2));
+    let prove = [
+        "prove",
+        "--pk",
+        &pk,
+        "--r1cs",
+        &other_r1cs,
+        "--witness",
+        &witness,
+        "--out",
+        &proof,
+    ];
+    assert_eq!(marlin(&prove), 2);
+}
+
+#[test]
+fn rejects_unusable_arguments() {
+    assert_eq!(marlin(&[]), 2);
+    assert_eq!(marlin(&["frobnicate"]), 2);
+    assert_eq!(marlin(&["setup", "--max-constraints", "many"]), 2);
+    assert_eq!(marlin(&["inspect", "--vk"]), 2);
+    assert_eq!(
+        marlin(&["inspect", "--curve", "secp256k1", "--vk", "vk"]),
+        2
+    );
+}